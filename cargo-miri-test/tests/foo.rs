@@ -2,3 +2,16 @@
 fn bar() {
     assert_eq!(4, 4);
 }
+
+static SQUARES: [u32; 4] = [0, 1, 4, 9];
+
+// Both tests run in the same interpreter, which evaluates `SQUARES` only for the first one.
+#[test]
+fn squares() {
+    assert_eq!(SQUARES[2], 4);
+}
+
+#[test]
+fn more_squares() {
+    assert_eq!(SQUARES[3], 9);
+}
@@ -0,0 +1,151 @@
+//! The behavior of the Rust global allocator (`__rust_alloc` and friends). By default, miri hands
+//! out exactly what was asked for: memory with the requested alignment and undefined contents.
+//! Real allocators give more than that, and a program that relies on it works with one allocator
+//! but not with another. `-Zmiri-allocator=<behavior>` makes miri behave like one of them, so the
+//! results can be compared.
+//!
+//! Allocations have no addresses in miri and are never reused, so there is nothing to emulate
+//! about address reuse.
+
+use rustc_miri::interpret::{EvalContext, EvalErrorKind, EvalResult, MemoryPointer};
+
+use super::Evaluator;
+use super::memory::MemoryKind;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AllocatorBehavior {
+    /// The requested alignment, undefined contents.
+    Exact,
+    /// Like the `System` allocator on top of `malloc`: every allocation is aligned to at least
+    /// twice the pointer size, the contents are undefined.
+    System,
+    /// Like an allocator serving fresh pages from the OS: the requested alignment, but the
+    /// contents are zeroed.
+    Zeroing,
+}
+
+impl Default for AllocatorBehavior {
+    fn default() -> Self {
+        AllocatorBehavior::Exact
+    }
+}
+
+impl AllocatorBehavior {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "exact" => Some(AllocatorBehavior::Exact),
+            "system" => Some(AllocatorBehavior::System),
+            "zeroing" => Some(AllocatorBehavior::Zeroing),
+            _ => None,
+        }
+    }
+
+    /// The alignment an allocation requested with `align` actually gets.
+    fn align(self, align: u64, pointer_size: u64) -> u64 {
+        match self {
+            AllocatorBehavior::System => ::std::cmp::max(align, 2 * pointer_size),
+            AllocatorBehavior::Exact | AllocatorBehavior::Zeroing => align,
+        }
+    }
+}
+
+pub trait EvalContextExt<'tcx> {
+    /// `__rust_alloc` and `__rust_alloc_zeroed`
+    fn rust_alloc(
+        &mut self,
+        size: u64,
+        align: u64,
+        zeroed: bool,
+    ) -> EvalResult<'tcx, MemoryPointer>;
+
+    /// `__rust_dealloc`
+    fn rust_dealloc(&mut self, ptr: MemoryPointer, size: u64, align: u64) -> EvalResult<'tcx>;
+
+    /// `__rust_realloc`
+    fn rust_realloc(
+        &mut self,
+        ptr: MemoryPointer,
+        old_size: u64,
+        old_align: u64,
+        new_size: u64,
+        new_align: u64,
+    ) -> EvalResult<'tcx, MemoryPointer>;
+
+    /// The alignment the allocation of `ptr` got, if the program says it asked for `size` bytes
+    /// with alignment `align`.
+    fn actual_align(&mut self, ptr: MemoryPointer, size: u64, align: u64) -> EvalResult<'tcx, u64>;
+}
+
+impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, Evaluator> {
+    fn rust_alloc(
+        &mut self,
+        size: u64,
+        align: u64,
+        zeroed: bool,
+    ) -> EvalResult<'tcx, MemoryPointer> {
+        let behavior = self.machine_data.config.allocator;
+        let actual_align = behavior.align(align, self.memory.pointer_size());
+        let ptr = self.memory.allocate(size, actual_align, MemoryKind::Rust.into())?;
+        if actual_align != align {
+            self.machine_data.requested_aligns.insert(ptr.alloc_id, align);
+        }
+        if zeroed || behavior == AllocatorBehavior::Zeroing {
+            self.memory.write_repeat(ptr.into(), 0, size)?;
+        }
+        Ok(ptr)
+    }
+
+    fn rust_dealloc(&mut self, ptr: MemoryPointer, size: u64, align: u64) -> EvalResult<'tcx> {
+        let align = self.actual_align(ptr, size, align)?;
+        self.memory.deallocate(ptr, Some((size, align)), MemoryKind::Rust.into())
+    }
+
+    fn rust_realloc(
+        &mut self,
+        ptr: MemoryPointer,
+        old_size: u64,
+        old_align: u64,
+        new_size: u64,
+        new_align: u64,
+    ) -> EvalResult<'tcx, MemoryPointer> {
+        let behavior = self.machine_data.config.allocator;
+        let old_actual_align = self.actual_align(ptr, old_size, old_align)?;
+        let new_actual_align = behavior.align(new_align, self.memory.pointer_size());
+        let new_ptr = self.memory.reallocate(
+            ptr,
+            old_size,
+            old_actual_align,
+            new_size,
+            new_actual_align,
+            MemoryKind::Rust.into(),
+        )?;
+        if new_actual_align != new_align {
+            self.machine_data.requested_aligns.insert(new_ptr.alloc_id, new_align);
+        }
+        if behavior == AllocatorBehavior::Zeroing && new_size > old_size {
+            let grown = new_ptr.offset(old_size, &self)?;
+            self.memory.write_repeat(grown.into(), 0, new_size - old_size)?;
+        }
+        Ok(new_ptr)
+    }
+
+    fn actual_align(&mut self, ptr: MemoryPointer, size: u64, align: u64) -> EvalResult<'tcx, u64> {
+        // The alignment was only raised if it was recorded here, `Memory` checks all others.
+        match self.machine_data.requested_aligns.get(&ptr.alloc_id).cloned() {
+            Some(requested) if requested != align => {
+                let allocation_size = self.memory.get(ptr.alloc_id)?.bytes.len() as u64;
+                Err(EvalErrorKind::IncorrectAllocationInformation {
+                    size,
+                    align,
+                    allocation_size,
+                    allocation_align: requested,
+                }.into())
+            }
+            Some(_) => {
+                self.machine_data.requested_aligns.remove(&ptr.alloc_id);
+                Ok(self.memory.get(ptr.alloc_id)?.align)
+            }
+            None => Ok(align),
+        }
+    }
+}
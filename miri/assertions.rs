@@ -0,0 +1,157 @@
+//! Extern functions that interpreted programs can call to assert facts about the state of the
+//! machine, like whether some memory is initialized. They let tests check what miri knows about a
+//! program, not only what the program can observe itself.
+//!
+//! A program declares the hooks it uses in an `extern "C"` block:
+//!
+//! ```rust,ignore
+//! extern "C" {
+//!     fn miri_assert_defined(ptr: *const u8, len: usize);
+//!     fn miri_assert_undefined(ptr: *const u8, len: usize);
+//!     fn miri_assert_live(ptr: *const u8);
+//!     fn miri_assert_freed(ptr: *const u8);
+//!     fn miri_write_protect(ptr: *const u8);
+//!     fn miri_write_unprotect(ptr: *const u8);
+//!     fn miri_current_function(len: *mut usize) -> *const u8;
+//!     fn miri_current_location(len: *mut usize) -> *const u8;
+//! }
+//! ```
+//!
+//! `miri_write_protect` makes the whole allocation `ptr` points into read-only until
+//! `miri_write_unprotect` is called for it, so a test can check that a function it hands the
+//! memory to does not write to it.
+//!
+//! `miri_current_function` and `miri_current_location` return the path of the calling function
+//! and the `file:line:column` of the call as UTF-8 strings, and store their length in `len`. The
+//! strings are never freed, and asking for the same string twice returns the same pointer.
+
+use std::error::Error;
+use std::fmt;
+
+use rustc::ty::Ty;
+use rustc_miri::interpret::{EvalContext, EvalError, EvalErrorKind, EvalResult, Lvalue, MemoryKind,
+                            MemoryPointer, PrimVal, ValTy};
+use syntax::ast::Mutability;
+
+use super::Evaluator;
+
+pub const HOOKS: &[&str] = &[
+    "miri_assert_defined",
+    "miri_assert_undefined",
+    "miri_assert_live",
+    "miri_assert_freed",
+    "miri_write_protect",
+    "miri_write_unprotect",
+    "miri_current_function",
+    "miri_current_location",
+];
+
+#[derive(Clone, Debug)]
+struct AssertionFailed(String);
+
+impl fmt::Display for AssertionFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "miri assertion failed: {}", self.0)
+    }
+}
+
+impl Error for AssertionFailed {
+    fn description(&self) -> &str {
+        "miri assertion failed"
+    }
+}
+
+fn fail<'tcx>(msg: String) -> EvalResult<'tcx> {
+    Err(EvalErrorKind::MachineError(Box::new(AssertionFailed(msg))).into())
+}
+
+/// Checks the assertion `name`, which must be one of `HOOKS`. Only the hooks returning something
+/// write to `dest`.
+pub fn check<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let ptr = args[0].into_ptr(&ecx.memory)?.to_ptr()?;
+    match name {
+        "miri_assert_defined" | "miri_assert_undefined" => {
+            let len = ecx.value_to_primval(args[1])?.to_u64()?;
+            let expected = name == "miri_assert_defined";
+            let defined = range_defined(ecx, ptr, len, expected)?;
+            if defined != expected {
+                let what = if expected { "defined" } else { "undefined" };
+                return fail(format!("{} bytes at {:?} are not all {}", len, ptr, what));
+            }
+        }
+        "miri_assert_live" | "miri_assert_freed" => {
+            let live = match ecx.memory.get(ptr.alloc_id) {
+                Ok(_) => true,
+                Err(EvalError { kind: EvalErrorKind::DanglingPointerDeref(_), .. }) |
+                Err(EvalError { kind: EvalErrorKind::DanglingLocalDeref(_), .. }) => false,
+                Err(e) => return Err(e),
+            };
+            if live && name == "miri_assert_freed" {
+                return fail(format!("allocation {} has not been freed", ptr.alloc_id));
+            }
+            if !live && name == "miri_assert_live" {
+                return fail(format!("allocation {} has been freed", ptr.alloc_id));
+            }
+        }
+        "miri_write_protect" => {
+            let read_only = {
+                let alloc = ecx.memory.get(ptr.alloc_id)?;
+                alloc.kind == MemoryKind::Static || alloc.mutable == Mutability::Immutable
+            };
+            if read_only {
+                return fail(format!("allocation {} is read-only already", ptr.alloc_id));
+            }
+            ecx.memory.set_mutability(ptr.alloc_id, Mutability::Immutable)?;
+            ecx.machine_data.write_protected.insert(ptr.alloc_id);
+        }
+        "miri_write_unprotect" => {
+            if !ecx.machine_data.write_protected.remove(&ptr.alloc_id) {
+                return fail(format!("allocation {} is not write-protected", ptr.alloc_id));
+            }
+            ecx.memory.set_mutability(ptr.alloc_id, Mutability::Mutable)?;
+        }
+        "miri_current_function" | "miri_current_location" => {
+            let string = {
+                // The hook has no frame of its own, so the topmost one is the caller's.
+                let frame = ecx.frame();
+                if name == "miri_current_function" {
+                    ecx.tcx.item_path_str(frame.instance.def_id())
+                } else {
+                    let loc = ecx.tcx.sess.codemap().lookup_char_pos(frame.cur_span().lo());
+                    format!("{}:{}:{}", loc.file.name, loc.line, loc.col.0 + 1)
+                }
+            };
+            let len = PrimVal::from_u128(string.len() as u128);
+            let string_ptr = ecx.memory.allocate_cached(string.as_bytes())?;
+            ecx.memory.write_ptr_sized_unsigned(ptr, len)?;
+            ecx.write_primval(dest, PrimVal::Ptr(string_ptr), dest_ty)?;
+        }
+        _ => bug!("unknown miri assertion hook `{}`", name),
+    }
+    Ok(())
+}
+
+/// Whether every byte (if `all` is set) or any byte (otherwise) in the range is defined.
+fn range_defined<'a, 'tcx>(
+    ecx: &EvalContext<'a, 'tcx, Evaluator>,
+    ptr: MemoryPointer,
+    len: u64,
+    all: bool,
+) -> EvalResult<'tcx, bool> {
+    ecx.memory.check_range_bounds(ptr, len)?;
+    let alloc = ecx.memory.get(ptr.alloc_id)?;
+    let mut defined = (ptr.offset..ptr.offset + len).map(|i| {
+        alloc.undef_mask.is_range_defined(i, i + 1)
+    });
+    Ok(if all {
+        defined.all(|d| d)
+    } else {
+        defined.any(|d| d)
+    })
+}
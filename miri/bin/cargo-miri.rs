@@ -9,6 +9,7 @@ const CARGO_MIRI_HELP: &str = r#"Interprets bin crates
 
 Usage:
     cargo miri [options] [--] [<opts>...]
+    cargo miri setup
 
 Common options:
     -h, --help               Print this message
@@ -23,6 +24,9 @@ it to configure the resource limits
     #![cfg_attr(feature = "cargo-miri", memory_size = 42)]
 
 available resource limits are `memory_size`, `step_limit`, `stack_limit`
+
+`cargo miri setup` builds a libstd with MIR using xargo. Once it exists, `cargo miri` interprets
+calls into libstd instead of stopping at them.
 "#;
 
 fn show_help() {
@@ -47,6 +51,11 @@ fn main() {
     if let Some("miri") = std::env::args().nth(1).as_ref().map(AsRef::as_ref) {
         // this arm is when `cargo miri` is called
 
+        if std::env::args().nth(2).map_or(false, |arg| arg == "setup") {
+            setup();
+            return;
+        }
+
         let test = std::env::args().nth(2).map_or(false, |text| text == "test");
         let skip = if test { 3 } else { 2 };
 
@@ -121,7 +130,10 @@ fn main() {
 
         let home = option_env!("RUSTUP_HOME").or(option_env!("MULTIRUST_HOME"));
         let toolchain = option_env!("RUSTUP_TOOLCHAIN").or(option_env!("MULTIRUST_TOOLCHAIN"));
-        let sys_root = if let (Some(home), Some(toolchain)) = (home, toolchain) {
+        let sys_root = if let Some(sys_root) = mir_sys_root() {
+            // Dependencies have to be built against the same libstd as the interpreted crate.
+            sys_root
+        } else if let (Some(home), Some(toolchain)) = (home, toolchain) {
             format!("{}/toolchains/{}", home, toolchain)
         } else {
             option_env!("RUST_SYSROOT")
@@ -177,6 +189,70 @@ fn main() {
     }
 }
 
+/// Where `cargo miri setup` puts its sysroot, and xargo the sources it builds it from.
+fn setup_dir() -> PathBuf {
+    std::env::home_dir()
+        .expect("could not find the home directory")
+        .join(".cache")
+        .join("miri")
+}
+
+/// The sysroot with a libstd that has MIR, from `MIRI_SYSROOT` or `cargo miri setup`.
+fn mir_sys_root() -> Option<String> {
+    if let Ok(sys_root) = std::env::var("MIRI_SYSROOT") {
+        return Some(sys_root);
+    }
+    let sys_root = setup_dir().join("HOST");
+    if sys_root.is_dir() {
+        sys_root.to_str().map(|s| s.to_owned())
+    } else {
+        None
+    }
+}
+
+/// Builds libstd and its dependencies with MIR in their metadata. xargo only builds a sysroot
+/// for a crate, so an empty one is created for it, with the same configuration as `xargo/`.
+fn setup() {
+    let dir = setup_dir();
+    let files = [
+        ("Cargo.toml", include_str!("../../xargo/Cargo.toml")),
+        ("Xargo.toml", include_str!("../../xargo/Xargo.toml")),
+        ("src/lib.rs", include_str!("../../xargo/src/lib.rs")),
+    ];
+    for &(name, contents) in &files {
+        let path = dir.join(name);
+        let written = std::fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| std::fs::File::create(&path))
+            .and_then(|mut file| file.write_all(contents.as_bytes()));
+        if let Err(e) = written {
+            let msg = format!("error: could not write {}: {}", path.display(), e);
+            let _ = writeln!(std::io::stderr(), "{}", msg);
+            std::process::exit(1);
+        }
+    }
+
+    let status = Command::new("xargo")
+        .arg("build")
+        .current_dir(&dir)
+        .env("XARGO_HOME", &dir)
+        .env("RUSTFLAGS", "-Zalways-encode-mir -Zmir-emit-validate=1")
+        .status();
+    match status {
+        Ok(status) if status.success() => {
+            println!("A libstd with MIR was built in {}", dir.join("HOST").display());
+        }
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(e) => {
+            let _ = writeln!(
+                std::io::stderr(),
+                "error: could not run xargo ({}), install it with `cargo install xargo`",
+                e,
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
 fn process<I>(old_args: I) -> Result<(), i32>
 where
     I: Iterator<Item = String>,
@@ -16,12 +16,14 @@ use rustc_driver::{Compilation, CompilerCalls, RustcDefaultCalls};
 use rustc_driver::driver::{CompileState, CompileController};
 use rustc::session::config::{self, Input, ErrorOutputType};
 use rustc::hir::{self, itemlikevisit};
+use rustc::hir::def_id::DefId;
 use rustc::ty::TyCtxt;
 use syntax::ast::{self, MetaItemKind, NestedMetaItemKind};
 use std::path::PathBuf;
 
 struct MiriCompilerCalls {
     default: RustcDefaultCalls,
+    config: miri::MiriConfig,
 }
 
 impl<'a> CompilerCalls<'a> for MiriCompilerCalls {
@@ -77,7 +79,9 @@ impl<'a> CompilerCalls<'a> for MiriCompilerCalls {
     ) -> CompileController<'a> {
         let mut control = self.default.build_controller(sess, matches);
         control.after_hir_lowering.callback = Box::new(after_hir_lowering);
-        control.after_analysis.callback = Box::new(after_analysis);
+        let config = self.config.clone();
+        control.after_analysis.callback =
+            Box::new(move |state| after_analysis(state, config.clone()));
         if sess.target.target != sess.host {
             // only fully compile targets on the host. linking will fail for cross-compilation.
             control.after_analysis.stop = Compilation::Stop;
@@ -94,18 +98,14 @@ fn after_hir_lowering(state: &mut CompileState) {
     state.session.plugin_attributes.borrow_mut().push(attr);
 }
 
-fn after_analysis<'a, 'tcx>(state: &mut CompileState<'a, 'tcx>) {
+fn after_analysis<'a, 'tcx>(state: &mut CompileState<'a, 'tcx>, mut config: miri::MiriConfig) {
     state.session.abort_if_errors();
 
     let tcx = state.tcx.unwrap();
-    let limits = resource_limits_from_attributes(state);
+    let limits = resource_limits_from_attributes(state, &mut config);
 
     if std::env::args().any(|arg| arg == "--test") {
-        struct Visitor<'a, 'tcx: 'a>(
-            miri::ResourceLimits,
-            TyCtxt<'a, 'tcx, 'tcx>,
-            &'a CompileState<'a, 'tcx>
-        );
+        struct Visitor<'a, 'tcx: 'a>(TyCtxt<'a, 'tcx, 'tcx>, Vec<DefId>);
         impl<'a, 'tcx: 'a, 'hir> itemlikevisit::ItemLikeVisitor<'hir> for Visitor<'a, 'tcx> {
             fn visit_item(&mut self, i: &'hir hir::Item) {
                 if let hir::Item_::ItemFn(_, _, _, _, _, body_id) = i.node {
@@ -113,32 +113,31 @@ fn after_analysis<'a, 'tcx>(state: &mut CompileState<'a, 'tcx>) {
                         attr.name().map_or(false, |n| n == "test")
                     })
                     {
-                        let did = self.1.hir.body_owner_def_id(body_id);
-                        println!(
-                            "running test: {}",
-                            self.1.hir.def_path(did).to_string(self.1)
-                        );
-                        miri::eval_main(self.1, did, None, self.0);
-                        self.2.session.abort_if_errors();
+                        let did = self.0.hir.body_owner_def_id(body_id);
+                        self.1.push(did);
                     }
                 }
             }
             fn visit_trait_item(&mut self, _trait_item: &'hir hir::TraitItem) {}
             fn visit_impl_item(&mut self, _impl_item: &'hir hir::ImplItem) {}
         }
-        state.hir_crate.unwrap().visit_all_item_likes(
-            &mut Visitor(limits, tcx, state),
-        );
+        let mut tests = Visitor(tcx, Vec::new());
+        state.hir_crate.unwrap().visit_all_item_likes(&mut tests);
+        // All tests run in one interpreter, sharing what it computed for earlier ones.
+        miri::eval_batch(tcx, &tests.1, limits, config);
+        state.session.abort_if_errors();
     } else if let Some((entry_node_id, _)) = *state.session.entry_fn.borrow() {
         let entry_def_id = tcx.hir.local_def_id(entry_node_id);
+        // A `#[start]` function replaces the start lang item.
+        let is_main = state.session.entry_type.get() == Some(config::EntryMain);
         let start_wrapper = tcx.lang_items().start_fn().and_then(|start_fn| {
-            if tcx.is_mir_available(start_fn) {
+            if is_main && tcx.is_mir_available(start_fn) {
                 Some(start_fn)
             } else {
                 None
             }
         });
-        miri::eval_main(tcx, entry_def_id, start_wrapper, limits);
+        miri::eval_main(tcx, entry_def_id, start_wrapper, limits, config);
 
         state.session.abort_if_errors();
     } else {
@@ -146,7 +145,12 @@ fn after_analysis<'a, 'tcx>(state: &mut CompileState<'a, 'tcx>) {
     }
 }
 
-fn resource_limits_from_attributes(state: &CompileState) -> miri::ResourceLimits {
+/// The resource limits set with `#![miri(...)]` attributes. The other settings of the attributes
+/// are applied to `config`.
+fn resource_limits_from_attributes(
+    state: &CompileState,
+    config: &mut miri::MiriConfig,
+) -> miri::ResourceLimits {
     let mut limits = miri::ResourceLimits::default();
     let krate = state.hir_crate.as_ref().unwrap();
     let err_msg = "miri attributes need to be in the form `miri(key = value)`";
@@ -162,6 +166,18 @@ fn resource_limits_from_attributes(state: &CompileState) -> miri::ResourceLimits
         }
     };
 
+    let extract_bool = |lit: &syntax::ast::Lit| -> bool {
+        match lit.node {
+            syntax::ast::LitKind::Bool(b) => b,
+            _ => {
+                state.session.span_fatal(
+                    lit.span,
+                    "expected a boolean literal",
+                )
+            }
+        }
+    };
+
     for attr in krate.attrs.iter().filter(|a| {
         a.name().map_or(false, |n| n == "miri")
     })
@@ -174,6 +190,7 @@ fn resource_limits_from_attributes(state: &CompileState) -> miri::ResourceLimits
                             "memory_size" => limits.memory_size = extract_int(value) as u64,
                             "step_limit" => limits.step_limit = extract_int(value) as u64,
                             "stack_limit" => limits.stack_limit = extract_int(value) as usize,
+                            "overflow_checks" => config.overflow_checks = Some(extract_bool(value)),
                             _ => state.session.span_err(item.span, "unknown miri attribute"),
                         }
                     } else {
@@ -250,6 +267,49 @@ fn main() {
     init_logger();
     let mut args: Vec<String> = std::env::args().collect();
 
+    if args.len() == 2 && args[1] == "--print-capabilities" {
+        miri::print_capabilities();
+        return;
+    }
+
+    if args.len() == 3 && args[1] == "--print-core-dump" {
+        if let Err(e) = miri::print_core_dump(&args[2]) {
+            eprintln!("could not read core dump `{}`: {}", args[2], e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut config = miri::MiriConfig::default();
+    // Everything after `--` is passed to the evaluated program.
+    if let Some(dashes) = args.iter().position(|arg| arg == "--") {
+        config.args = args.split_off(dashes + 1);
+        args.pop();
+    }
+    // The flags in `MIRIFLAGS` come before those on the command line, which thus win.
+    if let Ok(flags) = std::env::var("MIRIFLAGS") {
+        let rest = args.split_off(1);
+        args.extend(flags.split_whitespace().map(str::to_owned));
+        args.extend(rest);
+    }
+    // Our own flags must not reach rustc, which would reject them.
+    let mut invalid_flag = None;
+    args.retain(|arg| match miri::parse_flag(&mut config, arg) {
+        Ok(is_miri_flag) => !is_miri_flag,
+        Err(msg) => {
+            invalid_flag = Some(msg);
+            false
+        }
+    });
+    if let Some(msg) = invalid_flag {
+        eprintln!("{}", msg);
+        std::process::exit(1);
+    }
+    if let Err(msg) = miri::read_env(&mut config) {
+        eprintln!("{}", msg);
+        std::process::exit(1);
+    }
+
     let sysroot_flag = String::from("--sysroot");
     if !args.contains(&sysroot_flag) {
         args.push(sysroot_flag);
@@ -261,5 +321,6 @@ fn main() {
 
     rustc_driver::run_compiler(&args, &mut MiriCompilerCalls {
         default: RustcDefaultCalls,
+        config,
     }, None, None);
 }
@@ -0,0 +1,56 @@
+//! A machine-readable description of what this build of miri supports, so tools driving miri
+//! (like cargo-miri) can adapt to it without parsing help texts. `miri --print-capabilities`
+//! prints it as a JSON object:
+//!
+//! ```json
+//! {
+//!   "version": "0.1.0",
+//!   "intrinsics": ["add_with_overflow", ...],
+//!   "targets": [{"os": "linux", "family": "unix", "foreign_fns": ["close", ...]}, ...],
+//!   "flags": ["-Zmiri-disable-isolation", ...],
+//!   "env_vars": ["MIRI_CORE_DUMP", ...]
+//! }
+//! ```
+
+use assertions;
+use config::{ENV_VARS, FLAGS};
+use fn_call::{BUILTIN_FNS, DIVERGING_FOREIGN_FNS};
+use intrinsic::INTRINSICS;
+use rustc_miri::interpret::Json;
+use shims;
+
+/// The operating systems with shims, and whether they are Unix ones.
+const TARGETS: &[(&str, bool)] = &[("linux", true), ("macos", true), ("windows", false)];
+
+fn strings(strs: &[&str]) -> Json {
+    Json::Array(strs.iter().map(|s| Json::str(s)).collect())
+}
+
+pub fn capabilities() -> Json {
+    let targets = TARGETS
+        .iter()
+        .map(|&(os, unix)| {
+            let mut foreign_fns = shims::names(os, unix);
+            foreign_fns.extend(BUILTIN_FNS);
+            foreign_fns.extend(DIVERGING_FOREIGN_FNS.iter().map(|&(name, _)| name));
+            foreign_fns.extend(assertions::HOOKS);
+            foreign_fns.sort();
+            Json::object(vec![
+                ("os", Json::str(os)),
+                ("family", Json::str(if unix { "unix" } else { "windows" })),
+                ("foreign_fns", strings(&foreign_fns)),
+            ])
+        })
+        .collect();
+    Json::object(vec![
+        ("version", Json::str(env!("CARGO_PKG_VERSION"))),
+        ("intrinsics", strings(INTRINSICS)),
+        ("targets", Json::Array(targets)),
+        ("flags", strings(FLAGS)),
+        ("env_vars", strings(ENV_VARS)),
+    ])
+}
+
+pub fn print_capabilities() {
+    println!("{}", capabilities());
+}
@@ -0,0 +1,161 @@
+//! The flags and environment variables configuring miri. `parse_flag` and `read_env` apply them
+//! to a `MiriConfig`, driven by the tables here that `--print-capabilities` reports, so the two
+//! cannot disagree.
+
+use std::env;
+
+use super::{AllocatorBehavior, MiriConfig, SeverityConfig};
+
+/// The flags `miri` accepts on top of those of rustc. Flags taking a value end in `=`.
+pub const FLAGS: &[&str] = &[
+    "-Zmiri-disable-isolation",
+    "-Zmiri-disable-validation",
+    "-Zmiri-ignore-leaks",
+    "-Zmiri-tolerate-undef-ints",
+    "-Zmiri-undef-int-garbage",
+    "-Zmiri-randomize-addresses",
+    "-Zmiri-survey",
+    "-Zmiri-check-reachable",
+    "-Zmiri-check-references",
+    "-Zmiri-check-aliasing",
+    "-Zmiri-seed=",
+    "-Zmiri-memory-limit=",
+    "-Zmiri-stack-limit=",
+    "-Zmiri-allocator=",
+    "-Zmiri-step-limit=",
+    "-Zmiri-skip-asm=",
+];
+
+/// The environment variables configuring the evaluation. `MIRI_LOG`, `MIRI_SYSROOT` and
+/// `MIRIFLAGS` are read by the driver before there is a configuration.
+pub const ENV_VARS: &[&str] = &[
+    "MIRI_CORE_DUMP",
+    "MIRI_DAP_PORT",
+    "MIRI_EVENTS",
+    "MIRI_GENERATE_SUPPRESSIONS",
+    "MIRI_HEATMAP",
+    "MIRI_HEATMAP_BUCKET",
+    "MIRI_LOG",
+    "MIRI_SEVERITY",
+    "MIRI_SUPPRESSIONS",
+    "MIRI_SYSROOT",
+    "MIRIFLAGS",
+];
+
+/// Applies `arg` to `config` if it is one of `FLAGS`. Returns false for the flags of rustc.
+pub fn parse_flag(config: &mut MiriConfig, arg: &str) -> Result<bool, String> {
+    let flag = FLAGS.iter().cloned().find(|flag| if flag.ends_with('=') {
+        arg.starts_with(flag)
+    } else {
+        arg == *flag
+    });
+    let flag = match flag {
+        Some(flag) => flag,
+        None => return Ok(false),
+    };
+    let value = &arg[flag.len()..];
+    match flag {
+        "-Zmiri-disable-isolation" => config.isolation = false,
+        "-Zmiri-disable-validation" => config.validate = false,
+        "-Zmiri-ignore-leaks" => config.ignore_leaks = true,
+        "-Zmiri-tolerate-undef-ints" => config.tolerate_undef_ints = true,
+        "-Zmiri-undef-int-garbage" => {
+            config.tolerate_undef_ints = true;
+            config.undef_int_garbage = true;
+        }
+        "-Zmiri-randomize-addresses" => config.randomize_addresses = true,
+        "-Zmiri-survey" => config.survey = true,
+        "-Zmiri-check-reachable" => config.check_reachable = true,
+        "-Zmiri-check-references" => config.check_references = true,
+        "-Zmiri-check-aliasing" => config.check_aliasing = true,
+        "-Zmiri-seed=" => {
+            config.seed = value.parse().map_err(|_| {
+                format!("`{}` is not a valid seed, it has to be an unsigned 64-bit integer", arg)
+            })?;
+        }
+        "-Zmiri-memory-limit=" => {
+            let limit = value.parse().map_err(|_| {
+                format!("`{}` is not a valid memory limit, it has to be a number of bytes", arg)
+            })?;
+            config.memory_limit = Some(limit);
+        }
+        "-Zmiri-stack-limit=" => {
+            let limit = value.parse().map_err(|_| {
+                format!("`{}` is not a valid stack limit, it has to be a number of frames", arg)
+            })?;
+            config.stack_limit = Some(limit);
+        }
+        "-Zmiri-allocator=" => {
+            match AllocatorBehavior::parse(value) {
+                Some(allocator) => config.allocator = allocator,
+                None => {
+                    return Err(format!(
+                        "`{}` is not a known allocator, use `exact`, `system` or `zeroing`",
+                        arg,
+                    ))
+                }
+            }
+        }
+        "-Zmiri-skip-asm=" => config.skip_asm.push(value.to_owned()),
+        "-Zmiri-step-limit=" => {
+            // The number cannot contain a `=`, so split at the last one. Without a function path,
+            // the limit is the global one.
+            let mut parts = value.rsplitn(2, '=');
+            let limit = parts.next().and_then(|limit| limit.parse().ok());
+            match (parts.next(), limit) {
+                (Some(path), Some(limit)) => {
+                    config.step_budgets.insert(path.to_owned(), limit);
+                }
+                (None, Some(limit)) => config.step_limit = Some(limit),
+                _ => {
+                    return Err(format!(
+                        "`{}` is not of the form `-Zmiri-step-limit=[<function path>=]<steps>`",
+                        arg,
+                    ))
+                }
+            }
+        }
+        _ => bug!("the flag `{}` is in `FLAGS`, but not parsed", flag),
+    }
+    Ok(true)
+}
+
+/// Applies the environment variables of `ENV_VARS` that are set to `config`.
+pub fn read_env(config: &mut MiriConfig) -> Result<(), String> {
+    for &name in ENV_VARS {
+        if let Ok(value) = env::var(name) {
+            apply_env_var(config, name, value)?;
+        }
+    }
+    Ok(())
+}
+
+fn apply_env_var(config: &mut MiriConfig, name: &str, value: String) -> Result<(), String> {
+    match name {
+        "MIRI_CORE_DUMP" => config.core_dump = Some(value),
+        "MIRI_DAP_PORT" => {
+            let port = value.parse().map_err(|_| {
+                format!("`MIRI_DAP_PORT={}` is not a valid port", value)
+            })?;
+            config.dap_port = Some(port);
+        }
+        "MIRI_EVENTS" => config.events = Some(value),
+        "MIRI_GENERATE_SUPPRESSIONS" => config.generate_suppressions = Some(value),
+        "MIRI_HEATMAP" => config.heatmap = Some(value),
+        "MIRI_HEATMAP_BUCKET" => {
+            let size = value.parse().map_err(|_| {
+                format!("`MIRI_HEATMAP_BUCKET={}` is not a valid number of bytes", value)
+            })?;
+            // A size of 0 means no buckets.
+            config.heatmap_bucket = if size == 0 { None } else { Some(size) };
+        }
+        "MIRI_SEVERITY" => {
+            config.severities = SeverityConfig::parse(&value)
+                .map_err(|e| format!("`MIRI_SEVERITY` is invalid: {}", e))?;
+        }
+        "MIRI_SUPPRESSIONS" => config.suppressions = Some(value),
+        "MIRI_LOG" | "MIRI_SYSROOT" | "MIRIFLAGS" => {}
+        _ => bug!("the environment variable `{}` is in `ENV_VARS`, but not read", name),
+    }
+    Ok(())
+}
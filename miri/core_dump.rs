@@ -0,0 +1,234 @@
+//! Writing the state of the interpreter at the point of an error to a file, and printing such
+//! files, so failures can be analyzed without rerunning the program.
+//!
+//! A dump is written when `MIRI_CORE_DUMP` names a file, and can be displayed with
+//! `miri --print-core-dump <file>`.
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::{self, Read, Write};
+
+use rustc_miri::interpret::{AllocId, EvalContext, EvalError, EvalErrorKind, Frame, Json,
+                            MemoryKind, PrimVal, Value};
+use syntax::ast::Mutability;
+
+use super::Evaluator;
+
+/// We do not want to dump the entire heap of a program that ran for a long time.
+const MAX_ALLOCATIONS: usize = 256;
+
+pub fn write_core_dump<'a, 'tcx>(
+    ecx: &EvalContext<'a, 'tcx, Evaluator>,
+    e: &EvalError<'tcx>,
+    path: &str,
+) -> io::Result<()> {
+    let codemap = ecx.tcx.sess.codemap();
+    let mut roots = Vec::new();
+    if let EvalErrorKind::PointerOutOfBounds { ptr, .. } = e.kind {
+        roots.push(ptr.alloc_id);
+    }
+
+    let frames = ecx.stack()
+        .iter()
+        .map(|frame| {
+            let locals = frame.mir.local_decls
+                .iter_enumerated()
+                .skip(1) // the return pointer has no `Value`
+                .map(|(local, decl)| {
+                    let value = match frame.get_local(local) {
+                        Ok(value) => {
+                            collect_alloc_ids(value, &mut roots);
+                            format!("{:?}", value)
+                        }
+                        Err(_) => "<dead>".to_owned(),
+                    };
+                    let name = decl.name.map_or_else(
+                        || format!("{:?}", local),
+                        |name| name.as_str().to_string(),
+                    );
+                    Json::object(vec![
+                        ("name", Json::str(&name)),
+                        ("type", Json::str(&decl.ty.to_string())),
+                        ("value", Json::str(&value)),
+                    ])
+                })
+                .collect();
+            Json::object(vec![
+                ("function", Json::str(&frame.instance.to_string())),
+                ("span", Json::str(&codemap.span_to_string(frame.cur_span()))),
+                ("locals", Json::Array(locals)),
+            ])
+        })
+        .collect();
+
+    let statement = match ecx.stack().last() {
+        Some(frame) => {
+            Json::object(vec![
+                ("span", Json::str(&codemap.span_to_string(frame.cur_span()))),
+                ("mir", Json::str(&current_statement(frame))),
+            ])
+        }
+        None => Json::Null,
+    };
+
+    let dump = Json::object(vec![
+        ("error", Json::str(&e.to_string())),
+        ("kind", Json::str(&format!("{:?}", e.kind))),
+        ("statement", statement),
+        ("frames", Json::Array(frames)),
+        ("allocations", Json::Array(allocations(ecx, roots))),
+    ]);
+    let mut file = File::create(path)?;
+    writeln!(file, "{}", dump)
+}
+
+fn current_statement(frame: &Frame) -> String {
+    let block = &frame.mir.basic_blocks()[frame.block];
+    if frame.stmt < block.statements.len() {
+        format!("{:?}", block.statements[frame.stmt])
+    } else {
+        format!("{:?}", block.terminator().kind)
+    }
+}
+
+fn collect_alloc_ids(value: Value, ids: &mut Vec<AllocId>) {
+    let mut add = |val: PrimVal| if let PrimVal::Ptr(ptr) = val {
+        ids.push(ptr.alloc_id);
+    };
+    match value {
+        Value::ByRef(ptr) => add(ptr.ptr.into_inner_primval()),
+        Value::ByVal(a) => add(a),
+        Value::ByValPair(a, b) => {
+            add(a);
+            add(b);
+        }
+    }
+}
+
+/// The allocations in `roots`, and everything reachable from them.
+fn allocations<'a, 'tcx>(ecx: &EvalContext<'a, 'tcx, Evaluator>, roots: Vec<AllocId>) -> Vec<Json> {
+    let mut seen: HashSet<AllocId> = roots.iter().cloned().collect();
+    let mut todo: VecDeque<AllocId> = roots.into_iter().collect();
+    let mut result = Vec::new();
+    while let Some(id) = todo.pop_front() {
+        if result.len() == MAX_ALLOCATIONS {
+            break;
+        }
+        let alloc = match ecx.memory.get(id) {
+            Ok(alloc) => alloc,
+            Err(EvalError { kind: EvalErrorKind::DanglingPointerDeref(_), .. }) |
+            Err(EvalError { kind: EvalErrorKind::DanglingLocalDeref(_), .. }) => {
+                result.push(Json::object(vec![
+                    ("id", Json::str(&id.to_string())),
+                    ("kind", Json::str("deallocated")),
+                ]));
+                continue;
+            }
+            // function pointers
+            Err(_) => continue,
+        };
+        let mut bytes = String::new();
+        for (i, byte) in alloc.bytes.iter().enumerate() {
+            if alloc.undef_mask.is_range_defined(i as u64, i as u64 + 1) {
+                write!(bytes, "{:02x}", byte).unwrap();
+            } else {
+                bytes.push_str("__");
+            }
+        }
+        let relocations = alloc.relocations
+            .iter()
+            .map(|(&offset, &target)| {
+                if seen.insert(target) {
+                    todo.push_back(target);
+                }
+                Json::object(vec![
+                    ("offset", Json::Number(offset as f64)),
+                    ("target", Json::str(&target.to_string())),
+                ])
+            })
+            .collect();
+        let kind = match alloc.kind {
+            MemoryKind::Machine(m) => format!("{:?}", m),
+            kind => format!("{:?}", kind),
+        };
+        result.push(Json::object(vec![
+            ("id", Json::str(&id.to_string())),
+            ("kind", Json::str(&kind)),
+            ("align", Json::Number(alloc.align as f64)),
+            ("mutable", Json::Bool(alloc.mutable == Mutability::Mutable)),
+            ("bytes", Json::str(&bytes)),
+            ("relocations", Json::Array(relocations)),
+        ]));
+    }
+    result
+}
+
+/// Pretty-prints a dump written by `write_core_dump`.
+pub fn print_core_dump(path: &str) -> io::Result<()> {
+    let mut content = String::new();
+    File::open(path)?.read_to_string(&mut content)?;
+    let dump = Json::parse(content.trim()).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    })?;
+    let field = |json: &Json, key: &str| -> String {
+        match json.get(key) {
+            Some(&Json::String(ref s)) => s.clone(),
+            Some(other) => other.to_string(),
+            None => String::new(),
+        }
+    };
+    let empty = Vec::new();
+    let list = |json: &Json, key: &str| -> Vec<Json> {
+        json.get(key).and_then(Json::as_array).map_or_else(|| empty.clone(), |a| a.to_vec())
+    };
+
+    println!("error: {}", field(&dump, "error"));
+    if let Some(statement) = dump.get("statement") {
+        println!("  at {}", field(statement, "span"));
+        println!("  in `{}`", field(statement, "mir"));
+    }
+    println!();
+    println!("stack (innermost last):");
+    for (i, frame) in list(&dump, "frames").iter().enumerate() {
+        println!("  #{} {} at {}", i, field(frame, "function"), field(frame, "span"));
+        for local in list(frame, "locals") {
+            println!(
+                "      {}: {} = {}",
+                field(&local, "name"),
+                field(&local, "type"),
+                field(&local, "value")
+            );
+        }
+    }
+    println!();
+    println!("allocations:");
+    for alloc in list(&dump, "allocations") {
+        if field(&alloc, "kind") == "deallocated" {
+            println!("  {} (deallocated)", field(&alloc, "id"));
+            continue;
+        }
+        println!(
+            "  {} ({}, alignment {}{})",
+            field(&alloc, "id"),
+            field(&alloc, "kind"),
+            field(&alloc, "align"),
+            if alloc.get("mutable") == Some(&Json::Bool(false)) { ", immutable" } else { "" }
+        );
+        let bytes = field(&alloc, "bytes");
+        // Print 16 bytes per line, like a hexdump.
+        for (line, chunk) in bytes.as_bytes().chunks(32).enumerate() {
+            let mut text = String::new();
+            for byte in chunk.chunks(2) {
+                text.push_str(::std::str::from_utf8(byte).unwrap_or("??"));
+                text.push(' ');
+            }
+            println!("    {:04x}: {}", line * 16, text.trim_right());
+        }
+        for reloc in list(&alloc, "relocations") {
+            println!("    pointer at offset {} to {}",
+                     field(&reloc, "offset"), field(&reloc, "target"));
+        }
+    }
+    Ok(())
+}
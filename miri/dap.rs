@@ -0,0 +1,398 @@
+//! A server for the Debug Adapter Protocol, so editors like VS Code can step through the
+//! evaluation, set breakpoints and look at the locals of every frame.
+//!
+//! The server is started by setting `MIRI_DAP_PORT`, where port 0 picks a free one. Miri prints
+//! the port it listens on to stderr and waits for a single client to connect before it starts
+//! evaluating. The client may only change breakpoints while the
+//! program is stopped.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+
+use rustc_miri::interpret::{EvalContext, EvalError, Frame, Json};
+
+use super::Evaluator;
+
+/// What the debugger is waiting for before it stops the program again.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Mode {
+    /// Stop at the next breakpoint.
+    Continue,
+    /// Stop as soon as we reach a different line.
+    StepIn,
+    /// Stop as soon as we reach a different line in this frame or one of its callers.
+    Next { depth: usize },
+    /// Stop as soon as the frame at this depth returned.
+    StepOut { depth: usize },
+}
+
+pub struct Debugger {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    seq: u64,
+    mode: Mode,
+    breakpoints: HashMap<String, HashSet<usize>>,
+    /// File, line and stack depth of the previous step.
+    last_location: Option<(String, usize, usize)>,
+    /// Set when the client went away; from then on we just keep running.
+    detached: bool,
+}
+
+impl Debugger {
+    /// Waits for a client on `port` and performs the configuration handshake with it.
+    pub fn listen(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let port = listener.local_addr()?.port();
+        eprintln!("miri: waiting for a debug adapter client on port {}", port);
+        let (stream, _) = listener.accept()?;
+        let mut debugger = Debugger {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            seq: 0,
+            mode: Mode::Continue,
+            breakpoints: HashMap::new(),
+            last_location: None,
+            detached: false,
+        };
+        debugger.configure()?;
+        Ok(debugger)
+    }
+
+    /// Handles requests until the client signals that it is done configuring.
+    fn configure(&mut self) -> io::Result<()> {
+        loop {
+            let request = self.recv()?;
+            let command = request.get("command").and_then(Json::as_str).unwrap_or("");
+            match command {
+                "initialize" => {
+                    let body = Json::object(vec![
+                        ("supportsConfigurationDoneRequest", Json::Bool(true)),
+                    ]);
+                    self.respond(&request, true, body)?;
+                    self.event("initialized", Json::object(vec![]))?;
+                }
+                "launch" | "attach" => {
+                    let stop_on_entry = request
+                        .get("arguments")
+                        .and_then(|args| args.get("stopOnEntry"))
+                        .and_then(Json::as_bool)
+                        .unwrap_or(false);
+                    if stop_on_entry {
+                        self.mode = Mode::StepIn;
+                    }
+                    self.respond(&request, true, Json::Null)?;
+                }
+                "setBreakpoints" => self.set_breakpoints(&request)?,
+                "configurationDone" => {
+                    self.respond(&request, true, Json::Null)?;
+                    return Ok(());
+                }
+                "disconnect" => {
+                    self.respond(&request, true, Json::Null)?;
+                    self.detached = true;
+                    return Ok(());
+                }
+                _ => self.respond(&request, true, Json::Null)?,
+            }
+        }
+    }
+
+    /// Called before every step of the evaluation. Blocks while the program is stopped.
+    pub fn before_step<'a, 'tcx>(&mut self, ecx: &EvalContext<'a, 'tcx, Evaluator>) {
+        if self.detached || ecx.stack().is_empty() {
+            return;
+        }
+        let depth = ecx.stack().len();
+        let (file, line, _) = location(ecx, ecx.frame());
+        let location = (file, line, depth);
+        let moved = self.last_location.as_ref().map_or(true, |last| {
+            last.0 != location.0 || last.1 != location.1
+        });
+        let reason = match self.mode {
+            Mode::Continue if moved && self.is_breakpoint(&location.0, line) => Some("breakpoint"),
+            Mode::StepIn if moved => Some("step"),
+            Mode::Next { depth: d } if moved && depth <= d => Some("step"),
+            Mode::StepOut { depth: d } if depth < d => Some("step"),
+            _ => None,
+        };
+        self.last_location = Some(location);
+        if let Some(reason) = reason {
+            let body = Json::object(vec![
+                ("reason", Json::str(reason)),
+                ("threadId", Json::Number(1.0)),
+            ]);
+            let result = self.event("stopped", body).and_then(|()| self.stopped(ecx));
+            self.check_io(result);
+        }
+    }
+
+    /// Called when the evaluation failed, so the user can inspect the state of the program at
+    /// the point where the error happened.
+    pub fn on_error<'a, 'tcx>(&mut self, ecx: &EvalContext<'a, 'tcx, Evaluator>, e: &EvalError) {
+        if self.detached || ecx.stack().is_empty() {
+            return;
+        }
+        let body = Json::object(vec![
+            ("reason", Json::str("exception")),
+            ("description", Json::str(&e.to_string())),
+            ("text", Json::str(&e.to_string())),
+            ("threadId", Json::Number(1.0)),
+        ]);
+        let result = self.event("stopped", body).and_then(|()| self.stopped(ecx));
+        self.check_io(result);
+    }
+
+    /// Tells the client that the program is done.
+    pub fn finish(&mut self, exit_code: i32) {
+        if self.detached {
+            return;
+        }
+        let exited = Json::object(vec![("exitCode", Json::Number(exit_code as f64))]);
+        let result = self.event("exited", exited)
+            .and_then(|()| self.event("terminated", Json::object(vec![])));
+        self.check_io(result);
+    }
+
+    fn check_io(&mut self, result: io::Result<()>) {
+        if let Err(e) = result {
+            warn!("lost connection to the debug adapter client: {}", e);
+            self.detached = true;
+        }
+    }
+
+    fn is_breakpoint(&self, file: &str, line: usize) -> bool {
+        // Clients send absolute paths, while the codemap may contain relative ones.
+        self.breakpoints.iter().any(|(path, lines)| {
+            lines.contains(&line) &&
+                (Path::new(path).ends_with(file) || Path::new(file).ends_with(path))
+        })
+    }
+
+    /// Handles requests while the program is stopped, until the client lets it run again.
+    fn stopped<'a, 'tcx>(&mut self, ecx: &EvalContext<'a, 'tcx, Evaluator>) -> io::Result<()> {
+        loop {
+            let request = self.recv()?;
+            let args = request.get("arguments").cloned().unwrap_or(Json::Null);
+            let command = request.get("command").and_then(Json::as_str).unwrap_or("").to_owned();
+            let depth = ecx.stack().len();
+            match &command[..] {
+                "threads" => {
+                    let thread = Json::object(vec![
+                        ("id", Json::Number(1.0)),
+                        ("name", Json::str("main")),
+                    ]);
+                    let body = Json::object(vec![("threads", Json::Array(vec![thread]))]);
+                    self.respond(&request, true, body)?;
+                }
+                "stackTrace" => {
+                    let frames = ecx.stack()
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .map(|(i, frame)| {
+                            let (file, line, column) = location(ecx, frame);
+                            Json::object(vec![
+                                ("id", Json::Number((i + 1) as f64)),
+                                ("name", Json::str(&frame.instance.to_string())),
+                                ("source", Json::object(vec![("path", Json::str(&file))])),
+                                ("line", Json::Number(line as f64)),
+                                ("column", Json::Number(column as f64)),
+                            ])
+                        })
+                        .collect();
+                    let body = Json::object(vec![
+                        ("stackFrames", Json::Array(frames)),
+                        ("totalFrames", Json::Number(depth as f64)),
+                    ]);
+                    self.respond(&request, true, body)?;
+                }
+                "scopes" => {
+                    let frame_id = args.get("frameId").and_then(Json::as_u64).unwrap_or(0);
+                    let scope = Json::object(vec![
+                        ("name", Json::str("Locals")),
+                        ("variablesReference", Json::Number(frame_id as f64)),
+                        ("expensive", Json::Bool(false)),
+                    ]);
+                    let body = Json::object(vec![("scopes", Json::Array(vec![scope]))]);
+                    self.respond(&request, true, body)?;
+                }
+                "variables" => {
+                    let frame_id = args.get("variablesReference")
+                        .and_then(Json::as_u64)
+                        .unwrap_or(0) as usize;
+                    let variables = match ecx.stack().get(frame_id.wrapping_sub(1)) {
+                        Some(frame) => locals(ecx, frame),
+                        None => Vec::new(),
+                    };
+                    let body = Json::object(vec![("variables", Json::Array(variables))]);
+                    self.respond(&request, true, body)?;
+                }
+                "setBreakpoints" => self.set_breakpoints(&request)?,
+                // Not part of the protocol; lets tools look at the memory of a stopped program.
+                "miriAllocations" => {
+                    let body = Json::object(vec![("allocations", Json::Array(allocations(ecx)))]);
+                    self.respond(&request, true, body)?;
+                }
+                "continue" => {
+                    self.mode = Mode::Continue;
+                    let body = Json::object(vec![("allThreadsContinued", Json::Bool(true))]);
+                    self.respond(&request, true, body)?;
+                    return Ok(());
+                }
+                "next" | "stepIn" | "stepOut" => {
+                    self.mode = match &command[..] {
+                        "next" => Mode::Next { depth },
+                        "stepIn" => Mode::StepIn,
+                        _ => Mode::StepOut { depth },
+                    };
+                    self.respond(&request, true, Json::Null)?;
+                    return Ok(());
+                }
+                "disconnect" | "terminate" => {
+                    self.respond(&request, true, Json::Null)?;
+                    self.detached = true;
+                    return Ok(());
+                }
+                // We are already stopped.
+                "pause" => self.respond(&request, true, Json::Null)?,
+                _ => self.respond(&request, false, Json::Null)?,
+            }
+        }
+    }
+
+    fn set_breakpoints(&mut self, request: &Json) -> io::Result<()> {
+        let args = request.get("arguments").cloned().unwrap_or(Json::Null);
+        let path = args.get("source")
+            .and_then(|source| source.get("path"))
+            .and_then(Json::as_str)
+            .unwrap_or("")
+            .to_owned();
+        let lines: Vec<usize> = args.get("breakpoints")
+            .and_then(Json::as_array)
+            .map(|bps| {
+                bps.iter()
+                    .filter_map(|bp| bp.get("line").and_then(Json::as_u64))
+                    .map(|line| line as usize)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let verified = lines
+            .iter()
+            .map(|&line| {
+                Json::object(vec![
+                    ("verified", Json::Bool(true)),
+                    ("line", Json::Number(line as f64)),
+                ])
+            })
+            .collect();
+        self.breakpoints.insert(path, lines.into_iter().collect());
+        let body = Json::object(vec![("breakpoints", Json::Array(verified))]);
+        self.respond(request, true, body)
+    }
+
+    fn recv(&mut self) -> io::Result<Json> {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            if self.reader.read_line(&mut header)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "client disconnected"));
+            }
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if header.starts_with("Content-Length:") {
+                length = header["Content-Length:".len()..].trim().parse::<usize>().ok();
+            }
+        }
+        let length = length.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+        })?;
+        let mut content = vec![0; length];
+        self.reader.read_exact(&mut content)?;
+        let content = String::from_utf8(content).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, e)
+        })?;
+        Json::parse(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn send(&mut self, mut fields: Vec<(&str, Json)>) -> io::Result<()> {
+        self.seq += 1;
+        fields.insert(0, ("seq", Json::Number(self.seq as f64)));
+        let content = Json::object(fields).to_string();
+        write!(self.writer, "Content-Length: {}\r\n\r\n{}", content.len(), content)?;
+        self.writer.flush()
+    }
+
+    fn respond(&mut self, request: &Json, success: bool, body: Json) -> io::Result<()> {
+        let request_seq = request.get("seq").cloned().unwrap_or(Json::Null);
+        let command = request.get("command").cloned().unwrap_or(Json::Null);
+        self.send(vec![
+            ("type", Json::str("response")),
+            ("request_seq", request_seq),
+            ("success", Json::Bool(success)),
+            ("command", command),
+            ("body", body),
+        ])
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> io::Result<()> {
+        self.send(vec![
+            ("type", Json::str("event")),
+            ("event", Json::str(event)),
+            ("body", body),
+        ])
+    }
+}
+
+/// The source file, line and column that `frame` is currently at.
+fn location<'a, 'tcx>(
+    ecx: &EvalContext<'a, 'tcx, Evaluator>,
+    frame: &Frame<'tcx>,
+) -> (String, usize, usize) {
+    let loc = ecx.tcx.sess.codemap().lookup_char_pos(frame.cur_span().lo());
+    (loc.file.name.to_string(), loc.line, loc.col.0 + 1)
+}
+
+fn allocations<'a, 'tcx>(ecx: &EvalContext<'a, 'tcx, Evaluator>) -> Vec<Json> {
+    ecx.memory
+        .live_allocations()
+        .into_iter()
+        .map(|info| {
+            let loc = ecx.tcx.sess.codemap().lookup_char_pos(info.created_at.lo());
+            Json::object(vec![
+                ("id", Json::str(&info.id.to_string())),
+                ("size", Json::Number(info.size as f64)),
+                ("align", Json::Number(info.align as f64)),
+                ("kind", Json::str(&format!("{:?}", info.kind))),
+                ("label", info.label.map_or(Json::Null, Json::str)),
+                ("source", Json::object(vec![("path", Json::str(&loc.file.name.to_string()))])),
+                ("line", Json::Number(loc.line as f64)),
+            ])
+        })
+        .collect()
+}
+
+fn locals<'a, 'tcx>(ecx: &EvalContext<'a, 'tcx, Evaluator>, frame: &Frame<'tcx>) -> Vec<Json> {
+    frame.mir.local_decls
+        .iter_enumerated()
+        .filter_map(|(local, decl)| {
+            let name = match decl.name {
+                Some(name) => name,
+                None => return None,
+            };
+            let value = match frame.get_local(local) {
+                Ok(value) => ecx.render_value(value, decl.ty),
+                Err(_) => "<dead>".to_owned(),
+            };
+            Some(Json::object(vec![
+                ("name", Json::str(&name.as_str())),
+                ("value", Json::str(&value)),
+                ("type", Json::str(&decl.ty.to_string())),
+                ("variablesReference", Json::Number(0.0)),
+            ]))
+        })
+        .collect()
+}
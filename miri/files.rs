@@ -0,0 +1,244 @@
+//! Shims giving the evaluated program access to files of the host. They are only available if
+//! isolation is disabled, because the program's behavior then depends on the host.
+
+use std::cmp;
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use rustc_miri::interpret::{EvalContext, EvalResult, MemoryPointer, Pointer, PrimVal, ValTy};
+
+use super::Evaluator;
+
+// The flags of `open`, which differ between platforms.
+struct OpenFlags {
+    write_only: u64,
+    read_write: u64,
+    create: u64,
+    truncate: u64,
+    append: u64,
+}
+
+const LINUX_FLAGS: OpenFlags = OpenFlags {
+    write_only: 0o1,
+    read_write: 0o2,
+    create: 0o100,
+    truncate: 0o1000,
+    append: 0o2000,
+};
+
+const MACOS_FLAGS: OpenFlags = OpenFlags {
+    write_only: 0x1,
+    read_write: 0x2,
+    create: 0x200,
+    truncate: 0x400,
+    append: 0x8,
+};
+
+/// The most bytes one `read` copies. Programs have to handle short reads anyway, and this keeps a
+/// huge `count` from making miri allocate that much.
+const MAX_READ: u64 = 1 << 20;
+
+/// Offset and size of the fields of `struct stat`, in the order `stat_fields` returns them in:
+/// `st_dev`, `st_ino`, `st_nlink`, `st_mode`, `st_uid`, `st_gid`, `st_rdev`, `st_size`,
+/// `st_blksize`, `st_blocks`, then seconds and nanoseconds of `st_atime`, `st_mtime` and
+/// `st_ctime`.
+struct StatLayout {
+    size: u64,
+    fields: [(u64, u64); 16],
+}
+
+const LINUX_X86_64_STAT: StatLayout = StatLayout {
+    size: 144,
+    fields: [
+        (0, 8), (8, 8), (16, 8), (24, 4), (28, 4), (32, 4), (40, 8), (48, 8),
+        (56, 8), (64, 8), (72, 8), (80, 8), (88, 8), (96, 8), (104, 8), (112, 8),
+    ],
+};
+
+/// The layout with 64 bit inode numbers, which `stat$INODE64` and `fstat$INODE64` use.
+const MACOS_X86_64_STAT: StatLayout = StatLayout {
+    size: 144,
+    fields: [
+        (0, 4), (8, 8), (6, 2), (4, 2), (16, 4), (20, 4), (24, 4), (96, 8),
+        (112, 4), (104, 8), (32, 8), (40, 8), (48, 8), (56, 8), (64, 8), (72, 8),
+    ],
+};
+
+#[cfg(unix)]
+fn stat_fields(metadata: &Metadata) -> [u64; 16] {
+    use std::os::unix::fs::MetadataExt;
+    let m = metadata;
+    [
+        m.dev(), m.ino(), m.nlink(), m.mode() as u64, m.uid() as u64, m.gid() as u64, m.rdev(),
+        m.size(), m.blksize(), m.blocks(), m.atime() as u64, m.atime_nsec() as u64,
+        m.mtime() as u64, m.mtime_nsec() as u64, m.ctime() as u64, m.ctime_nsec() as u64,
+    ]
+}
+
+/// Other hosts only tell us the type and the size of a file.
+#[cfg(not(unix))]
+fn stat_fields(metadata: &Metadata) -> [u64; 16] {
+    let file_type = if metadata.is_dir() { 0o040000 } else { 0o100000 };
+    let mut fields = [0; 16];
+    fields[3] = file_type | 0o644;
+    fields[7] = metadata.len();
+    fields
+}
+
+pub trait EvalContextExt<'tcx> {
+    /// Errors if the program may not access host files.
+    fn check_file_access(&self, name: &str) -> EvalResult<'tcx>;
+
+    /// Like their libc counterparts, these return -1 if the operation failed.
+    fn open(&mut self, args: &[ValTy<'tcx>]) -> EvalResult<'tcx, i64>;
+    fn read(&mut self, fd: i64, buf: Pointer, count: u64) -> EvalResult<'tcx, i64>;
+    fn write_file(&mut self, fd: i64, buf: Pointer, count: u64) -> EvalResult<'tcx, i64>;
+    fn close(&mut self, fd: i64) -> EvalResult<'tcx, i64>;
+    fn lseek(&mut self, fd: i64, offset: i64, whence: i64) -> EvalResult<'tcx, i64>;
+    fn fstat(&mut self, fd: i64, buf: Pointer) -> EvalResult<'tcx, i64>;
+    fn stat(&mut self, path: Pointer, buf: Pointer) -> EvalResult<'tcx, i64>;
+
+    /// Fills the `struct stat` at `buf` with what the host knows about a file.
+    fn write_stat(&mut self, name: &str, metadata: &Metadata, buf: Pointer) -> EvalResult<'tcx>;
+}
+
+impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, Evaluator> {
+    fn check_file_access(&self, name: &str) -> EvalResult<'tcx> {
+        if self.machine_data.config.isolation {
+            return err!(Unimplemented(format!(
+                "`{}` accesses host files, which requires -Zmiri-disable-isolation",
+                name
+            )));
+        }
+        Ok(())
+    }
+
+    fn open(&mut self, args: &[ValTy<'tcx>]) -> EvalResult<'tcx, i64> {
+        self.check_file_access("open")?;
+        let path_ptr = args[0].into_ptr(&self.memory)?.to_ptr()?;
+        let path = String::from_utf8_lossy(self.memory.read_c_str(path_ptr)?).into_owned();
+        let flags = self.value_to_primval(args[1])?.to_u64()?;
+        let known = if self.tcx.sess.target.target.target_os == "macos" {
+            MACOS_FLAGS
+        } else {
+            LINUX_FLAGS
+        };
+
+        let mut options = OpenOptions::new();
+        let access = flags & (known.write_only | known.read_write);
+        options
+            .read(access != known.write_only)
+            .write(access != 0)
+            .create(flags & known.create != 0)
+            .truncate(flags & known.truncate != 0)
+            .append(flags & known.append != 0);
+        let file = match options.open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("could not open `{}`: {}", path, e);
+                return Ok(-1);
+            }
+        };
+        // Like the kernel, hand out the lowest unused descriptor.
+        let fd = (3..).find(|fd| !self.machine_data.files.contains_key(fd)).unwrap();
+        self.machine_data.files.insert(fd, file);
+        Ok(fd)
+    }
+
+    fn read(&mut self, fd: i64, buf: Pointer, count: u64) -> EvalResult<'tcx, i64> {
+        self.check_file_access("read")?;
+        // Reading less than asked for is fine, writing outside of `buf` is not.
+        if count != 0 {
+            self.memory.check_range_bounds(buf.to_ptr()?, count)?;
+        }
+        let mut bytes = vec![0; cmp::min(count, MAX_READ) as usize];
+        let result = match self.machine_data.files.get_mut(&fd) {
+            Some(file) => file.read(&mut bytes),
+            None => return Ok(-1),
+        };
+        match result {
+            Ok(n) => {
+                self.memory.write_bytes(buf, &bytes[..n])?;
+                Ok(n as i64)
+            }
+            Err(_) => Ok(-1),
+        }
+    }
+
+    fn write_file(&mut self, fd: i64, buf: Pointer, count: u64) -> EvalResult<'tcx, i64> {
+        self.check_file_access("write")?;
+        let bytes = self.memory.read_bytes(buf, count)?;
+        match self.machine_data.files.get_mut(&fd) {
+            Some(file) => Ok(file.write(bytes).map(|n| n as i64).unwrap_or(-1)),
+            None => Ok(-1),
+        }
+    }
+
+    fn close(&mut self, fd: i64) -> EvalResult<'tcx, i64> {
+        self.check_file_access("close")?;
+        match self.machine_data.files.remove(&fd) {
+            Some(_) => Ok(0),
+            None => Ok(-1),
+        }
+    }
+
+    fn lseek(&mut self, fd: i64, offset: i64, whence: i64) -> EvalResult<'tcx, i64> {
+        self.check_file_access("lseek")?;
+        let pos = match whence {
+            0 => SeekFrom::Start(offset as u64),
+            1 => SeekFrom::Current(offset),
+            2 => SeekFrom::End(offset),
+            _ => return Ok(-1),
+        };
+        match self.machine_data.files.get_mut(&fd) {
+            Some(file) => Ok(file.seek(pos).map(|n| n as i64).unwrap_or(-1)),
+            None => Ok(-1),
+        }
+    }
+
+    fn fstat(&mut self, fd: i64, buf: Pointer) -> EvalResult<'tcx, i64> {
+        self.check_file_access("fstat")?;
+        let metadata = match self.machine_data.files.get(&fd).map(File::metadata) {
+            Some(Ok(metadata)) => metadata,
+            _ => return Ok(-1),
+        };
+        self.write_stat("fstat", &metadata, buf)?;
+        Ok(0)
+    }
+
+    fn stat(&mut self, path: Pointer, buf: Pointer) -> EvalResult<'tcx, i64> {
+        self.check_file_access("stat")?;
+        let path_ptr = path.to_ptr()?;
+        let path = String::from_utf8_lossy(self.memory.read_c_str(path_ptr)?).into_owned();
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                warn!("could not stat `{}`: {}", path, e);
+                return Ok(-1);
+            }
+        };
+        self.write_stat("stat", &metadata, buf)?;
+        Ok(0)
+    }
+
+    fn write_stat(&mut self, name: &str, metadata: &Metadata, buf: Pointer) -> EvalResult<'tcx> {
+        let layout = {
+            let target = &self.tcx.sess.target.target;
+            match (&target.target_os[..], &target.arch[..]) {
+                ("linux", "x86_64") => LINUX_X86_64_STAT,
+                ("macos", "x86_64") => MACOS_X86_64_STAT,
+                (os, arch) => {
+                    return err!(Unimplemented(format!("{} on {}-{}", name, arch, os)));
+                }
+            }
+        };
+        let buf: MemoryPointer = buf.to_ptr()?;
+        // Padding and the fields nobody reads are zeroed.
+        self.memory.write_repeat(buf.into(), 0, layout.size)?;
+        for (&(offset, size), value) in layout.fields.iter().zip(stat_fields(metadata).iter()) {
+            let field = buf.offset(offset, &self)?;
+            self.memory.write_primval(field, PrimVal::Bytes(*value as u128), size, false)?;
+        }
+        Ok(())
+    }
+}
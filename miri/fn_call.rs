@@ -1,6 +1,7 @@
 use rustc::ty::{self, Ty};
 use rustc::hir::def_id::{DefId, CRATE_DEF_INDEX};
 use rustc::mir;
+use syntax::ast::Mutability;
 use syntax::attr;
 use syntax::abi::Abi;
 use syntax::codemap::Span;
@@ -9,12 +10,116 @@ use std::mem;
 
 use rustc_miri::interpret::*;
 
-use super::{TlsKey, EvalContext};
+use super::{EvalContext, Evaluator};
 
-use tls::MemoryExt;
+use allocator::EvalContextExt as AllocatorEvalContextExt;
+use panic::EvalContextExt as PanicEvalContextExt;
+use assertions;
+use host_fns;
+use shims;
 
 use super::memory::MemoryKind;
 
+/// The foreign functions `call_foreign_symbol` handles before looking for a shim.
+pub const BUILTIN_FNS: &[&str] = &[
+    "__rust_maybe_catch_panic",
+    "__rust_start_panic",
+    "miri_live_heap_bytes",
+    "miri_peak_heap_bytes",
+];
+
+/// Stands in for a Rust function without MIR that returns, like a `Shim` does for a foreign
+/// function. It gets the path of the function.
+type MissingFn = for<'a, 'tcx> fn(&mut EvalContext<'a, 'tcx, Evaluator>,
+                                  &str,
+                                  &[ValTy<'tcx>],
+                                  Lvalue,
+                                  Ty<'tcx>)
+                                  -> EvalResult<'tcx>;
+
+/// Stands in for a function without MIR that never returns, so it usually has no destination.
+pub type DivergingFn = for<'a, 'tcx> fn(&mut EvalContext<'a, 'tcx, Evaluator>, &[ValTy<'tcx>])
+                                        -> EvalResult<'tcx>;
+
+/// The Rust functions without MIR that `call_missing_fn` stands in for, by path.
+const MISSING_RUST_FNS: &[(&str, MissingFn)] = &[
+    // Allocators are magic.  They have no MIR, even when the rest of libstd does.
+    ("alloc::heap::::__rust_alloc", rust_alloc),
+    ("alloc::heap::::__rust_alloc_zeroed", rust_alloc),
+    ("alloc::heap::::__rust_dealloc", rust_dealloc),
+    ("alloc::heap::::__rust_realloc", rust_realloc),
+    // The rest is only missing when running with MIR missing for libstd (or other dependencies).
+    // Still, we can make many things mostly work by "emulating" or ignoring some functions.
+    ("std::io::_print", ignore_print),
+    ("std::panicking::panicking", panicking),
+    ("std::rt::panicking", panicking),
+    ("std::sys::imp::c::::AddVectoredExceptionHandler", windows_stack_overflow_setup),
+    ("std::sys::imp::c::::SetThreadStackGuarantee", windows_stack_overflow_setup),
+];
+
+/// The Rust functions without MIR that never return, by path.
+const DIVERGING_RUST_FNS: &[(&str, DivergingFn)] = &[
+    ("std::panicking::begin_panic", panic),
+    ("std::panicking::rust_panic_with_hook", panic),
+    ("core::panicking::panic_fmt::::panic_impl", panic),
+    ("std::rt::begin_panic_fmt", panic),
+    ("std::process::exit", exit),
+    ("std::process::abort", abort),
+];
+
+/// The foreign functions that never return, by link name.
+pub const DIVERGING_FOREIGN_FNS: &[(&str, DivergingFn)] = &[
+    ("exit", exit),
+    ("_exit", exit),
+    ("abort", abort),
+];
+
+/// The Rust functions without MIR miri knows it cannot stand in for, with the reason.
+const UNSUPPORTED_RUST_FNS: &[(&str, &str)] = &[
+    ("std::thread::Builder::new", "miri does not support threading"),
+    ("std::env::args", "miri does not support program arguments"),
+];
+
+/// What a call of a foreign function is dispatched to.
+#[derive(Copy, Clone)]
+enum ForeignFn {
+    /// A function of the host, see `host_fns`.
+    Host,
+    /// One of `BUILTIN_FNS`.
+    Builtin,
+    /// One of `assertions::HOOKS`.
+    Hook,
+    /// One of `DIVERGING_FOREIGN_FNS`.
+    Diverging(DivergingFn),
+    /// A shim, see `shims::find_or_stub`.
+    Shim(shims::Shim),
+}
+
+fn find<T: Copy>(table: &[(&str, T)], name: &str) -> Option<T> {
+    table.iter().find(|&&(n, _)| n == name).map(|&(_, f)| f)
+}
+
+fn foreign_fn<'a, 'tcx>(
+    ecx: &EvalContext<'a, 'tcx, Evaluator>,
+    link_name: &str,
+) -> Option<ForeignFn> {
+    if ecx.machine_data.config.host_fns.get(link_name).is_some() {
+        Some(ForeignFn::Host)
+    } else if BUILTIN_FNS.contains(&link_name) {
+        Some(ForeignFn::Builtin)
+    } else if assertions::HOOKS.contains(&link_name) {
+        Some(ForeignFn::Hook)
+    } else if let Some(f) = find(DIVERGING_FOREIGN_FNS, link_name) {
+        Some(ForeignFn::Diverging(f))
+    } else {
+        shims::find_or_stub(ecx.tcx.sess, link_name).map(ForeignFn::Shim)
+    }
+}
+
+fn unknown_foreign_fn<'tcx>(link_name: &str) -> EvalError<'tcx> {
+    EvalErrorKind::Unimplemented(format!("can't call C ABI function: {}", link_name)).into()
+}
+
 pub trait EvalContextExt<'tcx> {
     fn call_c_abi(
         &mut self,
@@ -25,8 +130,41 @@ pub trait EvalContextExt<'tcx> {
         dest_block: mir::BasicBlock,
     ) -> EvalResult<'tcx>;
 
+    /// Calls the shim of the C function `link_name`.
+    fn call_foreign_symbol(
+        &mut self,
+        link_name: &str,
+        args: &[ValTy<'tcx>],
+        dest: Lvalue,
+        dest_ty: Ty<'tcx>,
+        dest_block: mir::BasicBlock,
+    ) -> EvalResult<'tcx>;
+
+    /// The pointer `dlsym` returns for `symbol`, or `None` if there is no shim for it. Calls
+    /// through the pointer end up in `call_foreign_symbol`.
+    fn dlsym(&mut self, symbol: &str) -> EvalResult<'tcx, Option<MemoryPointer>>;
+
+    /// Calls the shim behind a pointer returned by `dlsym`. Returns false for all other pointers.
+    fn call_dlsym_ptr(
+        &mut self,
+        fn_ptr: MemoryPointer,
+        destination: Option<(Lvalue, mir::BasicBlock)>,
+        args: &[ValTy<'tcx>],
+        sig: ty::FnSig<'tcx>,
+    ) -> EvalResult<'tcx, bool>;
+
     fn resolve_path(&self, path: &[&str]) -> EvalResult<'tcx, ty::Instance<'tcx>>;
 
+    /// Copies `value` into a null-terminated allocation owned by the environment.
+    fn alloc_env_var(&mut self, value: &[u8]) -> EvalResult<'tcx, MemoryPointer>;
+
+    /// The message `std::panicking::begin_panic(msg: &str, file_line_col)` would print.
+    fn literal_panic_message(
+        &mut self,
+        instance: ty::Instance<'tcx>,
+        args: &[ValTy<'tcx>],
+    ) -> EvalResult<'tcx, Option<String>>;
+
     fn call_missing_fn(
         &mut self,
         instance: ty::Instance<'tcx>,
@@ -36,6 +174,10 @@ pub trait EvalContextExt<'tcx> {
         path: String,
     ) -> EvalResult<'tcx>;
 
+    /// The error calling `instance`, which has no MIR, would result in, if `call_missing_fn`
+    /// cannot stand in for it either.
+    fn check_missing_fn(&self, instance: ty::Instance<'tcx>) -> EvalResult<'tcx>;
+
     fn eval_fn_call(
         &mut self,
         instance: ty::Instance<'tcx>,
@@ -96,378 +238,64 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
         dest_ty: Ty<'tcx>,
         dest_block: mir::BasicBlock,
     ) -> EvalResult<'tcx> {
-        let attrs = self.tcx.get_attrs(def_id);
-        let link_name = match attr::first_attr_value_str_by_name(&attrs, "link_name") {
-            Some(name) => name.as_str(),
-            None => self.tcx.item_name(def_id),
-        };
-
-        match &link_name[..] {
-            "malloc" => {
-                let size = self.value_to_primval(args[0])?.to_u64()?;
-                if size == 0 {
-                    self.write_null(dest, dest_ty)?;
-                } else {
-                    let align = self.memory.pointer_size();
-                    let ptr = self.memory.allocate(size, align, MemoryKind::C.into())?;
-                    self.write_primval(dest, PrimVal::Ptr(ptr), dest_ty)?;
-                }
-            }
-
-            "free" => {
-                let ptr = args[0].into_ptr(&mut self.memory)?;
-                if !ptr.is_null()? {
-                    self.memory.deallocate(
-                        ptr.to_ptr()?,
-                        None,
-                        MemoryKind::C.into(),
-                    )?;
-                }
-            }
-
-            "syscall" => {
-                // TODO: read `syscall` ids like `sysconf` ids and
-                // figure out some way to actually process some of them
-                //
-                // libc::syscall(NR_GETRANDOM, buf.as_mut_ptr(), buf.len(), GRND_NONBLOCK)
-                // is called if a `HashMap` is created the regular way.
-                match self.value_to_primval(args[0])?.to_u64()? {
-                    318 | 511 => {
-                        return err!(Unimplemented(
-                            "miri does not support random number generators".to_owned(),
-                        ))
-                    }
-                    id => {
-                        return err!(Unimplemented(
-                            format!("miri does not support syscall id {}", id),
-                        ))
-                    }
-                }
-            }
-
-            "dlsym" => {
-                let _handle = args[0].into_ptr(&mut self.memory)?;
-                let symbol = args[1].into_ptr(&mut self.memory)?.to_ptr()?;
-                let symbol_name = self.memory.read_c_str(symbol)?;
-                let err = format!("bad c unicode symbol: {:?}", symbol_name);
-                let symbol_name = ::std::str::from_utf8(symbol_name).unwrap_or(&err);
-                return err!(Unimplemented(format!(
-                    "miri does not support dynamically loading libraries (requested symbol: {})",
-                    symbol_name
-                )));
-            }
-
-            "__rust_maybe_catch_panic" => {
-                // fn __rust_maybe_catch_panic(f: fn(*mut u8), data: *mut u8, data_ptr: *mut usize, vtable_ptr: *mut usize) -> u32
-                // We abort on panic, so not much is going on here, but we still have to call the closure
-                let u8_ptr_ty = self.tcx.mk_mut_ptr(self.tcx.types.u8);
-                let f = args[0].into_ptr(&mut self.memory)?.to_ptr()?;
-                let data = args[1].into_ptr(&mut self.memory)?;
-                let f_instance = self.memory.get_fn(f)?;
-                self.write_null(dest, dest_ty)?;
-
-                // Now we make a function call.  TODO: Consider making this re-usable?  EvalContext::step does sth. similar for the TLS dtors,
-                // and of course eval_main.
-                let mir = self.load_mir(f_instance.def)?;
-                self.push_stack_frame(
-                    f_instance,
-                    mir.span,
-                    mir,
-                    Lvalue::undef(),
-                    StackPopCleanup::Goto(dest_block),
-                )?;
-                let mut args = self.frame().mir.args_iter();
-
-                let arg_local = args.next().ok_or(
-                    EvalErrorKind::AbiViolation(
-                        "Argument to __rust_maybe_catch_panic does not take enough arguments."
-                            .to_owned(),
-                    ),
-                )?;
-                let arg_dest = self.eval_lvalue(&mir::Lvalue::Local(arg_local))?;
-                self.write_ptr(arg_dest, data, u8_ptr_ty)?;
-
-                assert!(args.next().is_none(), "__rust_maybe_catch_panic argument has more arguments than expected");
-
-                // We ourselves return 0
-                self.write_null(dest, dest_ty)?;
+        let link_name = link_name(self.tcx, def_id);
+        self.call_foreign_symbol(&link_name, args, dest, dest_ty, dest_block)
+    }
 
-                // Don't fall through
+    fn call_foreign_symbol(
+        &mut self,
+        link_name: &str,
+        args: &[ValTy<'tcx>],
+        dest: Lvalue,
+        dest_ty: Ty<'tcx>,
+        dest_block: mir::BasicBlock,
+    ) -> EvalResult<'tcx> {
+        match foreign_fn(self, link_name) {
+            Some(ForeignFn::Host) => {
+                let f = self.machine_data.config.host_fns.get(link_name).unwrap();
+                host_fns::call(self, link_name, &*f, args, dest, dest_ty)?;
+                self.goto_block(dest_block);
                 return Ok(());
             }
 
-            "__rust_start_panic" => {
-                return err!(Panic);
-            }
-
-            "memcmp" => {
-                let left = args[0].into_ptr(&mut self.memory)?;
-                let right = args[1].into_ptr(&mut self.memory)?;
-                let n = self.value_to_primval(args[2])?.to_u64()?;
-
-                let result = {
-                    let left_bytes = self.memory.read_bytes(left, n)?;
-                    let right_bytes = self.memory.read_bytes(right, n)?;
-
-                    use std::cmp::Ordering::*;
-                    match left_bytes.cmp(right_bytes) {
-                        Less => -1i8,
-                        Equal => 0,
-                        Greater => 1,
+            Some(ForeignFn::Builtin) => {
+                match link_name {
+                    "__rust_maybe_catch_panic" => {
+                        self.maybe_catch_panic(args, dest, dest_ty, dest_block)?;
+                        // Don't fall through, the closure returns to `dest_block`
+                        return Ok(());
                     }
-                };
-
-                self.write_primval(
-                    dest,
-                    PrimVal::Bytes(result as u128),
-                    dest_ty,
-                )?;
-            }
 
-            "memrchr" => {
-                let ptr = args[0].into_ptr(&mut self.memory)?;
-                let val = self.value_to_primval(args[1])?.to_u64()? as u8;
-                let num = self.value_to_primval(args[2])?.to_u64()?;
-                if let Some(idx) = self.memory.read_bytes(ptr, num)?.iter().rev().position(
-                    |&c| c == val,
-                )
-                {
-                    let new_ptr = ptr.offset(num - idx as u64 - 1, &self)?;
-                    self.write_ptr(dest, new_ptr, dest_ty)?;
-                } else {
-                    self.write_null(dest, dest_ty)?;
-                }
-            }
-
-            "memchr" => {
-                let ptr = args[0].into_ptr(&mut self.memory)?;
-                let val = self.value_to_primval(args[1])?.to_u64()? as u8;
-                let num = self.value_to_primval(args[2])?.to_u64()?;
-                if let Some(idx) = self.memory.read_bytes(ptr, num)?.iter().position(
-                    |&c| c == val,
-                )
-                {
-                    let new_ptr = ptr.offset(idx as u64, &self)?;
-                    self.write_ptr(dest, new_ptr, dest_ty)?;
-                } else {
-                    self.write_null(dest, dest_ty)?;
-                }
-            }
-
-            "getenv" => {
-                let result = {
-                    let name_ptr = args[0].into_ptr(&mut self.memory)?.to_ptr()?;
-                    let name = self.memory.read_c_str(name_ptr)?;
-                    match self.machine_data.env_vars.get(name) {
-                        Some(&var) => PrimVal::Ptr(var),
-                        None => PrimVal::Bytes(0),
+                    "__rust_start_panic" => {
+                        self.start_panic(args)?;
+                        // Don't fall through, we are unwinding
+                        return Ok(());
                     }
-                };
-                self.write_primval(dest, result, dest_ty)?;
-            }
 
-            "unsetenv" => {
-                let mut success = None;
-                {
-                    let name_ptr = args[0].into_ptr(&mut self.memory)?;
-                    if !name_ptr.is_null()? {
-                        let name = self.memory.read_c_str(name_ptr.to_ptr()?)?;
-                        if !name.is_empty() && !name.contains(&b'=') {
-                            success = Some(self.machine_data.env_vars.remove(name));
-                        }
-                    }
-                }
-                if let Some(old) = success {
-                    if let Some(var) = old {
-                        self.memory.deallocate(var, None, MemoryKind::Env.into())?;
+                    "miri_live_heap_bytes" => {
+                        let usage = self.memory.machine_memory_usage();
+                        self.write_primval(dest, PrimVal::from_u128(usage as u128), dest_ty)?;
                     }
-                    self.write_null(dest, dest_ty)?;
-                } else {
-                    self.write_primval(dest, PrimVal::from_i128(-1), dest_ty)?;
-                }
-            }
 
-            "setenv" => {
-                let mut new = None;
-                {
-                    let name_ptr = args[0].into_ptr(&mut self.memory)?;
-                    let value_ptr = args[1].into_ptr(&mut self.memory)?.to_ptr()?;
-                    let value = self.memory.read_c_str(value_ptr)?;
-                    if !name_ptr.is_null()? {
-                        let name = self.memory.read_c_str(name_ptr.to_ptr()?)?;
-                        if !name.is_empty() && !name.contains(&b'=') {
-                            new = Some((name.to_owned(), value.to_owned()));
-                        }
-                    }
-                }
-                if let Some((name, value)) = new {
-                    // +1 for the null terminator
-                    let value_copy = self.memory.allocate(
-                        (value.len() + 1) as u64,
-                        1,
-                        MemoryKind::Env.into(),
-                    )?;
-                    self.memory.write_bytes(value_copy.into(), &value)?;
-                    let trailing_zero_ptr = value_copy.offset(value.len() as u64, &self)?.into();
-                    self.memory.write_bytes(trailing_zero_ptr, &[0])?;
-                    if let Some(var) = self.machine_data.env_vars.insert(
-                        name.to_owned(),
-                        value_copy,
-                    )
-                    {
-                        self.memory.deallocate(var, None, MemoryKind::Env.into())?;
-                    }
-                    self.write_null(dest, dest_ty)?;
-                } else {
-                    self.write_primval(dest, PrimVal::from_i128(-1), dest_ty)?;
-                }
-            }
-
-            "write" => {
-                let fd = self.value_to_primval(args[0])?.to_u64()?;
-                let buf = args[1].into_ptr(&mut self.memory)?;
-                let n = self.value_to_primval(args[2])?.to_u64()?;
-                trace!("Called write({:?}, {:?}, {:?})", fd, buf, n);
-                let result = if fd == 1 || fd == 2 {
-                    // stdout/stderr
-                    use std::io::{self, Write};
-
-                    let buf_cont = self.memory.read_bytes(buf, n)?;
-                    let res = if fd == 1 {
-                        io::stdout().write(buf_cont)
-                    } else {
-                        io::stderr().write(buf_cont)
-                    };
-                    match res {
-                        Ok(n) => n as isize,
-                        Err(_) => -1,
+                    "miri_peak_heap_bytes" => {
+                        let usage = self.memory.peak_machine_memory_usage();
+                        self.write_primval(dest, PrimVal::from_u128(usage as u128), dest_ty)?;
                     }
-                } else {
-                    warn!("Ignored output to FD {}", fd);
-                    n as isize // pretend it all went well
-                }; // now result is the value we return back to the program
-                self.write_primval(
-                    dest,
-                    PrimVal::Bytes(result as u128),
-                    dest_ty,
-                )?;
-            }
-
-            "strlen" => {
-                let ptr = args[0].into_ptr(&mut self.memory)?.to_ptr()?;
-                let n = self.memory.read_c_str(ptr)?.len();
-                self.write_primval(dest, PrimVal::Bytes(n as u128), dest_ty)?;
-            }
-
-            // Some things needed for sys::thread initialization to go through
-            "signal" | "sigaction" | "sigaltstack" => {
-                self.write_primval(dest, PrimVal::Bytes(0), dest_ty)?;
-            }
 
-            "sysconf" => {
-                let name = self.value_to_primval(args[0])?.to_u64()?;
-                trace!("sysconf() called with name {}", name);
-                // cache the sysconf integers via miri's global cache
-                let paths = &[
-                    (&["libc", "_SC_PAGESIZE"], PrimVal::Bytes(4096)),
-                    (&["libc", "_SC_GETPW_R_SIZE_MAX"], PrimVal::from_i128(-1)),
-                ];
-                let mut result = None;
-                for &(path, path_value) in paths {
-                    if let Ok(instance) = self.resolve_path(path) {
-                        let cid = GlobalId {
-                            instance,
-                            promoted: None,
-                        };
-                        // compute global if not cached
-                        let val = match self.globals.get(&cid).cloned() {
-                            Some(ptr) => self.value_to_primval(ValTy { value: Value::ByRef(ptr), ty: args[0].ty })?.to_u64()?,
-                            None => eval_body_as_primval(self.tcx, instance)?.0.to_u64()?,
-                        };
-                        if val == name {
-                            result = Some(path_value);
-                            break;
-                        }
-                    }
-                }
-                if let Some(result) = result {
-                    self.write_primval(dest, result, dest_ty)?;
-                } else {
-                    return err!(Unimplemented(
-                        format!("Unimplemented sysconf name: {}", name),
-                    ));
+                    _ => bug!("`{}` is in `BUILTIN_FNS` but not handled", link_name),
                 }
             }
 
-            // Hook pthread calls that go to the thread-local storage memory subsystem
-            "pthread_key_create" => {
-                let key_ptr = args[0].into_ptr(&mut self.memory)?;
-
-                // Extract the function type out of the signature (that seems easier than constructing it ourselves...)
-                let dtor = match args[1].into_ptr(&mut self.memory)?.into_inner_primval() {
-                    PrimVal::Ptr(dtor_ptr) => Some(self.memory.get_fn(dtor_ptr)?),
-                    PrimVal::Bytes(0) => None,
-                    PrimVal::Bytes(_) => return err!(ReadBytesAsPointer),
-                    PrimVal::Undef => return err!(ReadUndefBytes),
-                };
-
-                // Figure out how large a pthread TLS key actually is. This is libc::pthread_key_t.
-                let key_type = args[0].ty.builtin_deref(true, ty::LvaluePreference::NoPreference)
-                                   .ok_or(EvalErrorKind::AbiViolation("Wrong signature used for pthread_key_create: First argument must be a raw pointer.".to_owned()))?.ty;
-                let key_size = {
-                    let layout = self.type_layout(key_type)?;
-                    layout.size(&self.tcx.data_layout)
-                };
-
-                // Create key and write it into the memory where key_ptr wants it
-                let key = self.memory.create_tls_key(dtor) as u128;
-                if key_size.bits() < 128 && key >= (1u128 << key_size.bits() as u128) {
-                    return err!(OutOfTls);
-                }
-                self.memory.write_primval(
-                    key_ptr.to_ptr()?,
-                    PrimVal::Bytes(key),
-                    key_size.bytes(),
-                    false,
-                )?;
+            Some(ForeignFn::Hook) => assertions::check(self, link_name, args, dest, dest_ty)?,
 
-                // Return success (0)
-                self.write_null(dest, dest_ty)?;
-            }
-            "pthread_key_delete" => {
-                // The conversion into TlsKey here is a little fishy, but should work as long as usize >= libc::pthread_key_t
-                let key = self.value_to_primval(args[0])?.to_u64()? as TlsKey;
-                self.memory.delete_tls_key(key)?;
-                // Return success (0)
-                self.write_null(dest, dest_ty)?;
-            }
-            "pthread_getspecific" => {
-                // The conversion into TlsKey here is a little fishy, but should work as long as usize >= libc::pthread_key_t
-                let key = self.value_to_primval(args[0])?.to_u64()? as TlsKey;
-                let ptr = self.memory.load_tls(key)?;
-                self.write_ptr(dest, ptr, dest_ty)?;
-            }
-            "pthread_setspecific" => {
-                // The conversion into TlsKey here is a little fishy, but should work as long as usize >= libc::pthread_key_t
-                let key = self.value_to_primval(args[0])?.to_u64()? as TlsKey;
-                let new_ptr = args[1].into_ptr(&mut self.memory)?;
-                self.memory.store_tls(key, new_ptr)?;
-
-                // Return success (0)
-                self.write_null(dest, dest_ty)?;
+            Some(ForeignFn::Diverging(f)) => {
+                f(self, args)?;
+                bug!("`{}` returned", link_name);
             }
 
-            // Stub out all the other pthread calls to just return 0
-            link_name if link_name.starts_with("pthread_") => {
-                info!("ignoring C ABI call: {}", link_name);
-                self.write_null(dest, dest_ty)?;
-            }
+            Some(ForeignFn::Shim(shim)) => shim(self, link_name, args, dest, dest_ty)?,
 
-            _ => {
-                return err!(Unimplemented(
-                    format!("can't call C ABI function: {}", link_name),
-                ));
-            }
+            None => return Err(unknown_foreign_fn(link_name)),
         }
 
         // Since we pushed no stack frame, the main loop will act
@@ -478,6 +306,41 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
         Ok(())
     }
 
+    fn dlsym(&mut self, symbol: &str) -> EvalResult<'tcx, Option<MemoryPointer>> {
+        if let Some(&ptr) = self.machine_data.dlsym_ptrs.get(symbol) {
+            return Ok(Some(ptr));
+        }
+        let host_fn = self.machine_data.config.host_fns.get(symbol).is_some();
+        if !host_fn && shims::find(self.tcx.sess, symbol).is_none() {
+            return Ok(None);
+        }
+        // The pointer only has to be unique, nothing ever reads from it.
+        let kind = ::rustc_miri::interpret::MemoryKind::UninitializedStatic;
+        let ptr = self.memory.allocate(1, 1, kind)?;
+        self.memory.mark_static_initalized(ptr.alloc_id, Mutability::Immutable)?;
+        self.machine_data.dlsym_ptrs.insert(symbol.to_owned(), ptr);
+        self.machine_data.dlsym_symbols.insert(ptr.alloc_id, symbol.to_owned());
+        Ok(Some(ptr))
+    }
+
+    fn call_dlsym_ptr(
+        &mut self,
+        fn_ptr: MemoryPointer,
+        destination: Option<(Lvalue, mir::BasicBlock)>,
+        args: &[ValTy<'tcx>],
+        sig: ty::FnSig<'tcx>,
+    ) -> EvalResult<'tcx, bool> {
+        let symbol = match self.machine_data.dlsym_symbols.get(&fn_ptr.alloc_id) {
+            Some(symbol) if fn_ptr.offset == 0 => symbol.clone(),
+            _ => return Ok(false),
+        };
+        let (dest, dest_block) = destination.ok_or_else(
+            || EvalErrorKind::NoMirFor(symbol.clone()),
+        )?;
+        self.call_foreign_symbol(&symbol, args, dest, sig.output(), dest_block)?;
+        Ok(true)
+    }
+
     /// Get an instance for a path.
     fn resolve_path(&self, path: &[&str]) -> EvalResult<'tcx, ty::Instance<'tcx>> {
         self.tcx
@@ -512,145 +375,258 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
             })
     }
 
-    fn call_missing_fn(
+    fn alloc_env_var(&mut self, value: &[u8]) -> EvalResult<'tcx, MemoryPointer> {
+        // +1 for the null terminator
+        let value_copy = self.memory.allocate(
+            (value.len() + 1) as u64,
+            1,
+            MemoryKind::Env.into(),
+        )?;
+        self.memory.write_bytes(value_copy.into(), value)?;
+        let trailing_zero_ptr = value_copy.offset(value.len() as u64, &self)?.into();
+        self.memory.write_bytes(trailing_zero_ptr, &[0])?;
+        Ok(value_copy)
+    }
+
+    fn literal_panic_message(
         &mut self,
         instance: ty::Instance<'tcx>,
-        destination: Option<(Lvalue, mir::BasicBlock)>,
         args: &[ValTy<'tcx>],
-        sig: ty::FnSig<'tcx>,
-        path: String,
-    ) -> EvalResult<'tcx> {
-        // In some cases in non-MIR libstd-mode, not having a destination is legit.  Handle these early.
-        match &path[..] {
-            "std::panicking::rust_panic_with_hook" |
-            "core::panicking::panic_fmt::::panic_impl" |
-            "std::rt::begin_panic_fmt" => return err!(Panic),
-            _ => {}
+    ) -> EvalResult<'tcx, Option<String>> {
+        let is_str_ref = |ty: Ty<'tcx>| match ty.sty {
+            ty::TyRef(_, ty::TypeAndMut { ty, .. }) => ty.sty == ty::TyStr,
+            _ => false,
+        };
+        if args.len() != 2 || !is_str_ref(instance.substs.type_at(0)) {
+            return Ok(None);
         }
+        let location_ty = match args[1].ty.sty {
+            ty::TyRef(_, ty::TypeAndMut { ty, .. }) => ty,
+            _ => return Ok(None),
+        };
+        let field_tys = match location_ty.sty {
+            ty::TyTuple(tys, _) if tys.len() == 3 && is_str_ref(tys[0]) => tys,
+            _ => return Ok(None),
+        };
 
-        let dest_ty = sig.output();
-        let (dest, dest_block) = destination.ok_or_else(
-            || EvalErrorKind::NoMirFor(path.clone()),
-        )?;
-
-        if sig.abi == Abi::C {
-            // An external C function
-            // TODO: That functions actually has a similar preamble to what follows here.  May make sense to
-            // unify these two mechanisms for "hooking into missing functions".
-            self.call_c_abi(
-                instance.def_id(),
-                args,
-                dest,
-                dest_ty,
-                dest_block,
-            )?;
-            return Ok(());
+        let (msg_ptr, msg_len) = args[0].into_slice(&self.memory)?;
+        let msg = self.memory.read_bytes(msg_ptr, msg_len)?;
+        let msg = String::from_utf8_lossy(msg).into_owned();
+        let location = Lvalue::from_primval_ptr(args[1].into_ptr(&self.memory)?);
+        let mut fields = Vec::new();
+        for (i, &ty) in field_tys.iter().enumerate() {
+            let field = self.lvalue_field(location, mir::Field::new(i), location_ty, ty)?;
+            fields.push(ValTy { value: self.read_lvalue(field)?, ty });
         }
+        let (file_ptr, file_len) = fields[0].into_slice(&self.memory)?;
+        let file = self.memory.read_bytes(file_ptr, file_len)?;
+        let file = String::from_utf8_lossy(file).into_owned();
+        let line = self.value_to_primval(fields[1])?.to_u64()?;
+        let col = self.value_to_primval(fields[2])?.to_u64()?;
+        Ok(Some(format!("thread 'main' panicked at '{}', {}:{}:{}", msg, file, line, col)))
+    }
 
-        match &path[..] {
-            // Allocators are magic.  They have no MIR, even when the rest of libstd does.
-            "alloc::heap::::__rust_alloc" => {
-                let size = self.value_to_primval(args[0])?.to_u64()?;
-                let align = self.value_to_primval(args[1])?.to_u64()?;
-                if size == 0 {
-                    return err!(HeapAllocZeroBytes);
-                }
-                if !align.is_power_of_two() {
-                    return err!(HeapAllocNonPowerOfTwoAlignment(align));
-                }
-                let ptr = self.memory.allocate(size, align, MemoryKind::Rust.into())?;
-                self.write_primval(dest, PrimVal::Ptr(ptr), dest_ty)?;
+    fn check_missing_fn(&self, instance: ty::Instance<'tcx>) -> EvalResult<'tcx> {
+        let def_id = instance.def_id();
+        let sig = instance.def.def_ty(self.tcx).fn_sig(self.tcx);
+        if sig.abi() == Abi::C {
+            let link_name = link_name(self.tcx, def_id);
+            if foreign_fn(self, &link_name).is_none() {
+                return Err(unknown_foreign_fn(&link_name));
             }
-            "alloc::heap::::__rust_alloc_zeroed" => {
-                let size = self.value_to_primval(args[0])?.to_u64()?;
-                let align = self.value_to_primval(args[1])?.to_u64()?;
-                if size == 0 {
-                    return err!(HeapAllocZeroBytes);
-                }
-                if !align.is_power_of_two() {
-                    return err!(HeapAllocNonPowerOfTwoAlignment(align));
-                }
-                let ptr = self.memory.allocate(size, align, MemoryKind::Rust.into())?;
-                self.memory.write_repeat(ptr.into(), 0, size)?;
-                self.write_primval(dest, PrimVal::Ptr(ptr), dest_ty)?;
-            }
-            "alloc::heap::::__rust_dealloc" => {
-                let ptr = args[0].into_ptr(&mut self.memory)?.to_ptr()?;
-                let old_size = self.value_to_primval(args[1])?.to_u64()?;
-                let align = self.value_to_primval(args[2])?.to_u64()?;
-                if old_size == 0 {
-                    return err!(HeapAllocZeroBytes);
-                }
-                if !align.is_power_of_two() {
-                    return err!(HeapAllocNonPowerOfTwoAlignment(align));
-                }
-                self.memory.deallocate(
-                    ptr,
-                    Some((old_size, align)),
-                    MemoryKind::Rust.into(),
-                )?;
+        } else {
+            let path = self.tcx.item_path_str(def_id);
+            if let Some(reason) = find(UNSUPPORTED_RUST_FNS, &path) {
+                return err!(Unimplemented(reason.to_owned()));
             }
-            "alloc::heap::::__rust_realloc" => {
-                let ptr = args[0].into_ptr(&mut self.memory)?.to_ptr()?;
-                let old_size = self.value_to_primval(args[1])?.to_u64()?;
-                let old_align = self.value_to_primval(args[2])?.to_u64()?;
-                let new_size = self.value_to_primval(args[3])?.to_u64()?;
-                let new_align = self.value_to_primval(args[4])?.to_u64()?;
-                if old_size == 0 || new_size == 0 {
-                    return err!(HeapAllocZeroBytes);
-                }
-                if !old_align.is_power_of_two() {
-                    return err!(HeapAllocNonPowerOfTwoAlignment(old_align));
-                }
-                if !new_align.is_power_of_two() {
-                    return err!(HeapAllocNonPowerOfTwoAlignment(new_align));
-                }
-                let new_ptr = self.memory.reallocate(
-                    ptr,
-                    old_size,
-                    old_align,
-                    new_size,
-                    new_align,
-                    MemoryKind::Rust.into(),
-                )?;
-                self.write_primval(dest, PrimVal::Ptr(new_ptr), dest_ty)?;
+            if find(MISSING_RUST_FNS, &path).is_none() &&
+                find(DIVERGING_RUST_FNS, &path).is_none()
+            {
+                return Err(self.no_mir_error(def_id, path).into());
             }
+        }
+        Ok(())
+    }
 
-            // A Rust function is missing, which means we are running with MIR missing for libstd (or other dependencies).
-            // Still, we can make many things mostly work by "emulating" or ignoring some functions.
-            "std::io::_print" => {
-                warn!(
-                    "Ignoring output.  To run programs that print, make sure you have a libstd with full MIR."
-                );
-            }
-            "std::thread::Builder::new" => {
-                return err!(Unimplemented("miri does not support threading".to_owned()))
-            }
-            "std::env::args" => {
-                return err!(Unimplemented(
-                    "miri does not support program arguments".to_owned(),
-                ))
+    fn call_missing_fn(
+        &mut self,
+        instance: ty::Instance<'tcx>,
+        destination: Option<(Lvalue, mir::BasicBlock)>,
+        args: &[ValTy<'tcx>],
+        sig: ty::FnSig<'tcx>,
+        path: String,
+    ) -> EvalResult<'tcx> {
+        if sig.abi == Abi::C {
+            // `exit` and friends have no destination, which `call_c_abi` needs.
+            let link_name = link_name(self.tcx, instance.def_id());
+            if let Some(f) = find(DIVERGING_FOREIGN_FNS, &link_name) {
+                f(self, args)?;
+                bug!("`{}` returned", link_name);
             }
-            "std::panicking::panicking" |
-            "std::rt::panicking" => {
-                // we abort on panic -> `std::rt::panicking` always returns false
-                let bool = self.tcx.types.bool;
-                self.write_primval(dest, PrimVal::from_bool(false), bool)?;
+            let (dest, dest_block) = destination.ok_or_else(
+                || self.no_mir_error(instance.def_id(), path.clone()),
+            )?;
+            // An external C function
+            return self.call_c_abi(instance.def_id(), args, dest, sig.output(), dest_block);
+        }
+
+        // Without libstd's MIR, nothing would print the message of a `panic!("...")`. With it,
+        // the panic unwinds like it would natively.
+        if path == "std::panicking::begin_panic" {
+            if let Some(msg) = self.literal_panic_message(instance, args)? {
+                eprintln!("{}", msg);
             }
-            "std::sys::imp::c::::AddVectoredExceptionHandler" |
-            "std::sys::imp::c::::SetThreadStackGuarantee" => {
-                let usize = self.tcx.types.usize;
-                // any non zero value works for the stdlib. This is just used for stackoverflows anyway
-                self.write_primval(dest, PrimVal::Bytes(1), usize)?;
-            },
-            _ => return err!(NoMirFor(path)),
         }
+        // In some cases in non-MIR libstd-mode, not having a destination is legit.  Handle these early.
+        if let Some(f) = find(DIVERGING_RUST_FNS, &path) {
+            f(self, args)?;
+            bug!("`{}` returned", path);
+        }
+        if let Some(reason) = find(UNSUPPORTED_RUST_FNS, &path) {
+            return err!(Unimplemented(reason.to_owned()));
+        }
+        let f = match find(MISSING_RUST_FNS, &path) {
+            Some(f) => f,
+            None => return Err(self.no_mir_error(instance.def_id(), path).into()),
+        };
+        let (dest, dest_block) = destination.ok_or_else(
+            || self.no_mir_error(instance.def_id(), path.clone()),
+        )?;
+        f(self, &path, args, dest, sig.output())?;
 
         // Since we pushed no stack frame, the main loop will act
         // as if the call just completed and it's returning to the
         // current frame.
         self.dump_local(dest);
         self.goto_block(dest_block);
-        return Ok(());
+        Ok(())
+    }
+}
+
+fn rust_alloc<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    path: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let size = ecx.value_to_primval(args[0])?.to_u64()?;
+    let align = ecx.value_to_primval(args[1])?.to_u64()?;
+    if size == 0 {
+        return err!(HeapAllocZeroBytes);
+    }
+    if !align.is_power_of_two() {
+        return err!(HeapAllocNonPowerOfTwoAlignment(align));
+    }
+    let zeroed = path == "alloc::heap::::__rust_alloc_zeroed";
+    let ptr = ecx.rust_alloc(size, align, zeroed)?;
+    ecx.write_primval(dest, PrimVal::Ptr(ptr), dest_ty)
+}
+
+fn rust_dealloc<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _path: &str,
+    args: &[ValTy<'tcx>],
+    _dest: Lvalue,
+    _dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let ptr = args[0].into_ptr(&mut ecx.memory)?.to_ptr()?;
+    let old_size = ecx.value_to_primval(args[1])?.to_u64()?;
+    let align = ecx.value_to_primval(args[2])?.to_u64()?;
+    if old_size == 0 {
+        return err!(HeapAllocZeroBytes);
+    }
+    if !align.is_power_of_two() {
+        return err!(HeapAllocNonPowerOfTwoAlignment(align));
+    }
+    ecx.rust_dealloc(ptr, old_size, align)
+}
+
+fn rust_realloc<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _path: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let ptr = args[0].into_ptr(&mut ecx.memory)?.to_ptr()?;
+    let old_size = ecx.value_to_primval(args[1])?.to_u64()?;
+    let old_align = ecx.value_to_primval(args[2])?.to_u64()?;
+    let new_size = ecx.value_to_primval(args[3])?.to_u64()?;
+    let new_align = ecx.value_to_primval(args[4])?.to_u64()?;
+    if old_size == 0 || new_size == 0 {
+        return err!(HeapAllocZeroBytes);
+    }
+    if !old_align.is_power_of_two() {
+        return err!(HeapAllocNonPowerOfTwoAlignment(old_align));
+    }
+    if !new_align.is_power_of_two() {
+        return err!(HeapAllocNonPowerOfTwoAlignment(new_align));
+    }
+    let new_ptr = ecx.rust_realloc(ptr, old_size, old_align, new_size, new_align)?;
+    ecx.write_primval(dest, PrimVal::Ptr(new_ptr), dest_ty)
+}
+
+fn ignore_print<'a, 'tcx>(
+    _ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _path: &str,
+    _args: &[ValTy<'tcx>],
+    _dest: Lvalue,
+    _dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    warn!(
+        "Ignoring output.  To run programs that print, make sure you have a libstd with full MIR."
+    );
+    Ok(())
+}
+
+fn panicking<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _path: &str,
+    _args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    _dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    // Without libstd's MIR nobody counts the panics, but the thread is panicking exactly while
+    // the stack unwinds with a payload.
+    let panicking = ecx.machine_data.panic_payload.is_some();
+    let bool = ecx.tcx.types.bool;
+    ecx.write_primval(dest, PrimVal::from_bool(panicking), bool)
+}
+
+fn windows_stack_overflow_setup<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _path: &str,
+    _args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    _dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let usize = ecx.tcx.types.usize;
+    // any non zero value works for the stdlib. This is just used for stackoverflows anyway
+    ecx.write_primval(dest, PrimVal::Bytes(1), usize)
+}
+
+fn panic<'a, 'tcx>(_ecx: &mut EvalContext<'a, 'tcx, Evaluator>, _args: &[ValTy<'tcx>])
+                   -> EvalResult<'tcx> {
+    err!(Panic)
+}
+
+fn exit<'a, 'tcx>(ecx: &mut EvalContext<'a, 'tcx, Evaluator>, args: &[ValTy<'tcx>])
+                  -> EvalResult<'tcx> {
+    let code = ecx.value_to_primval(args[0])?.to_i128()? as i32;
+    err!(Exit(code))
+}
+
+fn abort<'a, 'tcx>(_ecx: &mut EvalContext<'a, 'tcx, Evaluator>, _args: &[ValTy<'tcx>])
+                   -> EvalResult<'tcx> {
+    err!(Abort)
+}
+
+/// The name a foreign function is linked by.
+pub(crate) fn link_name(tcx: ty::TyCtxt, def_id: DefId) -> ::syntax::symbol::InternedString {
+    let attrs = tcx.get_attrs(def_id);
+    match attr::first_attr_value_str_by_name(&attrs, "link_name") {
+        Some(name) => name.as_str(),
+        None => tcx.item_name(def_id),
     }
 }
@@ -0,0 +1,102 @@
+//! An entry point for fuzzing the interpreter itself.
+//!
+//! A fuzz target generates a program, compiles it up to MIR and hands its `main` to
+//! `eval_for_fuzzing`. The program runs with tight resource limits and nothing is reported to the
+//! session, so the caller can decide what to do based on the returned `FuzzOutcome`. Only
+//! `FuzzOutcome::InterpreterBug` means that miri itself is broken.
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+
+use rustc::hir::def_id::DefId;
+use rustc::ty::TyCtxt;
+use rustc_miri::interpret::{EvalContext, EvalErrorKind, ResourceLimits};
+
+use super::{run_main, survey, MiriConfig, EvaluatorData};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FuzzOutcome {
+    /// The program ran to completion or exited.
+    Finished,
+    /// The program hit one of the resource limits.
+    ResourceExhausted(String),
+    /// The program uses something miri does not implement.
+    Unsupported(String),
+    /// Miri found undefined behavior or a panic in the program.
+    ProgramError(String),
+    /// Miri panicked, e.g. because of a `bug!`.
+    InterpreterBug(String),
+}
+
+/// Limits that keep every single fuzzing run short.
+pub fn fuzz_limits() -> ResourceLimits {
+    ResourceLimits {
+        memory_size: 1024 * 1024,
+        step_limit: 10_000,
+        stack_limit: 32,
+    }
+}
+
+pub fn eval_for_fuzzing<'a, 'tcx: 'a>(
+    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+    main_id: DefId,
+    start_wrapper: Option<DefId>,
+    limits: ResourceLimits,
+) -> FuzzOutcome {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let data = EvaluatorData { config: MiriConfig::default(), ..Default::default() };
+        let mut ecx = EvalContext::new(tcx, limits, data, Default::default());
+        run_main(&mut ecx, main_id, start_wrapper, None).map_err(|e| (e.to_string(), e.kind))
+    }));
+    match result {
+        Ok(Ok(())) => FuzzOutcome::Finished,
+        Ok(Err((msg, kind))) => error_outcome(msg, &kind),
+        Err(payload) => FuzzOutcome::InterpreterBug(panic_message(payload)),
+    }
+}
+
+/// The outcome of a run that stopped with the error `kind`, displayed as `msg`.
+fn error_outcome(msg: String, kind: &EvalErrorKind) -> FuzzOutcome {
+    use self::EvalErrorKind::*;
+    match *kind {
+        Exit(_) => FuzzOutcome::Finished,
+        ExecutionTimeLimitReached |
+        StackFrameLimitReached |
+        OutOfMemory { .. } => FuzzOutcome::ResourceExhausted(msg),
+        ref kind if survey::is_unsupported(kind) => FuzzOutcome::Unsupported(msg),
+        _ => FuzzOutcome::ProgramError(msg),
+    }
+}
+
+fn panic_message(payload: Box<Any + Send>) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        return (*msg).to_owned();
+    }
+    match payload.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(_) => "the interpreter panicked".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_outcomes() {
+        use self::EvalErrorKind::*;
+        let outcome = |kind| error_outcome("msg".to_owned(), &kind);
+        assert_eq!(outcome(Exit(1)), FuzzOutcome::Finished);
+        assert_eq!(outcome(StackFrameLimitReached), FuzzOutcome::ResourceExhausted("msg".into()));
+        assert_eq!(outcome(Unimplemented("asm".into())), FuzzOutcome::Unsupported("msg".into()));
+        assert_eq!(outcome(InvalidBool(2)), FuzzOutcome::ProgramError("msg".into()));
+    }
+
+    #[test]
+    fn interpreter_bugs() {
+        let payload = |f: fn()| panic::catch_unwind(f).unwrap_err();
+        assert_eq!(panic_message(payload(|| panic!("static"))), "static");
+        assert_eq!(panic_message(payload(|| panic!("formatted {}", 1))), "formatted 1");
+        assert_eq!(panic_message(Box::new(0)), "the interpreter panicked");
+    }
+}
@@ -0,0 +1,46 @@
+//! A report of how often each allocation was read and written, to find the hottest memory of a
+//! program.
+//!
+//! Counting starts when `MIRI_HEATMAP` names a file, and the report is written to it when the
+//! evaluation ends. If `MIRI_HEATMAP_BUCKET` is set to a number of bytes, the accesses of every
+//! allocation are also broken down by buckets of that size.
+
+use std::fs::File;
+use std::io::{self, Write};
+
+use rustc_miri::interpret::{AccessCounts, EvalContext};
+
+use super::Evaluator;
+
+pub fn write_heatmap<'a, 'tcx>(
+    ecx: &EvalContext<'a, 'tcx, Evaluator>,
+    path: &str,
+) -> io::Result<()> {
+    let counts = match ecx.memory().access_counts() {
+        Some(counts) => counts,
+        None => return Ok(()),
+    };
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    // Hottest first, ties broken by allocation for a stable order.
+    counts.sort_by_key(|&(id, ref counts)| (!(counts.reads + counts.writes), id));
+
+    let mut file = File::create(path)?;
+    for (id, counts) in counts {
+        let AccessCounts { size, ref kind, reads, writes, ref buckets } = counts;
+        writeln!(
+            file,
+            "alloc {} ({}, {} bytes): {} reads, {} writes",
+            id,
+            kind,
+            size,
+            reads,
+            writes
+        )?;
+        let bucket_size = ecx.memory().access_bucket_size();
+        for (&start, &(reads, writes)) in buckets {
+            let end = ::std::cmp::min(start + bucket_size, size);
+            writeln!(file, "    bytes {}..{}: {} reads, {} writes", start, end, reads, writes)?;
+        }
+    }
+    Ok(())
+}
@@ -52,6 +52,10 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                 err!(InvalidNullPointerUsage)
             };
         }
+        // Like LLVM's `getelementptr inbounds`, the pointer we start from also has to be in bounds.
+        if let PrimVal::Ptr(ptr) = ptr.into_inner_primval() {
+            self.memory.check_bounds(ptr, false)?;
+        }
         // FIXME: assuming here that type size is < i64::max_value()
         let pointee_size = self.type_size(pointee_ty)?.expect(
             "cannot offset a pointer to an unsized type",
@@ -0,0 +1,201 @@
+//! Functions of the embedder that the evaluated program can call like foreign functions. This
+//! allows testing setups where some components run natively and others are interpreted.
+//!
+//! The program declares a host function in an `extern "C"` block, just like a C function. Only
+//! primitive values can be passed: the host function gets the bits of each argument as a `u128`
+//! (signed integers sign-extended, floats as their bit pattern) and returns the bits of the
+//! result in the same form, which is ignored for functions returning `()`. A result that is not a
+//! valid value of the return type, like a `bool` other than 0 or 1, is an error.
+//!
+//! ```rust,ignore
+//! let mut config = MiriConfig::default();
+//! config.host_fns.register("host_add", |args| {
+//!     Ok((args[0] as u64).wrapping_add(args[1] as u64) as u128)
+//! });
+//! // and in the evaluated program:
+//! extern "C" {
+//!     fn host_add(a: u64, b: u64) -> u64;
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+use rustc::ty::{self, Ty};
+use syntax::ast::{FloatTy, IntTy, UintTy};
+use rustc_miri::interpret::{EvalContext, EvalErrorKind, EvalResult, Lvalue, PrimVal, ValTy};
+
+use super::Evaluator;
+
+pub type HostFn = Fn(&[u128]) -> Result<u128, String>;
+
+/// The host functions registered by the embedder, by the symbol they are called through.
+#[derive(Clone, Default)]
+pub struct HostFunctions {
+    fns: HashMap<String, Rc<HostFn>>,
+}
+
+impl HostFunctions {
+    pub fn register<F>(&mut self, symbol: &str, f: F)
+    where
+        F: Fn(&[u128]) -> Result<u128, String> + 'static,
+    {
+        self.fns.insert(symbol.to_owned(), Rc::new(f));
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<Rc<HostFn>> {
+        self.fns.get(symbol).cloned()
+    }
+}
+
+impl fmt::Debug for HostFunctions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.fns.keys()).finish()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct HostFnFailed {
+    symbol: String,
+    msg: String,
+}
+
+impl fmt::Display for HostFnFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "host function `{}` failed: {}", self.symbol, self.msg)
+    }
+}
+
+impl Error for HostFnFailed {
+    fn description(&self) -> &str {
+        "host function failed"
+    }
+}
+
+/// Calls the host function `f` registered for `symbol`.
+pub fn call<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    symbol: &str,
+    f: &HostFn,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let mut bits = Vec::with_capacity(args.len());
+    for &arg in args {
+        if !is_primitive(arg.ty) {
+            return err!(Unimplemented(format!(
+                "host function `{}` called with an argument of non-primitive type {}",
+                symbol,
+                arg.ty
+            )));
+        }
+        bits.push(ecx.value_to_primval(arg)?.to_bytes()?);
+    }
+    let result = f(&bits).map_err(|msg| {
+        EvalErrorKind::MachineError(Box::new(HostFnFailed { symbol: symbol.to_owned(), msg }))
+    })?;
+    if !dest_ty.is_nil() {
+        if !is_primitive(dest_ty) {
+            return err!(Unimplemented(format!(
+                "host function `{}` returns the non-primitive type {}",
+                symbol,
+                dest_ty
+            )));
+        }
+        let pointer_bits = ecx.memory.pointer_size() * 8;
+        if !is_valid_result(&dest_ty.sty, result, pointer_bits) {
+            let msg = format!("returned {:#x}, which is not a valid `{}`", result, dest_ty);
+            let failed = HostFnFailed { symbol: symbol.to_owned(), msg };
+            return err!(MachineError(Box::new(failed)));
+        }
+        ecx.write_primval(dest, PrimVal::Bytes(result), dest_ty)?;
+    }
+    Ok(())
+}
+
+fn is_primitive(ty: Ty) -> bool {
+    match ty.sty {
+        ty::TyBool | ty::TyChar | ty::TyInt(_) | ty::TyUint(_) | ty::TyFloat(_) => true,
+        _ => false,
+    }
+}
+
+/// Whether the bits `result` returned by a host function are a valid value of the primitive type
+/// `sty`.
+fn is_valid_result(sty: &ty::TypeVariants, result: u128, pointer_bits: u64) -> bool {
+    let (bits, signed) = match *sty {
+        ty::TyBool => return result <= 1,
+        ty::TyChar => {
+            return result <= u32::max_value() as u128 &&
+                ::std::char::from_u32(result as u32).is_some()
+        }
+        ty::TyInt(int_ty) => {
+            let bits = match int_ty {
+                IntTy::I8 => 8,
+                IntTy::I16 => 16,
+                IntTy::I32 => 32,
+                IntTy::I64 => 64,
+                IntTy::I128 => 128,
+                IntTy::Is => pointer_bits,
+            };
+            (bits, true)
+        }
+        ty::TyUint(uint_ty) => {
+            let bits = match uint_ty {
+                UintTy::U8 => 8,
+                UintTy::U16 => 16,
+                UintTy::U32 => 32,
+                UintTy::U64 => 64,
+                UintTy::U128 => 128,
+                UintTy::Us => pointer_bits,
+            };
+            (bits, false)
+        }
+        ty::TyFloat(FloatTy::F32) => (32, false),
+        ty::TyFloat(FloatTy::F64) => (64, false),
+        _ => return false,
+    };
+    let shift = 128 - bits;
+    if signed {
+        // Signed integers are sign-extended, like the arguments.
+        ((result << shift) as i128 >> shift) as u128 == result
+    } else {
+        bits == 128 || result >> bits == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bools() {
+        assert!(is_valid_result(&ty::TyBool, 0, 64));
+        assert!(is_valid_result(&ty::TyBool, 1, 64));
+        assert!(!is_valid_result(&ty::TyBool, 2, 64));
+        assert!(!is_valid_result(&ty::TyBool, 1 << 64, 64));
+    }
+
+    #[test]
+    fn chars() {
+        assert!(is_valid_result(&ty::TyChar, 'x' as u128, 64));
+        assert!(!is_valid_result(&ty::TyChar, 0xd800, 64));
+        assert!(!is_valid_result(&ty::TyChar, 1 << 32 | 'x' as u128, 64));
+    }
+
+    #[test]
+    fn integers() {
+        assert!(is_valid_result(&ty::TyUint(UintTy::U8), 0xff, 64));
+        assert!(!is_valid_result(&ty::TyUint(UintTy::U8), 0x100, 64));
+        assert!(is_valid_result(&ty::TyUint(UintTy::U128), u128::max_value(), 64));
+        assert!(!is_valid_result(&ty::TyUint(UintTy::Us), 1 << 32, 32));
+        assert!(is_valid_result(&ty::TyInt(IntTy::I8), -128i128 as u128, 64));
+        assert!(!is_valid_result(&ty::TyInt(IntTy::I8), 0x80, 64));
+        assert!(is_valid_result(&ty::TyInt(IntTy::Is), -1i128 as u128, 64));
+        assert!(is_valid_result(&ty::TyFloat(FloatTy::F32), 1.5f32.to_bits() as u128, 64));
+        assert!(!is_valid_result(&ty::TyFloat(FloatTy::F32), 1.5f64.to_bits() as u128, 64));
+    }
+}
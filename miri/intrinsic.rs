@@ -8,6 +8,41 @@ use rustc_miri::interpret::{EvalResult, Lvalue, LvalueExtra, PrimVal, PrimValKin
 
 use helpers::EvalContextExt as HelperEvalContextExt;
 
+/// The intrinsics `call_intrinsic` implements. It rejects all others, so the capabilities of this
+/// build report exactly these. The diverging `abort` and `unreachable` are handled by
+/// `rustc_miri` itself.
+pub const INTRINSICS: &[&str] = &[
+    "add_with_overflow", "align_of_val", "align_offset", "arith_offset", "assume", "atomic_and",
+    "atomic_and_acq", "atomic_and_acqrel", "atomic_and_rel", "atomic_and_relaxed", "atomic_cxchg",
+    "atomic_cxchg_acq", "atomic_cxchg_acq_failrelaxed", "atomic_cxchg_acqrel",
+    "atomic_cxchg_acqrel_failrelaxed", "atomic_cxchg_failacq", "atomic_cxchg_failrelaxed",
+    "atomic_cxchg_rel", "atomic_cxchg_relaxed", "atomic_cxchgweak", "atomic_cxchgweak_acq",
+    "atomic_cxchgweak_acq_failrelaxed", "atomic_cxchgweak_acqrel",
+    "atomic_cxchgweak_acqrel_failrelaxed", "atomic_cxchgweak_failacq",
+    "atomic_cxchgweak_failrelaxed", "atomic_cxchgweak_rel", "atomic_cxchgweak_relaxed",
+    "atomic_fence_acq", "atomic_load", "atomic_load_acq", "atomic_load_relaxed", "atomic_or",
+    "atomic_or_acq", "atomic_or_acqrel", "atomic_or_rel", "atomic_or_relaxed", "atomic_store",
+    "atomic_store_rel", "atomic_store_relaxed", "atomic_xadd", "atomic_xadd_acq",
+    "atomic_xadd_acqrel", "atomic_xadd_rel", "atomic_xadd_relaxed", "atomic_xchg",
+    "atomic_xchg_acq", "atomic_xchg_acqrel", "atomic_xchg_rel", "atomic_xchg_relaxed",
+    "atomic_xor", "atomic_xor_acq", "atomic_xor_acqrel", "atomic_xor_rel", "atomic_xor_relaxed",
+    "atomic_xsub", "atomic_xsub_acq", "atomic_xsub_acqrel", "atomic_xsub_rel",
+    "atomic_xsub_relaxed", "bitreverse", "bswap", "ceilf32", "ceilf64", "copy",
+    "copy_nonoverlapping", "copysignf32", "copysignf64", "cosf32", "cosf64", "ctlz",
+    "ctlz_nonzero", "ctpop", "cttz", "cttz_nonzero", "discriminant_value", "exp2f32", "exp2f64",
+    "expf32", "expf64", "fabsf32", "fabsf64", "fadd_fast", "fdiv_fast", "floorf32", "floorf64",
+    "fmaf32", "fmaf64", "fmul_fast", "forget", "frem_fast", "fsub_fast", "init", "likely",
+    "log10f32", "log10f64", "log2f32", "log2f64", "logf32", "logf64", "maxnumf32", "maxnumf64",
+    "min_align_of", "min_align_of_val", "minnumf32", "minnumf64", "move_val_init",
+    "mul_with_overflow", "needs_drop", "offset", "overflowing_add", "overflowing_mul",
+    "overflowing_sub", "powf32", "powf64", "powif32", "powif64", "pref_align_of", "roundf32",
+    "roundf64", "sinf32", "sinf64", "size_of", "size_of_val", "sqrtf32", "sqrtf64",
+    "sub_with_overflow", "transmute", "truncf32", "truncf64", "type_id", "type_name",
+    "unaligned_volatile_load", "unaligned_volatile_store", "unchecked_add", "unchecked_div",
+    "unchecked_mul", "unchecked_rem", "unchecked_shl", "unchecked_shr", "unchecked_sub", "uninit",
+    "unlikely", "volatile_load", "volatile_store", "write_bytes",
+];
+
 pub trait EvalContextExt<'tcx> {
     fn call_intrinsic(
         &mut self,
@@ -33,6 +68,9 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
         let substs = instance.substs;
 
         let intrinsic_name = &self.tcx.item_name(instance.def_id())[..];
+        if !INTRINSICS.contains(&intrinsic_name) {
+            return err!(Unimplemented(format!("unimplemented intrinsic: {}", intrinsic_name)));
+        }
         match intrinsic_name {
             "align_offset" => {
                 // FIXME: return a real value in case the target allocation has an
@@ -105,6 +143,23 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                 self.write_value_to_ptr(args[1].value, dest, ty)?;
             }
 
+            "unaligned_volatile_load" => {
+                let ptr = args[0].into_ptr(&self.memory)?;
+                let valty = ValTy {
+                    value: Value::ByRef(PtrAndAlign { ptr, aligned: false }),
+                    ty: substs.type_at(0),
+                };
+                self.write_value(valty, dest)?;
+            }
+
+            "unaligned_volatile_store" => {
+                let ty = substs.type_at(0);
+                let dest = args[0].into_ptr(&self.memory)?;
+                self.write_maybe_aligned_mut(false, |ectx| {
+                    ectx.write_value_to_ptr(args[1].value, dest, ty)
+                })?;
+            }
+
             "atomic_fence_acq" => {
                 // we are inherently singlethreaded and singlecored, this is a nop
             }
@@ -198,8 +253,6 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                 self.write_primval(Lvalue::from_primval_ptr(ptr), val, ty)?;
             }
 
-            "breakpoint" => unimplemented!(), // halt miri
-
             "copy" |
             "copy_nonoverlapping" => {
                 let elem_ty = substs.type_at(0);
@@ -221,7 +274,7 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                 }
             }
 
-            "ctpop" | "cttz" | "cttz_nonzero" | "ctlz" | "ctlz_nonzero" | "bswap" => {
+            "ctpop" | "cttz" | "cttz_nonzero" | "ctlz" | "ctlz_nonzero" | "bswap" | "bitreverse" => {
                 let ty = substs.type_at(0);
                 let num = self.value_to_primval(args[0])?.to_bytes()?;
                 let kind = self.ty_to_primval_kind(ty)?;
@@ -239,12 +292,12 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
             "discriminant_value" => {
                 let ty = substs.type_at(0);
                 let adt_ptr = args[0].into_ptr(&self.memory)?.to_ptr()?;
-                let discr_val = self.read_discriminant_value(adt_ptr, ty)?;
+                let discr_val = self.read_valid_discriminant_value(adt_ptr, ty)?;
                 self.write_primval(dest, PrimVal::Bytes(discr_val), dest_ty)?;
             }
 
             "sinf32" | "fabsf32" | "cosf32" | "sqrtf32" | "expf32" | "exp2f32" | "logf32" |
-            "log10f32" | "log2f32" | "floorf32" | "ceilf32" | "truncf32" => {
+            "log10f32" | "log2f32" | "floorf32" | "ceilf32" | "truncf32" | "roundf32" => {
                 let f = self.value_to_primval(args[0])?.to_f32()?;
                 let f = match intrinsic_name {
                     "sinf32" => f.sin(),
@@ -259,13 +312,14 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                     "floorf32" => f.floor(),
                     "ceilf32" => f.ceil(),
                     "truncf32" => f.trunc(),
+                    "roundf32" => f.round(),
                     _ => bug!(),
                 };
                 self.write_primval(dest, PrimVal::from_f32(f), dest_ty)?;
             }
 
             "sinf64" | "fabsf64" | "cosf64" | "sqrtf64" | "expf64" | "exp2f64" | "logf64" |
-            "log10f64" | "log2f64" | "floorf64" | "ceilf64" | "truncf64" => {
+            "log10f64" | "log2f64" | "floorf64" | "ceilf64" | "truncf64" | "roundf64" => {
                 let f = self.value_to_primval(args[0])?.to_f64()?;
                 let f = match intrinsic_name {
                     "sinf64" => f.sin(),
@@ -280,6 +334,7 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                     "floorf64" => f.floor(),
                     "ceilf64" => f.ceil(),
                     "truncf64" => f.trunc(),
+                    "roundf64" => f.round(),
                     _ => bug!(),
                 };
                 self.write_primval(dest, PrimVal::from_f64(f), dest_ty)?;
@@ -297,7 +352,26 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                     "frem_fast" => mir::BinOp::Rem,
                     _ => bug!(),
                 };
+                // The fast-math intrinsics are UB if any operand or the result is NaN or infinite.
+                let kind = self.ty_to_primval_kind(ty)?;
+                let is_finite = |val: PrimVal| -> EvalResult<'tcx, bool> {
+                    Ok(match kind {
+                        PrimValKind::F32 => val.to_f32()?.is_finite(),
+                        PrimValKind::F64 => val.to_f64()?.is_finite(),
+                        _ => bug!("{} called on non-float type {:?}", intrinsic_name, ty),
+                    })
+                };
+                if !is_finite(a)? || !is_finite(b)? {
+                    return err!(Intrinsic(
+                        format!("{} called with a non-finite operand", intrinsic_name),
+                    ));
+                }
                 let result = self.binary_op(op, a, ty, b, ty)?;
+                if !is_finite(result.0)? {
+                    return err!(Intrinsic(
+                        format!("{} produced a non-finite result", intrinsic_name),
+                    ));
+                }
                 self.write_primval(dest, result.0, dest_ty)?;
             }
 
@@ -438,7 +512,7 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                 let c = self.value_to_primval(args[2])?.to_f32()?;
                 self.write_primval(
                     dest,
-                    PrimVal::from_f32(a * b + c),
+                    PrimVal::from_f32(a.mul_add(b, c)),
                     dest_ty,
                 )?;
             }
@@ -449,11 +523,25 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                 let c = self.value_to_primval(args[2])?.to_f64()?;
                 self.write_primval(
                     dest,
-                    PrimVal::from_f64(a * b + c),
+                    PrimVal::from_f64(a.mul_add(b, c)),
                     dest_ty,
                 )?;
             }
 
+            "copysignf32" | "minnumf32" | "maxnumf32" => {
+                let a = self.value_to_primval(args[0])?.to_f32()?;
+                let b = self.value_to_primval(args[1])?.to_f32()?;
+                let f = match intrinsic_name {
+                    // `abs` only clears the sign bit, so this also does the right thing for NaN.
+                    "copysignf32" if b.is_sign_negative() => -a.abs(),
+                    "copysignf32" => a.abs(),
+                    "minnumf32" => a.min(b),
+                    "maxnumf32" => a.max(b),
+                    _ => bug!(),
+                };
+                self.write_primval(dest, PrimVal::from_f32(f), dest_ty)?;
+            }
+
             "powif32" => {
                 let f = self.value_to_primval(args[0])?.to_f32()?;
                 let i = self.value_to_primval(args[1])?.to_i128()?;
@@ -464,6 +552,20 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                 )?;
             }
 
+            "copysignf64" | "minnumf64" | "maxnumf64" => {
+                let a = self.value_to_primval(args[0])?.to_f64()?;
+                let b = self.value_to_primval(args[1])?.to_f64()?;
+                let f = match intrinsic_name {
+                    // `abs` only clears the sign bit, so this also does the right thing for NaN.
+                    "copysignf64" if b.is_sign_negative() => -a.abs(),
+                    "copysignf64" => a.abs(),
+                    "minnumf64" => a.min(b),
+                    "maxnumf64" => a.max(b),
+                    _ => bug!(),
+                };
+                self.write_primval(dest, PrimVal::from_f64(f), dest_ty)?;
+            }
+
             "powif64" => {
                 let f = self.value_to_primval(args[0])?.to_f64()?;
                 let i = self.value_to_primval(args[1])?.to_i128()?;
@@ -517,6 +619,17 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
 
             "transmute" => {
                 let src_ty = substs.type_at(0);
+                let src_size = self.type_size(src_ty)?.expect("transmute from unsized type");
+                let dest_size = self.type_size(dest_ty)?.expect("transmute to unsized type");
+                if src_size != dest_size {
+                    return err!(Intrinsic(format!(
+                        "transmute called with types of different sizes: {} ({} bytes) to {} ({} bytes)",
+                        src_ty,
+                        src_size,
+                        dest_ty,
+                        dest_size,
+                    )));
+                }
                 let ptr = self.force_allocation(dest)?.to_ptr()?;
                 self.write_maybe_aligned_mut(
                     /*aligned*/
@@ -525,6 +638,10 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                         ectx.write_value_to_ptr(args[0].value, ptr.into(), src_ty)
                     },
                 )?;
+                // `char::from_u32_unchecked` and friends end up here, so catch invalid
+                // values right at the conversion instead of at the next use.
+                let value = Value::ByRef(PtrAndAlign { ptr: ptr.into(), aligned: false });
+                self.check_written_value(value, dest_ty)?;
             }
 
             "unchecked_shl" => {
@@ -573,13 +690,16 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                 if rhs == 0 {
                     return err!(Intrinsic(format!("Division by 0 in unchecked_div")));
                 }
-                self.intrinsic_overflowing(
+                let overflowed = self.intrinsic_overflowing(
                     mir::BinOp::Div,
                     args[0],
                     args[1],
                     dest,
                     dest_ty,
                 )?;
+                if overflowed {
+                    return err!(Intrinsic(format!("Overflowing division in unchecked_div")));
+                }
             }
 
             "unchecked_rem" => {
@@ -588,31 +708,56 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                 if rhs == 0 {
                     return err!(Intrinsic(format!("Division by 0 in unchecked_rem")));
                 }
-                self.intrinsic_overflowing(
+                let overflowed = self.intrinsic_overflowing(
                     mir::BinOp::Rem,
                     args[0],
                     args[1],
                     dest,
                     dest_ty,
                 )?;
+                if overflowed {
+                    return err!(Intrinsic(format!("Overflowing division in unchecked_rem")));
+                }
+            }
+
+            "unchecked_add" | "unchecked_sub" | "unchecked_mul" => {
+                let op = match intrinsic_name {
+                    "unchecked_add" => mir::BinOp::Add,
+                    "unchecked_sub" => mir::BinOp::Sub,
+                    "unchecked_mul" => mir::BinOp::Mul,
+                    _ => bug!(),
+                };
+                let overflowed = self.intrinsic_overflowing(op, args[0], args[1], dest, dest_ty)?;
+                if overflowed {
+                    return err!(Intrinsic(
+                        format!("Overflowing arithmetic in {}", intrinsic_name),
+                    ));
+                }
             }
 
             "uninit" => {
                 let size = dest_layout.size(&self.tcx.data_layout).bytes();
-                let uninit = |this: &mut Self, val: Value| match val {
-                    Value::ByRef(PtrAndAlign { ptr, .. }) => {
-                        this.memory.mark_definedness(ptr, size, false)?;
-                        Ok(val)
+                // The intrinsic is only called by `mem::uninitialized`, whose caller is where the
+                // uninitialized memory comes from.
+                let origin = {
+                    let frame = self.frame();
+                    let caller = self.tcx.item_path_str(frame.instance.def_id());
+                    if caller.ends_with("mem::uninitialized") {
+                        frame.span
+                    } else {
+                        frame.cur_span()
                     }
-                    _ => Ok(Value::ByVal(PrimVal::Undef)),
                 };
-                match dest {
-                    Lvalue::Local { frame, local } => self.modify_local(frame, local, uninit)?,
+                // A local that stayed a value would not remember where it became undefined.
+                match self.force_allocation(dest)? {
                     Lvalue::Ptr {
                         ptr: PtrAndAlign { ptr, aligned: true },
                         extra: LvalueExtra::None,
-                    } => self.memory.mark_definedness(ptr, size, false)?,
-                    Lvalue::Ptr { .. } => {
+                    } => {
+                        self.memory.mark_definedness(ptr, size, false)?;
+                        self.memory.set_undef_origin(ptr, size, origin)?;
+                    }
+                    _ => {
                         bug!("uninit intrinsic tried to write to fat or unaligned ptr target")
                     }
                 }
@@ -631,11 +776,15 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                     // HashMap relies on write_bytes on a NULL ptr with count == 0 to work
                     // TODO: Should we, at least, validate the alignment? (Also see the copy intrinsic)
                     self.memory.check_align(ptr, ty_align, Some(AccessKind::Write))?;
-                    self.memory.write_repeat(ptr, val_byte, size * count)?;
+                    let bytes = match size.checked_mul(count) {
+                        Some(bytes) => bytes,
+                        None => return err!(OverflowingMath),
+                    };
+                    self.memory.write_repeat(ptr, val_byte, bytes)?;
                 }
             }
 
-            name => return err!(Unimplemented(format!("unimplemented intrinsic: {}", name))),
+            name => bug!("the intrinsic `{}` is in `INTRINSICS`, but not implemented", name),
         }
 
         self.goto_block(target);
@@ -678,8 +827,32 @@ fn numeric_intrinsic<'tcx>(
         "ctlz" => integer_intrinsic!(leading_zeros),
         "ctpop" => integer_intrinsic!(count_ones),
         "cttz" => integer_intrinsic!(trailing_zeros),
+        "bitreverse" => integer_intrinsic!(reverse_bits_compat),
         _ => bug!("not a numeric intrinsic: {}", name),
     };
 
     Ok(result_val)
 }
+
+/// The standard library does not provide `reverse_bits` yet, so we bring our own.
+trait ReverseBits {
+    fn reverse_bits_compat(self) -> Self;
+}
+
+macro_rules! impl_reverse_bits {
+    ($($ty:ty),*) => {$(
+        impl ReverseBits for $ty {
+            fn reverse_bits_compat(self) -> Self {
+                let mut val = self;
+                let mut result: $ty = 0;
+                for _ in 0..(::std::mem::size_of::<$ty>() * 8) {
+                    result = (result << 1) | (val & 1);
+                    val >>= 1;
+                }
+                result
+            }
+        }
+    )*}
+}
+
+impl_reverse_bits!(i8, u8, i16, u16, i32, u32, i64, u64, i128, u128);
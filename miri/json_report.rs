@@ -0,0 +1,153 @@
+//! Reports for `--error-format=json`, so editors and CI tools can consume what miri found without
+//! parsing the human-readable diagnostics.
+//!
+//! Every report is one JSON object on its own line on stderr, next to rustc's own JSON
+//! diagnostics. Its `"miri"` key says what is reported (`"error"`, `"leak"`, or `"warning"` for
+//! findings that do not stop the evaluation), and it carries the error category used by
+//! `MIRI_SUPPRESSIONS` and `MIRI_SEVERITY` as `"kind"`, the message, the spans involved, the
+//! interpreted call stack and the allocations concerned.
+
+use rustc::session::Session;
+use rustc::session::config::ErrorOutputType;
+use rustc_miri::interpret::{AllocId, AllocationInfo, EvalContext, EvalError, EvalErrorKind,
+                            FrameInfo, Json, MemoryKind, Warning};
+use syntax::codemap::{CodeMap, Span, DUMMY_SP};
+
+use super::{survey, Evaluator};
+use super::memory::MemoryKind as MiriMemoryKind;
+
+pub fn enabled(sess: &Session) -> bool {
+    match sess.opts.error_format {
+        ErrorOutputType::Json(..) => true,
+        _ => false,
+    }
+}
+
+/// Reports the error `e` of the category `kind` at `level` (`"error"` or `"warning"`).
+pub fn error<'a, 'tcx>(
+    ecx: &EvalContext<'a, 'tcx, Evaluator>,
+    e: &EvalError<'tcx>,
+    kind: &str,
+    level: &str,
+) {
+    let codemap = ecx.tcx.sess.codemap();
+    let stacktrace = if e.stacktrace.is_empty() {
+        ecx.generate_stacktrace()
+    } else {
+        e.stacktrace.clone()
+    };
+    let mut spans = Vec::new();
+    if let Some(frame) = stacktrace.first() {
+        spans.push(span(codemap, frame.span, "error"));
+        ecx.emit_diagnostic_event(&e.to_string(), frame.span);
+    }
+    for (note_span, label) in ecx.error_notes(&e.kind) {
+        spans.push(span(codemap, note_span, label));
+    }
+    let allocs = match e.kind {
+        EvalErrorKind::PointerOutOfBounds { ptr, .. } |
+        EvalErrorKind::ReferenceOutOfBounds { ptr, .. } => vec![ptr.alloc_id],
+        _ => Vec::new(),
+    };
+    let backtrace = backtrace(codemap, &stacktrace);
+    let allocations = ecx.memory
+        .live_allocations()
+        .into_iter()
+        .filter(|info| allocs.contains(&info.id))
+        .map(|info| allocation(codemap, &info))
+        .collect();
+    let report = Json::object(vec![
+        ("miri", Json::str("error")),
+        ("level", Json::str(level)),
+        ("kind", Json::str(kind)),
+        ("message", Json::str(&e.to_string())),
+        ("unsupported", Json::Bool(survey::is_unsupported(&e.kind))),
+        ("spans", Json::Array(spans)),
+        ("backtrace", Json::Array(backtrace)),
+        ("allocations", Json::Array(allocations)),
+    ]);
+    eprintln!("{}", report);
+}
+
+/// Reports a finding that does not stop the evaluation at `level` (`"error"` or `"warning"`).
+pub fn warning<'a, 'tcx>(ecx: &EvalContext<'a, 'tcx, Evaluator>, warning: &Warning, level: &str) {
+    let codemap = ecx.tcx.sess.codemap();
+    let backtrace = backtrace(codemap, &ecx.generate_stacktrace());
+    let report = Json::object(vec![
+        ("miri", Json::str("warning")),
+        ("level", Json::str(level)),
+        ("kind", Json::str(warning.kind)),
+        ("message", Json::str(&warning.message)),
+        ("spans", Json::Array(vec![span(codemap, warning.span, level)])),
+        ("backtrace", Json::Array(backtrace)),
+    ]);
+    eprintln!("{}", report);
+}
+
+/// Reports the allocations that are still live at the end of the evaluation, except for those in
+/// `leaks_before`, which were leaked by earlier entry points.
+pub fn leak<'a, 'tcx>(
+    ecx: &EvalContext<'a, 'tcx, Evaluator>,
+    leaks_before: &[AllocId],
+    message: &str,
+    level: &str,
+) {
+    let codemap = ecx.tcx.sess.codemap();
+    let allocations = ecx.memory
+        .live_allocations()
+        .into_iter()
+        .filter(|info| info.kind != MemoryKind::Static && !leaks_before.contains(&info.id))
+        .map(|info| allocation(codemap, &info))
+        .collect();
+    let report = Json::object(vec![
+        ("miri", Json::str("leak")),
+        ("level", Json::str(level)),
+        ("kind", Json::str("MemoryLeak")),
+        ("message", Json::str(message)),
+        ("allocations", Json::Array(allocations)),
+    ]);
+    eprintln!("{}", report);
+}
+
+fn backtrace(codemap: &CodeMap, stacktrace: &[FrameInfo]) -> Vec<Json> {
+    stacktrace
+        .iter()
+        .map(|frame| {
+            Json::object(vec![
+                ("description", Json::str(&frame.description)),
+                ("span", span(codemap, frame.span, "")),
+                ("call_site", span(codemap, frame.call_site, "")),
+            ])
+        })
+        .collect()
+}
+
+/// A span in the shape rustc uses for its JSON diagnostics.
+fn span(codemap: &CodeMap, span: Span, label: &str) -> Json {
+    let lo = codemap.lookup_char_pos(span.lo());
+    let hi = codemap.lookup_char_pos(span.hi());
+    Json::object(vec![
+        ("file_name", Json::str(&lo.file.name.to_string())),
+        ("line_start", Json::Number(lo.line as f64)),
+        ("line_end", Json::Number(hi.line as f64)),
+        ("column_start", Json::Number((lo.col.0 + 1) as f64)),
+        ("column_end", Json::Number((hi.col.0 + 1) as f64)),
+        ("label", if label.is_empty() { Json::Null } else { Json::str(label) }),
+    ])
+}
+
+fn allocation(codemap: &CodeMap, info: &AllocationInfo<MiriMemoryKind>) -> Json {
+    let created_at = if info.created_at == DUMMY_SP {
+        Json::Null
+    } else {
+        span(codemap, info.created_at, "")
+    };
+    Json::object(vec![
+        ("id", Json::str(&info.id.to_string())),
+        ("size", Json::Number(info.size as f64)),
+        ("align", Json::Number(info.align as f64)),
+        ("kind", Json::str(&format!("{:?}", info.kind))),
+        ("label", info.label.map_or(Json::Null, Json::str)),
+        ("created_at", created_at),
+    ])
+}
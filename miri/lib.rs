@@ -10,149 +10,822 @@ extern crate log;
 extern crate rustc;
 extern crate syntax;
 
-use rustc::ty::{self, TyCtxt};
+use rustc::ty::{self, Ty, TyCtxt};
 use rustc::ty::layout::Layout;
-use rustc::hir::def_id::DefId;
+use rustc::ty::subst::Kind;
+use rustc::hir::def_id::{DefId, LOCAL_CRATE};
 use rustc::mir;
 
-use syntax::ast::Mutability;
-use syntax::codemap::Span;
+use syntax::ast::{FloatTy, Mutability};
+use syntax::codemap::{Span, DUMMY_SP};
 
-use std::collections::{HashMap, BTreeMap};
+use std::collections::{HashMap, HashSet, BTreeMap};
+use std::iter;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 #[macro_use]
 extern crate rustc_miri;
 pub use rustc_miri::interpret::*;
 
 mod fn_call;
+mod shims;
 mod operator;
 mod intrinsic;
 mod helpers;
 mod memory;
 mod tls;
+mod files;
+mod mmap;
+mod time;
+mod random;
+mod host_fns;
+mod panic;
+mod assertions;
+mod dap;
+mod core_dump;
+mod heatmap;
+mod suppressions;
+mod severity;
+mod allocator;
+mod fuzz;
+mod survey;
+mod capabilities;
+mod config;
+mod json_report;
+
+pub use allocator::AllocatorBehavior;
+pub use capabilities::print_capabilities;
+pub use config::{parse_flag, read_env};
+pub use core_dump::print_core_dump;
+pub use fuzz::{eval_for_fuzzing, fuzz_limits, FuzzOutcome};
+pub use host_fns::{HostFn, HostFunctions};
 
 use fn_call::EvalContextExt as MissingFnsEvalContextExt;
 use operator::EvalContextExt as OperatorEvalContextExt;
 use intrinsic::EvalContextExt as IntrinsicEvalContextExt;
 use tls::EvalContextExt as TlsEvalContextExt;
+use panic::EvalContextExt as PanicEvalContextExt;
+use survey::EvalContextExt as SurveyEvalContextExt;
+use suppressions::{Suppressions, Finding, error_category};
+pub use severity::{Severity, SeverityConfig};
 
-pub fn eval_main<'a, 'tcx: 'a>(
-    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+/// Settings of the machine that are not resource limits.
+#[derive(Clone, Debug)]
+pub struct MiriConfig {
+    /// Keep the evaluated program from observing the host. If this is disabled, the program sees
+    /// the environment variables, files and clocks of the host.
+    pub isolation: bool,
+    /// The arguments passed to the program, not including the program name.
+    pub args: Vec<String>,
+    /// The seed of the generator behind the program's source of randomness.
+    pub seed: u64,
+    /// Step limits of individual functions, by item path, see `EvalContext::step_budgets`.
+    pub step_budgets: HashMap<String, u64>,
+    /// Functions of the embedder the program can call as foreign functions.
+    pub host_fns: HostFunctions,
+    /// Read undefined integers as 0 with a warning, see `EvalContext::tolerate_undef_ints`.
+    pub tolerate_undef_ints: bool,
+    /// Read the tolerated undefined integers as garbage generated from `seed` instead of as 0,
+    /// see `Memory::fill_undef_with_garbage`.
+    pub undef_int_garbage: bool,
+    /// Give allocations base addresses generated from `seed`, see `Memory::address`.
+    pub randomize_addresses: bool,
+    /// Force overflow checks on or off, see `EvalContext::overflow_checks`. `None` uses the
+    /// setting of the crate being interpreted.
+    pub overflow_checks: Option<bool>,
+    /// How the Rust global allocator behaves, see the `allocator` module.
+    pub allocator: AllocatorBehavior,
+    /// Unwind from unsupported operations instead of stopping, and report them at the end, see the
+    /// `survey` module.
+    pub survey: bool,
+    /// Functions, by item path, whose inline assembly is known to be harmless and is skipped.
+    pub skip_asm: Vec<String>,
+    /// Look for functions that are reachable from `main` but cannot be evaluated before the
+    /// evaluation starts, see `check_reachable`.
+    pub check_reachable: bool,
+    /// Check references when they are created or passed, see `EvalContext::check_references`.
+    pub check_references: bool,
+    /// Check that `&mut`s are not used after their memory was accessed through another path, see
+    /// `EvalContext::check_aliasing`.
+    pub check_aliasing: bool,
+    /// Execute the validation statements emitted with `-Zmir-emit-validate`, see
+    /// `EvalContext::validate`.
+    pub validate: bool,
+    /// Do not report the memory a program leaks.
+    pub ignore_leaks: bool,
+    /// Override the resource limits set with `#![miri(...)]` attributes.
+    pub memory_limit: Option<u64>,
+    pub stack_limit: Option<usize>,
+    pub step_limit: Option<u64>,
+    /// Where to write the access counts to when the evaluation ends, see the `heatmap` module,
+    /// and the size of the buckets they are broken down into.
+    pub heatmap: Option<String>,
+    pub heatmap_bucket: Option<u64>,
+    /// Where to send the evaluation events to, see `EventStream`.
+    pub events: Option<String>,
+    /// The port to serve the debug adapter protocol on, see the `dap` module.
+    pub dap_port: Option<u16>,
+    /// Where to write a core dump of the evaluation to if it fails, see the `core_dump` module.
+    pub core_dump: Option<String>,
+    /// The file to read suppressions from, and the one to record unsuppressed findings in, see
+    /// the `suppressions` module.
+    pub suppressions: Option<String>,
+    pub generate_suppressions: Option<String>,
+    /// How findings of each category are reported, see the `severity` module.
+    pub severities: SeverityConfig,
+}
+
+impl Default for MiriConfig {
+    fn default() -> Self {
+        MiriConfig {
+            isolation: true,
+            args: Vec::new(),
+            seed: 0,
+            step_budgets: HashMap::new(),
+            host_fns: HostFunctions::default(),
+            tolerate_undef_ints: false,
+            undef_int_garbage: false,
+            randomize_addresses: false,
+            overflow_checks: None,
+            allocator: AllocatorBehavior::default(),
+            survey: false,
+            skip_asm: Vec::new(),
+            check_reachable: false,
+            check_references: false,
+            check_aliasing: false,
+            validate: true,
+            ignore_leaks: false,
+            memory_limit: None,
+            stack_limit: None,
+            step_limit: None,
+            heatmap: None,
+            heatmap_bucket: None,
+            events: None,
+            dap_port: None,
+            core_dump: None,
+            suppressions: None,
+            generate_suppressions: None,
+            severities: SeverityConfig::default(),
+        }
+    }
+}
+
+/// Fails if a function reachable from `main` (or the `start` lang item calling it) has neither MIR
+/// nor a replacement in miri, before anything is evaluated.
+fn check_reachable<'a, 'tcx: 'a>(
+    ecx: &rustc_miri::interpret::EvalContext<'a, 'tcx, Evaluator>,
+    main_instance: ty::Instance<'tcx>,
+    start_wrapper: Option<DefId>,
+) -> EvalResult<'tcx> {
+    // The search takes the last entry first, so what `main` calls is reported before the
+    // functions of the runtime.
+    let mut entries: Vec<_> = start_wrapper
+        .map(|start_id| ty::Instance::mono(ecx.tcx, start_id))
+        .into_iter()
+        .collect();
+    entries.push(main_instance);
+    let reachable = ecx.collect_reachable(&entries);
+    info!(
+        "{} functions are reachable, {} of them without MIR",
+        reachable.instances.len(),
+        reachable.missing_mir.len(),
+    );
+    for &instance in &reachable.missing_mir {
+        ecx.check_missing_fn(instance)?;
+    }
+    Ok(())
+}
+
+fn run_main<'a, 'tcx: 'a>(
+    ecx: &mut rustc_miri::interpret::EvalContext<'a, 'tcx, Evaluator>,
     main_id: DefId,
     start_wrapper: Option<DefId>,
-    limits: ResourceLimits,
-) {
-    fn run_main<'a, 'tcx: 'a>(
-        ecx: &mut rustc_miri::interpret::EvalContext<'a, 'tcx, Evaluator>,
-        main_id: DefId,
-        start_wrapper: Option<DefId>,
-    ) -> EvalResult<'tcx> {
-        let main_instance = ty::Instance::mono(ecx.tcx, main_id);
-        let main_mir = ecx.load_mir(main_instance.def)?;
-        let mut cleanup_ptr = None; // Pointer to be deallocated when we are done
-
-        if !main_mir.return_ty.is_nil() || main_mir.arg_count != 0 {
-            return err!(Unimplemented(
-                "miri does not support main functions without `fn()` type signatures"
-                    .to_owned(),
-            ));
+    mut debugger: Option<&mut dap::Debugger>,
+) -> EvalResult<'tcx> {
+    let main_instance = ty::Instance::mono(ecx.tcx, main_id);
+    if ecx.machine_data.config.check_reachable {
+        check_reachable(ecx, main_instance, start_wrapper)?;
+    }
+    let main_mir = ecx.load_mir(main_instance.def)?;
+
+    let main_ret_ty = main_mir.return_ty;
+    let returns_result = termination_err_ty(ecx.tcx, main_ret_ty).is_some();
+    // Both `fn main() -> i32` and `#[start] fn start(argc: isize, argv: *const *const u8) -> isize`
+    // return the exit code of the program.
+    let returns_code = match main_ret_ty.sty {
+        ty::TyInt(_) => true,
+        _ => false,
+    };
+    let start_instance = start_wrapper.map(|start_id| {
+        start_lang_item(ecx.tcx, start_id, main_ret_ty)
+    });
+    let supported = match (main_mir.arg_count, start_instance) {
+        // A generic start lang item runs `main` through the `Termination` trait, an old one
+        // only knows `fn main()`.
+        (0, Some(start_instance)) => main_ret_ty.is_nil() || !start_instance.substs.is_empty(),
+        (0, None) => main_ret_ty.is_nil() || returns_result || returns_code,
+        // A `#[start]` function is called like the start lang item.
+        (2, None) => returns_code,
+        _ => false,
+    };
+    if !supported {
+        return err!(Unimplemented(
+            "miri does not support main functions without `fn()` type signatures"
+                .to_owned(),
+        ));
+    }
+    // Where the exit code ends up, and its size
+    let mut exit_code_ptr = None;
+    let mut main_ret_ptr = None;
+
+    if let Some(start_instance) = start_instance {
+        let start_mir = ecx.load_mir(start_instance.def)?;
+
+        if start_mir.arg_count != 3 {
+            return err!(AbiViolation(format!(
+                "'start' lang item should have three arguments, but has {}",
+                start_mir.arg_count
+            )));
         }
 
-        if let Some(start_id) = start_wrapper {
-            let start_instance = ty::Instance::mono(ecx.tcx, start_id);
-            let start_mir = ecx.load_mir(start_instance.def)?;
+        // Return value
+        let size = ecx.tcx.data_layout.pointer_size.bytes();
+        let align = ecx.tcx.data_layout.pointer_align.abi();
+        let ret_ptr = ecx.memory_mut().allocate(size, align, MemoryKind::Stack)?;
+        exit_code_ptr = Some((ret_ptr, size));
 
-            if start_mir.arg_count != 3 {
-                return err!(AbiViolation(format!(
-                    "'start' lang item should have three arguments, but has {}",
-                    start_mir.arg_count
-                )));
-            }
+        // Push our stack frame
+        ecx.push_stack_frame(
+            start_instance,
+            start_mir.span,
+            start_mir,
+            Lvalue::from_ptr(ret_ptr),
+            StackPopCleanup::None,
+        )?;
 
-            // Return value
-            let size = ecx.tcx.data_layout.pointer_size.bytes();
-            let align = ecx.tcx.data_layout.pointer_align.abi();
+        let mut args = ecx.frame().mir.args_iter();
+
+        // First argument: pointer to main()
+        let main_ptr = ecx.memory_mut().create_fn_alloc(main_instance);
+        let dest = ecx.eval_lvalue(&mir::Lvalue::Local(args.next().unwrap()))?;
+        let main_ty = main_instance.def.def_ty(ecx.tcx);
+        let main_ptr_ty = ecx.tcx.mk_fn_ptr(main_ty.fn_sig(ecx.tcx));
+        ecx.write_value(
+            ValTy {
+                value: Value::ByVal(PrimVal::Ptr(main_ptr)),
+                ty: main_ptr_ty,
+            },
+            dest,
+        )?;
+
+        let argc = args.next().unwrap();
+        let argv = args.next().unwrap();
+        write_program_args(ecx, argc, argv)?;
+
+        assert!(args.next().is_none(), "start lang item has more arguments than expected");
+    } else {
+        let return_lvalue = if returns_result || returns_code {
+            let size = ecx.type_size(main_ret_ty)?.expect("main returns a sized type");
+            let align = ecx.type_align(main_ret_ty)?;
             let ret_ptr = ecx.memory_mut().allocate(size, align, MemoryKind::Stack)?;
-            cleanup_ptr = Some(ret_ptr);
+            if returns_code {
+                exit_code_ptr = Some((ret_ptr, size));
+            } else {
+                main_ret_ptr = Some(ret_ptr);
+            }
+            Lvalue::from_ptr(ret_ptr)
+        } else {
+            Lvalue::undef()
+        };
+        ecx.push_stack_frame(
+            main_instance,
+            main_mir.span,
+            main_mir,
+            return_lvalue,
+            StackPopCleanup::None,
+        )?;
+
+        let mut args = ecx.frame().mir.args_iter();
+        if main_mir.arg_count == 2 {
+            let argc = args.next().unwrap();
+            let argv = args.next().unwrap();
+            write_program_args(ecx, argc, argv)?;
+        }
+        assert!(args.next().is_none(), "main function has more arguments than expected");
+    }
 
-            // Push our stack frame
-            ecx.push_stack_frame(
-                start_instance,
-                start_mir.span,
-                start_mir,
+    loop {
+        if let Some(ref mut debugger) = debugger {
+            debugger.before_step(ecx);
+        }
+        match ecx.step() {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => {
+                let survey = ecx.machine_data.config.survey;
+                if survey && survey::is_unsupported(&e.kind) && ecx.skip_unsupported(&e)? {
+                    continue;
+                }
+                if let Some(ref mut debugger) = debugger {
+                    debugger.on_error(ecx, &e);
+                }
+                return Err(e);
+            }
+        }
+    }
+    ecx.run_tls_dtors()?;
+    if let Some((code_ptr, size)) = exit_code_ptr {
+        // The start lang item returns the exit code of the program, which is where libstd
+        // reports a `main` that returned an error.
+        let code = ecx.memory.read_primval(code_ptr, size, true)?.to_i128()? as i32;
+        ecx.memory_mut().deallocate(
+            code_ptr,
+            None,
+            MemoryKind::Stack,
+        )?;
+        if code != 0 {
+            return err!(Exit(code));
+        }
+    }
+    if let Some(ret_ptr) = main_ret_ptr {
+        // Without libstd's MIR there is nobody to implement `Termination` for us.
+        let failed = ecx.read_discriminant_value(ret_ptr, main_ret_ty)? == 1;
+        if failed {
+            let err_ty = termination_err_ty(ecx.tcx, main_ret_ty).unwrap();
+            let adt_def = main_ret_ty.ty_adt_def().unwrap();
+            let err_lvalue = ecx.eval_lvalue_projection(
                 Lvalue::from_ptr(ret_ptr),
-                StackPopCleanup::None,
+                main_ret_ty,
+                &mir::ProjectionElem::Downcast(adt_def, 1),
             )?;
+            let err_lvalue =
+                ecx.lvalue_field(err_lvalue, mir::Field::new(0), main_ret_ty, err_ty)?;
+            let rendered = render_termination_error(ecx, err_lvalue.to_ptr()?, err_ty)?;
+            eprintln!("Error: {}", rendered);
+        }
+        ecx.memory_mut().deallocate(ret_ptr, None, MemoryKind::Stack)?;
+        if failed {
+            return err!(Exit(1));
+        }
+    }
+    Ok(())
+}
 
-            let mut args = ecx.frame().mir.args_iter();
-
-            // First argument: pointer to main()
-            let main_ptr = ecx.memory_mut().create_fn_alloc(main_instance);
-            let dest = ecx.eval_lvalue(&mir::Lvalue::Local(args.next().unwrap()))?;
-            let main_ty = main_instance.def.def_ty(ecx.tcx);
-            let main_ptr_ty = ecx.tcx.mk_fn_ptr(main_ty.fn_sig(ecx.tcx));
-            ecx.write_value(
-                ValTy {
-                    value: Value::ByVal(PrimVal::Ptr(main_ptr)),
-                    ty: main_ptr_ty,
-                },
-                dest,
-            )?;
+pub fn eval_main<'a, 'tcx: 'a>(
+    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+    main_id: DefId,
+    start_wrapper: Option<DefId>,
+    limits: ResourceLimits,
+    config: MiriConfig,
+) {
+    let mut ecx = new_eval_context(tcx, limits, config);
+    let mut debugger = start_debugger(tcx, ecx.machine_data.config.dap_port);
+    let exit_code = eval_entry(&mut ecx, main_id, start_wrapper, debugger.as_mut());
+    finish(&ecx, debugger.as_mut(), exit_code);
+}
 
-            // Second argument (argc): 1
-            let dest = ecx.eval_lvalue(&mir::Lvalue::Local(args.next().unwrap()))?;
-            let ty = ecx.tcx.types.isize;
-            ecx.write_primval(dest, PrimVal::Bytes(1), ty)?;
-
-            // FIXME: extract main source file path
-            // Third argument (argv): &[b"foo"]
-            let dest = ecx.eval_lvalue(&mir::Lvalue::Local(args.next().unwrap()))?;
-            let ty = ecx.tcx.mk_imm_ptr(ecx.tcx.mk_imm_ptr(ecx.tcx.types.u8));
-            let foo = ecx.memory.allocate_cached(b"foo\0")?;
-            let ptr_size = ecx.memory.pointer_size();
-            let foo_ptr = ecx.memory.allocate(ptr_size * 1, ptr_size, MemoryKind::UninitializedStatic)?;
-            ecx.memory.write_primval(foo_ptr.into(), PrimVal::Ptr(foo.into()), ptr_size, false)?;
-            ecx.memory.mark_static_initalized(foo_ptr.alloc_id, Mutability::Immutable)?;
-            ecx.write_ptr(dest, foo_ptr.into(), ty)?;
-
-            assert!(args.next().is_none(), "start lang item has more arguments than expected");
-        } else {
-            ecx.push_stack_frame(
-                main_instance,
-                main_mir.span,
-                main_mir,
-                Lvalue::undef(),
-                StackPopCleanup::None,
-            )?;
+/// Evaluates the functions `entries` one after the other in a single interpreter, like the test
+/// harness runs tests in a single process. The entries share the evaluated constants and statics,
+/// including what earlier entries wrote to the statics, and the MIR and layouts are only computed
+/// once. An entry that fails is reported and the next one runs, only `exit`, `abort` and an error
+/// while popping the frames of a failed entry end the whole batch. Returns the number of entries
+/// that failed.
+pub fn eval_batch<'a, 'tcx: 'a>(
+    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+    entries: &[DefId],
+    limits: ResourceLimits,
+    config: MiriConfig,
+) -> usize {
+    let mut ecx = new_eval_context(tcx, limits, config);
+    let mut debugger = start_debugger(tcx, ecx.machine_data.config.dap_port);
+    let mut failed = 0;
+    let mut exit_code = None;
+    let mut not_run = 0;
+    for (i, &entry) in entries.iter().enumerate() {
+        // The evaluated program owns stdout.
+        eprintln!("running test: {}", tcx.hir.def_path(entry).to_string(tcx));
+        let errors = tcx.sess.err_count();
+        exit_code = eval_entry(&mut ecx, entry, None, debugger.as_mut());
+        if tcx.sess.err_count() > errors {
+            failed += 1;
+        }
+        if exit_code.is_some() {
+            not_run = entries.len() - i - 1;
+            break;
+        }
+        // A failed entry stops wherever the error happened.
+        if let Err(e) = ecx.abandon_stack() {
+            let path = tcx.item_path_str(entry);
+            let msg = format!("could not clean up after `{}`, stopping the tests: {}", path, e);
+            tcx.sess.err(&msg);
+            not_run = entries.len() - i - 1;
+            break;
+        }
+        ecx.machine_data.catch_panics.clear();
+        ecx.machine_data.panic_payload = None;
+    }
+    let passed = entries.len() - failed - not_run;
+    if not_run == 0 {
+        eprintln!("test result: {} passed; {} failed", passed, failed);
+    } else {
+        eprintln!("test result: {} passed; {} failed; {} not run", passed, failed, not_run);
+    }
+    finish(&ecx, debugger.as_mut(), exit_code);
+    failed
+}
 
-            // No arguments
-            let mut args = ecx.frame().mir.args_iter();
-            assert!(args.next().is_none(), "main function must not have arguments");
+fn new_eval_context<'a, 'tcx: 'a>(
+    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+    mut limits: ResourceLimits,
+    config: MiriConfig,
+) -> EvalContext<'a, 'tcx, Evaluator> {
+    limits.memory_size = config.memory_limit.unwrap_or(limits.memory_size);
+    limits.stack_limit = config.stack_limit.unwrap_or(limits.stack_limit);
+    limits.step_limit = config.step_limit.unwrap_or(limits.step_limit);
+    let step_budgets = config.step_budgets.clone();
+    let tolerate_undef_ints = config.tolerate_undef_ints;
+    let check_references = config.check_references;
+    let check_aliasing = config.check_aliasing;
+    let validate = config.validate;
+    let seed = config.seed;
+    let undef_int_garbage = config.undef_int_garbage;
+    let randomize_addresses = config.randomize_addresses;
+    let overflow_checks = config.overflow_checks;
+    let reporting = Rc::new(Reporting::new(tcx, &config));
+    let data = EvaluatorData { config, reporting, ..Default::default() };
+    let mut ecx = EvalContext::new(tcx, limits, data, Default::default());
+    ecx.step_budgets = step_budgets;
+    ecx.tolerate_undef_ints = tolerate_undef_ints;
+    if undef_int_garbage {
+        ecx.memory.fill_undef_with_garbage(seed);
+    }
+    if randomize_addresses {
+        ecx.memory.randomize_addresses(seed);
+    }
+    if let Some(overflow_checks) = overflow_checks {
+        ecx.overflow_checks = overflow_checks;
+    }
+    ecx.check_references = check_references;
+    ecx.check_aliasing = check_aliasing;
+    ecx.validate = validate;
+    if ecx.machine_data.config.heatmap.is_some() {
+        let bucket_size = ecx.machine_data.config.heatmap_bucket;
+        ecx.memory.record_access_counts(bucket_size);
+    }
+    if let Some(path) = ecx.machine_data.config.events.clone() {
+        // This may also be a named pipe, in case the consumer wants to follow along live.
+        match std::fs::OpenOptions::new().write(true).create(true).truncate(true).open(&path) {
+            Ok(file) => ecx.memory.set_event_stream(EventStream::new(file)),
+            Err(e) => tcx.sess.warn(&format!("could not open event stream `{}`: {}", path, e)),
         }
+    }
+    ecx
+}
 
-        while ecx.step()? {}
-        ecx.run_tls_dtors()?;
-        if let Some(cleanup_ptr) = cleanup_ptr {
-            ecx.memory_mut().deallocate(
-                cleanup_ptr,
-                None,
-                MemoryKind::Stack,
-            )?;
+fn start_debugger(tcx: TyCtxt, port: Option<u16>) -> Option<dap::Debugger> {
+    match port {
+        Some(port) => {
+            match dap::Debugger::listen(port) {
+                Ok(debugger) => Some(debugger),
+                Err(e) => {
+                    tcx.sess.warn(&format!("could not start the debug adapter server: {}", e));
+                    None
+                }
+            }
+        }
+        None => None,
+    }
+}
+
+/// How findings are reported, see `MiriConfig::suppressions` and `MiriConfig::severities`.
+#[derive(Default)]
+pub(crate) struct Reporting {
+    suppressions: Suppressions,
+    severities: SeverityConfig,
+    generate_suppressions: Option<String>,
+}
+
+impl Reporting {
+    fn new(tcx: TyCtxt, config: &MiriConfig) -> Self {
+        let suppressions = match config.suppressions {
+            Some(ref path) => Suppressions::load(path).unwrap_or_else(|e| {
+                tcx.sess.warn(&format!("could not read the suppressions file: {}", e));
+                Suppressions::default()
+            }),
+            None => Suppressions::default(),
+        };
+        Reporting {
+            suppressions,
+            severities: config.severities.clone(),
+            generate_suppressions: config.generate_suppressions.clone(),
         }
-        Ok(())
     }
 
-    let mut ecx = EvalContext::new(tcx, limits, Default::default(), Default::default());
-    match run_main(&mut ecx, main_id, start_wrapper) {
+    /// Records `finding` in the file for generated suppressions, if there is one.
+    fn record(&self, tcx: TyCtxt, finding: &Finding) {
+        if let Some(ref path) = self.generate_suppressions {
+            if let Err(e) = suppressions::record(path, finding) {
+                tcx.sess.warn(&format!("could not write to the suppressions file: {}", e));
+            }
+        }
+    }
+}
+
+/// Evaluates `main_id` and reports what went wrong. Returns the exit code the program asked for,
+/// if it did not return from `main`.
+fn eval_entry<'a, 'tcx: 'a>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    main_id: DefId,
+    start_wrapper: Option<DefId>,
+    debugger: Option<&mut dap::Debugger>,
+) -> Option<i32> {
+    let tcx = ecx.tcx;
+    let reporting = ecx.machine_data.reporting.clone();
+    let Reporting { ref suppressions, ref severities, .. } = *reporting;
+    let json = json_report::enabled(tcx.sess);
+    // Only what this entry leaked is its fault, earlier ones may have leaked too.
+    let leaks_before = ecx.memory().leak_report();
+    let leaked_before = live_non_statics(ecx);
+    // A program that returns from `main` exits with 0, one that panics has an error reported and
+    // thus exits like a failed compilation (with 101). Only `exit` picks its own exit code, and
+    // `abort` exits like a process killed by `SIGABRT` would in a shell.
+    let mut exit_code = None;
+    match run_main(ecx, main_id, start_wrapper, debugger) {
+        Err(EvalError { kind: EvalErrorKind::Exit(code), .. }) => {
+            // Memory still in use at `exit` is not leaked, the process is simply torn down.
+            exit_code = Some(code);
+        }
         Ok(()) => {
             let leaks = ecx.memory().leak_report();
-            if leaks != 0 {
-                tcx.sess.err("the evaluated program leaked memory");
+            if leaks > leaks_before && !ecx.machine_data.config.ignore_leaks {
+                let finding = Finding::new(ecx, "MemoryLeak".to_owned());
+                let msg = "the evaluated program leaked memory";
+                if suppressions.is_suppressed(&finding) {
+                    tcx.sess.warn(&format!("suppressed: {}", msg));
+                } else {
+                    reporting.record(tcx, &finding);
+                    match severities.severity(&finding.kind) {
+                        Severity::Error if json => {
+                            json_report::leak(ecx, &leaked_before, msg, "error");
+                            tcx.sess.diagnostic().bump_err_count();
+                        }
+                        Severity::Warn if json => {
+                            json_report::leak(ecx, &leaked_before, msg, "warning");
+                        }
+                        Severity::Error => report_leak(ecx, main_id, &leaked_before, msg, true),
+                        Severity::Warn => report_leak(ecx, main_id, &leaked_before, msg, false),
+                        Severity::Allow => {}
+                    }
+                }
             }
         }
         Err(mut e) => {
-            ecx.report(&mut e);
+            if let EvalErrorKind::Abort = e.kind {
+                exit_code = Some(134);
+            }
+            let finding = Finding::new(ecx, error_category(&e.kind));
+            let severity = severities.severity(&finding.kind);
+            if suppressions.is_suppressed(&finding) {
+                tcx.sess.warn(&format!("suppressed: {}", e));
+            } else if severity != Severity::Error {
+                reporting.record(tcx, &finding);
+                if severity == Severity::Warn && json {
+                    json_report::error(ecx, &e, &finding.kind, "warning");
+                } else if severity == Severity::Warn {
+                    tcx.sess.warn(&format!("{} (at {})", e, finding.location));
+                }
+            } else {
+                reporting.record(tcx, &finding);
+                if json {
+                    json_report::error(ecx, &e, &finding.kind, "error");
+                    // Fail like for any other error, without rustc's rendering of it.
+                    tcx.sess.diagnostic().bump_err_count();
+                } else {
+                    ecx.report(&mut e);
+                }
+                if let Some(path) = ecx.machine_data.config.core_dump.clone() {
+                    if let Err(io_err) = core_dump::write_core_dump(ecx, &e, &path) {
+                        let msg = format!("could not write core dump to `{}`: {}", path, io_err);
+                        tcx.sess.warn(&msg);
+                    }
+                }
+            }
+        }
+    }
+    exit_code
+}
+
+/// Writes the reports that cover the whole evaluation, and exits with `exit_code` if it is set.
+fn finish<'a, 'tcx: 'a>(
+    ecx: &EvalContext<'a, 'tcx, Evaluator>,
+    debugger: Option<&mut dap::Debugger>,
+    exit_code: Option<i32>,
+) {
+    let tcx = ecx.tcx;
+    if ecx.machine_data.config.survey {
+        survey::print_report(&ecx.machine_data.survey);
+    }
+    if let Some(ref path) = ecx.machine_data.config.heatmap {
+        if let Err(e) = heatmap::write_heatmap(ecx, path) {
+            tcx.sess.warn(&format!("could not write the heatmap to `{}`: {}", path, e));
+        }
+    }
+    if let Some(debugger) = debugger {
+        debugger.finish(exit_code.unwrap_or(if tcx.sess.has_errors() { 1 } else { 0 }));
+    }
+    if let Some(code) = exit_code {
+        if code != 0 {
+            std::process::exit(code);
+        }
+    }
+}
+
+/// The instance of the start lang item that calls a `main` returning `main_ret_ty`. Newer
+/// libstds make it generic over the return type, which has to implement `Termination`.
+fn start_lang_item<'a, 'tcx: 'a>(
+    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+    start_id: DefId,
+    main_ret_ty: Ty<'tcx>,
+) -> ty::Instance<'tcx> {
+    if tcx.generics_of(start_id).types.is_empty() {
+        ty::Instance::mono(tcx, start_id)
+    } else {
+        ty::Instance::new(start_id, tcx.mk_substs(iter::once(Kind::from(main_ret_ty))))
+    }
+}
+
+/// Writes `argc` and `argv` to the locals of the topmost frame with these names, like the
+/// operating system passes them to a compiled program.
+fn write_program_args<'a, 'tcx: 'a>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    argc_local: mir::Local,
+    argv_local: mir::Local,
+) -> EvalResult<'tcx> {
+    // The program is named after its crate, followed by the arguments from the command line.
+    let mut program_args = vec![ecx.tcx.crate_name(LOCAL_CRATE).as_str().to_string()];
+    program_args.extend(ecx.machine_data.config.args.iter().cloned());
+    let argc = program_args.len() as u64;
+
+    let dest = ecx.eval_lvalue(&mir::Lvalue::Local(argc_local))?;
+    let ty = ecx.tcx.types.isize;
+    ecx.write_primval(dest, PrimVal::Bytes(argc as u128), ty)?;
+
+    // A null-terminated array of pointers to null-terminated strings
+    let dest = ecx.eval_lvalue(&mir::Lvalue::Local(argv_local))?;
+    let ty = ecx.tcx.mk_imm_ptr(ecx.tcx.mk_imm_ptr(ecx.tcx.types.u8));
+    let ptr_size = ecx.memory.pointer_size();
+    let static_kind = MemoryKind::UninitializedStatic;
+    let argv = ecx.memory.allocate(ptr_size * (argc + 1), ptr_size, static_kind)?;
+    for (i, arg) in program_args.iter().enumerate() {
+        let mut bytes = arg.as_bytes().to_owned();
+        bytes.push(0);
+        let arg_ptr = ecx.memory.allocate(bytes.len() as u64, 1, static_kind)?;
+        ecx.memory.write_bytes(arg_ptr.into(), &bytes)?;
+        ecx.memory.mark_static_initalized(arg_ptr.alloc_id, Mutability::Mutable)?;
+        let entry = argv.offset(i as u64 * ptr_size, &ecx)?;
+        ecx.memory.write_primval(entry, PrimVal::Ptr(arg_ptr), ptr_size, false)?;
+    }
+    let end = argv.offset(argc * ptr_size, &ecx)?;
+    ecx.memory.write_primval(end, PrimVal::Bytes(0), ptr_size, false)?;
+    ecx.memory.mark_static_initalized(argv.alloc_id, Mutability::Mutable)?;
+    ecx.write_ptr(dest, argv.into(), ty)?;
+
+    // Some platforms (e.g. macOS) let libstd ask for the arguments again later
+    let argc_ptr = ecx.memory.allocate(4, 4, static_kind)?;
+    ecx.memory.write_primval(argc_ptr, PrimVal::Bytes(argc as u128), 4, false)?;
+    ecx.memory.mark_static_initalized(argc_ptr.alloc_id, Mutability::Mutable)?;
+    let argv_ptr = ecx.memory.allocate(ptr_size, ptr_size, static_kind)?;
+    ecx.memory.write_primval(argv_ptr, PrimVal::Ptr(argv), ptr_size, false)?;
+    ecx.memory.mark_static_initalized(argv_ptr.alloc_id, Mutability::Mutable)?;
+    ecx.machine_data.argc = Some(argc_ptr);
+    ecx.machine_data.argv = Some(argv_ptr);
+    Ok(())
+}
+
+/// The error type `E` if `ty` is `Result<(), E>`, the only non-unit return type of `main` we
+/// know how to report.
+fn termination_err_ty<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>, ty: Ty<'tcx>) -> Option<Ty<'tcx>> {
+    match ty.sty {
+        ty::TyAdt(adt_def, substs) => {
+            let path = tcx.item_path_str(adt_def.did);
+            let is_result = path == "std::result::Result" || path == "core::result::Result";
+            if is_result && substs.type_at(0).is_nil() {
+                Some(substs.type_at(1))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Mimics the `Debug` output of the error returned from `main`. Running the program's own `Debug`
+/// impl needs the MIR of libstd's formatting machinery, so other types are only named.
+fn render_termination_error<'a, 'tcx>(
+    ecx: &EvalContext<'a, 'tcx, Evaluator>,
+    ptr: MemoryPointer,
+    ty: Ty<'tcx>,
+) -> EvalResult<'tcx, String> {
+    let value = ecx.read_value(ptr.into(), ty)?;
+    let primval = || ecx.value_to_primval(ValTy { value, ty });
+    Ok(match ty.sty {
+        ty::TyBool => format!("{:?}", primval()?.to_bool()?),
+        ty::TyChar => {
+            let c = primval()?.to_u128()? as u32;
+            match ::std::char::from_u32(c) {
+                Some(c) => format!("{:?}", c),
+                None => return err!(InvalidChar(c as u128)),
+            }
         }
+        ty::TyInt(_) => format!("{}", primval()?.to_i128()?),
+        ty::TyUint(_) => format!("{}", primval()?.to_u128()?),
+        ty::TyFloat(FloatTy::F32) => format!("{:?}", primval()?.to_f32()?),
+        ty::TyFloat(FloatTy::F64) => format!("{:?}", primval()?.to_f64()?),
+        ty::TyRef(_, ty::TypeAndMut { ty: pointee, .. }) if pointee.sty == ty::TyStr => {
+            match value {
+                Value::ByValPair(ptr, len) => {
+                    let bytes = ecx.memory.read_bytes(ptr.into(), len.to_u64()?)?;
+                    format!("{:?}", String::from_utf8_lossy(bytes))
+                }
+                _ => bug!("a `&str` is not a pair: {:?}", value),
+            }
+        }
+        _ => format!("<value of type `{}`>", ty),
+    })
+}
+
+/// The allocations that are not statics, to tell the leaks of an entry point from earlier ones.
+fn live_non_statics<'a, 'tcx>(ecx: &EvalContext<'a, 'tcx, Evaluator>) -> Vec<AllocId> {
+    ecx.memory
+        .live_allocations()
+        .into_iter()
+        .filter(|info| info.kind != MemoryKind::Static)
+        .map(|info| info.id)
+        .collect()
+}
+
+/// How many of the leaked allocations a leak report points at.
+const MAX_LEAK_NOTES: usize = 10;
+
+/// Reports the allocations that are still live at the end of the evaluation, except for those in
+/// `leaked_before`, with where they were created. The report points at the entry point `main_id`,
+/// which finished without freeing them.
+fn report_leak<'a, 'tcx>(
+    ecx: &EvalContext<'a, 'tcx, Evaluator>,
+    main_id: DefId,
+    leaked_before: &[AllocId],
+    msg: &str,
+    error: bool,
+) {
+    let sess = ecx.tcx.sess;
+    let leaked: Vec<_> = ecx.memory
+        .live_allocations()
+        .into_iter()
+        .filter(|info| info.kind != MemoryKind::Static && !leaked_before.contains(&info.id))
+        .collect();
+    let span = ecx.tcx.def_span(main_id);
+    let mut diag = if error {
+        sess.struct_span_err(span, msg)
+    } else {
+        sess.struct_span_warn(span, msg)
+    };
+    for info in leaked.iter().take(MAX_LEAK_NOTES) {
+        if info.created_at != DUMMY_SP {
+            let note = format!("the allocation of {} bytes was created here", info.size);
+            diag.span_note(info.created_at, &note);
+        }
+    }
+    if leaked.len() > MAX_LEAK_NOTES {
+        diag.note(&format!("{} more allocations were leaked", leaked.len() - MAX_LEAK_NOTES));
+    }
+    diag.emit();
+}
+
+/// Reports a finding that does not stop the evaluation. One configured as an error still lets
+/// the evaluation go on, but makes it fail in the end.
+fn report_warning<'a, 'tcx>(ecx: &EvalContext<'a, 'tcx, Evaluator>, warning: Warning) {
+    let tcx = ecx.tcx;
+    let finding = Finding::new(ecx, warning.kind.to_owned());
+    let reporting = &ecx.machine_data.reporting;
+    if reporting.suppressions.is_suppressed(&finding) {
+        return;
+    }
+    let severity = reporting.severities.severity(&finding.kind);
+    if severity == Severity::Allow {
+        return;
+    }
+    reporting.record(tcx, &finding);
+    if json_report::enabled(tcx.sess) {
+        let level = if severity == Severity::Error { "error" } else { "warning" };
+        json_report::warning(ecx, &warning, level);
+        if severity == Severity::Error {
+            tcx.sess.diagnostic().bump_err_count();
+        }
+    } else if severity == Severity::Error {
+        tcx.sess.span_err(warning.span, &warning.message);
+    } else {
+        tcx.sess.span_warn(warning.span, &warning.message);
     }
 }
 
@@ -160,8 +833,47 @@ pub struct Evaluator;
 #[derive(Default)]
 pub struct EvaluatorData {
     /// Environment variables set by `setenv`
-    /// Miri does not expose env vars from the host to the emulated program
+    /// Miri does not expose env vars from the host to the emulated program, unless isolation is
+    /// disabled
     pub(crate) env_vars: HashMap<Vec<u8>, MemoryPointer>,
+
+    pub(crate) config: MiriConfig,
+
+    /// Host files opened by the program, by file descriptor
+    pub(crate) files: HashMap<i64, std::fs::File>,
+
+    /// Places holding `argc` and `argv`, for `_NSGetArgc` and `_NSGetArgv`
+    pub(crate) argc: Option<MemoryPointer>,
+    pub(crate) argv: Option<MemoryPointer>,
+
+    /// The time the fake clock shows, and when the host's monotonic clock was first read
+    pub(crate) fake_clock: Duration,
+    pub(crate) monotonic_origin: Option<Instant>,
+
+    /// Generates the random bytes the program asks for, seeded on first use
+    pub(crate) rng: Option<Rng>,
+
+    /// The running calls of `__rust_maybe_catch_panic`, innermost last, and the payload of the
+    /// panic that is unwinding the stack
+    pub(crate) catch_panics: Vec<panic::CatchPanic>,
+    pub(crate) panic_payload: Option<(PrimVal, PrimVal)>,
+
+    /// The pointers `dlsym` returned, and the symbol behind each of them
+    pub(crate) dlsym_ptrs: HashMap<String, MemoryPointer>,
+    pub(crate) dlsym_symbols: HashMap<AllocId, String>,
+
+    /// The alignment the program asked for, of the allocations of the Rust global allocator that
+    /// got a larger one
+    pub(crate) requested_aligns: HashMap<AllocId, u64>,
+
+    /// The unsupported operations encountered in survey mode
+    pub(crate) survey: survey::Survey,
+
+    /// Allocations made read-only by `miri_write_protect`
+    pub(crate) write_protected: HashSet<AllocId>,
+
+    /// How findings are reported, shared with the driver reporting the errors
+    pub(crate) reporting: Rc<Reporting>,
 }
 
 pub type TlsKey = usize;
@@ -179,6 +891,9 @@ pub struct MemoryData<'tcx> {
 
     /// pthreads-style thread-local storage.
     thread_local: BTreeMap<TlsKey, TlsEntry<'tcx>>,
+
+    /// Destructors of `#[thread_local]` statics with their arguments, in registration order.
+    thread_dtors: Vec<(ty::Instance<'tcx>, Pointer)>,
 }
 
 impl<'tcx> Machine<'tcx> for Evaluator {
@@ -198,6 +913,16 @@ impl<'tcx> Machine<'tcx> for Evaluator {
         ecx.eval_fn_call(instance, destination, args, span, sig)
     }
 
+    fn call_memory_fn_ptr<'a>(
+        ecx: &mut EvalContext<'a, 'tcx, Self>,
+        fn_ptr: MemoryPointer,
+        destination: Option<(Lvalue, mir::BasicBlock)>,
+        args: &[ValTy<'tcx>],
+        sig: ty::FnSig<'tcx>,
+    ) -> EvalResult<'tcx, bool> {
+        ecx.call_dlsym_ptr(fn_ptr, destination, args, sig)
+    }
+
     fn call_intrinsic<'a>(
         ecx: &mut rustc_miri::interpret::EvalContext<'a, 'tcx, Self>,
         instance: ty::Instance<'tcx>,
@@ -287,14 +1012,21 @@ impl<'tcx> Machine<'tcx> for Evaluator {
         mutability: Mutability,
     ) -> EvalResult<'tcx> {
         // FIXME: check that it's `#[linkage = "extern_weak"]`
-        trace!("Initializing an extern global with NULL");
+        // A weak symbol is the address of the function, or NULL if nobody defines it. Ours are
+        // the shims, so that e.g. libstd finds `__cxa_thread_atexit_impl` like it would natively.
+        let name = fn_call::link_name(ecx.tcx, instance.def_id());
+        let target = match ecx.dlsym(&name)? {
+            Some(f) => PrimVal::Ptr(f),
+            None => PrimVal::Bytes(0),
+        };
+        trace!("Initializing the extern global `{}` with {:?}", name, target);
         let ptr_size = ecx.memory.pointer_size();
         let ptr = ecx.memory.allocate(
             ptr_size,
             ptr_size,
             MemoryKind::UninitializedStatic,
         )?;
-        ecx.memory.write_ptr_sized_unsigned(ptr, PrimVal::Bytes(0))?;
+        ecx.memory.write_ptr_sized_unsigned(ptr, target)?;
         ecx.memory.mark_static_initalized(ptr.alloc_id, mutability)?;
         ecx.globals.insert(
             GlobalId {
@@ -308,4 +1040,20 @@ impl<'tcx> Machine<'tcx> for Evaluator {
         );
         Ok(())
     }
+
+    fn catch_unwind<'a>(ecx: &mut EvalContext<'a, 'tcx, Self>) -> EvalResult<'tcx> {
+        ecx.finish_catch_panic()
+    }
+
+    fn skip_inline_asm<'a>(
+        ecx: &EvalContext<'a, 'tcx, Self>,
+        instance: ty::Instance<'tcx>,
+    ) -> bool {
+        let path = ecx.tcx.item_path_str(instance.def_id());
+        ecx.machine_data.config.skip_asm.contains(&path)
+    }
+
+    fn warn<'a>(ecx: &EvalContext<'a, 'tcx, Self>, warning: Warning) {
+        report_warning(ecx, warning)
+    }
 }
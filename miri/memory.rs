@@ -7,6 +7,8 @@ pub enum MemoryKind {
     C,
     /// Part of env var emulation
     Env,
+    /// Error if deallocated any other way than `munmap`
+    Mmap,
 }
 
 impl Into<::rustc_miri::interpret::MemoryKind<MemoryKind>> for MemoryKind {
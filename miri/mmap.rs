@@ -0,0 +1,101 @@
+//! Shims for anonymous memory mappings. Every mapping is an allocation of its own, so `munmap` only
+//! supports whole mappings. `mprotect` makes a whole mapping read-only or writable and ignores
+//! everything else.
+
+use rustc_miri::interpret::{EvalContext, EvalResult, MemoryKind, Pointer, PrimVal};
+use syntax::ast::Mutability;
+
+use super::Evaluator;
+use super::memory::MemoryKind as MiriMemoryKind;
+
+const PAGE_SIZE: u64 = 4096;
+
+const PROT_WRITE: u64 = 0x2;
+const MAP_FIXED: u64 = 0x10;
+
+/// `MAP_ANONYMOUS`, which differs between platforms.
+const LINUX_MAP_ANONYMOUS: u64 = 0x20;
+const MACOS_MAP_ANONYMOUS: u64 = 0x1000;
+
+pub trait EvalContextExt<'tcx> {
+    /// Returns `MAP_FAILED` if the mapping could not be created.
+    fn mmap(&mut self, len: u64, prot: u64, flags: u64) -> EvalResult<'tcx, PrimVal>;
+
+    /// Like their libc counterparts, these return -1 if the operation failed.
+    fn munmap(&mut self, addr: Pointer, len: u64) -> EvalResult<'tcx, i64>;
+    fn mprotect(&mut self, addr: Pointer, len: u64, prot: u64) -> EvalResult<'tcx, i64>;
+}
+
+fn round_to_pages(len: u64) -> Option<u64> {
+    len.checked_add(PAGE_SIZE - 1).map(|len| len / PAGE_SIZE * PAGE_SIZE)
+}
+
+fn mutability(prot: u64) -> Mutability {
+    if prot & PROT_WRITE != 0 {
+        Mutability::Mutable
+    } else {
+        Mutability::Immutable
+    }
+}
+
+impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, Evaluator> {
+    fn mmap(&mut self, len: u64, prot: u64, flags: u64) -> EvalResult<'tcx, PrimVal> {
+        let anonymous = if self.tcx.sess.target.target.target_os == "macos" {
+            MACOS_MAP_ANONYMOUS
+        } else {
+            LINUX_MAP_ANONYMOUS
+        };
+        if flags & anonymous == 0 {
+            return err!(Unimplemented("miri does not support mapping files".to_owned()));
+        }
+        if flags & MAP_FIXED != 0 {
+            return err!(Unimplemented(
+                "miri does not support mappings at fixed addresses".to_owned(),
+            ));
+        }
+        let size = match round_to_pages(len) {
+            Some(size) if size != 0 => size,
+            _ => return Ok(PrimVal::from_i128(-1)),
+        };
+        let ptr = self.memory.allocate(size, PAGE_SIZE, MiriMemoryKind::Mmap.into())?;
+        // Anonymous mappings are zero-initialized.
+        self.memory.write_repeat(ptr.into(), 0, size)?;
+        self.memory.set_mutability(ptr.alloc_id, mutability(prot))?;
+        Ok(PrimVal::Ptr(ptr))
+    }
+
+    fn munmap(&mut self, addr: Pointer, len: u64) -> EvalResult<'tcx, i64> {
+        let ptr = addr.to_ptr()?;
+        let size = self.memory.get(ptr.alloc_id)?.bytes.len() as u64;
+        if ptr.offset != 0 || round_to_pages(len) != Some(size) {
+            return err!(Unimplemented(
+                "miri can only unmap whole mappings".to_owned(),
+            ));
+        }
+        self.memory.deallocate(
+            ptr,
+            Some((size, PAGE_SIZE)),
+            MiriMemoryKind::Mmap.into(),
+        )?;
+        Ok(0)
+    }
+
+    fn mprotect(&mut self, addr: Pointer, len: u64, prot: u64) -> EvalResult<'tcx, i64> {
+        let ptr = addr.to_ptr()?;
+        let (size, kind) = {
+            let alloc = self.memory.get(ptr.alloc_id)?;
+            (alloc.bytes.len() as u64, alloc.kind)
+        };
+        let whole_mapping = kind == MemoryKind::Machine(MiriMemoryKind::Mmap) &&
+            ptr.offset == 0 && round_to_pages(len) == Some(size);
+        if whole_mapping {
+            self.memory.set_mutability(ptr.alloc_id, mutability(prot))?;
+        } else {
+            self.warn(
+                "IgnoredCall",
+                "ignoring `mprotect` of memory that is not a whole mapping".to_owned(),
+            );
+        }
+        Ok(0)
+    }
+}
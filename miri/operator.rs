@@ -57,9 +57,12 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                 let result = match (left, right) {
                     (PrimVal::Bytes(left), PrimVal::Bytes(right)) => left == right,
                     (PrimVal::Ptr(left), PrimVal::Ptr(right)) => left == right,
+                    (PrimVal::Ptr(ptr), PrimVal::Bytes(bytes)) |
+                    (PrimVal::Bytes(bytes), PrimVal::Ptr(ptr)) => {
+                        self.memory.address(ptr).map_or(false, |address| address as u128 == bytes)
+                    }
                     (PrimVal::Undef, _) |
                     (_, PrimVal::Undef) => return err!(ReadUndefBytes),
-                    _ => false,
                 };
                 Ok(Some((PrimVal::from_bool(result), false)))
             }
@@ -67,9 +70,12 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                 let result = match (left, right) {
                     (PrimVal::Bytes(left), PrimVal::Bytes(right)) => left != right,
                     (PrimVal::Ptr(left), PrimVal::Ptr(right)) => left != right,
+                    (PrimVal::Ptr(ptr), PrimVal::Bytes(bytes)) |
+                    (PrimVal::Bytes(bytes), PrimVal::Ptr(ptr)) => {
+                        self.memory.address(ptr).map_or(true, |address| address as u128 != bytes)
+                    }
                     (PrimVal::Undef, _) |
                     (_, PrimVal::Undef) => return err!(ReadUndefBytes),
-                    _ => true,
                 };
                 Ok(Some((PrimVal::from_bool(result), false)))
             }
@@ -98,9 +104,17 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                         _ => bug!("We already established it has to be one of these operators."),
                     };
                     Ok(Some((PrimVal::from_bool(res), false)))
+                } else if left_kind != Ptr && self.memory.address(left).is_some() {
+                    // Integers made from pointers into different allocations compare by their
+                    // addresses, which `binary_op` uses from here on.
+                    Ok(None)
                 } else {
                     // Both are pointers, but from different allocations.
-                    err!(InvalidPointerMath)
+                    err!(InvalidPointerMath {
+                        op: bin_op,
+                        left: self.render_primval(PrimVal::Ptr(left), left_ty),
+                        right: self.render_primval(PrimVal::Ptr(right), right_ty),
+                    })
                 }
             }
             // These work if one operand is a pointer, the other an integer
@@ -161,6 +175,9 @@ impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, super::Evaluator>
                 } else if right & base_mask == 0 {
                     // Case 2: The base address bits are all taken away, i.e., right is all-0 there
                     (PrimVal::from_u128((left.offset & right) as u128), false)
+                } else if let Some(address) = self.memory.address(left) {
+                    // Case 3: Some of the base address bits are preserved, the base address decides
+                    (PrimVal::from_u128((address & right) as u128), false)
                 } else {
                     return err!(ReadPointerAsBytes);
                 }
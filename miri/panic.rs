@@ -0,0 +1,158 @@
+//! Panics that unwind the stack of the evaluated program.
+//!
+//! libstd starts unwinding with `__rust_start_panic` and catches it with
+//! `__rust_maybe_catch_panic`, the functions a panic runtime provides. The frame of the closure
+//! run by `__rust_maybe_catch_panic` is marked as catching the unwinding; when unwinding pops it,
+//! `__rust_maybe_catch_panic` returns 1 and hands out the panic payload.
+//!
+//! A panic that only the `catch_unwind` around `main` in `std::rt::lang_start` would catch is
+//! reported as an error right away instead, so the error shows where the program panicked.
+
+use rustc::ty::Ty;
+use rustc::mir;
+use rustc_miri::interpret::{EvalContext, EvalErrorKind, EvalResult, Lvalue, Pointer, PrimVal,
+                            StackPopCleanup, ValTy};
+
+use super::Evaluator;
+
+/// A call of `__rust_maybe_catch_panic` whose closure is running.
+#[derive(Copy, Clone, Debug)]
+pub struct CatchPanic {
+    /// The stack depth including the frame of the closure
+    depth: usize,
+    data_ptr: Pointer,
+    vtable_ptr: Pointer,
+    /// Where to write the `u32` the call returns
+    dest: Lvalue,
+    dest_block: mir::BasicBlock,
+}
+
+pub trait EvalContextExt<'tcx> {
+    /// `__rust_maybe_catch_panic(f, data, data_ptr, vtable_ptr)`
+    fn maybe_catch_panic(
+        &mut self,
+        args: &[ValTy<'tcx>],
+        dest: Lvalue,
+        dest_ty: Ty<'tcx>,
+        dest_block: mir::BasicBlock,
+    ) -> EvalResult<'tcx>;
+
+    /// Whether a panic starting now would be caught, by something else than the
+    /// `catch_unwind` around `main`.
+    fn panic_is_caught(&self) -> bool;
+
+    /// `__rust_start_panic(data, vtable)`
+    fn start_panic(&mut self, args: &[ValTy<'tcx>]) -> EvalResult<'tcx>;
+
+    /// Unwinds the stack with the panic payload `Box<Any + Send>` given by its data and vtable
+    /// pointers.
+    fn unwind_with_payload(&mut self, data: PrimVal, vtable: PrimVal) -> EvalResult<'tcx>;
+
+    /// Returns from the `__rust_maybe_catch_panic` whose closure frame was just popped.
+    fn finish_catch_panic(&mut self) -> EvalResult<'tcx>;
+}
+
+impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, Evaluator> {
+    fn maybe_catch_panic(
+        &mut self,
+        args: &[ValTy<'tcx>],
+        dest: Lvalue,
+        dest_ty: Ty<'tcx>,
+        dest_block: mir::BasicBlock,
+    ) -> EvalResult<'tcx> {
+        let u8_ptr_ty = self.tcx.mk_mut_ptr(self.tcx.types.u8);
+        let f = args[0].into_ptr(&self.memory)?.to_ptr()?;
+        let data = args[1].into_ptr(&self.memory)?;
+        let data_ptr = args[2].into_ptr(&self.memory)?;
+        let vtable_ptr = args[3].into_ptr(&self.memory)?;
+        let f_instance = self.memory.get_fn(f)?;
+
+        // Returning normally from the closure returns 0.
+        self.write_null(dest, dest_ty)?;
+
+        let mir = self.load_mir(f_instance.def)?;
+        self.push_stack_frame(
+            f_instance,
+            mir.span,
+            mir,
+            Lvalue::undef(),
+            StackPopCleanup::Goto(dest_block),
+        )?;
+        self.frame_mut().catch_unwind = true;
+        let mut f_args = self.frame().mir.args_iter();
+        let arg_local = f_args.next().ok_or(EvalErrorKind::AbiViolation(
+            "Argument to __rust_maybe_catch_panic does not take enough arguments.".to_owned(),
+        ))?;
+        let arg_dest = self.eval_lvalue(&mir::Lvalue::Local(arg_local))?;
+        self.write_ptr(arg_dest, data, u8_ptr_ty)?;
+        assert!(
+            f_args.next().is_none(),
+            "__rust_maybe_catch_panic argument has more arguments than expected"
+        );
+
+        // Calls whose closure returned normally were never removed.
+        let depth = self.stack().len();
+        let catches = &mut self.machine_data.catch_panics;
+        catches.retain(|catch| catch.depth < depth);
+        catches.push(CatchPanic {
+            depth,
+            data_ptr,
+            vtable_ptr,
+            dest,
+            dest_block,
+        });
+        Ok(())
+    }
+
+    fn panic_is_caught(&self) -> bool {
+        let catching = self.stack().iter().rposition(|frame| frame.catch_unwind);
+        let outermost = self.stack().iter().position(|frame| frame.catch_unwind);
+        let in_lang_start = self.stack().first().map_or(false, |frame| {
+            self.tcx
+                .item_path_str(frame.instance.def_id())
+                .starts_with("std::rt::lang_start")
+        });
+        match catching {
+            None => false,
+            Some(catching) => catching != outermost.unwrap() || !in_lang_start,
+        }
+    }
+
+    fn start_panic(&mut self, args: &[ValTy<'tcx>]) -> EvalResult<'tcx> {
+        if !self.panic_is_caught() {
+            return err!(Panic);
+        }
+        let data = self.value_to_primval(args[0])?;
+        let vtable = self.value_to_primval(args[1])?;
+        self.unwind_with_payload(data, vtable)
+    }
+
+    fn unwind_with_payload(&mut self, data: PrimVal, vtable: PrimVal) -> EvalResult<'tcx> {
+        self.machine_data.panic_payload = Some((data, vtable));
+        trace!("unwinding with payload {:?}", self.machine_data.panic_payload);
+        self.unwind()
+    }
+
+    fn finish_catch_panic(&mut self) -> EvalResult<'tcx> {
+        let depth = self.stack().len() + 1;
+        let catch = {
+            // Calls of nested closures that returned normally were never removed.
+            let catches = &mut self.machine_data.catch_panics;
+            catches.retain(|catch| catch.depth <= depth);
+            catches.pop().expect(
+                "a frame caught unwinding without a call of __rust_maybe_catch_panic",
+            )
+        };
+        assert_eq!(catch.depth, depth);
+        let (data, vtable) = self.machine_data.panic_payload.take().expect(
+            "unwinding without a panic payload",
+        );
+        let usize = self.tcx.types.usize;
+        self.write_primval(Lvalue::from_primval_ptr(catch.data_ptr), data, usize)?;
+        self.write_primval(Lvalue::from_primval_ptr(catch.vtable_ptr), vtable, usize)?;
+        let u32 = self.tcx.types.u32;
+        self.write_primval(catch.dest, PrimVal::Bytes(1), u32)?;
+        self.goto_block(catch.dest_block);
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+//! A source of random bytes for the evaluated program, e.g. for the keys of `HashMap`'s hasher.
+//! The bytes come from a PRNG seeded with `-Zmiri-seed`, so hash iteration order and everything
+//! else that depends on them is the same in every run.
+
+use rustc_miri::interpret::{EvalContext, EvalResult, Pointer, Rng};
+
+use super::Evaluator;
+
+pub trait EvalContextExt<'tcx> {
+    /// Fills `len` bytes at `ptr` with random data.
+    fn fill_random(&mut self, ptr: Pointer, len: u64) -> EvalResult<'tcx>;
+}
+
+impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, Evaluator> {
+    fn fill_random(&mut self, ptr: Pointer, len: u64) -> EvalResult<'tcx> {
+        // `len` is up to the program, so it has to fit into the destination before we allocate.
+        if len != 0 {
+            self.memory.check_range_bounds(ptr.to_ptr()?, len)?;
+        }
+        let mut bytes = vec![0; len as usize];
+        let seed = self.machine_data.config.seed;
+        self.machine_data
+            .rng
+            .get_or_insert_with(|| Rng::new(seed))
+            .fill_bytes(&mut bytes);
+        self.memory.write_bytes(ptr, &bytes)
+    }
+}
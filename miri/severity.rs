@@ -0,0 +1,62 @@
+//! Per-category overrides of how findings are reported.
+//!
+//! `MIRI_SEVERITY` contains a comma separated list of `category=level` pairs, where the category
+//! is an error kind as it appears in suppression files (e.g. `MemoryLeak`) and the level is one
+//! of `allow`, `warn` or `error`.
+//!
+//! Undefined behavior and operations miri does not support stop the evaluation, and still stop it
+//! when configured as warnings, only the way they are reported changes. Memory leaks and the
+//! findings in `WARNINGS` do not stop it: they are reported and the evaluation goes on, which a
+//! finding configured as an error only makes fail in the end.
+
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Allow,
+    Warn,
+    Error,
+}
+
+/// The findings that are warnings unless configured otherwise.
+pub const WARNINGS: &[&str] = &[
+    // An undefined integer read as 0 or garbage because of `-Zmiri-tolerate-undef-ints`.
+    "UndefinedInt",
+    // A dereference of a pointer made from an integer, through which miri cannot access memory.
+    "IntPtrDeref",
+    // A call miri does not carry out, but pretends to have done, like writing to a file
+    // descriptor that is not open.
+    "IgnoredCall",
+];
+
+#[derive(Clone, Debug, Default)]
+pub struct SeverityConfig {
+    overrides: HashMap<String, Severity>,
+}
+
+impl SeverityConfig {
+    pub fn parse(config: &str) -> Result<Self, String> {
+        let mut overrides = HashMap::new();
+        for entry in config.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let mut parts = entry.splitn(2, '=');
+            let category = parts.next().unwrap().trim();
+            let severity = match parts.next().map(str::trim) {
+                Some("allow") => Severity::Allow,
+                Some("warn") => Severity::Warn,
+                Some("error") => Severity::Error,
+                _ => return Err(format!("invalid severity override `{}`", entry)),
+            };
+            overrides.insert(category.to_owned(), severity);
+        }
+        Ok(SeverityConfig { overrides })
+    }
+
+    /// Findings other than `WARNINGS` are errors unless configured otherwise.
+    pub fn severity(&self, category: &str) -> Severity {
+        match self.overrides.get(category) {
+            Some(&severity) => severity,
+            None if WARNINGS.contains(&category) => Severity::Warn,
+            None => Severity::Error,
+        }
+    }
+}
@@ -0,0 +1,469 @@
+//! Shims for the C standard library, which every target has.
+
+use rustc::ty::Ty;
+use rustc_miri::interpret::*;
+
+use Evaluator;
+use memory::MemoryKind;
+
+use super::Shim;
+
+pub(super) const SHIMS: &[(&str, Shim)] = &[
+    ("malloc", malloc),
+    ("free", free),
+    ("calloc", calloc),
+    ("realloc", realloc),
+    ("memcpy", memcpy),
+    ("memmove", memcpy),
+    ("memset", memset),
+    ("memcmp", memcmp),
+    ("memchr", memchr),
+    ("strlen", strlen),
+    ("cbrtf", math_f32),
+    ("expm1f", math_f32),
+    ("log1pf", math_f32),
+    ("tanf", math_f32),
+    ("tanhf", math_f32),
+    ("sinhf", math_f32),
+    ("coshf", math_f32),
+    ("asinf", math_f32),
+    ("acosf", math_f32),
+    ("atanf", math_f32),
+    ("cbrt", math_f64),
+    ("expm1", math_f64),
+    ("log1p", math_f64),
+    ("tan", math_f64),
+    ("tanh", math_f64),
+    ("sinh", math_f64),
+    ("cosh", math_f64),
+    ("asin", math_f64),
+    ("acos", math_f64),
+    ("atan", math_f64),
+    ("atan2f", binary_math_f32),
+    ("hypotf", binary_math_f32),
+    ("atan2", binary_math_f64),
+    ("hypot", binary_math_f64),
+    ("printf", printf),
+];
+
+fn malloc<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let size = ecx.value_to_primval(args[0])?.to_u64()?;
+    if size == 0 {
+        ecx.write_null(dest, dest_ty)?;
+    } else {
+        let align = ecx.memory.pointer_size();
+        let ptr = ecx.memory.allocate(size, align, MemoryKind::C.into())?;
+        ecx.write_primval(dest, PrimVal::Ptr(ptr), dest_ty)?;
+    }
+    Ok(())
+}
+
+fn free<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    _dest: Lvalue,
+    _dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let ptr = args[0].into_ptr(&mut ecx.memory)?;
+    if !ptr.is_null()? {
+        ecx.memory.deallocate(
+            ptr.to_ptr()?,
+            None,
+            MemoryKind::C.into(),
+        )?;
+    }
+    Ok(())
+}
+
+fn calloc<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let items = ecx.value_to_primval(args[0])?.to_u64()?;
+    let len = ecx.value_to_primval(args[1])?.to_u64()?;
+    match items.checked_mul(len) {
+        // C reports an overflowing size by returning a null pointer
+        None | Some(0) => ecx.write_null(dest, dest_ty)?,
+        Some(size) => {
+            let align = ecx.memory.pointer_size();
+            let ptr = ecx.memory.allocate(size, align, MemoryKind::C.into())?;
+            ecx.memory.write_repeat(ptr.into(), 0, size)?;
+            ecx.write_primval(dest, PrimVal::Ptr(ptr), dest_ty)?;
+        }
+    }
+    Ok(())
+}
+
+fn realloc<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let old_ptr = args[0].into_ptr(&mut ecx.memory)?;
+    let new_size = ecx.value_to_primval(args[1])?.to_u64()?;
+    let align = ecx.memory.pointer_size();
+    if old_ptr.is_null()? {
+        // `realloc(NULL, size)` is `malloc(size)`
+        if new_size == 0 {
+            ecx.write_null(dest, dest_ty)?;
+        } else {
+            let ptr = ecx.memory.allocate(new_size, align, MemoryKind::C.into())?;
+            ecx.write_primval(dest, PrimVal::Ptr(ptr), dest_ty)?;
+        }
+    } else {
+        let old_ptr = old_ptr.to_ptr()?;
+        if new_size == 0 {
+            // `realloc(ptr, 0)` frees `ptr`
+            ecx.memory.deallocate(old_ptr, None, MemoryKind::C.into())?;
+            ecx.write_null(dest, dest_ty)?;
+        } else {
+            let old_size = ecx.memory.get(old_ptr.alloc_id)?.bytes.len() as u64;
+            let new_ptr = ecx.memory.reallocate(
+                old_ptr,
+                old_size,
+                align,
+                new_size,
+                align,
+                MemoryKind::C.into(),
+            )?;
+            ecx.write_primval(dest, PrimVal::Ptr(new_ptr), dest_ty)?;
+        }
+    }
+    Ok(())
+}
+
+/// `memcpy` and `memmove`.
+fn memcpy<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let dest_ptr = args[0].into_ptr(&mut ecx.memory)?;
+    let src = args[1].into_ptr(&mut ecx.memory)?;
+    let n = ecx.value_to_primval(args[2])?.to_u64()?;
+    // Undefined bytes and pointers are copied as they are. Only `memmove` may be used
+    // on overlapping ranges.
+    let nonoverlapping = link_name == "memcpy";
+    ecx.memory.copy(src, dest_ptr, n, 1, nonoverlapping)?;
+    ecx.write_ptr(dest, dest_ptr, dest_ty)?;
+    Ok(())
+}
+
+fn memset<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let ptr = args[0].into_ptr(&mut ecx.memory)?;
+    let val = ecx.value_to_primval(args[1])?.to_i128()? as u8;
+    let n = ecx.value_to_primval(args[2])?.to_u64()?;
+    ecx.memory.write_repeat(ptr, val, n)?;
+    ecx.write_ptr(dest, ptr, dest_ty)?;
+    Ok(())
+}
+
+fn memcmp<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let left = args[0].into_ptr(&mut ecx.memory)?;
+    let right = args[1].into_ptr(&mut ecx.memory)?;
+    let n = ecx.value_to_primval(args[2])?.to_u64()?;
+
+    let result = {
+        let left_bytes = ecx.memory.read_bytes(left, n)?;
+        let right_bytes = ecx.memory.read_bytes(right, n)?;
+
+        use std::cmp::Ordering::*;
+        match left_bytes.cmp(right_bytes) {
+            Less => -1i8,
+            Equal => 0,
+            Greater => 1,
+        }
+    };
+
+    ecx.write_primval(
+        dest,
+        PrimVal::Bytes(result as u128),
+        dest_ty,
+    )?;
+    Ok(())
+}
+
+fn memchr<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let ptr = args[0].into_ptr(&mut ecx.memory)?;
+    let val = ecx.value_to_primval(args[1])?.to_u64()? as u8;
+    let num = ecx.value_to_primval(args[2])?.to_u64()?;
+    if let Some(idx) = ecx.memory.read_bytes(ptr, num)?.iter().position(
+        |&c| c == val,
+    )
+    {
+        let new_ptr = ptr.offset(idx as u64, &ecx)?;
+        ecx.write_ptr(dest, new_ptr, dest_ty)?;
+    } else {
+        ecx.write_null(dest, dest_ty)?;
+    }
+    Ok(())
+}
+
+fn strlen<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let ptr = args[0].into_ptr(&mut ecx.memory)?.to_ptr()?;
+    let n = ecx.memory.read_c_str(ptr)?.len();
+    ecx.write_primval(dest, PrimVal::Bytes(n as u128), dest_ty)?;
+    Ok(())
+}
+
+/// Math functions that `std` forwards to `libm`.
+fn math_f32<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let f = ecx.value_to_primval(args[0])?.to_f32()?;
+    let f = match link_name {
+        "cbrtf" => f.cbrt(),
+        "expm1f" => f.exp_m1(),
+        "log1pf" => f.ln_1p(),
+        "tanf" => f.tan(),
+        "tanhf" => f.tanh(),
+        "sinhf" => f.sinh(),
+        "coshf" => f.cosh(),
+        "asinf" => f.asin(),
+        "acosf" => f.acos(),
+        "atanf" => f.atan(),
+        _ => bug!(),
+    };
+    ecx.write_primval(dest, PrimVal::from_f32(f), dest_ty)?;
+    Ok(())
+}
+
+fn math_f64<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let f = ecx.value_to_primval(args[0])?.to_f64()?;
+    let f = match link_name {
+        "cbrt" => f.cbrt(),
+        "expm1" => f.exp_m1(),
+        "log1p" => f.ln_1p(),
+        "tan" => f.tan(),
+        "tanh" => f.tanh(),
+        "sinh" => f.sinh(),
+        "cosh" => f.cosh(),
+        "asin" => f.asin(),
+        "acos" => f.acos(),
+        "atan" => f.atan(),
+        _ => bug!(),
+    };
+    ecx.write_primval(dest, PrimVal::from_f64(f), dest_ty)?;
+    Ok(())
+}
+
+fn binary_math_f32<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let a = ecx.value_to_primval(args[0])?.to_f32()?;
+    let b = ecx.value_to_primval(args[1])?.to_f32()?;
+    let f = match link_name {
+        "atan2f" => a.atan2(b),
+        "hypotf" => a.hypot(b),
+        _ => bug!(),
+    };
+    ecx.write_primval(dest, PrimVal::from_f32(f), dest_ty)?;
+    Ok(())
+}
+
+fn binary_math_f64<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let a = ecx.value_to_primval(args[0])?.to_f64()?;
+    let b = ecx.value_to_primval(args[1])?.to_f64()?;
+    let f = match link_name {
+        "atan2" => a.atan2(b),
+        "hypot" => a.hypot(b),
+        _ => bug!(),
+    };
+    ecx.write_primval(dest, PrimVal::from_f64(f), dest_ty)?;
+    Ok(())
+}
+
+/// The extra arguments of a variadic function arrive with the types the caller passed them at,
+/// which already went through C's default argument promotions.
+fn printf<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    use std::io::{self, Write};
+
+    let format = args[0].into_ptr(&ecx.memory)?.to_ptr()?;
+    let format = ecx.memory.read_c_str(format)?.to_owned();
+    let output = format_c(ecx, &format, &args[1..])?;
+    let result = match io::stdout().write_all(&output) {
+        Ok(()) => output.len() as i128,
+        Err(_) => -1,
+    };
+    ecx.write_primval(dest, PrimVal::from_i128(result), dest_ty)?;
+    Ok(())
+}
+
+/// Formats `args` like `printf` does. The `-` and `0` flags, widths and precisions are supported.
+/// Length modifiers are skipped, every argument knows its size anyway.
+fn format_c<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    format: &[u8],
+    args: &[ValTy<'tcx>],
+) -> EvalResult<'tcx, Vec<u8>> {
+    fn number<I: Iterator<Item = u8>>(chars: &mut ::std::iter::Peekable<I>) -> usize {
+        let mut n = 0;
+        while let Some(&d @ b'0'...b'9') = chars.peek() {
+            n = n * 10 + (d - b'0') as usize;
+            chars.next();
+        }
+        n
+    }
+
+    let mut out = Vec::new();
+    let mut args = args.iter();
+    let mut chars = format.iter().cloned().peekable();
+    while let Some(c) = chars.next() {
+        if c != b'%' {
+            out.push(c);
+            continue;
+        }
+        let (mut left, mut zero) = (false, false);
+        loop {
+            match chars.peek() {
+                Some(&b'-') => left = true,
+                Some(&b'0') => zero = true,
+                _ => break,
+            }
+            chars.next();
+        }
+        let width = number(&mut chars);
+        let precision = if chars.peek() == Some(&b'.') {
+            chars.next();
+            Some(number(&mut chars))
+        } else {
+            None
+        };
+        loop {
+            match chars.peek() {
+                Some(&b'h') | Some(&b'l') | Some(&b'j') | Some(&b'z') | Some(&b't') => {}
+                _ => break,
+            }
+            chars.next();
+        }
+        let conv = match chars.next() {
+            Some(b'%') => {
+                out.push(b'%');
+                continue;
+            }
+            Some(conv) => conv,
+            None => return err!(AbiViolation("printf format ends in `%`".to_owned())),
+        };
+        let arg = match args.next() {
+            Some(&arg) => arg,
+            None => {
+                return err!(AbiViolation(format!(
+                    "printf format `{}` needs more arguments than it got",
+                    String::from_utf8_lossy(format)
+                )))
+            }
+        };
+        let val = ecx.value_to_primval(arg)?;
+        let bits = ecx.type_size(arg.ty)?.expect("variadic arguments are sized") * 8;
+        let unsigned = || -> EvalResult<'tcx, u128> {
+            let bytes = val.to_bytes()?;
+            Ok(if bits >= 128 { bytes } else { bytes & ((1 << bits) - 1) })
+        };
+        let text = match conv {
+            b'd' | b'i' => {
+                let shift = 128 - bits as u32;
+                ((val.to_bytes()? as i128) << shift >> shift).to_string().into_bytes()
+            }
+            b'u' => unsigned()?.to_string().into_bytes(),
+            b'x' => format!("{:x}", unsigned()?).into_bytes(),
+            b'X' => format!("{:X}", unsigned()?).into_bytes(),
+            b'o' => format!("{:o}", unsigned()?).into_bytes(),
+            b'c' => vec![val.to_bytes()? as u8],
+            b'f' | b'F' => format!("{:.*}", precision.unwrap_or(6), val.to_f64()?).into_bytes(),
+            b's' => {
+                let s = ecx.memory.read_c_str(val.to_ptr()?)?;
+                let len = precision.map_or(s.len(), |p| p.min(s.len()));
+                s[..len].to_owned()
+            }
+            b'p' => {
+                match val {
+                    PrimVal::Bytes(addr) => format!("{:#x}", addr).into_bytes(),
+                    _ => {
+                        return err!(Unimplemented(
+                            "printing the address of an allocation with `%p`".to_owned(),
+                        ))
+                    }
+                }
+            }
+            _ => return err!(Unimplemented(format!("printf conversion `%{}`", conv as char))),
+        };
+        let padding = width.saturating_sub(text.len());
+        if left {
+            out.extend(text);
+            out.extend(::std::iter::repeat(b' ').take(padding));
+        } else if zero && conv != b's' && conv != b'c' {
+            let sign = if text.first() == Some(&b'-') { 1 } else { 0 };
+            out.extend(&text[..sign]);
+            out.extend(::std::iter::repeat(b'0').take(padding));
+            out.extend(&text[sign..]);
+        } else {
+            out.extend(::std::iter::repeat(b' ').take(padding));
+            out.extend(text);
+        }
+    }
+    Ok(out)
+}
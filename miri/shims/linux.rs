@@ -0,0 +1,69 @@
+//! Shims for functions only Linux has, or that only Linux programs call.
+
+use rustc::ty::Ty;
+use rustc_miri::interpret::*;
+
+use Evaluator;
+use random::EvalContextExt as RandomEvalContextExt;
+
+use super::{Shim, unix};
+
+pub(super) const SHIMS: &[(&str, Shim)] = &[
+    ("syscall", syscall),
+    ("memrchr", memrchr),
+    ("open64", unix::open),
+    ("lseek64", unix::lseek),
+    ("fstat64", unix::fstat),
+    ("stat64", unix::stat),
+    ("__cxa_thread_atexit_impl", unix::thread_atexit),
+];
+
+fn syscall<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    // TODO: read `syscall` ids like `sysconf` ids and
+    // figure out some way to actually process more of them
+    //
+    // libc::syscall(NR_GETRANDOM, buf.as_mut_ptr(), buf.len(), GRND_NONBLOCK)
+    // is called if a `HashMap` is created the regular way.
+    match ecx.value_to_primval(args[0])?.to_u64()? {
+        318 | 511 => {
+            let buf = args[1].into_ptr(&mut ecx.memory)?;
+            let len = ecx.value_to_primval(args[2])?.to_u64()?;
+            ecx.fill_random(buf, len)?;
+            ecx.write_primval(dest, PrimVal::Bytes(len as u128), dest_ty)?;
+        }
+        id => {
+            return err!(Unimplemented(
+                format!("miri does not support syscall id {}", id),
+            ))
+        }
+    }
+    Ok(())
+}
+
+fn memrchr<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let ptr = args[0].into_ptr(&mut ecx.memory)?;
+    let val = ecx.value_to_primval(args[1])?.to_u64()? as u8;
+    let num = ecx.value_to_primval(args[2])?.to_u64()?;
+    if let Some(idx) = ecx.memory.read_bytes(ptr, num)?.iter().rev().position(
+        |&c| c == val,
+    )
+    {
+        let new_ptr = ptr.offset(num - idx as u64 - 1, &ecx)?;
+        ecx.write_ptr(dest, new_ptr, dest_ty)?;
+    } else {
+        ecx.write_null(dest, dest_ty)?;
+    }
+    Ok(())
+}
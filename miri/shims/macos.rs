@@ -0,0 +1,72 @@
+//! Shims for functions only macOS has.
+
+use rustc::ty::Ty;
+use rustc_miri::interpret::*;
+
+use Evaluator;
+use time::{self, EvalContextExt as TimeEvalContextExt};
+
+use super::{Shim, unix};
+
+pub(super) const SHIMS: &[(&str, Shim)] = &[
+    ("_NSGetArgc", ns_get_args),
+    ("_NSGetArgv", ns_get_args),
+    ("mach_absolute_time", mach_absolute_time),
+    ("mach_timebase_info", mach_timebase_info),
+    ("_tlv_atexit", unix::thread_atexit),
+    ("fstat$INODE64", unix::fstat),
+    ("stat$INODE64", unix::stat),
+];
+
+/// `_NSGetArgc` and `_NSGetArgv`.
+fn ns_get_args<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    link_name: &str,
+    _args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let place = if link_name == "_NSGetArgc" {
+        ecx.machine_data.argc
+    } else {
+        ecx.machine_data.argv
+    };
+    match place {
+        Some(ptr) => ecx.write_primval(dest, PrimVal::Ptr(ptr), dest_ty)?,
+        None => {
+            return err!(Unimplemented(
+                format!("{} called without a start lang item", link_name),
+            ))
+        }
+    }
+    Ok(())
+}
+
+fn mach_absolute_time<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    _args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    // The time base below makes this count nanoseconds.
+    let now = ecx.now(time::Clock::Monotonic);
+    let nanos = now.as_secs() as u128 * 1_000_000_000 + now.subsec_nanos() as u128;
+    ecx.write_primval(dest, PrimVal::Bytes(nanos), dest_ty)?;
+    Ok(())
+}
+
+fn mach_timebase_info<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let info = args[0].into_ptr(&mut ecx.memory)?.to_ptr()?;
+    let denom = info.offset(4, &ecx)?;
+    ecx.memory.write_primval(info, PrimVal::Bytes(1), 4, false)?;
+    ecx.memory.write_primval(denom, PrimVal::Bytes(1), 4, false)?;
+    ecx.write_null(dest, dest_ty)?;
+    Ok(())
+}
@@ -0,0 +1,72 @@
+//! The shims of foreign functions, in one table per group of targets.
+//!
+//! A shim is looked up by the link name of the function, first among the shims of the target's
+//! operating system, then among those of all Unix targets (on Unix) and finally among those of the
+//! C standard library. Adding a shim only takes a function of type `Shim` and an entry in the
+//! table of the targets that have the function. Windows targets only get the C standard library
+//! for now.
+
+use rustc::ty::Ty;
+use rustc::session::Session;
+use rustc_miri::interpret::{EvalContext, EvalResult, Lvalue, ValTy};
+
+use Evaluator;
+
+mod libc;
+mod unix;
+mod linux;
+mod macos;
+
+/// Emulates the call of a foreign function. It gets the link name the function was called by, so
+/// one shim can implement several related functions. Writing the return value to `dest` is the
+/// shim's job, continuing at the next block is done by the caller.
+pub type Shim = for<'a, 'tcx> fn(&mut EvalContext<'a, 'tcx, Evaluator>,
+                                 &str,
+                                 &[ValTy<'tcx>],
+                                 Lvalue,
+                                 Ty<'tcx>)
+                                 -> EvalResult<'tcx>;
+
+/// The shim for `link_name` on the target of `sess`, if there is one.
+pub fn find(sess: &Session, link_name: &str) -> Option<Shim> {
+    let target = &sess.target.target;
+    tables(&target.target_os, is_unix(sess))
+        .iter()
+        .flat_map(|table| table.iter())
+        .find(|&&(name, _)| name == link_name)
+        .map(|&(_, shim)| shim)
+}
+
+/// The link names of all shims on targets with the operating system `target_os`, for reporting
+/// the capabilities of this build.
+pub fn names(target_os: &str, unix: bool) -> Vec<&'static str> {
+    tables(target_os, unix)
+        .iter()
+        .flat_map(|table| table.iter())
+        .map(|&(name, _)| name)
+        .collect()
+}
+
+/// The tables to search, in order.
+fn tables(target_os: &str, unix: bool) -> [&'static [(&'static str, Shim)]; 3] {
+    let os_shims: &[(&str, Shim)] = match target_os {
+        "linux" => linux::SHIMS,
+        "macos" => macos::SHIMS,
+        _ => &[],
+    };
+    let unix_shims: &[(&str, Shim)] = if unix { unix::SHIMS } else { &[] };
+    [os_shims, unix_shims, libc::SHIMS]
+}
+
+/// Like `find`, but pthread functions without a shim of their own are stubbed out.
+pub fn find_or_stub(sess: &Session, link_name: &str) -> Option<Shim> {
+    find(sess, link_name).or_else(|| if is_unix(sess) && link_name.starts_with("pthread_") {
+        Some(unix::ignored_pthread_call)
+    } else {
+        None
+    })
+}
+
+fn is_unix(sess: &Session) -> bool {
+    sess.target.target.options.target_family.as_ref().map(|f| &f[..]) == Some("unix")
+}
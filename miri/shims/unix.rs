@@ -0,0 +1,613 @@
+//! Shims for the POSIX functions all Unix targets have.
+
+use rustc::ty::{self, Ty};
+use rustc_miri::interpret::*;
+
+use {Evaluator, TlsKey};
+use memory::MemoryKind;
+use tls::MemoryExt;
+use files::EvalContextExt as FilesEvalContextExt;
+use fn_call::EvalContextExt as MissingFnsEvalContextExt;
+use mmap::EvalContextExt as MmapEvalContextExt;
+use time::EvalContextExt as TimeEvalContextExt;
+
+use super::Shim;
+
+pub(super) const SHIMS: &[(&str, Shim)] = &[
+    ("getenv", getenv),
+    ("unsetenv", unsetenv),
+    ("setenv", setenv),
+    ("write", write),
+    ("open", open),
+    ("read", read),
+    ("close", close),
+    ("lseek", lseek),
+    ("fstat", fstat),
+    ("stat", stat),
+    ("clock_gettime", clock_gettime),
+    ("gettimeofday", gettimeofday),
+    ("mmap", mmap),
+    ("munmap", munmap),
+    ("mprotect", mprotect),
+    ("dlsym", dlsym),
+    ("signal", ignore_signals),
+    ("sigaction", ignore_signals),
+    ("sigaltstack", ignore_signals),
+    ("sysconf", sysconf),
+    ("fcntl", fcntl),
+    ("ioctl", ioctl),
+    // pthread calls that go to the thread-local storage memory subsystem
+    ("pthread_key_create", pthread_key_create),
+    ("pthread_key_delete", pthread_key_delete),
+    ("pthread_getspecific", pthread_getspecific),
+    ("pthread_setspecific", pthread_setspecific),
+    ("pthread_create", pthread_create),
+];
+
+fn getenv<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let (name, result) = {
+        let name_ptr = args[0].into_ptr(&mut ecx.memory)?.to_ptr()?;
+        let name = ecx.memory.read_c_str(name_ptr)?;
+        let result = match ecx.machine_data.env_vars.get(name) {
+            Some(&var) => PrimVal::Ptr(var),
+            None => PrimVal::Bytes(0),
+        };
+        (name.to_owned(), result)
+    };
+    let result = match result {
+        PrimVal::Bytes(0) if !ecx.machine_data.config.isolation => {
+            // Copy the variable from the host, so the pointer we return stays valid
+            // until the program changes the variable.
+            let host_value = ::std::str::from_utf8(&name).ok().and_then(|name| {
+                ::std::env::var(name).ok()
+            });
+            match host_value {
+                Some(value) => {
+                    let var = ecx.alloc_env_var(value.as_bytes())?;
+                    ecx.machine_data.env_vars.insert(name, var);
+                    PrimVal::Ptr(var)
+                }
+                None => result,
+            }
+        }
+        result => result,
+    };
+    ecx.write_primval(dest, result, dest_ty)?;
+    Ok(())
+}
+
+fn unsetenv<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let mut success = None;
+    {
+        let name_ptr = args[0].into_ptr(&mut ecx.memory)?;
+        if !name_ptr.is_null()? {
+            let name = ecx.memory.read_c_str(name_ptr.to_ptr()?)?;
+            if !name.is_empty() && !name.contains(&b'=') {
+                let old = ecx.machine_data.env_vars.remove(name);
+                success = Some((name.to_owned(), old));
+            }
+        }
+    }
+    if let Some((name, old)) = success {
+        if let Some(var) = old {
+            ecx.memory.deallocate(var, None, MemoryKind::Env.into())?;
+        }
+        if !ecx.machine_data.config.isolation {
+            if let Ok(name) = ::std::str::from_utf8(&name) {
+                ::std::env::remove_var(name);
+            }
+        }
+        ecx.write_null(dest, dest_ty)?;
+    } else {
+        ecx.write_primval(dest, PrimVal::from_i128(-1), dest_ty)?;
+    }
+    Ok(())
+}
+
+fn setenv<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let mut new = None;
+    {
+        let name_ptr = args[0].into_ptr(&mut ecx.memory)?;
+        let value_ptr = args[1].into_ptr(&mut ecx.memory)?.to_ptr()?;
+        let value = ecx.memory.read_c_str(value_ptr)?;
+        if !name_ptr.is_null()? {
+            let name = ecx.memory.read_c_str(name_ptr.to_ptr()?)?;
+            if !name.is_empty() && !name.contains(&b'=') {
+                new = Some((name.to_owned(), value.to_owned()));
+            }
+        }
+    }
+    if let Some((name, value)) = new {
+        let value_copy = ecx.alloc_env_var(&value)?;
+        if !ecx.machine_data.config.isolation {
+            let name = ::std::str::from_utf8(&name);
+            let value = ::std::str::from_utf8(&value);
+            if let (Ok(name), Ok(value)) = (name, value) {
+                ::std::env::set_var(name, value);
+            }
+        }
+        if let Some(var) = ecx.machine_data.env_vars.insert(
+            name.to_owned(),
+            value_copy,
+        )
+        {
+            ecx.memory.deallocate(var, None, MemoryKind::Env.into())?;
+        }
+        ecx.write_null(dest, dest_ty)?;
+    } else {
+        ecx.write_primval(dest, PrimVal::from_i128(-1), dest_ty)?;
+    }
+    Ok(())
+}
+
+fn write<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let fd = ecx.value_to_primval(args[0])?.to_u64()?;
+    let buf = args[1].into_ptr(&mut ecx.memory)?;
+    let n = ecx.value_to_primval(args[2])?.to_u64()?;
+    trace!("Called write({:?}, {:?}, {:?})", fd, buf, n);
+    let result = if fd == 1 || fd == 2 {
+        // stdout/stderr
+        use std::io::{self, Write};
+
+        let buf_cont = ecx.memory.read_bytes(buf, n)?;
+        let res = if fd == 1 {
+            io::stdout().write(buf_cont)
+        } else {
+            io::stderr().write(buf_cont)
+        };
+        match res {
+            Ok(n) => n as isize,
+            Err(_) => -1,
+        }
+    } else if ecx.machine_data.files.contains_key(&(fd as i64)) {
+        ecx.write_file(fd as i64, buf, n)? as isize
+    } else {
+        ecx.warn("IgnoredCall", format!("ignoring output to file descriptor {}", fd));
+        n as isize // pretend it all went well
+    }; // now result is the value we return back to the program
+    ecx.write_primval(
+        dest,
+        PrimVal::Bytes(result as u128),
+        dest_ty,
+    )?;
+    Ok(())
+}
+
+pub(super) fn open<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let fd = ecx.open(args)?;
+    ecx.write_primval(dest, PrimVal::from_i128(fd as i128), dest_ty)?;
+    Ok(())
+}
+
+fn read<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let fd = ecx.value_to_primval(args[0])?.to_i128()? as i64;
+    let buf = args[1].into_ptr(&mut ecx.memory)?;
+    let count = ecx.value_to_primval(args[2])?.to_u64()?;
+    let result = ecx.read(fd, buf, count)?;
+    ecx.write_primval(dest, PrimVal::from_i128(result as i128), dest_ty)?;
+    Ok(())
+}
+
+fn close<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let fd = ecx.value_to_primval(args[0])?.to_i128()? as i64;
+    let result = ecx.close(fd)?;
+    ecx.write_primval(dest, PrimVal::from_i128(result as i128), dest_ty)?;
+    Ok(())
+}
+
+pub(super) fn lseek<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let fd = ecx.value_to_primval(args[0])?.to_i128()? as i64;
+    let offset = ecx.value_to_primval(args[1])?.to_i128()? as i64;
+    let whence = ecx.value_to_primval(args[2])?.to_i128()? as i64;
+    let result = ecx.lseek(fd, offset, whence)?;
+    ecx.write_primval(dest, PrimVal::from_i128(result as i128), dest_ty)?;
+    Ok(())
+}
+
+pub(super) fn fstat<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let fd = ecx.value_to_primval(args[0])?.to_i128()? as i64;
+    let buf = args[1].into_ptr(&mut ecx.memory)?;
+    let result = ecx.fstat(fd, buf)?;
+    ecx.write_primval(dest, PrimVal::from_i128(result as i128), dest_ty)?;
+    Ok(())
+}
+
+pub(super) fn stat<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let path = args[0].into_ptr(&mut ecx.memory)?;
+    let buf = args[1].into_ptr(&mut ecx.memory)?;
+    let result = ecx.stat(path, buf)?;
+    ecx.write_primval(dest, PrimVal::from_i128(result as i128), dest_ty)?;
+    Ok(())
+}
+
+fn clock_gettime<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let clk_id = ecx.value_to_primval(args[0])?.to_u64()?;
+    let tp = args[1].into_ptr(&mut ecx.memory)?;
+    let result = ecx.clock_gettime(clk_id, tp)?;
+    ecx.write_primval(dest, PrimVal::from_i128(result as i128), dest_ty)?;
+    Ok(())
+}
+
+fn gettimeofday<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let tv = args[0].into_ptr(&mut ecx.memory)?;
+    ecx.gettimeofday(tv)?;
+    ecx.write_null(dest, dest_ty)?;
+    Ok(())
+}
+
+fn mmap<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    // The address hint, file descriptor and offset do not matter for anonymous
+    // mappings.
+    let len = ecx.value_to_primval(args[1])?.to_u64()?;
+    let prot = ecx.value_to_primval(args[2])?.to_u64()?;
+    let flags = ecx.value_to_primval(args[3])?.to_u64()?;
+    let ptr = ecx.mmap(len, prot, flags)?;
+    ecx.write_primval(dest, ptr, dest_ty)?;
+    Ok(())
+}
+
+fn munmap<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let addr = args[0].into_ptr(&mut ecx.memory)?;
+    let len = ecx.value_to_primval(args[1])?.to_u64()?;
+    let result = ecx.munmap(addr, len)?;
+    ecx.write_primval(dest, PrimVal::from_i128(result as i128), dest_ty)?;
+    Ok(())
+}
+
+fn mprotect<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let addr = args[0].into_ptr(&mut ecx.memory)?;
+    let len = ecx.value_to_primval(args[1])?.to_u64()?;
+    let prot = ecx.value_to_primval(args[2])?.to_u64()?;
+    let result = ecx.mprotect(addr, len, prot)?;
+    ecx.write_primval(dest, PrimVal::from_i128(result as i128), dest_ty)?;
+    Ok(())
+}
+
+fn dlsym<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    // All symbols are looked up among the shims, whatever the handle.
+    let _handle = args[0].into_ptr(&mut ecx.memory)?;
+    let symbol = args[1].into_ptr(&mut ecx.memory)?.to_ptr()?;
+    let symbol_name = String::from_utf8_lossy(ecx.memory.read_c_str(symbol)?)
+        .into_owned();
+    match ecx.dlsym(&symbol_name)? {
+        Some(ptr) => ecx.write_primval(dest, PrimVal::Ptr(ptr), dest_ty)?,
+        None => ecx.write_null(dest, dest_ty)?,
+    }
+    Ok(())
+}
+
+/// Some things needed for sys::thread initialization to go through.
+fn ignore_signals<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    _args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    ecx.write_primval(dest, PrimVal::Bytes(0), dest_ty)?;
+    Ok(())
+}
+
+fn sysconf<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let name = ecx.value_to_primval(args[0])?.to_u64()?;
+    trace!("sysconf() called with name {}", name);
+    // cache the sysconf integers via miri's global cache
+    let paths = &[
+        (&["libc", "_SC_PAGESIZE"], PrimVal::Bytes(4096)),
+        (&["libc", "_SC_GETPW_R_SIZE_MAX"], PrimVal::from_i128(-1)),
+    ];
+    let mut result = None;
+    for &(path, path_value) in paths {
+        if let Ok(instance) = ecx.resolve_path(path) {
+            let cid = GlobalId {
+                instance,
+                promoted: None,
+            };
+            // compute global if not cached
+            let val = match ecx.globals.get(&cid).cloned() {
+                Some(ptr) => ecx.value_to_primval(ValTy { value: Value::ByRef(ptr), ty: args[0].ty })?.to_u64()?,
+                None => eval_body_as_primval(ecx.tcx, instance)?.0.to_u64()?,
+            };
+            if val == name {
+                result = Some(path_value);
+                break;
+            }
+        }
+    }
+    if let Some(result) = result {
+        ecx.write_primval(dest, result, dest_ty)?;
+    } else {
+        return err!(Unimplemented(
+            format!("Unimplemented sysconf name: {}", name),
+        ));
+    }
+    Ok(())
+}
+
+/// libstd only uses this variadic function to read and set `FD_CLOEXEC`, which does not mean
+/// anything without `exec`.
+fn fcntl<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    const F_GETFD: u64 = 1;
+    const F_SETFD: u64 = 2;
+    const FD_CLOEXEC: i128 = 1;
+
+    let fd = ecx.value_to_primval(args[0])?.to_i128()? as i64;
+    let cmd = ecx.value_to_primval(args[1])?.to_u64()?;
+    let result = if !is_open_fd(ecx, fd) {
+        -1
+    } else {
+        match cmd {
+            F_GETFD => FD_CLOEXEC,
+            F_SETFD => 0,
+            _ => return err!(Unimplemented(format!("fcntl command {}", cmd))),
+        }
+    };
+    ecx.write_primval(dest, PrimVal::from_i128(result), dest_ty)?;
+    Ok(())
+}
+
+/// Only `FIOCLEX`, which libstd uses like `fcntl`, is supported.
+fn ioctl<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let fioclex = if ecx.tcx.sess.target.target.target_os == "macos" {
+        0x2000_6601
+    } else {
+        0x5451
+    };
+    let fd = ecx.value_to_primval(args[0])?.to_i128()? as i64;
+    let request = ecx.value_to_primval(args[1])?.to_u64()?;
+    if request != fioclex {
+        return err!(Unimplemented(format!("ioctl request {:#x}", request)));
+    }
+    let result = if is_open_fd(ecx, fd) { 0 } else { -1 };
+    ecx.write_primval(dest, PrimVal::from_i128(result), dest_ty)?;
+    Ok(())
+}
+
+fn is_open_fd<'a, 'tcx>(ecx: &EvalContext<'a, 'tcx, Evaluator>, fd: i64) -> bool {
+    (0 <= fd && fd <= 2) || ecx.machine_data.files.contains_key(&fd)
+}
+
+fn pthread_key_create<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let key_ptr = args[0].into_ptr(&mut ecx.memory)?;
+
+    // Extract the function type out of the signature (that seems easier than constructing it ourselves...)
+    let dtor = match args[1].into_ptr(&mut ecx.memory)?.into_inner_primval() {
+        PrimVal::Ptr(dtor_ptr) => Some(ecx.memory.get_fn(dtor_ptr)?),
+        PrimVal::Bytes(0) => None,
+        PrimVal::Bytes(_) => return err!(ReadBytesAsPointer),
+        PrimVal::Undef => return err!(ReadUndefBytes),
+    };
+
+    // Figure out how large a pthread TLS key actually is. This is libc::pthread_key_t.
+    let key_type = args[0].ty.builtin_deref(true, ty::LvaluePreference::NoPreference)
+                       .ok_or(EvalErrorKind::AbiViolation("Wrong signature used for pthread_key_create: First argument must be a raw pointer.".to_owned()))?.ty;
+    let key_size = {
+        let layout = ecx.type_layout(key_type)?;
+        layout.size(&ecx.tcx.data_layout)
+    };
+
+    // Create key and write it into the memory where key_ptr wants it
+    let key = ecx.memory.create_tls_key(dtor) as u128;
+    if key_size.bits() < 128 && key >= (1u128 << key_size.bits() as u128) {
+        return err!(OutOfTls);
+    }
+    ecx.memory.write_primval(
+        key_ptr.to_ptr()?,
+        PrimVal::Bytes(key),
+        key_size.bytes(),
+        false,
+    )?;
+
+    // Return success (0)
+    ecx.write_null(dest, dest_ty)?;
+    Ok(())
+}
+
+/// Registers a destructor of a `#[thread_local]` static.
+pub(super) fn thread_atexit<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    let dtor = args[0].into_ptr(&mut ecx.memory)?.to_ptr()?;
+    let dtor = ecx.memory.get_fn(dtor)?;
+    let data = args[1].into_ptr(&mut ecx.memory)?;
+    ecx.memory.register_thread_dtor(dtor, data);
+    if link_name == "__cxa_thread_atexit_impl" {
+        ecx.write_null(dest, dest_ty)?;
+    }
+    Ok(())
+}
+
+fn pthread_key_delete<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    // The conversion into TlsKey here is a little fishy, but should work as long as usize >= libc::pthread_key_t
+    let key = ecx.value_to_primval(args[0])?.to_u64()? as TlsKey;
+    ecx.memory.delete_tls_key(key)?;
+    // Return success (0)
+    ecx.write_null(dest, dest_ty)?;
+    Ok(())
+}
+
+fn pthread_getspecific<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    // The conversion into TlsKey here is a little fishy, but should work as long as usize >= libc::pthread_key_t
+    let key = ecx.value_to_primval(args[0])?.to_u64()? as TlsKey;
+    let ptr = ecx.memory.load_tls(key)?;
+    ecx.write_ptr(dest, ptr, dest_ty)?;
+    Ok(())
+}
+
+fn pthread_setspecific<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    // The conversion into TlsKey here is a little fishy, but should work as long as usize >= libc::pthread_key_t
+    let key = ecx.value_to_primval(args[0])?.to_u64()? as TlsKey;
+    let new_ptr = args[1].into_ptr(&mut ecx.memory)?;
+    ecx.memory.store_tls(key, new_ptr)?;
+
+    // Return success (0)
+    ecx.write_null(dest, dest_ty)?;
+    Ok(())
+}
+
+/// Threads are not emulated. Reporting a spawn as successful would silently skip the thread's
+/// code, and with it any race between the threads, so this is an error instead.
+fn pthread_create<'a, 'tcx>(
+    _ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    _link_name: &str,
+    _args: &[ValTy<'tcx>],
+    _dest: Lvalue,
+    _dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    err!(Unimplemented("miri does not support spawning threads".to_owned()))
+}
+
+/// Stubs out all the pthread calls without a shim of their own to just return 0.
+pub(super) fn ignored_pthread_call<'a, 'tcx>(
+    ecx: &mut EvalContext<'a, 'tcx, Evaluator>,
+    link_name: &str,
+    _args: &[ValTy<'tcx>],
+    dest: Lvalue,
+    dest_ty: Ty<'tcx>,
+) -> EvalResult<'tcx> {
+    info!("ignoring C ABI call: {}", link_name);
+    ecx.write_null(dest, dest_ty)?;
+    Ok(())
+}
@@ -0,0 +1,91 @@
+//! Suppressing known findings, so a project can start checking for new errors before all the
+//! existing ones are fixed.
+//!
+//! A suppressions file contains one finding per line: the error kind, the function the error
+//! happened in and the `file:line` where it happened, separated by tabs. Any field may be `*` to
+//! match everything. Empty lines and lines starting with `#` are ignored.
+//!
+//! `MIRI_SUPPRESSIONS` names a file to read suppressions from. If `MIRI_GENERATE_SUPPRESSIONS`
+//! names a file, every finding that was not suppressed is appended to it.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+
+use rustc_miri::interpret::{EvalContext, EvalErrorKind};
+
+use super::Evaluator;
+
+/// The name of the variant of `kind`, e.g. `DanglingPointerDeref`.
+pub fn error_category(kind: &EvalErrorKind) -> String {
+    let debug = format!("{:?}", kind);
+    debug
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .next()
+        .unwrap_or("")
+        .to_owned()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub kind: String,
+    pub function: String,
+    pub location: String,
+}
+
+impl Finding {
+    /// A finding of `kind` at the statement the topmost frame is executing right now.
+    pub fn new<'a, 'tcx>(ecx: &EvalContext<'a, 'tcx, Evaluator>, kind: String) -> Self {
+        let (function, location) = match ecx.stack().last() {
+            Some(frame) => {
+                let loc = ecx.tcx.sess.codemap().lookup_char_pos(frame.cur_span().lo());
+                (frame.instance.to_string(), format!("{}:{}", loc.file.name, loc.line))
+            }
+            None => ("*".to_owned(), "*".to_owned()),
+        };
+        Finding { kind, function, location }
+    }
+
+    fn to_line(&self) -> String {
+        format!("{}\t{}\t{}", self.kind, self.function, self.location)
+    }
+}
+
+#[derive(Default)]
+pub struct Suppressions {
+    entries: Vec<Finding>,
+}
+
+impl Suppressions {
+    pub fn load(path: &str) -> io::Result<Self> {
+        let mut entries = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split('\t').map(str::trim);
+            let mut next = || fields.next().unwrap_or("*").to_owned();
+            entries.push(Finding {
+                kind: next(),
+                function: next(),
+                location: next(),
+            });
+        }
+        Ok(Suppressions { entries })
+    }
+
+    pub fn is_suppressed(&self, finding: &Finding) -> bool {
+        let matches = |pattern: &str, value: &str| pattern == "*" || pattern == value;
+        self.entries.iter().any(|entry| {
+            matches(&entry.kind, &finding.kind) && matches(&entry.function, &finding.function) &&
+                matches(&entry.location, &finding.location)
+        })
+    }
+}
+
+/// Appends `finding` to the suppressions file at `path`.
+pub fn record(path: &str, finding: &Finding) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", finding.to_line())
+}
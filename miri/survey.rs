@@ -0,0 +1,90 @@
+//! A mode for planning the adoption of miri on a code base: instead of stopping at the first
+//! operation miri does not support, the evaluation records it, unwinds the stack as if the
+//! function using it had panicked, and goes on. The test harness of libtest catches that panic
+//! like any other, so the remaining tests still run. At the end, a report lists everything that
+//! was missing and the functions that needed it.
+
+use std::collections::BTreeMap;
+
+use rustc::ty;
+use rustc_miri::interpret::{EvalContext, EvalError, EvalErrorKind, EvalResult, MemoryPointer,
+                            PrimVal};
+
+use super::Evaluator;
+use fn_call::EvalContextExt as MissingFnsEvalContextExt;
+use panic::EvalContextExt as PanicEvalContextExt;
+
+#[derive(Default)]
+pub struct Survey {
+    /// How often each missing operation was encountered, by the function encountering it
+    missing: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+/// Whether `kind` means that the program uses something miri does not implement.
+pub fn is_unsupported(kind: &EvalErrorKind) -> bool {
+    use self::EvalErrorKind::*;
+    match *kind {
+        Unimplemented(_) | NoMirFor(_) | NoMirInCrate { .. } | InlineAsm(_) |
+        TypeNotPrimitive(_) | Layout(_) => true,
+        _ => false,
+    }
+}
+
+pub trait EvalContextExt<'tcx> {
+    /// Records the unsupported operation of `err` and unwinds the stack. Returns false if nothing
+    /// would catch the unwinding, the evaluation has to stop then.
+    fn skip_unsupported(&mut self, err: &EvalError<'tcx>) -> EvalResult<'tcx, bool>;
+
+    /// The vtable of `()` as `Any + Send`.
+    fn unit_payload_vtable(&mut self) -> EvalResult<'tcx, MemoryPointer>;
+}
+
+impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, Evaluator> {
+    fn skip_unsupported(&mut self, err: &EvalError<'tcx>) -> EvalResult<'tcx, bool> {
+        let function = match self.stack().last() {
+            Some(frame) => frame.instance.to_string(),
+            None => "<no function>".to_owned(),
+        };
+        *self.machine_data
+            .survey
+            .missing
+            .entry(err.to_string())
+            .or_insert_with(BTreeMap::new)
+            .entry(function)
+            .or_insert(0) += 1;
+        if !self.panic_is_caught() {
+            return Ok(false);
+        }
+        // A `Box<()>` needs no allocation, so any aligned pointer will do.
+        let vtable = self.unit_payload_vtable()?;
+        self.unwind_with_payload(PrimVal::Bytes(1), PrimVal::Ptr(vtable))?;
+        Ok(true)
+    }
+
+    fn unit_payload_vtable(&mut self) -> EvalResult<'tcx, MemoryPointer> {
+        let any = self.resolve_path(&["core", "any", "Any"])?.def_id();
+        let unit = self.tcx.mk_nil();
+        let trait_ref = ty::Binder(ty::TraitRef {
+            def_id: any,
+            substs: self.tcx.mk_substs_trait(unit, &[]),
+        });
+        self.get_vtable(unit, trait_ref)
+    }
+}
+
+/// Prints what was missing and which functions needed it, most frequent first.
+pub fn print_report(survey: &Survey) {
+    let mut missing: Vec<_> = survey
+        .missing
+        .iter()
+        .map(|(what, functions)| (functions.values().sum::<u64>(), what, functions))
+        .collect();
+    missing.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+    eprintln!("survey: {} unsupported operations encountered", missing.len());
+    for (count, what, functions) in missing {
+        eprintln!("{:>6}  {}", count, what);
+        for (function, count) in functions {
+            eprintln!("        {:>6} in {}", count, function);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Shims for the clocks of the host. With isolation, which is the default, all clocks are replaced
+//! by one fake clock, which advances by a fixed amount every time it is read. Runs are then
+//! reproducible, and time never stands still for programs that measure how long something took.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rustc_miri::interpret::{EvalContext, EvalResult, Pointer, PrimVal};
+
+use super::Evaluator;
+
+/// How many nanoseconds the fake clock advances on every read.
+const FAKE_CLOCK_TICK: u32 = 1000;
+
+// The values of `clockid_t` on Linux. Coarse and raw clocks behave like the precise ones.
+const CLOCK_REALTIME: u64 = 0;
+const CLOCK_MONOTONIC: u64 = 1;
+const CLOCK_MONOTONIC_RAW: u64 = 4;
+const CLOCK_REALTIME_COARSE: u64 = 5;
+const CLOCK_MONOTONIC_COARSE: u64 = 6;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Clock {
+    /// Time since the Unix epoch, like `SystemTime`
+    Realtime,
+    /// Time since some arbitrary point, like `Instant`
+    Monotonic,
+}
+
+pub trait EvalContextExt<'tcx> {
+    fn now(&mut self, clock: Clock) -> Duration;
+
+    /// `clock_gettime(clk_id, tp)`, which returns -1 for unknown clocks.
+    fn clock_gettime(&mut self, clk_id: u64, tp: Pointer) -> EvalResult<'tcx, i64>;
+
+    /// `gettimeofday(tv, tz)`, ignoring the obsolete time zone argument.
+    fn gettimeofday(&mut self, tv: Pointer) -> EvalResult<'tcx>;
+
+    /// Writes a `timespec` or `timeval` with the given seconds and seconds fraction.
+    fn write_time_pair(&mut self, ptr: Pointer, secs: u64, frac: u64, frac_size: u64)
+        -> EvalResult<'tcx>;
+}
+
+impl<'a, 'tcx> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, Evaluator> {
+    fn now(&mut self, clock: Clock) -> Duration {
+        if self.machine_data.config.isolation {
+            // Every clock starts at zero, which for the realtime clock is the Unix epoch.
+            self.machine_data.fake_clock += Duration::new(0, FAKE_CLOCK_TICK);
+            return self.machine_data.fake_clock;
+        }
+        match clock {
+            Clock::Realtime => SystemTime::now().duration_since(UNIX_EPOCH).unwrap(),
+            // The host's monotonic clock counts from when it is first read.
+            Clock::Monotonic => {
+                self.machine_data.monotonic_origin.get_or_insert_with(Instant::now).elapsed()
+            }
+        }
+    }
+
+    fn clock_gettime(&mut self, clk_id: u64, tp: Pointer) -> EvalResult<'tcx, i64> {
+        let clock = match clk_id {
+            CLOCK_REALTIME | CLOCK_REALTIME_COARSE => Clock::Realtime,
+            CLOCK_MONOTONIC | CLOCK_MONOTONIC_RAW | CLOCK_MONOTONIC_COARSE => Clock::Monotonic,
+            _ => return Ok(-1),
+        };
+        let now = self.now(clock);
+        let long_size = self.memory.pointer_size();
+        self.write_time_pair(tp, now.as_secs(), now.subsec_nanos() as u64, long_size)?;
+        Ok(0)
+    }
+
+    fn gettimeofday(&mut self, tv: Pointer) -> EvalResult<'tcx> {
+        let now = self.now(Clock::Realtime);
+        // `suseconds_t` is an `i32` on macOS and a `long` everywhere else.
+        let usec_size = if self.tcx.sess.target.target.target_os == "macos" {
+            4
+        } else {
+            self.memory.pointer_size()
+        };
+        self.write_time_pair(tv, now.as_secs(), now.subsec_nanos() as u64 / 1000, usec_size)
+    }
+
+    fn write_time_pair(&mut self, ptr: Pointer, secs: u64, frac: u64, frac_size: u64)
+        -> EvalResult<'tcx>
+    {
+        let secs_size = self.memory.pointer_size();
+        let ptr = ptr.to_ptr()?;
+        let frac_ptr = ptr.offset(secs_size, &self)?;
+        self.memory.write_primval(ptr, PrimVal::Bytes(secs as u128), secs_size, true)?;
+        self.memory.write_primval(frac_ptr, PrimVal::Bytes(frac as u128), frac_size, true)
+    }
+}
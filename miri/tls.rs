@@ -12,10 +12,13 @@ pub trait MemoryExt<'tcx> {
         &mut self,
         key: Option<TlsKey>,
     ) -> EvalResult<'tcx, Option<(ty::Instance<'tcx>, Pointer, TlsKey)>>;
+    fn register_thread_dtor(&mut self, dtor: ty::Instance<'tcx>, data: Pointer);
+    fn fetch_thread_dtor(&mut self) -> Option<(ty::Instance<'tcx>, Pointer)>;
 }
 
 pub trait EvalContextExt<'tcx> {
     fn run_tls_dtors(&mut self) -> EvalResult<'tcx>;
+    fn call_tls_dtor(&mut self, instance: ty::Instance<'tcx>, ptr: Pointer) -> EvalResult<'tcx>;
 }
 
 impl<'a, 'tcx: 'a> MemoryExt<'tcx> for Memory<'a, 'tcx, Evaluator> {
@@ -104,33 +107,32 @@ impl<'a, 'tcx: 'a> MemoryExt<'tcx> for Memory<'a, 'tcx, Evaluator> {
         }
         return Ok(None);
     }
+
+    /// Registers a destructor for a `#[thread_local]` static, like `__cxa_thread_atexit_impl`
+    /// does. The statics themselves need no special treatment, since there is only one thread.
+    fn register_thread_dtor(&mut self, dtor: ty::Instance<'tcx>, data: Pointer) {
+        trace!("Thread dtor {:?} registered for {:?}", dtor, data);
+        self.data.thread_dtors.push((dtor, data));
+    }
+
+    /// Returns the destructor registered last, which has to run first.
+    fn fetch_thread_dtor(&mut self) -> Option<(ty::Instance<'tcx>, Pointer)> {
+        self.data.thread_dtors.pop()
+    }
 }
 
 impl<'a, 'tcx: 'a> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, Evaluator> {
     fn run_tls_dtors(&mut self) -> EvalResult<'tcx> {
+        // Like glibc, destroy the `#[thread_local]` statics before running the destructors of the
+        // pthread keys. Destructors may register further destructors.
+        while let Some((instance, ptr)) = self.memory.fetch_thread_dtor() {
+            self.call_tls_dtor(instance, ptr)?;
+        }
+
         let mut dtor = self.memory.fetch_tls_dtor(None)?;
         // FIXME: replace loop by some structure that works with stepping
         while let Some((instance, ptr, key)) = dtor {
-            trace!("Running TLS dtor {:?} on {:?}", instance, ptr);
-            // TODO: Potentially, this has to support all the other possible instances?
-            // See eval_fn_call in interpret/terminator/mod.rs
-            let mir = self.load_mir(instance.def)?;
-            self.push_stack_frame(
-                instance,
-                mir.span,
-                mir,
-                Lvalue::undef(),
-                StackPopCleanup::None,
-            )?;
-            let arg_local = self.frame().mir.args_iter().next().ok_or(
-                EvalErrorKind::AbiViolation("TLS dtor does not take enough arguments.".to_owned()),
-            )?;
-            let dest = self.eval_lvalue(&mir::Lvalue::Local(arg_local))?;
-            let ty = self.tcx.mk_mut_ptr(self.tcx.types.u8);
-            self.write_ptr(dest, ptr, ty)?;
-
-            // step until out of stackframes
-            while self.step()? {}
+            self.call_tls_dtor(instance, ptr)?;
 
             dtor = match self.memory.fetch_tls_dtor(Some(key))? {
                 dtor @ Some(_) => dtor,
@@ -139,4 +141,28 @@ impl<'a, 'tcx: 'a> EvalContextExt<'tcx> for EvalContext<'a, 'tcx, Evaluator> {
         }
         Ok(())
     }
+
+    fn call_tls_dtor(&mut self, instance: ty::Instance<'tcx>, ptr: Pointer) -> EvalResult<'tcx> {
+        trace!("Running TLS dtor {:?} on {:?}", instance, ptr);
+        // TODO: Potentially, this has to support all the other possible instances?
+        // See eval_fn_call in interpret/terminator/mod.rs
+        let mir = self.load_mir(instance.def)?;
+        self.push_stack_frame(
+            instance,
+            mir.span,
+            mir,
+            Lvalue::undef(),
+            StackPopCleanup::None,
+        )?;
+        let arg_local = self.frame().mir.args_iter().next().ok_or(
+            EvalErrorKind::AbiViolation("TLS dtor does not take enough arguments.".to_owned()),
+        )?;
+        let dest = self.eval_lvalue(&mir::Lvalue::Local(arg_local))?;
+        let ty = self.tcx.mk_mut_ptr(self.tcx.types.u8);
+        self.write_ptr(dest, ptr, ty)?;
+
+        // step until out of stackframes
+        while self.step()? {}
+        Ok(())
+    }
 }
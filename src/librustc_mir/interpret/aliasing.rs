@@ -0,0 +1,286 @@
+//! An experimental check that memory behind a `&mut` is not accessed through other paths while
+//! the `&mut` is still used, enabled with `EvalContext::check_aliasing`.
+//!
+//! Every allocation has a stack of the `&mut` borrows of its memory that may still be used,
+//! oldest first. Creating a `&mut` pushes a borrow for the memory it points to. Using a `&mut`
+//! pops the overlapping borrows that were created after it, which must not be used anymore, and
+//! fails if its own borrow was popped already. Accessing memory through a raw pointer pops the
+//! overlapping borrows created after the last `&mut` that was turned into a raw pointer, because
+//! the raw pointer may have been derived from that one. Borrowing memory directly instead of
+//! through a reference pops all overlapping borrows of it, like an access by its owner. The
+//! borrows of an allocation are forgotten when it is deallocated.
+//!
+//! Pointers do not say which borrow they belong to, so only the `&mut`s held in locals are
+//! tracked: a local assigned a new `&mut` gets a new borrow, and copying a `&mut` into another
+//! local or passing it to a function carries its borrow along. Uses of `&mut`s stored in memory
+//! and of shared references are not checked. The check misses many kinds of aliasing, but it
+//! should not report programs that only use references the way the borrow checker allows.
+
+use std::collections::{BTreeMap, HashMap};
+use std::mem;
+
+use rustc::hir;
+use rustc::mir;
+use rustc::ty::{self, Ty};
+use syntax::codemap::Span;
+
+use super::{AllocId, EvalContext, EvalResult, Lvalue, Machine, PrimVal, Value};
+
+#[derive(Clone, Debug)]
+struct MutBorrow {
+    tag: u64,
+    offset: u64,
+    len: u64,
+    /// Whether the `&mut` was turned into a raw pointer.
+    exposed: bool,
+    created_at: Span,
+}
+
+impl MutBorrow {
+    fn overlaps(&self, offset: u64, len: u64) -> bool {
+        self.offset < offset + len && offset < self.offset + self.len
+    }
+}
+
+/// How many of the popped borrows of an allocation are remembered. Using a borrow that was popped
+/// longer ago is not detected.
+const MAX_POPPED: usize = 1024;
+
+/// The borrows of the memory of one allocation.
+#[derive(Default)]
+struct BorrowStack {
+    borrows: Vec<MutBorrow>,
+    /// Where the popped borrows were created and popped, to explain the error.
+    popped: BTreeMap<u64, (Span, Span)>,
+}
+
+/// The state of the aliasing check.
+#[derive(Default)]
+pub struct MutBorrows {
+    next_tag: u64,
+    stacks: HashMap<AllocId, BorrowStack>,
+    /// The allocation of each borrow that is on a stack.
+    allocs: HashMap<u64, AllocId>,
+    /// The borrows of the arguments of the call that is being evaluated, if they are locals.
+    pub(crate) arg_tags: Vec<Option<u64>>,
+}
+
+impl MutBorrows {
+    fn create(&mut self, alloc: AllocId, offset: u64, len: u64, created_at: Span) -> u64 {
+        let tag = self.next_tag;
+        self.next_tag += 1;
+        self.stacks.entry(alloc).or_insert_with(BorrowStack::default).borrows.push(MutBorrow {
+            tag,
+            offset,
+            len,
+            exposed: false,
+            created_at,
+        });
+        self.allocs.insert(tag, alloc);
+        tag
+    }
+
+    fn expose(&mut self, tag: u64) {
+        let alloc = match self.allocs.get(&tag) {
+            Some(&alloc) => alloc,
+            None => return,
+        };
+        if let Some(stack) = self.stacks.get_mut(&alloc) {
+            if let Some(borrow) = stack.borrows.iter_mut().rev().find(|borrow| borrow.tag == tag) {
+                borrow.exposed = true;
+            }
+        }
+    }
+
+    /// Pops the borrows from `above` upwards that overlap the range.
+    fn pop_overlapping(&mut self, alloc: AllocId, above: usize, offset: u64, len: u64, at: Span) {
+        let stack = match self.stacks.get_mut(&alloc) {
+            Some(stack) => stack,
+            None => return,
+        };
+        let mut i = above;
+        while i < stack.borrows.len() {
+            if stack.borrows[i].overlaps(offset, len) {
+                let borrow = stack.borrows.remove(i);
+                self.allocs.remove(&borrow.tag);
+                stack.popped.insert(borrow.tag, (borrow.created_at, at));
+                if stack.popped.len() > MAX_POPPED {
+                    let oldest = *stack.popped.keys().next().unwrap();
+                    stack.popped.remove(&oldest);
+                }
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn use_borrow<'tcx>(&mut self, alloc: AllocId, tag: u64, at: Span) -> EvalResult<'tcx> {
+        let position = self.stacks.get(&alloc).and_then(|stack| {
+            // Recent borrows are used the most.
+            stack.borrows.iter().rposition(|borrow| borrow.tag == tag).map(|i| {
+                (i, stack.borrows[i].offset, stack.borrows[i].len)
+            })
+        });
+        match position {
+            Some((i, offset, len)) => {
+                self.pop_overlapping(alloc, i + 1, offset, len, at);
+                Ok(())
+            }
+            None => {
+                let popped = self.stacks.get(&alloc).and_then(|stack| stack.popped.get(&tag));
+                match popped {
+                    Some(&(created_at, invalidated_at)) => {
+                        err!(InvalidatedMutBorrow { created_at, invalidated_at })
+                    }
+                    // The `&mut` was cast to point into another allocation.
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+
+    fn raw_access(&mut self, alloc: AllocId, offset: u64, len: u64, at: Span) {
+        let above = match self.stacks.get(&alloc) {
+            Some(stack) => {
+                stack
+                    .borrows
+                    .iter()
+                    .rposition(|borrow| borrow.exposed && borrow.overlaps(offset, len))
+                    .map_or(0, |i| i + 1)
+            }
+            None => return,
+        };
+        self.pop_overlapping(alloc, above, offset, len, at);
+    }
+
+    /// Accessing memory directly instead of through a reference pops all borrows of it.
+    fn owner_access(&mut self, alloc: AllocId, offset: u64, len: u64, at: Span) {
+        self.pop_overlapping(alloc, 0, offset, len, at);
+    }
+
+    /// Forgets the borrows of the deallocated allocation `alloc`.
+    pub(crate) fn deallocated(&mut self, alloc: AllocId) {
+        if let Some(stack) = self.stacks.remove(&alloc) {
+            for borrow in stack.borrows {
+                self.allocs.remove(&borrow.tag);
+            }
+        }
+    }
+}
+
+impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
+    /// The allocation, offset and size of the memory `val` points to, if it is not zero-sized.
+    fn borrowed_range(
+        &mut self,
+        val: Value,
+        pointee_ty: Ty<'tcx>,
+    ) -> EvalResult<'tcx, Option<(AllocId, u64, u64)>> {
+        let ptr = match val.into_ptr(&self.memory)?.into_inner_primval() {
+            PrimVal::Ptr(ptr) => ptr,
+            PrimVal::Bytes(_) | PrimVal::Undef => return Ok(None),
+        };
+        let (size, _) = self.size_and_align_of_dst(pointee_ty, val)?;
+        if size == 0 {
+            return Ok(None);
+        }
+        Ok(Some((ptr.alloc_id, ptr.offset, size)))
+    }
+
+    /// Checks the dereference of the pointer `val` of type `base_ty`, which is stored in `base`.
+    pub(crate) fn track_deref(
+        &mut self,
+        base: Lvalue,
+        base_ty: Ty<'tcx>,
+        val: Value,
+        pointee_ty: Ty<'tcx>,
+    ) -> EvalResult<'tcx> {
+        let at = self.memory.cur_span;
+        match base_ty.sty {
+            ty::TyRawPtr(_) => {
+                if let Some((alloc, offset, len)) = self.borrowed_range(val, pointee_ty)? {
+                    self.memory.mut_borrows.raw_access(alloc, offset, len, at);
+                }
+            }
+            ty::TyRef(_, ty::TypeAndMut { mutbl: hir::MutMutable, .. }) => {
+                let tag = match base {
+                    Lvalue::Local { frame, local } => {
+                        self.stack[frame].ref_tags.get(&local).cloned()
+                    }
+                    Lvalue::Ptr { .. } => None,
+                };
+                if let Some(tag) = tag {
+                    if let Some((alloc, _, _)) = self.borrowed_range(val, pointee_ty)? {
+                        self.memory.mut_borrows.use_borrow(alloc, tag, at)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Updates the borrows held by `dest` after `rvalue` was assigned to it.
+    pub(crate) fn track_assignment(
+        &mut self,
+        dest: Lvalue,
+        dest_ty: Ty<'tcx>,
+        rvalue: &mir::Rvalue<'tcx>,
+    ) -> EvalResult<'tcx> {
+        let (frame, local) = match dest {
+            Lvalue::Local { frame, local } => (frame, local),
+            Lvalue::Ptr { .. } => return Ok(()),
+        };
+        self.stack[frame].ref_tags.remove(&local);
+        let source = match *rvalue {
+            mir::Rvalue::Ref(_, mir::BorrowKind::Mut, ref borrowed) => {
+                let pointee_ty = match dest_ty.sty {
+                    ty::TyRef(_, ref tam) => tam.ty,
+                    _ => bug!("a mutable borrow of type {}", dest_ty),
+                };
+                let val = self.stack[frame].get_local(local)?;
+                if let Some((alloc, offset, len)) = self.borrowed_range(val, pointee_ty)? {
+                    let created_at = self.memory.cur_span;
+                    let borrows = &mut self.memory.mut_borrows;
+                    if !through_deref(borrowed) {
+                        borrows.owner_access(alloc, offset, len, created_at);
+                    }
+                    let tag = borrows.create(alloc, offset, len, created_at);
+                    self.stack[frame].ref_tags.insert(local, tag);
+                }
+                return Ok(());
+            }
+            mir::Rvalue::Use(mir::Operand::Consume(mir::Lvalue::Local(source))) |
+            mir::Rvalue::Cast(_, mir::Operand::Consume(mir::Lvalue::Local(source)), _) => source,
+            _ => return Ok(()),
+        };
+        let tag = match self.frame().ref_tags.get(&source) {
+            Some(&tag) => tag,
+            None => return Ok(()),
+        };
+        match dest_ty.sty {
+            ty::TyRef(_, ty::TypeAndMut { mutbl: hir::MutMutable, .. }) => {
+                self.stack[frame].ref_tags.insert(local, tag);
+            }
+            ty::TyRawPtr(_) => self.memory.mut_borrows.expose(tag),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Takes the borrows of the arguments of the current call, see `MutBorrows::arg_tags`.
+    pub(crate) fn take_arg_tags(&mut self) -> Vec<Option<u64>> {
+        mem::replace(&mut self.memory.mut_borrows.arg_tags, Vec::new())
+    }
+}
+
+/// Whether `lvalue` is reached by dereferencing a pointer, rather than memory owned by a local or
+/// a static.
+fn through_deref(lvalue: &mir::Lvalue) -> bool {
+    match *lvalue {
+        mir::Lvalue::Projection(ref projection) => match projection.elem {
+            mir::ProjectionElem::Deref => true,
+            _ => through_deref(&projection.base),
+        },
+        mir::Lvalue::Local(_) | mir::Lvalue::Static(_) => false,
+    }
+}
@@ -26,6 +26,8 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                         self.cast_from_signed_int(val.to_i128()?, dest_ty)
                     }
 
+                    Bool if val.to_u128()? > 1 => err!(InvalidBool(val.to_u128()?)),
+
                     Bool | Char | U8 | U16 | U32 | U64 | U128 | FnPtr | Ptr => {
                         self.cast_from_int(val.to_u128()?, dest_ty, false)
                     }
@@ -83,7 +85,10 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
             TyFloat(FloatTy::F32) if negative => Ok(PrimVal::from_f32(v as i128 as f32)),
             TyFloat(FloatTy::F32) => Ok(PrimVal::from_f32(v as f32)),
 
-            TyChar if v as u8 as u128 == v => Ok(PrimVal::Bytes(v)),
+            // rustc only permits `u8 as char`, but everything else that ends up here (e.g. through
+            // const evaluation of unchecked conversions) must still be a valid scalar value.
+            TyChar if v <= u32::max_value() as u128 &&
+                ::std::char::from_u32(v as u32).is_some() => Ok(PrimVal::Bytes(v)),
             TyChar => err!(InvalidChar(v)),
 
             // No alignment check needed for raw pointers.  But we have to truncate to target ptr size.
@@ -6,7 +6,7 @@ use syntax::ast::Mutability;
 use syntax::codemap::Span;
 
 use super::{EvalResult, EvalError, EvalErrorKind, GlobalId, Lvalue, Value, PrimVal, EvalContext,
-            StackPopCleanup, PtrAndAlign, MemoryKind, ValTy};
+            StackPopCleanup, PtrAndAlign, MemoryKind, MemoryPointer, ValTy, Warning};
 
 use rustc_const_math::ConstInt;
 
@@ -202,6 +202,16 @@ impl<'tcx> super::Machine<'tcx> for CompileTimeFunctionEvaluator {
         Ok(false)
     }
 
+    fn call_memory_fn_ptr<'a>(
+        _ecx: &mut EvalContext<'a, 'tcx, Self>,
+        _fn_ptr: MemoryPointer,
+        _destination: Option<(Lvalue, mir::BasicBlock)>,
+        _args: &[ValTy<'tcx>],
+        _sig: ty::FnSig<'tcx>,
+    ) -> EvalResult<'tcx, bool> {
+        Ok(false)
+    }
+
     fn call_intrinsic<'a>(
         _ecx: &mut EvalContext<'a, 'tcx, Self>,
         _instance: ty::Instance<'tcx>,
@@ -256,4 +266,20 @@ impl<'tcx> super::Machine<'tcx> for CompileTimeFunctionEvaluator {
             ConstEvalError::NotConst("statics with `linkage` attribute".to_string()).into(),
         )
     }
+
+    fn catch_unwind<'a>(_ecx: &mut EvalContext<'a, 'tcx, Self>) -> EvalResult<'tcx> {
+        bug!("const evaluation never catches unwinding")
+    }
+
+    fn skip_inline_asm<'a>(
+        _ecx: &EvalContext<'a, 'tcx, Self>,
+        _instance: ty::Instance<'tcx>,
+    ) -> bool {
+        false
+    }
+
+    fn warn<'a>(_ecx: &EvalContext<'a, 'tcx, Self>, _warning: Warning) {
+        // The warnings are about what a program does at runtime, e.g. casting integers to
+        // pointers is perfectly fine in a constant.
+    }
 }
@@ -5,8 +5,9 @@ use rustc::mir;
 use rustc::ty::{FnSig, Ty, layout};
 
 use super::{
-    MemoryPointer, Lock, AccessKind
+    AllocId, MemoryPointer, Lock, AccessKind
 };
+use super::render;
 
 use rustc_const_math::ConstMathErr;
 use syntax::codemap::Span;
@@ -16,6 +17,29 @@ use backtrace::Backtrace;
 pub struct EvalError<'tcx> {
     pub kind: EvalErrorKind<'tcx>,
     pub backtrace: Option<Backtrace>,
+    /// The interpreted call stack when the error occurred, innermost frame first. It is filled in
+    /// when the error leaves `EvalContext::step`, and empty for errors outside of any frame.
+    pub stacktrace: Vec<FrameInfo>,
+}
+
+/// A frame of the interpreted call stack, as it was when an error occurred.
+#[derive(Clone, Debug)]
+pub struct FrameInfo {
+    /// The statement or terminator the frame was executing.
+    pub span: Span,
+    /// Where the frame was called from.
+    pub call_site: Span,
+    /// What the frame was evaluating, like "inside call to foo".
+    pub description: String,
+}
+
+/// A finding that is worth reporting but does not stop the evaluation, see `EvalContext::warn`.
+#[derive(Clone, Debug)]
+pub struct Warning {
+    /// The category of the finding, like the name of an `EvalErrorKind` variant for errors.
+    pub kind: &'static str,
+    pub message: String,
+    pub span: Span,
 }
 
 impl<'tcx> From<EvalErrorKind<'tcx>> for EvalError<'tcx> {
@@ -27,6 +51,7 @@ impl<'tcx> From<EvalErrorKind<'tcx>> for EvalError<'tcx> {
         EvalError {
             kind,
             backtrace,
+            stacktrace: Vec::new(),
         }
     }
 }
@@ -38,22 +63,56 @@ pub enum EvalErrorKind<'tcx> {
     MachineError(Box<Error>),
     FunctionPointerTyMismatch(FnSig<'tcx>, FnSig<'tcx>),
     NoMirFor(String),
+    /// The function at `path` belongs to the crate `krate`, whose metadata does not contain
+    /// its MIR.
+    NoMirInCrate { path: String, krate: String },
     UnterminatedCString(MemoryPointer),
-    DanglingPointerDeref,
-    DoubleFree,
+    /// A pointer into the given allocation was dereferenced after it was deallocated.
+    DanglingPointerDeref(AllocId),
+    /// A pointer to a local of the given function was dereferenced after the function returned.
+    DanglingLocalDeref(String),
+    /// An allocation was deallocated again, after being deallocated at `freed_at` (or at an
+    /// unknown place if it is `DUMMY_SP`).
+    DoubleFree { freed_at: Span },
     InvalidMemoryAccess,
     InvalidFunctionPointer,
-    InvalidBool,
-    InvalidDiscriminant,
+    /// A `bool` that is neither 0 nor 1, with its bits.
+    InvalidBool(u128),
+    /// The discriminant of the enum does not belong to any of its variants.
+    InvalidDiscriminant(u128, Ty<'tcx>),
     PointerOutOfBounds {
         ptr: MemoryPointer,
         access: bool,
         allocation_size: u64,
     },
     InvalidNullPointerUsage,
+    /// A reference that is null was created or passed to a function, see `check_reference`.
+    NullReference,
+    /// A reference to deallocated memory, or to no memory at all, was created or passed to a
+    /// function.
+    DanglingReference,
+    /// A reference to `size` bytes at `ptr` does not fit into its allocation.
+    ReferenceOutOfBounds {
+        ptr: MemoryPointer,
+        size: u64,
+        allocation_size: u64,
+    },
+    /// A reference that is not aligned for its pointee was created or passed to a function.
+    UnalignedReference {
+        required: u64,
+        has: u64,
+    },
+    /// A `&mut` was used after an access through another path invalidated it, see the
+    /// `aliasing` module.
+    InvalidatedMutBorrow {
+        created_at: Span,
+        invalidated_at: Span,
+    },
     ReadPointerAsBytes,
     ReadBytesAsPointer,
-    InvalidPointerMath,
+    /// Pointers into different allocations were compared or subtracted, the operands are
+    /// rendered according to their type.
+    InvalidPointerMath { op: mir::BinOp, left: String, right: String },
     ReadUndefBytes,
     DeadLocal,
     InvalidBoolOp(mir::BinOp),
@@ -107,20 +166,36 @@ pub enum EvalErrorKind<'tcx> {
     VtableForArgumentlessMethod,
     ModifiedConstantMemory,
     AssumptionNotHeld,
-    InlineAsm,
+    /// Inline assembly in the function with this path.
+    InlineAsm(String),
     TypeNotPrimitive(Ty<'tcx>),
     ReallocatedWrongMemoryKind(String, String),
     DeallocatedWrongMemoryKind(String, String),
     ReallocateNonBasePtr,
     DeallocateNonBasePtr,
-    IncorrectAllocationInformation,
+    /// The size and alignment given on deallocation or reallocation do not match the allocation.
+    IncorrectAllocationInformation {
+        size: u64,
+        align: u64,
+        allocation_size: u64,
+        allocation_align: u64,
+    },
     Layout(layout::LayoutError<'tcx>),
     HeapAllocZeroBytes,
     HeapAllocNonPowerOfTwoAlignment(u64),
     Unreachable,
+    /// A value of a type without values, like `!` or an empty enum, was produced.
+    UninhabitedValue(Ty<'tcx>),
     Panic,
     ReadFromReturnPointer,
     PathNotFound(Vec<String>),
+    /// The evaluated program asked to terminate with the given exit code.
+    Exit(i32),
+    /// The evaluated program called `abort`.
+    Abort,
+    /// The evaluated program resumed a generator that had already returned or panicked.
+    GeneratorResumedAfterReturn,
+    GeneratorResumedAfterPanic,
 }
 
 pub type EvalResult<'tcx, T = ()> = Result<T, EvalError<'tcx>>;
@@ -134,20 +209,32 @@ impl<'tcx> Error for EvalError<'tcx> {
                 "tried to call a function through a function pointer of a different type",
             InvalidMemoryAccess =>
                 "tried to access memory through an invalid pointer",
-            DanglingPointerDeref =>
+            DanglingPointerDeref(_) =>
                 "dangling pointer was dereferenced",
-            DoubleFree =>
+            DanglingLocalDeref(_) =>
+                "dangling pointer to a local of a returned function was dereferenced",
+            DoubleFree { .. } =>
                 "tried to deallocate dangling pointer",
             InvalidFunctionPointer =>
                 "tried to use a function pointer after offsetting it",
-            InvalidBool =>
+            InvalidBool(_) =>
                 "invalid boolean value read",
-            InvalidDiscriminant =>
+            InvalidDiscriminant(..) =>
                 "invalid enum discriminant value read",
             PointerOutOfBounds { .. } =>
                 "pointer offset outside bounds of allocation",
             InvalidNullPointerUsage =>
                 "invalid use of NULL pointer",
+            NullReference =>
+                "encountered a null reference",
+            DanglingReference =>
+                "encountered a dangling reference",
+            ReferenceOutOfBounds { .. } =>
+                "encountered a reference that exceeds the bounds of its allocation",
+            UnalignedReference { .. } =>
+                "encountered an unaligned reference",
+            InvalidatedMutBorrow { .. } =>
+                "a `&mut` was used after the memory it points to was accessed through another path",
             MemoryLockViolation { .. } =>
                 "memory access conflicts with lock",
             MemoryAcquireConflict { .. } =>
@@ -162,7 +249,7 @@ impl<'tcx> Error for EvalError<'tcx> {
                 "a raw memory access tried to access part of a pointer value as raw bytes",
             ReadBytesAsPointer =>
                 "a memory access tried to interpret some bytes as a pointer",
-            InvalidPointerMath =>
+            InvalidPointerMath { .. } =>
                 "attempted to do invalid arithmetic on pointers that would leak base addresses, e.g. comparing pointers into different allocations",
             ReadUndefBytes =>
                 "attempted to read undefined bytes",
@@ -185,6 +272,8 @@ impl<'tcx> Error for EvalError<'tcx> {
                 "attempted to do overflowing math",
             NoMirFor(..) =>
                 "mir not found",
+            NoMirInCrate { .. } =>
+                "mir not found in the metadata of a dependency",
             InvalidChar(..) =>
                 "tried to interpret an invalid 32-bit value as a char",
             OutOfMemory{..} =>
@@ -208,7 +297,7 @@ impl<'tcx> Error for EvalError<'tcx> {
                 "tried to modify constant memory",
             AssumptionNotHeld =>
                 "`assume` argument was false",
-            InlineAsm =>
+            InlineAsm(_) =>
                 "miri does not support inline assembly",
             TypeNotPrimitive(_) =>
                 "expected primitive type, got nonprimitive",
@@ -220,7 +309,7 @@ impl<'tcx> Error for EvalError<'tcx> {
                 "tried to reallocate with a pointer not to the beginning of an existing object",
             DeallocateNonBasePtr =>
                 "tried to deallocate with a pointer not to the beginning of an existing object",
-            IncorrectAllocationInformation =>
+            IncorrectAllocationInformation { .. } =>
                 "tried to deallocate or reallocate using incorrect alignment or size",
             Layout(_) =>
                 "rustc layout computation failed",
@@ -230,6 +319,8 @@ impl<'tcx> Error for EvalError<'tcx> {
                 "tried to re-, de- or allocate zero bytes on the heap",
             HeapAllocNonPowerOfTwoAlignment(_) =>
                 "tried to re-, de-, or allocate heap memory with alignment that is not a power of two",
+            UninhabitedValue(_) =>
+                "encountered a value of an uninhabited type",
             Unreachable =>
                 "entered unreachable code",
             Panic =>
@@ -238,6 +329,14 @@ impl<'tcx> Error for EvalError<'tcx> {
                 "tried to read from the return pointer",
             EvalErrorKind::PathNotFound(_) =>
                 "a path could not be resolved, maybe the crate is not loaded",
+            Exit(_) =>
+                "the evaluated program exited",
+            Abort =>
+                "the evaluated program aborted",
+            GeneratorResumedAfterReturn =>
+                "generator resumed after completion",
+            GeneratorResumedAfterPanic =>
+                "generator resumed after panicking",
         }
     }
 
@@ -259,6 +358,15 @@ impl<'tcx> fmt::Display for EvalError<'tcx> {
                        if access { "memory access" } else { "pointer computed" },
                        ptr.offset, ptr.alloc_id, allocation_size)
             },
+            ReferenceOutOfBounds { ptr, size, allocation_size } => {
+                write!(f, "encountered a reference to {} bytes at offset {}, outside bounds of \
+                           allocation {} which has size {}",
+                       size, ptr.offset, ptr.alloc_id, allocation_size)
+            }
+            UnalignedReference { required, has } => {
+                write!(f, "encountered a reference with alignment {}, but alignment {} is required",
+                       has, required)
+            }
             MemoryLockViolation { ptr, len, frame, access, ref lock } => {
                 write!(f, "{:?} access by frame {} at {:?}, size {}, is in conflict with lock {:?}",
                        access, frame, ptr, len, lock)
@@ -279,6 +387,22 @@ impl<'tcx> fmt::Display for EvalError<'tcx> {
                 write!(f, "type validation failed: {}", err)
             }
             NoMirFor(ref func) => write!(f, "no mir for `{}`", func),
+            NoMirInCrate { ref path, ref krate } =>
+                write!(f, "no mir for `{}`, crate `{}` has to be built with `-Zalways-encode-mir`",
+                       path, krate),
+            InlineAsm(ref func) =>
+                write!(f, "miri does not support inline assembly, used in `{}`", func),
+            DanglingLocalDeref(ref func) =>
+                write!(f, "dangling pointer to a local of `{}` was dereferenced after its frame \
+                           was popped", func),
+            IncorrectAllocationInformation { size, align, allocation_size, allocation_align } => {
+                write!(f, "tried to deallocate or reallocate using incorrect alignment or size: \
+                           size {} and alignment {} were given, but the allocation has size {} and \
+                           alignment {}",
+                       size, align, allocation_size, allocation_align)
+            }
+            UninhabitedValue(ty) =>
+                write!(f, "encountered a value of the uninhabited type {}", ty),
             FunctionPointerTyMismatch(sig, got) =>
                 write!(f, "tried to call a function with sig {} through a function pointer of type {}", sig, got),
             ArrayIndexOutOfBounds(span, len, index) =>
@@ -293,6 +417,24 @@ impl<'tcx> fmt::Display for EvalError<'tcx> {
                 write!(f, "{}", err),
             InvalidChar(c) =>
                 write!(f, "tried to interpret an invalid 32-bit value as a char: {}", c),
+            InvalidBool(bits) =>
+                write!(f, "invalid boolean value read: {}",
+                       render::invalid_bits(bits, 1, "a bool")),
+            InvalidDiscriminant(discr, ty) =>
+                write!(f, "invalid enum discriminant value read: {} (expected a discriminant of `{}`)",
+                       discr, ty),
+            InvalidPointerMath { op, ref left, ref right } => {
+                let op = match op {
+                    mir::BinOp::Lt => "<",
+                    mir::BinOp::Le => "<=",
+                    mir::BinOp::Gt => ">",
+                    mir::BinOp::Ge => ">=",
+                    mir::BinOp::Sub => "-",
+                    _ => bug!("{:?} works for pointers into different allocations", op),
+                };
+                write!(f, "attempted to do invalid arithmetic on pointers into different allocations: `{} {} {}`",
+                       left, op, right)
+            }
             OutOfMemory { allocation_size, memory_size, memory_usage } =>
                 write!(f, "tried to allocate {} more bytes, but only {} bytes are free of the {} byte memory",
                        allocation_size, memory_size - memory_usage, memory_size),
@@ -307,6 +449,8 @@ impl<'tcx> fmt::Display for EvalError<'tcx> {
                 write!(f, "Cannot find path {:?}", path),
             MachineError(ref inner) =>
                 write!(f, "machine error: {}", inner),
+            Exit(code) =>
+                write!(f, "the evaluated program exited with code {}", code),
             _ => write!(f, "{}", self.description()),
         }
     }
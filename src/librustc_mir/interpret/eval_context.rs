@@ -1,5 +1,7 @@
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
+use std::mem;
 
 use rustc::hir::def_id::DefId;
 use rustc::hir::map::definitions::DefPathData;
@@ -13,12 +15,12 @@ use rustc::ty::subst::{Subst, Substs, Kind};
 use rustc::ty::{self, Ty, TyCtxt, TypeFoldable};
 use rustc_data_structures::indexed_vec::Idx;
 use syntax::codemap::{self, DUMMY_SP};
-use syntax::ast::Mutability;
+use syntax::ast::{self, Mutability};
 use syntax::abi::Abi;
 
-use super::{EvalError, EvalResult, EvalErrorKind, GlobalId, Lvalue, LvalueExtra, Memory,
-            MemoryPointer, HasMemory, MemoryKind, operator, PrimVal, PrimValKind, Value, Pointer,
-            ValidationQuery, Machine};
+use super::{EvalError, EvalResult, EvalErrorKind, Event, FrameInfo, GlobalId, Lvalue, LvalueExtra,
+            Memory, MemoryPointer, HasMemory, MemoryKind, operator, PrimVal, PrimValKind, Value,
+            Pointer, ValidationQuery, Machine, Warning};
 
 pub struct EvalContext<'a, 'tcx: 'a, M: Machine<'tcx>> {
     /// Stores data required by the `Machine`
@@ -46,6 +48,46 @@ pub struct EvalContext<'a, 'tcx: 'a, M: Machine<'tcx>> {
     /// This prevents infinite loops and huge computations from freezing up const eval.
     /// Remove once halting problem is solved.
     pub(crate) steps_remaining: u64,
+
+    /// Step limits of individual functions, by item path. While such a function is on the stack,
+    /// the steps it and its callees take count against its own limit instead of the global one.
+    /// A limit of 0 means unlimited. Functions can also be given a limit with
+    /// `#[miri(step_limit = N)]`.
+    pub step_budgets: HashMap<String, u64>,
+
+    /// Whether an overflow in an unchecked binop (as emitted by rustc when overflow checks are
+    /// disabled) is reported as an error rather than silently wrapping. Follows the setting of the
+    /// crate being interpreted unless the machine says otherwise.
+    pub overflow_checks: bool,
+
+    /// Whether reading an undefined integer yields 0 and a warning instead of an error, so
+    /// programs with such reads can still be evaluated further.
+    pub tolerate_undef_ints: bool,
+
+    /// The kinds of warnings that were already reported, and the spans they were reported at.
+    warned: RefCell<HashSet<(&'static str, codemap::Span)>>,
+
+    /// Whether references are checked with `check_reference` when they are created or passed to
+    /// a function. Unlike validation, this does not need the MIR to contain validation statements.
+    pub check_references: bool,
+
+    /// Whether the memory behind a `&mut` is checked not to be accessed through other paths
+    /// while the `&mut` is still used, see the `aliasing` module.
+    pub check_aliasing: bool,
+
+    /// Whether validation statements are executed. Without validation, the statements emitted by
+    /// `-Zmir-emit-validate` are ignored, like when it is 0.
+    pub validate: bool,
+
+    /// The vtables created so far. Every type gets a single vtable per trait, like in a compiled
+    /// program, no matter how often it is coerced to a trait object.
+    pub(crate) vtables: HashMap<(Ty<'tcx>, ty::PolyTraitRef<'tcx>), MemoryPointer>,
+
+    /// The instances callees were resolved to, see `resolve_cached`.
+    pub(crate) resolved: RefCell<HashMap<(DefId, &'tcx Substs<'tcx>), ty::Instance<'tcx>>>,
+
+    /// Whether each ADT asked about is uninhabited, see `type_is_uninhabited`.
+    uninhabited_adts: RefCell<HashMap<Ty<'tcx>, bool>>,
 }
 
 /// A stack frame.
@@ -79,6 +121,9 @@ pub struct Frame<'tcx> {
     /// Before being initialized, arguments are `Value::ByVal(PrimVal::Undef)` and other locals are `None`.
     pub locals: Vec<Option<Value>>,
 
+    /// The `&mut` borrows held by locals of this frame, see the `aliasing` module.
+    pub ref_tags: HashMap<mir::Local, u64>,
+
     ////////////////////////////////////////////////////////////////////////////////
     // Current position within the function
     ////////////////////////////////////////////////////////////////////////////////
@@ -88,6 +133,13 @@ pub struct Frame<'tcx> {
 
     /// The index of the currently evaluated statment.
     pub stmt: usize,
+
+    /// The steps this frame and its callees may still take, if the function has a step limit of
+    /// its own.
+    pub steps_remaining: Option<u64>,
+
+    /// Whether unwinding stops when it pops this frame, see `Machine::catch_unwind`.
+    pub catch_unwind: bool,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -181,6 +233,16 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
             stack: Vec::new(),
             stack_limit: limits.stack_limit,
             steps_remaining: limits.step_limit,
+            step_budgets: HashMap::new(),
+            overflow_checks: tcx.sess.overflow_checks(),
+            tolerate_undef_ints: false,
+            warned: RefCell::new(HashSet::new()),
+            check_references: false,
+            check_aliasing: false,
+            validate: true,
+            vtables: HashMap::new(),
+            resolved: RefCell::new(HashMap::new()),
+            uninhabited_adts: RefCell::new(HashMap::new()),
         }
     }
 
@@ -246,6 +308,7 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
             }
 
             Unevaluated(def_id, substs) => {
+                let substs = apply_param_substs(self.tcx, self.substs(), &substs);
                 let instance = self.resolve_associated_const(def_id, substs);
                 let cid = GlobalId {
                     instance,
@@ -269,6 +332,33 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         ty.is_sized(self.tcx, ty::ParamEnv::empty(Reveal::All), DUMMY_SP)
     }
 
+    /// Whether no value of `ty` can exist, like for `!`, empty enums and structs with a field of
+    /// such a type. Unions and the pointees of pointers are assumed to be inhabited.
+    pub fn type_is_uninhabited(&self, ty: Ty<'tcx>) -> bool {
+        match ty.sty {
+            ty::TyNever => true,
+            ty::TyTuple(tys, _) => tys.iter().any(|ty| self.type_is_uninhabited(ty)),
+            ty::TyArray(elem_ty, n) => {
+                let len = n.val.to_const_int().and_then(|n| n.to_u64()).unwrap_or(0);
+                len > 0 && self.type_is_uninhabited(elem_ty)
+            }
+            // Every `write_value` asks, so the walk over the fields is only done once per type.
+            ty::TyAdt(adt, substs) if !adt.is_union() && !adt.is_box() => {
+                if let Some(&uninhabited) = self.uninhabited_adts.borrow().get(&ty) {
+                    return uninhabited;
+                }
+                let uninhabited = adt.variants.iter().all(|variant| {
+                    variant.fields.iter().any(|field| {
+                        self.type_is_uninhabited(field.ty(self.tcx, substs))
+                    })
+                });
+                self.uninhabited_adts.borrow_mut().insert(ty, uninhabited);
+                uninhabited
+            }
+            _ => false,
+        }
+    }
+
     pub fn load_mir(
         &self,
         instance: ty::InstanceDef<'tcx>,
@@ -284,6 +374,18 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         }
     }
 
+    /// The error for a call of `def_id` that has neither MIR nor a replacement in the machine. The
+    /// MIR of other crates comes from their metadata, so for their items the error names the crate
+    /// that lacks it.
+    pub fn no_mir_error(&self, def_id: DefId, path: String) -> EvalErrorKind<'tcx> {
+        if def_id.is_local() {
+            EvalErrorKind::NoMirFor(path)
+        } else {
+            let krate = self.tcx.crate_name(def_id.krate).to_string();
+            EvalErrorKind::NoMirInCrate { path, krate }
+        }
+    }
+
     pub fn monomorphize(&self, ty: Ty<'tcx>, substs: &'tcx Substs<'tcx>) -> Ty<'tcx> {
         // miri doesn't care about lifetimes, and will choke on some crazy ones
         // let's simply get rid of them
@@ -456,6 +558,35 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
             .map_err(|layout| EvalErrorKind::Layout(layout).into())
     }
 
+    /// The step limit of a function, from its `#[miri(step_limit = N)]` attribute or
+    /// `step_budgets`.
+    fn function_step_limit(&self, def_id: DefId) -> Option<u64> {
+        for attr in self.tcx.get_attrs(def_id).iter().filter(|a| a.check_name("miri")) {
+            let items = match attr.meta_item_list() {
+                Some(items) => items,
+                None => continue,
+            };
+            for item in items.iter() {
+                let limit = item.meta_item().and_then(|item| match item.node {
+                    ast::MetaItemKind::NameValue(ref lit) if item.name() == "step_limit" => {
+                        match lit.node {
+                            ast::LitKind::Int(limit, _) => Some(limit as u64),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                });
+                if limit.is_some() {
+                    return limit;
+                }
+            }
+        }
+        if self.step_budgets.is_empty() {
+            return None;
+        }
+        self.step_budgets.get(&self.tcx.item_path_str(def_id)).cloned()
+    }
+
     pub fn push_stack_frame(
         &mut self,
         instance: ty::Instance<'tcx>,
@@ -501,18 +632,31 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
             locals
         };
 
+        let steps_remaining = self.function_step_limit(instance.def_id()).map(|limit| {
+            if limit == 0 { u64::max_value() } else { limit }
+        });
+
         self.stack.push(Frame {
             mir,
             block: mir::START_BLOCK,
             return_to_block,
             return_lvalue,
             locals,
+            ref_tags: HashMap::new(),
             span,
             instance,
             stmt: 0,
+            steps_remaining,
+            catch_unwind: false,
         });
 
         self.memory.cur_frame = self.cur_frame();
+        if self.memory.has_event_stream() {
+            self.memory.emit_event(Event::PushFrame {
+                depth: self.stack.len(),
+                function: &instance.to_string(),
+            });
+        }
 
         if self.stack.len() > self.stack_limit {
             err!(StackFrameLimitReached)
@@ -521,9 +665,12 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         }
     }
 
-    pub(super) fn pop_stack_frame(&mut self) -> EvalResult<'tcx> {
+    /// Removes the topmost frame from the stack, without doing anything with its return value or
+    /// locals.
+    fn remove_stack_frame(&mut self) -> EvalResult<'tcx, Frame<'tcx>> {
         ::log_settings::settings().indentation -= 1;
         self.end_region(None)?;
+        self.memory.emit_event(Event::PopFrame { depth: self.stack.len() });
         let frame = self.stack.pop().expect(
             "tried to pop a stack frame, but there were none",
         );
@@ -531,6 +678,11 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
             // TODO: Is this the correct time to start considering these accesses as originating from the returned-to stack frame?
             self.memory.cur_frame = self.cur_frame();
         }
+        Ok(frame)
+    }
+
+    pub(super) fn pop_stack_frame(&mut self) -> EvalResult<'tcx> {
+        let frame = self.remove_stack_frame()?;
         match frame.return_to_block {
             StackPopCleanup::MarkStatic(mutable) => {
                 if let Lvalue::Ptr { ptr, .. } = frame.return_lvalue {
@@ -546,11 +698,85 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
             StackPopCleanup::Goto(target) => self.goto_block(target),
             StackPopCleanup::None => {}
         }
-        // deallocate all locals that are backed by an allocation
-        for local in frame.locals {
-            self.deallocate_local(local)?;
+        self.deallocate_frame_locals(frame.instance, frame.locals)
+    }
+
+    /// Unwinds the stack from the call or drop the topmost frame is executing, until a frame with a
+    /// cleanup block for it or a frame catching the unwinding is found. Frames without cleanup
+    /// blocks are popped, and their return values are never written.
+    pub fn unwind(&mut self) -> EvalResult<'tcx> {
+        while !self.stack.is_empty() {
+            let cleanup = {
+                let frame = self.frame();
+                let block = &frame.mir.basic_blocks()[frame.block];
+                if frame.stmt == block.statements.len() {
+                    unwind_target(&block.terminator().kind)
+                } else {
+                    None
+                }
+            };
+            if let Some(cleanup) = cleanup {
+                trace!("unwinding into cleanup block {:?}", cleanup);
+                self.goto_block(cleanup);
+                return Ok(());
+            }
+            if self.pop_unwound_frame()? {
+                return Ok(());
+            }
         }
+        // The machine has to make sure there is a frame catching the unwinding.
+        err!(Panic)
+    }
+
+    /// Pops all frames without returning from them, after an evaluation stopped with an error, so
+    /// the context can evaluate something else. Globals whose initializer was interrupted are
+    /// forgotten, so they are evaluated again when they are used next.
+    pub fn abandon_stack(&mut self) -> EvalResult<'tcx> {
+        while !self.stack.is_empty() {
+            let frame = self.remove_stack_frame()?;
+            if let StackPopCleanup::MarkStatic(_) = frame.return_to_block {
+                let ptr = frame.return_lvalue.to_ptr()?;
+                self.globals.retain(|_, global| {
+                    global.ptr.to_ptr().ok().map(|p| p.alloc_id) != Some(ptr.alloc_id)
+                });
+                self.memory.deallocate(ptr, None, MemoryKind::UninitializedStatic)?;
+            }
+            self.deallocate_frame_locals(frame.instance, frame.locals)?;
+        }
+        Ok(())
+    }
 
+    /// Pops the topmost frame while unwinding. Returns whether the frame caught the unwinding.
+    pub(super) fn pop_unwound_frame(&mut self) -> EvalResult<'tcx, bool> {
+        let frame = self.remove_stack_frame()?;
+        let catch_unwind = frame.catch_unwind;
+        self.deallocate_frame_locals(frame.instance, frame.locals)?;
+        if catch_unwind {
+            M::catch_unwind(self)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Deallocates all locals of a popped frame of `instance` that are backed by an allocation,
+    /// keeping tombstones so later accesses through pointers to them can name the function.
+    fn deallocate_frame_locals(
+        &mut self,
+        instance: ty::Instance<'tcx>,
+        locals: Vec<Option<Value>>,
+    ) -> EvalResult<'tcx> {
+        for local in locals {
+            let alloc_id = match local {
+                Some(Value::ByRef(ptr)) => Some(ptr.to_ptr()?.alloc_id),
+                _ => None,
+            };
+            self.deallocate_local(local)?;
+            if let Some(alloc_id) = alloc_id {
+                if self.memory.get(alloc_id).is_err() {
+                    self.memory.mark_dead_frame_local(alloc_id, instance);
+                }
+            }
+        }
         Ok(())
     }
 
@@ -622,6 +848,48 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         Ok(())
     }
 
+    /// Whether `lvalue` is a field of a union, which may not be initialized on purpose.
+    fn is_union_field(&self, lvalue: &mir::Lvalue<'tcx>) -> bool {
+        match *lvalue {
+            mir::Lvalue::Projection(ref proj) => {
+                match (&proj.elem, &self.lvalue_ty(&proj.base).sty) {
+                    (&mir::ProjectionElem::Field(..), &ty::TyAdt(adt, _)) => adt.is_union(),
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Records that the undefined bytes of `dest` were just read out of a union, so an error
+    /// about them can point there. In a function called `assume_init`, like
+    /// `MaybeUninit::assume_init`, the call is what took them out.
+    fn record_union_escape(&mut self, dest: Lvalue, ty: Ty<'tcx>) -> EvalResult<'tcx> {
+        let ptr = match dest {
+            Lvalue::Ptr { ptr, .. } => ptr.ptr,
+            Lvalue::Local { frame, local } => {
+                match self.stack[frame].get_local(local)? {
+                    Value::ByRef(ptr) => ptr.ptr,
+                    // There is no memory the bytes could be tracked in.
+                    _ => return Ok(()),
+                }
+            }
+        };
+        let (ptr, size) = match (ptr.into_inner_primval(), self.type_size(ty)?) {
+            (PrimVal::Ptr(ptr), Some(size)) => (ptr, size),
+            _ => return Ok(()),
+        };
+        let escape = {
+            let frame = self.frame();
+            if &self.tcx.item_name(frame.instance.def_id())[..] == "assume_init" {
+                frame.span
+            } else {
+                self.memory.cur_span
+            }
+        };
+        self.memory.set_undef_escape(ptr, size, escape)
+    }
+
     /// Evaluate an assignment statement.
     ///
     /// There is no separate `eval_rvalue` function. Instead, the code for handling each rvalue
@@ -644,6 +912,11 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                     ty: dest_ty,
                 };
                 self.write_value(valty, dest)?;
+                if let mir::Operand::Consume(ref source) = *operand {
+                    if self.is_union_field(source) {
+                        self.record_union_escape(dest, dest_ty)?;
+                    }
+                }
             }
 
             BinaryOp(bin_op, ref left, ref right) => {
@@ -655,13 +928,13 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                     right,
                     dest,
                     dest_ty,
-                )?
+                )? && self.overflow_checks
                 {
-                    // There was an overflow in an unchecked binop.  Right now, we consider this an error and bail out.
-                    // The rationale is that the reason rustc emits unchecked binops in release mode (vs. the checked binops
-                    // it emits in debug mode) is performance, but it doesn't cost us any performance in miri.
-                    // If, however, the compiler ever starts transforming unchecked intrinsics into unchecked binops,
-                    // we have to go back to just ignoring the overflow here.
+                    // There was an overflow in an unchecked binop.  With overflow checks enabled, we consider this an
+                    // error and bail out. The rationale is that the reason rustc emits unchecked binops in release
+                    // mode (vs. the checked binops it emits in debug mode) is performance, but it doesn't cost us any
+                    // performance in miri. Without overflow checks, the result simply wraps around, just like it
+                    // would in the compiled program.
                     return err!(OverflowingMath);
                 }
             }
@@ -870,6 +1143,10 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                         bug!("attempted to take a reference to an enum downcast lvalue")
                     }
                 };
+                if self.check_references {
+                    let pointee_ty = self.lvalue_ty(lvalue);
+                    self.check_reference(val, pointee_ty)?;
+                }
                 let valty = ValTy {
                     value: val,
                     ty: dest_ty,
@@ -936,7 +1213,7 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                     ReifyFnPointer => {
                         match self.operand_ty(operand).sty {
                             ty::TyFnDef(def_id, substs) => {
-                                let instance = resolve(self.tcx, def_id, substs);
+                                let instance = self.resolve_cached(def_id, substs);
                                 let fn_ptr = self.memory.create_fn_alloc(instance);
                                 let valty = ValTy {
                                     value: Value::ByVal(PrimVal::Ptr(fn_ptr)),
@@ -985,22 +1262,19 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                 let lval = self.eval_lvalue(lvalue)?;
                 let ty = self.lvalue_ty(lvalue);
                 let ptr = self.force_allocation(lval)?.to_ptr()?;
-                let discr_val = self.read_discriminant_value(ptr, ty)?;
-                if let ty::TyAdt(adt_def, _) = ty.sty {
-                    trace!("Read discriminant {}, valid discriminants {:?}", discr_val, adt_def.discriminants(self.tcx).collect::<Vec<_>>());
-                    if adt_def.discriminants(self.tcx).all(|v| {
-                        discr_val != v.to_u128_unchecked()
-                    })
-                    {
-                        return err!(InvalidDiscriminant);
-                    }
-                    self.write_primval(dest, PrimVal::Bytes(discr_val), dest_ty)?;
-                } else {
-                    bug!("rustc only generates Rvalue::Discriminant for enums");
+                match ty.sty {
+                    ty::TyAdt(..) => {}
+                    _ => bug!("rustc only generates Rvalue::Discriminant for enums"),
                 }
+                let discr_val = self.read_valid_discriminant_value(ptr, ty)?;
+                self.write_primval(dest, PrimVal::Bytes(discr_val), dest_ty)?;
             }
         }
 
+        if self.check_aliasing {
+            self.track_assignment(dest, dest_ty, rvalue)?;
+        }
+
         if log_enabled!(::log::LogLevel::Trace) {
             self.dump_local(dest);
         }
@@ -1203,6 +1477,12 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                 packed: false,
             }),
 
+            // The upvars, followed by the state and the locals saved across yields
+            ty::TyGenerator(def_id, ref closure_substs, _) => Ok(TyAndPacked {
+                ty: closure_substs.field_tys(def_id, self.tcx).nth(field_index).unwrap(),
+                packed: false,
+            }),
+
             _ => {
                 err!(Unimplemented(
                     format!("can't handle type: {:?}, {:?}", ty, ty.sty),
@@ -1260,6 +1540,40 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         }
     }
 
+    /// Collects the byte ranges of a value of type `ty` at `offset` that may be mutated through
+    /// shared references. Enums, unions and arrays containing an `UnsafeCell` count as a whole.
+    pub(super) fn unsafe_cell_ranges(
+        &self,
+        ty: Ty<'tcx>,
+        offset: u64,
+        ranges: &mut Vec<(u64, u64)>,
+    ) -> EvalResult<'tcx> {
+        if ty.is_freeze(self.tcx, ty::ParamEnv::empty(Reveal::All), DUMMY_SP) {
+            return Ok(());
+        }
+        let has_fields = match ty.sty {
+            ty::TyAdt(adt, _) => {
+                adt.is_struct() && Some(adt.did) != self.tcx.lang_items().unsafe_cell_type()
+            }
+            ty::TyTuple(..) | ty::TyClosure(..) => true,
+            _ => false,
+        };
+        if has_fields {
+            if let Layout::Univariant { .. } = *self.type_layout(ty)? {
+                for i in 0..self.get_field_count(ty)? as usize {
+                    let field_ty = self.get_field_ty(ty, i)?.ty;
+                    let field_ty = self.tcx.normalize_associated_type(&field_ty);
+                    let field_offset = self.get_field_offset(ty, i)?.bytes();
+                    self.unsafe_cell_ranges(field_ty, offset + field_offset, ranges)?;
+                }
+                return Ok(());
+            }
+        }
+        let size = self.type_size(ty)?.expect("cell ranges of an unsized type");
+        ranges.push((offset, offset + size));
+        Ok(())
+    }
+
     pub(super) fn eval_operand_to_primval(
         &mut self,
         op: &mir::Operand<'tcx>,
@@ -1272,9 +1586,17 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         &mut self,
         ops: &[mir::Operand<'tcx>],
     ) -> EvalResult<'tcx, Vec<ValTy<'tcx>>> {
-        ops.into_iter()
+        let args = ops.into_iter()
             .map(|op| self.eval_operand(op))
-            .collect()
+            .collect::<EvalResult<'tcx, Vec<_>>>()?;
+        if self.check_references {
+            for arg in &args {
+                if let ty::TyRef(_, ref tam) = arg.ty.sty {
+                    self.check_reference(arg.value, tam.ty)?;
+                }
+            }
+        }
+        Ok(args)
     }
 
     pub fn eval_operand(&mut self, op: &mir::Operand<'tcx>) -> EvalResult<'tcx, ValTy<'tcx>> {
@@ -1377,6 +1699,24 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         Ok(discr_val)
     }
 
+    /// Like `read_discriminant_value`, but fails if the discriminant of the enum `adt_ty` does not
+    /// belong to any of its variants, e.g. because the enum was transmuted from an integer.
+    pub fn read_valid_discriminant_value(
+        &self,
+        adt_ptr: MemoryPointer,
+        adt_ty: Ty<'tcx>,
+    ) -> EvalResult<'tcx, u128> {
+        let discr_val = self.read_discriminant_value(adt_ptr, adt_ty)?;
+        if let ty::TyAdt(adt_def, _) = adt_ty.sty {
+            if adt_def.is_enum() &&
+                adt_def.discriminants(self.tcx).all(|v| discr_val != v.to_u128_unchecked())
+            {
+                return err!(InvalidDiscriminant(discr_val, adt_ty));
+            }
+        }
+        Ok(discr_val)
+    }
+
     fn read_nonnull_discriminant_value(
         &self,
         ptr: MemoryPointer,
@@ -1482,6 +1822,10 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         match self.follow_by_ref_value(value, ty)? {
             Value::ByRef { .. } => bug!("follow_by_ref_value can't result in `ByRef`"),
 
+            Value::ByVal(PrimVal::Undef) if self.tolerate_undef_ints && ty.is_integral() => {
+                self.tolerated_undef_int(value, ty)
+            }
+
             Value::ByVal(primval) => {
                 // TODO: Do we really want insta-UB here?
                 self.ensure_valid_value(primval, ty)?;
@@ -1492,6 +1836,51 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         }
     }
 
+    /// The value an undefined integer of type `ty` is read as, see `tolerate_undef_ints`. The
+    /// garbage of an integer in memory keeps the bytes of the integer that are defined.
+    fn tolerated_undef_int(&self, value: Value, ty: Ty<'tcx>) -> EvalResult<'tcx, PrimVal> {
+        let (value, what) = match (self.memory.undef_garbage(), value) {
+            (Some(_), Value::ByRef(PtrAndAlign { ptr, aligned })) => {
+                let size = self.type_size(ty)?.expect("integers are sized");
+                let value = self.read_maybe_aligned(aligned, |ectx| {
+                    ectx.memory.read_garbage(ptr.to_ptr()?, size, ty.is_signed())
+                })?;
+                (value, "garbage")
+            }
+            (Some(rng), _) => {
+                let mut rng = rng.borrow_mut();
+                let bits = self.type_size(ty)?.expect("integers are sized") * 8;
+                let garbage = (rng.next_u64() as u128) << 64 | rng.next_u64() as u128;
+                // Truncate to the size of `ty`, sign extending like `Memory::read_primval` does.
+                let shift = 128 - bits;
+                let value = if ty.is_signed() {
+                    (((garbage << shift) as i128) >> shift) as u128
+                } else {
+                    (garbage << shift) >> shift
+                };
+                (value, "garbage")
+            }
+            (None, _) => (0, "0"),
+        };
+        self.warn(
+            "UndefinedInt",
+            format!("read an undefined value of type `{}`, using {} instead", ty, what),
+        );
+        Ok(PrimVal::Bytes(value))
+    }
+
+    /// Hands a finding of the category `kind` that does not stop the evaluation to the machine,
+    /// once for every statement it happens at.
+    pub fn warn(&self, kind: &'static str, message: String) {
+        let span = match self.stack.last() {
+            Some(frame) => frame.cur_span(),
+            None => DUMMY_SP,
+        };
+        if self.warned.borrow_mut().insert((kind, span)) {
+            M::warn(self, Warning { kind, message, span });
+        }
+    }
+
     pub fn write_null(&mut self, dest: Lvalue, dest_ty: Ty<'tcx>) -> EvalResult<'tcx> {
         self.write_primval(dest, PrimVal::Bytes(0), dest_ty)
     }
@@ -1527,6 +1916,11 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         // In case `src_val` is a `ByValPair`, we don't do any magic here to handle padding properly, which is only
         // correct if we never look at this data with the wrong type.
 
+        if self.type_is_uninhabited(dest_ty) {
+            return err!(UninhabitedValue(dest_ty));
+        }
+        self.check_written_value(src_val, dest_ty)?;
+
         match dest {
             Lvalue::Ptr {
                 ptr: PtrAndAlign { ptr, aligned },
@@ -1592,12 +1986,19 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
             // source and write that into the destination without making an allocation, so
             // we do so here.
             self.read_maybe_aligned_mut(aligned, |ectx| {
-                if let Ok(Some(src_val)) = ectx.try_read_value(src_ptr, dest_ty) {
-                    write_dest(ectx, src_val)?;
-                } else {
-                    let dest_ptr = ectx.alloc_ptr(dest_ty)?.into();
-                    ectx.copy(src_ptr, dest_ptr, dest_ty)?;
-                    write_dest(ectx, Value::by_ref(dest_ptr))?;
+                match ectx.try_read_value(src_ptr, dest_ty) {
+                    Ok(Some(src_val)) => write_dest(ectx, src_val)?,
+                    // Invalid `bool`s and `char`s must be reported right here, where they are
+                    // read, not when the copied bytes are eventually used.
+                    Err(err @ EvalError { kind: EvalErrorKind::InvalidBool(_), .. }) |
+                    Err(err @ EvalError { kind: EvalErrorKind::InvalidChar(_), .. }) => {
+                        return Err(err)
+                    }
+                    _ => {
+                        let dest_ptr = ectx.alloc_ptr(dest_ty)?.into();
+                        ectx.copy(src_ptr, dest_ptr, dest_ty)?;
+                        write_dest(ectx, Value::by_ref(dest_ptr))?;
+                    }
                 }
                 Ok(())
             })?;
@@ -1766,7 +2167,7 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
 
     fn ensure_valid_value(&self, val: PrimVal, ty: Ty<'tcx>) -> EvalResult<'tcx> {
         match ty.sty {
-            ty::TyBool if val.to_bytes()? > 1 => err!(InvalidBool),
+            ty::TyBool if val.to_bytes()? > 1 => err!(InvalidBool(val.to_bytes()?)),
 
             ty::TyChar if ::std::char::from_u32(val.to_bytes()? as u32).is_none() => {
                 err!(InvalidChar(val.to_bytes()? as u32 as u128))
@@ -1819,7 +2220,9 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                     PrimVal::Bytes(0) => false,
                     PrimVal::Bytes(1) => true,
                     // TODO: This seems a little overeager, should reading at bool type already be insta-UB?
-                    _ => return err!(InvalidBool),
+                    PrimVal::Bytes(bits) => return err!(InvalidBool(bits)),
+                    PrimVal::Undef => return err!(ReadUndefBytes),
+                    PrimVal::Ptr(_) => return err!(ReadPointerAsBytes),
                 };
                 PrimVal::from_bool(val)
             }
@@ -1887,7 +2290,7 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         self.stack.last().expect("no call frames exist")
     }
 
-    pub(super) fn frame_mut(&mut self) -> &mut Frame<'tcx> {
+    pub fn frame_mut(&mut self) -> &mut Frame<'tcx> {
         self.stack.last_mut().expect("no call frames exist")
     }
 
@@ -2159,31 +2562,109 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
             }
             error!("{}", trace_text);
         }
-        if let Some(frame) = self.stack().last() {
-            let block = &frame.mir.basic_blocks()[frame.block];
-            let span = if frame.stmt < block.statements.len() {
-                block.statements[frame.stmt].source_info.span
-            } else {
-                block.terminator().source_info.span
-            };
+        let stacktrace = if e.stacktrace.is_empty() {
+            self.generate_stacktrace()
+        } else {
+            mem::replace(&mut e.stacktrace, Vec::new())
+        };
+        if let Some(span) = stacktrace.first().map(|frame| frame.span) {
+            self.emit_diagnostic_event(&e.to_string(), span);
             let mut err = self.tcx.sess.struct_span_err(span, &e.to_string());
-            for &Frame { instance, span, .. } in self.stack().iter().rev() {
-                if self.tcx.def_key(instance.def_id()).disambiguated_data.data ==
-                    DefPathData::ClosureExpr
-                {
-                    err.span_note(span, "inside call to closure");
-                    continue;
-                }
-                err.span_note(span, &format!("inside call to {}", instance));
+            for (span, note) in self.error_notes(&e.kind) {
+                err.span_note(span, note);
+            }
+            for frame in &stacktrace {
+                err.span_note(frame.call_site, &frame.description);
             }
             err.emit();
         } else {
             self.tcx.sess.err(&e.to_string());
         }
     }
+
+    /// The spans explaining an error of the kind `kind`, with what they show, in the order they
+    /// are reported. The stack trace is not part of them.
+    pub fn error_notes(&self, kind: &EvalErrorKind<'tcx>) -> Vec<(codemap::Span, &'static str)> {
+        let mut notes = Vec::new();
+        match *kind {
+            EvalErrorKind::ReadUndefBytes => {
+                if let Some(origin) = self.memory.last_undef_origin() {
+                    notes.push((origin, "uninitialized memory was created here"));
+                }
+                if let Some(escape) = self.memory.last_undef_escape() {
+                    notes.push((escape, "the uninitialized value left its union here"));
+                }
+            }
+            EvalErrorKind::DoubleFree { freed_at } => {
+                notes.push((freed_at, "the memory was deallocated here before"));
+            }
+            EvalErrorKind::InvalidatedMutBorrow { created_at, invalidated_at } => {
+                notes.push((created_at, "the `&mut` was created here"));
+                notes.push((invalidated_at, "the memory was accessed through another path here"));
+            }
+            _ => {}
+        }
+        notes.extend(self.memory.allocation_notes(kind));
+        notes.retain(|&(span, _)| span != DUMMY_SP);
+        notes
+    }
+
+    /// Tells the listeners of the event stream about an error reported at `span`.
+    pub fn emit_diagnostic_event(&self, message: &str, span: codemap::Span) {
+        if self.memory.has_event_stream() {
+            self.memory.emit_event(Event::Diagnostic {
+                message,
+                span: &self.tcx.sess.codemap().span_to_string(span),
+            });
+        }
+    }
+
+    /// The interpreted call stack, innermost frame first.
+    pub fn generate_stacktrace(&self) -> Vec<FrameInfo> {
+        self.stack().iter().rev().map(|frame| {
+            let instance = frame.instance;
+            let is_closure = self.tcx.def_key(instance.def_id()).disambiguated_data.data ==
+                DefPathData::ClosureExpr;
+            let description = if is_closure {
+                "inside call to closure".to_owned()
+            } else if let StackPopCleanup::MarkStatic(_) = frame.return_to_block {
+                // Globals are evaluated when they are first used, so the call site is the use
+                // that needed the value.
+                format!("inside the initializer of {}, which is first used here", instance)
+            } else {
+                format!("inside call to {}", instance)
+            };
+            FrameInfo {
+                span: frame.cur_span(),
+                call_site: frame.span,
+                description,
+            }
+        }).collect()
+    }
+}
+
+/// The block to continue in if the call or drop of `kind` unwinds.
+fn unwind_target(kind: &mir::TerminatorKind) -> Option<mir::BasicBlock> {
+    match *kind {
+        mir::TerminatorKind::Call { cleanup, .. } |
+        mir::TerminatorKind::Assert { cleanup, .. } => cleanup,
+        mir::TerminatorKind::Drop { unwind, .. } |
+        mir::TerminatorKind::DropAndReplace { unwind, .. } => unwind,
+        _ => None,
+    }
 }
 
 impl<'tcx> Frame<'tcx> {
+    /// The span of the statement or terminator that is executed next in this frame.
+    pub fn cur_span(&self) -> codemap::Span {
+        let block = &self.mir.basic_blocks()[self.block];
+        if self.stmt < block.statements.len() {
+            block.statements[self.stmt].source_info.span
+        } else {
+            block.terminator().source_info.span
+        }
+    }
+
     pub fn get_local(&self, local: mir::Local) -> EvalResult<'tcx, Value> {
         // Subtract 1 because we don't store a value for the ReturnPointer, the local with index 0.
         self.locals[local.index() - 1].ok_or(EvalErrorKind::DeadLocal.into())
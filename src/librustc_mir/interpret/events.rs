@@ -0,0 +1,74 @@
+//! A machine-readable stream of evaluation events.
+//!
+//! Tools like editor plugins can ask the interpreter to write one JSON object per line to a file
+//! or pipe, describing frames being pushed and popped, allocations and diagnostics, so they can
+//! follow the evaluation while it happens.
+
+use std::cell::RefCell;
+use std::io::Write;
+
+use super::Json;
+
+#[derive(Clone, Debug)]
+pub enum Event<'a> {
+    PushFrame { depth: usize, function: &'a str },
+    PopFrame { depth: usize },
+    Allocate { id: u64, size: u64, align: u64, kind: &'a str },
+    Deallocate { id: u64 },
+    Diagnostic { message: &'a str, span: &'a str },
+}
+
+pub struct EventStream {
+    out: RefCell<Box<Write>>,
+}
+
+impl EventStream {
+    pub fn new<W: Write + 'static>(out: W) -> Self {
+        EventStream { out: RefCell::new(Box::new(out)) }
+    }
+
+    pub fn emit(&self, event: Event) {
+        let line = match event {
+            Event::PushFrame { depth, function } => {
+                Json::object(vec![
+                    ("event", Json::str("push_frame")),
+                    ("depth", Json::Number(depth as f64)),
+                    ("function", Json::str(function)),
+                ])
+            }
+            Event::PopFrame { depth } => {
+                Json::object(vec![
+                    ("event", Json::str("pop_frame")),
+                    ("depth", Json::Number(depth as f64)),
+                ])
+            }
+            Event::Allocate { id, size, align, kind } => {
+                Json::object(vec![
+                    ("event", Json::str("allocate")),
+                    ("id", Json::Number(id as f64)),
+                    ("size", Json::Number(size as f64)),
+                    ("align", Json::Number(align as f64)),
+                    ("kind", Json::str(kind)),
+                ])
+            }
+            Event::Deallocate { id } => {
+                Json::object(vec![
+                    ("event", Json::str("deallocate")),
+                    ("id", Json::Number(id as f64)),
+                ])
+            }
+            Event::Diagnostic { message, span } => {
+                Json::object(vec![
+                    ("event", Json::str("diagnostic")),
+                    ("message", Json::str(message)),
+                    ("span", Json::str(span)),
+                ])
+            }
+        };
+        let mut out = self.out.borrow_mut();
+        // A consumer that went away must not take the evaluation down with it.
+        if let Err(e) = writeln!(out, "{}", line).and_then(|()| out.flush()) {
+            warn!("could not write to the event stream: {}", e);
+        }
+    }
+}
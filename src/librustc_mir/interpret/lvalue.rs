@@ -314,10 +314,13 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
             }
         };
 
+        let field_ty = self.monomorphize(field_ty, self.substs());
+
         let offset = match base_extra {
-            LvalueExtra::Vtable(tab) => {
+            // Only the unsized tail has a dynamic alignment, which its offset has to respect.
+            LvalueExtra::Vtable(tab) if !self.type_is_sized(field_ty) => {
                 let (_, align) = self.size_and_align_of_dst(
-                    base_ty,
+                    field_ty,
                     base_ptr.ptr.to_value_with_vtable(tab),
                 )?;
                 offset
@@ -332,8 +335,6 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         // no matter what we were, if we are packed, we must not be aligned anymore
         ptr.aligned &= !packed;
 
-        let field_ty = self.monomorphize(field_ty, self.substs());
-
         let extra = if self.type_is_sized(field_ty) {
             LvalueExtra::None
         } else {
@@ -398,7 +399,7 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         })
     }
 
-    pub(super) fn eval_lvalue_projection(
+    pub fn eval_lvalue_projection(
         &mut self,
         base: Lvalue,
         base_ty: Ty<'tcx>,
@@ -438,7 +439,29 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
 
                 trace!("deref to {} on {:?}", pointee_type, val);
 
-                return self.val_to_lvalue(val, pointee_type);
+                if self.check_aliasing {
+                    self.track_deref(base, base_ty, val, pointee_type)?;
+                }
+
+                let lvalue = self.val_to_lvalue(val, pointee_type)?;
+                if let Lvalue::Ptr { ptr, .. } = lvalue {
+                    // Only zero-sized values can be accessed through pointers made from integers.
+                    match ptr.ptr.into_inner_primval() {
+                        PrimVal::Bytes(addr) if addr != 0 &&
+                            self.type_size(pointee_type)?.map_or(false, |size| size > 0) => {
+                            self.warn(
+                                "IntPtrDeref",
+                                format!(
+                                    "dereferenced the pointer {:#x}, which was made from an \
+                                     integer, miri cannot access memory through it",
+                                    addr
+                                ),
+                            );
+                        }
+                        _ => {}
+                    }
+                }
+                return Ok(lvalue);
             }
 
             Index(local) => {
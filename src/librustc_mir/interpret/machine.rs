@@ -2,7 +2,7 @@
 //! This separation exists to ensure that no fancy miri features like
 //! interpreting common C functions leak into CTFE.
 
-use super::{EvalResult, EvalContext, Lvalue, PrimVal, ValTy};
+use super::{EvalResult, EvalContext, Lvalue, MemoryPointer, PrimVal, ValTy, Warning};
 
 use rustc::{mir, ty};
 use syntax::codemap::Span;
@@ -35,6 +35,18 @@ pub trait Machine<'tcx>: Sized {
         sig: ty::FnSig<'tcx>,
     ) -> EvalResult<'tcx, bool>;
 
+    /// Called for calls through a function pointer that points into memory instead of to a
+    /// function, e.g. one the machine handed out for a symbol looked up at runtime.
+    ///
+    /// Returns Ok(false) if the machine does not know the pointer, calling it is an error then
+    fn call_memory_fn_ptr<'a>(
+        ecx: &mut EvalContext<'a, 'tcx, Self>,
+        fn_ptr: MemoryPointer,
+        destination: Option<(Lvalue, mir::BasicBlock)>,
+        args: &[ValTy<'tcx>],
+        sig: ty::FnSig<'tcx>,
+    ) -> EvalResult<'tcx, bool>;
+
     /// directly process an intrinsic without pushing a stack frame.
     fn call_intrinsic<'a>(
         ecx: &mut EvalContext<'a, 'tcx, Self>,
@@ -79,4 +91,21 @@ pub trait Machine<'tcx>: Sized {
         instance: ty::Instance<'tcx>,
         mutability: Mutability,
     ) -> EvalResult<'tcx>;
+
+    /// Called when unwinding pops a frame that has `catch_unwind` set. The unwinding stops there,
+    /// and the machine has to continue the execution in the frame that is on top now.
+    fn catch_unwind<'a>(ecx: &mut EvalContext<'a, 'tcx, Self>) -> EvalResult<'tcx>;
+
+    /// Called for inline assembly that is not trivially a no-op, in the function `instance`.
+    ///
+    /// Returns true if the assembly is known to be harmless and can be skipped, it is an error
+    /// otherwise
+    fn skip_inline_asm<'a>(
+        ecx: &EvalContext<'a, 'tcx, Self>,
+        instance: ty::Instance<'tcx>,
+    ) -> bool;
+
+    /// Called for findings that do not stop the evaluation, the machine decides how (and
+    /// whether) to report them
+    fn warn<'a>(ecx: &EvalContext<'a, 'tcx, Self>, warning: Warning);
 }
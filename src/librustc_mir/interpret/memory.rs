@@ -1,15 +1,18 @@
 use byteorder::{ReadBytesExt, WriteBytesExt, LittleEndian, BigEndian};
 use std::collections::{btree_map, BTreeMap, HashMap, HashSet, VecDeque};
 use std::{fmt, iter, ptr, mem, io};
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+use std::cmp::{max, min};
 
 use rustc::ty::Instance;
 use rustc::ty::layout::{self, TargetDataLayout, HasDataLayout};
 use syntax::ast::Mutability;
+use syntax::codemap::{Span, DUMMY_SP};
 use rustc::middle::region;
 
+use super::aliasing::MutBorrows;
 use super::{EvalResult, EvalErrorKind, PrimVal, Pointer, EvalContext, DynamicLifetime, Machine,
-            RangeMap, AbsLvalue};
+            RangeMap, AbsLvalue, Event, EventStream, Rng};
 
 ////////////////////////////////////////////////////////////////////////////////
 // Locks
@@ -162,9 +165,62 @@ pub struct Allocation<'tcx, M> {
     pub kind: MemoryKind<M>,
     /// Memory regions that are locked by some function
     locks: RangeMap<LockInfo<'tcx>>,
+    /// The `(start, end)` offsets of `UnsafeCell`s, which stay writable in an immutable allocation.
+    pub unsafe_cells: Vec<(u64, u64)>,
+    /// What the allocation holds, e.g. the name of a static, if anyone told us.
+    pub label: Option<String>,
+    /// The statement that was evaluated when the allocation was created.
+    pub created_at: Span,
+    /// The `(start, end)` ranges that were made undefined after the allocation was created, and the
+    /// statement that did so. Later entries take precedence, bytes outside of all ranges have been
+    /// undefined since `created_at`.
+    pub undef_origins: Vec<(u64, u64, Span)>,
+    /// The ranges of undefined bytes that were read out of a union, like the value of a
+    /// `MaybeUninit`, and where. Later entries take precedence, like in `undef_origins`.
+    pub undef_escapes: Vec<(u64, u64, Span)>,
+}
+
+/// A live allocation, as seen by tools that inspect the memory between evaluation steps.
+#[derive(Clone, Debug)]
+pub struct AllocationInfo<'x, M: 'x> {
+    pub id: AllocId,
+    pub size: u64,
+    pub align: u64,
+    pub kind: MemoryKind<M>,
+    pub label: Option<&'x str>,
+    pub created_at: Span,
 }
 
 impl<'tcx, M> Allocation<'tcx, M> {
+    /// Where the byte at `offset` became undefined, see `undef_origins`.
+    fn undef_origin(&self, offset: u64) -> Span {
+        self.undef_origins
+            .iter()
+            .rev()
+            .find(|&&(start, end, _)| start <= offset && offset < end)
+            .map_or(self.created_at, |&(_, _, span)| span)
+    }
+
+    /// Where the undefined byte at `offset` was read out of a union, see `undef_escapes`.
+    fn undef_escape(&self, offset: u64) -> Option<Span> {
+        self.undef_escapes
+            .iter()
+            .rev()
+            .find(|&&(start, end, _)| start <= offset && offset < end)
+            .map(|&(_, _, span)| span)
+    }
+
+    /// The origin of the first undefined byte in the range and where it left its union, if there
+    /// is such a byte.
+    fn first_undef_origin(&self, offset: u64, size: u64) -> Option<(Span, Option<Span>)> {
+        if self.undef_mask.is_range_defined(offset, offset + size) {
+            return None;
+        }
+        (offset..offset + size)
+            .find(|&i| !self.undef_mask.get(i))
+            .map(|i| (self.undef_origin(i), self.undef_escape(i)))
+    }
+
     fn check_locks(
         &self,
         frame: Option<usize>,
@@ -183,6 +239,18 @@ impl<'tcx, M> Allocation<'tcx, M> {
         }
         Ok(())
     }
+
+    /// Finds a write lock that `frame` holds until it returns. These locks are taken for the memory
+    /// that reference arguments point to, and protect it for the whole call.
+    fn protector(&self, frame: usize) -> Option<&LockInfo<'tcx>> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        self.locks.iter(0, self.bytes.len() as u64).find(|lock| match lock.active {
+            WriteLock(ref lft) => lft.frame == frame && lft.region.is_none(),
+            _ => false,
+        })
+    }
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -243,10 +311,58 @@ impl<'tcx> MemoryPointer {
     }
 }
 
+/// How often an allocation was read and written, in total and per bucket of bytes.
+#[derive(Clone, Debug, Default)]
+pub struct AccessCounts {
+    /// The size and kind of the allocation, for reports that outlive it.
+    pub size: u64,
+    pub kind: String,
+    pub reads: u64,
+    pub writes: u64,
+    /// Reads and writes by the offset of the first byte of each bucket. Empty unless a bucket
+    /// size was given to `Memory::record_access_counts`.
+    pub buckets: BTreeMap<u64, (u64, u64)>,
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Top-level interpreter memory
 ////////////////////////////////////////////////////////////////////////////////
 
+/// How many deallocated allocations a `DeallocationHistory` remembers.
+const MAX_HISTORY: usize = 1 << 16;
+
+/// What is known about the last `MAX_HISTORY` deallocated allocations, so errors about dangling
+/// pointers can say more about them. Older entries are forgotten, so a program that frees a lot
+/// keeps running in constant memory, at the price of plainer errors for the oldest dangling
+/// pointers.
+struct DeallocationHistory<V> {
+    entries: HashMap<AllocId, V>,
+    /// The keys of `entries`, oldest first.
+    order: VecDeque<AllocId>,
+}
+
+impl<V> DeallocationHistory<V> {
+    fn new() -> Self {
+        DeallocationHistory {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn insert(&mut self, id: AllocId, value: V) {
+        if self.order.len() == MAX_HISTORY {
+            let oldest = self.order.pop_front().unwrap();
+            self.entries.remove(&oldest);
+        }
+        self.entries.insert(id, value);
+        self.order.push_back(id);
+    }
+
+    fn get(&self, id: &AllocId) -> Option<&V> {
+        self.entries.get(id)
+    }
+}
+
 pub struct Memory<'a, 'tcx, M: Machine<'tcx>> {
     /// Additional data required by the Machine
     pub data: M::MemoryData,
@@ -263,6 +379,11 @@ pub struct Memory<'a, 'tcx, M: Machine<'tcx>> {
     /// Maximum number of virtual bytes that may be allocated.
     memory_size: u64,
 
+    /// Number of virtual bytes in allocations of a machine-specific kind, i.e. on the heap of the
+    /// evaluated program, and the largest that number has been so far.
+    machine_memory_usage: u64,
+    peak_machine_memory_usage: u64,
+
     /// Function "allocations". They exist solely so pointers have something to point to, and
     /// we can figure out what they point to.
     functions: Vec<Instance<'tcx>>,
@@ -284,6 +405,46 @@ pub struct Memory<'a, 'tcx, M: Machine<'tcx>> {
 
     /// The current stack frame.  Used to check accesses against locks.
     pub(super) cur_frame: usize,
+
+    /// The statement being evaluated, recorded as the creation site of new allocations.
+    pub(super) cur_span: Span,
+
+    /// Where the undefined bytes that were read last by the current statement became undefined.
+    /// Undefined values are only an error once they are used, so this is a guess at the origin of
+    /// the undefined value an error is about. Values read by earlier statements are not guessed
+    /// at, the guess would too often be about some other read.
+    last_undef_origin: Cell<Option<Span>>,
+
+    /// Where the undefined bytes of `last_undef_origin` were read out of a union, if they were.
+    last_undef_escape: Cell<Option<Span>>,
+
+    /// Where to report evaluation events to, if anyone is listening.
+    events: Option<EventStream>,
+
+    /// Reads and writes of each allocation, if they are being counted, and the size of the
+    /// buckets they are also counted in (0 for none).
+    access_counts: Option<RefCell<HashMap<AllocId, AccessCounts>>>,
+    access_bucket_size: u64,
+
+    /// The function whose frame each deallocated local belonged to, so accesses through pointers
+    /// that outlived their frame can name it.
+    dead_frame_locals: DeallocationHistory<Instance<'tcx>>,
+
+    /// Where each deallocated allocation that was not a local was created and deallocated, so
+    /// freeing it again or accessing it can point there.
+    freed: DeallocationHistory<(Span, Span)>,
+
+    /// If set, the bytes of undefined memory are garbage from this generator instead of 0, see
+    /// `fill_undef_with_garbage`.
+    undef_garbage: Option<RefCell<Rng>>,
+
+    /// If set, allocations have base addresses drawn from this generator, see `address`.
+    address_rng: Option<RefCell<Rng>>,
+    /// The base addresses handed out so far, by allocation.
+    base_addresses: RefCell<HashMap<AllocId, u64>>,
+
+    /// The state of the aliasing check, kept here so it is dropped with the allocations.
+    pub(crate) mut_borrows: MutBorrows,
 }
 
 impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
@@ -297,10 +458,141 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
             layout,
             memory_size: max_memory,
             memory_usage: 0,
+            machine_memory_usage: 0,
+            peak_machine_memory_usage: 0,
             literal_alloc_cache: HashMap::new(),
             reads_are_aligned: Cell::new(true),
             writes_are_aligned: Cell::new(true),
             cur_frame: usize::max_value(),
+            cur_span: DUMMY_SP,
+            last_undef_origin: Cell::new(None),
+            last_undef_escape: Cell::new(None),
+            events: None,
+            access_counts: None,
+            access_bucket_size: 0,
+            dead_frame_locals: DeallocationHistory::new(),
+            freed: DeallocationHistory::new(),
+            undef_garbage: None,
+            address_rng: None,
+            base_addresses: RefCell::new(HashMap::new()),
+            mut_borrows: MutBorrows::default(),
+        }
+    }
+
+    pub fn set_event_stream(&mut self, events: EventStream) {
+        self.events = Some(events);
+    }
+
+    /// Whether anyone listens to the events, so building them is worth it.
+    pub fn has_event_stream(&self) -> bool {
+        self.events.is_some()
+    }
+
+    pub fn emit_event(&self, event: Event) {
+        if let Some(ref events) = self.events {
+            events.emit(event);
+        }
+    }
+
+    /// Starts counting the reads and writes of every allocation, with a finer breakdown for every
+    /// `bucket_size` bytes if a size is given.
+    pub fn record_access_counts(&mut self, bucket_size: Option<u64>) {
+        self.access_counts = Some(RefCell::new(HashMap::new()));
+        self.access_bucket_size = bucket_size.unwrap_or(0);
+    }
+
+    /// The access counts recorded so far, if they are being recorded.
+    pub fn access_counts(&self) -> Option<HashMap<AllocId, AccessCounts>> {
+        self.access_counts.as_ref().map(|counts| counts.borrow().clone())
+    }
+
+    /// Makes the bytes of undefined memory garbage generated from `seed`, so a program reading
+    /// them anyway sees different values with every seed. This includes the padding of values.
+    pub fn fill_undef_with_garbage(&mut self, seed: u64) {
+        self.undef_garbage = Some(RefCell::new(Rng::new(seed)));
+    }
+
+    /// The generator of the garbage in undefined memory, if there is garbage.
+    pub fn undef_garbage(&self) -> Option<&RefCell<Rng>> {
+        self.undef_garbage.as_ref()
+    }
+
+    /// Gives allocations random base addresses generated from `seed`, see `address`.
+    pub fn randomize_addresses(&mut self, seed: u64) {
+        self.address_rng = Some(RefCell::new(Rng::new(seed)));
+    }
+
+    /// The address `ptr` points to, if allocations have base addresses. An allocation gets its
+    /// base address when it is first asked for. It is only as aligned as the allocation has to
+    /// be, so a program relying on more alignment than it asked for only works with some seeds.
+    pub fn address(&self, ptr: MemoryPointer) -> Option<u64> {
+        let rng = match self.address_rng {
+            Some(ref rng) => rng,
+            None => return None,
+        };
+        let known = self.base_addresses.borrow().get(&ptr.alloc_id).cloned();
+        let base = match known {
+            Some(base) => base,
+            None => {
+                // Functions and dead allocations have no alignment to keep.
+                let align = self.get(ptr.alloc_id).map(|alloc| alloc.align).unwrap_or(1);
+                // Clear of the null page, and low enough that adding offsets does not wrap.
+                let bits = min(self.pointer_size() * 8 - 1, 47);
+                let random = rng.borrow_mut().next_u64() >> (64 - bits);
+                let base = (random & !(align - 1)) + max(align, 0x1000);
+                self.base_addresses.borrow_mut().insert(ptr.alloc_id, base);
+                base
+            }
+        };
+        Some(base.wrapping_add(ptr.offset))
+    }
+
+    /// `val`, with a pointer replaced by its address if allocations have base addresses.
+    pub fn with_address(&self, val: PrimVal) -> PrimVal {
+        match val {
+            PrimVal::Ptr(ptr) => {
+                self.address(ptr).map_or(val, |address| PrimVal::Bytes(address as u128))
+            }
+            _ => val,
+        }
+    }
+
+    /// The size of the buckets accesses are counted in, or 0.
+    pub fn access_bucket_size(&self) -> u64 {
+        self.access_bucket_size
+    }
+
+    fn count_access(&self, ptr: MemoryPointer, size: u64, write: bool) {
+        let counts = match self.access_counts {
+            Some(ref counts) => counts,
+            None => return,
+        };
+        let mut counts = counts.borrow_mut();
+        let entry = counts.entry(ptr.alloc_id).or_insert_with(|| {
+            let alloc = self.get(ptr.alloc_id).ok();
+            AccessCounts {
+                size: alloc.map_or(0, |alloc| alloc.bytes.len() as u64),
+                kind: alloc.map_or(String::new(), |alloc| format!("{:?}", alloc.kind)),
+                ..AccessCounts::default()
+            }
+        });
+        if write {
+            entry.writes += 1;
+        } else {
+            entry.reads += 1;
+        }
+        let bucket_size = self.access_bucket_size;
+        if bucket_size != 0 {
+            let first = ptr.offset / bucket_size;
+            let last = (ptr.offset + size - 1) / bucket_size;
+            for bucket in first..last + 1 {
+                let bucket = entry.buckets.entry(bucket * bucket_size).or_insert((0, 0));
+                if write {
+                    bucket.1 += 1;
+                } else {
+                    bucket.0 += 1;
+                }
+            }
         }
     }
 
@@ -312,6 +604,28 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
         })
     }
 
+    /// The allocations that have not been deallocated yet, ordered by their creation.
+    pub fn live_allocations<'x>(&'x self) -> Vec<AllocationInfo<'x, M::MemoryKinds>> {
+        let mut live: Vec<_> = self.allocations()
+            .map(|(id, alloc)| AllocationInfo {
+                id,
+                size: alloc.bytes.len() as u64,
+                align: alloc.align,
+                kind: alloc.kind,
+                label: alloc.label.as_ref().map(String::as_str),
+                created_at: alloc.created_at,
+            })
+            .collect();
+        live.sort_by_key(|info| info.id);
+        live
+    }
+
+    /// Describes what the allocation `id` holds, for `live_allocations` and error messages.
+    pub fn set_label(&mut self, id: AllocId, label: String) -> EvalResult<'tcx> {
+        self.get_mut_unchecked(id)?.label = Some(label);
+        Ok(())
+    }
+
     pub fn create_fn_alloc(&mut self, instance: Instance<'tcx>) -> MemoryPointer {
         if let Some(&alloc_id) = self.function_alloc_cache.get(&instance) {
             return MemoryPointer::new(alloc_id, 0);
@@ -363,18 +677,40 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
             });
         }
         self.memory_usage += size;
+        if let MemoryKind::Machine(_) = kind {
+            self.machine_memory_usage += size;
+            self.peak_machine_memory_usage =
+                max(self.peak_machine_memory_usage, self.machine_memory_usage);
+        }
         assert_eq!(size as usize as u64, size);
+        let mut bytes = vec![0; size as usize];
+        if let Some(ref rng) = self.undef_garbage {
+            rng.borrow_mut().fill_bytes(&mut bytes);
+        }
         let alloc = Allocation {
-            bytes: vec![0; size as usize],
+            bytes,
             relocations: BTreeMap::new(),
             undef_mask: UndefMask::new(size),
             align,
             kind,
             mutable: Mutability::Mutable,
             locks: RangeMap::new(),
+            unsafe_cells: Vec::new(),
+            label: None,
+            created_at: self.cur_span,
+            undef_origins: Vec::new(),
+            undef_escapes: Vec::new(),
         };
         let id = self.next_alloc_id;
         self.next_alloc_id += 1;
+        if self.has_event_stream() {
+            self.emit_event(Event::Allocate {
+                id,
+                size,
+                align,
+                kind: &format!("{:?}", alloc.kind),
+            });
+        }
         self.alloc_map.insert(id, alloc);
         Ok(MemoryPointer::new(
             AllocIdKind::Runtime(id).into_alloc_id(),
@@ -403,6 +739,16 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
                     format!("{:?}", kind),
                 ));
             }
+            // Check this before copying, or a too large `old_size` looks like an out-of-bounds
+            // access.
+            if old_size != alloc.bytes.len() as u64 || old_align != alloc.align {
+                return err!(IncorrectAllocationInformation {
+                    size: old_size,
+                    align: old_align,
+                    allocation_size: alloc.bytes.len() as u64,
+                    allocation_align: alloc.align,
+                });
+            }
         }
 
         // For simplicities' sake, we implement reallocate as "alloc, copy, dealloc"
@@ -442,8 +788,12 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
 
         let alloc = match self.alloc_map.remove(&alloc_id) {
             Some(alloc) => alloc,
-            None => return err!(DoubleFree),
+            None => {
+                let freed_at = self.freed.get(&ptr.alloc_id).map_or(DUMMY_SP, |&(_, freed)| freed);
+                return err!(DoubleFree { freed_at });
+            }
         };
+        self.base_addresses.get_mut().remove(&ptr.alloc_id);
 
         // It is okay for us to still holds locks on deallocation -- for example, we could store data we own
         // in a local, and the local could be deallocated (from StorageDead) before the function returns.
@@ -463,6 +813,19 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
                     lock: lock.active,
                 }
             })?;
+        // Even the frame holding a protector may not deallocate the memory, e.g. through a raw
+        // pointer stashed away before the reference was passed in. Locals are exempt, they are
+        // deallocated when the frame holding the locks on them is popped.
+        let protector = match alloc.kind {
+            MemoryKind::Stack => None,
+            _ => alloc.protector(self.cur_frame),
+        };
+        if let Some(lock) = protector {
+            return err!(DeallocatedLockedMemory {
+                ptr,
+                lock: lock.active.clone(),
+            });
+        }
 
         if alloc.kind != kind {
             return err!(DeallocatedWrongMemoryKind(
@@ -472,16 +835,39 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
         }
         if let Some((size, align)) = size_and_align {
             if size != alloc.bytes.len() as u64 || align != alloc.align {
-                return err!(IncorrectAllocationInformation);
+                return err!(IncorrectAllocationInformation {
+                    size,
+                    align,
+                    allocation_size: alloc.bytes.len() as u64,
+                    allocation_align: alloc.align,
+                });
             }
         }
+        if kind != MemoryKind::Stack {
+            self.freed.insert(ptr.alloc_id, (alloc.created_at, self.cur_span));
+        }
+        self.mut_borrows.deallocated(ptr.alloc_id);
 
         self.memory_usage -= alloc.bytes.len() as u64;
+        if let MemoryKind::Machine(_) = kind {
+            self.machine_memory_usage -= alloc.bytes.len() as u64;
+        }
         debug!("deallocated : {}", ptr.alloc_id);
+        self.emit_event(Event::Deallocate { id: alloc_id });
 
         Ok(())
     }
 
+    /// The number of bytes in live allocations of a machine-specific kind.
+    pub fn machine_memory_usage(&self) -> u64 {
+        self.machine_memory_usage
+    }
+
+    /// The largest `machine_memory_usage` seen during the evaluation so far.
+    pub fn peak_machine_memory_usage(&self) -> u64 {
+        self.peak_machine_memory_usage
+    }
+
     pub fn pointer_size(&self) -> u64 {
         self.layout.pointer_size.bytes()
     }
@@ -545,6 +931,22 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
         }
         Ok(())
     }
+
+    /// Check that the `len` bytes starting at `ptr` are inside its allocation. A pointer made by
+    /// `wrapping_offset` can be so far out of bounds that the end of the range wraps around, which
+    /// is an out-of-bounds access as well, not overflowing pointer arithmetic.
+    pub fn check_range_bounds(&self, ptr: MemoryPointer, len: u64) -> EvalResult<'tcx> {
+        let (end, overflowed) = ptr.overflowing_offset(len, self.layout);
+        if overflowed {
+            return err!(PointerOutOfBounds {
+                ptr,
+                access: true,
+                allocation_size: self.get(ptr.alloc_id)?.bytes.len() as u64,
+            });
+        }
+        // If the end is in bounds, the start is as well.
+        self.check_bounds(end, true)
+    }
 }
 
 /// Locking
@@ -591,7 +993,7 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
             len,
             region
         );
-        self.check_bounds(ptr.offset(len, self.layout)?, true)?; // if ptr.offset is in bounds, then so is ptr (because offset checks for overflow)
+        self.check_range_bounds(ptr, len)?;
         let alloc = self.get_mut_unchecked(ptr.alloc_id)?;
 
         // Iterate over our range and acquire the lock.  If the range is already split into pieces,
@@ -817,13 +1219,65 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
 
 /// Allocation accessors
 impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
+    /// The error for accessing the deallocated allocation `id`.
+    fn dangling<T>(&self, id: AllocId) -> EvalResult<'tcx, T> {
+        match self.dead_frame_locals.get(&id) {
+            Some(function) => err!(DanglingLocalDeref(function.to_string())),
+            None => err!(DanglingPointerDeref(id)),
+        }
+    }
+
+    /// Where the allocation `id` was created and, if it has been deallocated, where that
+    /// happened. Nothing is known about deallocated locals and functions.
+    pub fn allocation_history(&self, id: AllocId) -> Option<(Span, Option<Span>)> {
+        if let Some(&(created_at, freed_at)) = self.freed.get(&id) {
+            return Some((created_at, Some(freed_at)));
+        }
+        match id.into_alloc_id_kind() {
+            AllocIdKind::Runtime(index) => {
+                self.alloc_map.get(&index).map(|alloc| (alloc.created_at, None))
+            }
+            AllocIdKind::Function(_) => None,
+        }
+    }
+
+    /// Notes on where the allocation an error is about was created and deallocated, for the
+    /// errors about accesses to a single allocation.
+    pub fn allocation_notes(&self, kind: &EvalErrorKind<'tcx>) -> Vec<(Span, &'static str)> {
+        let id = match *kind {
+            EvalErrorKind::PointerOutOfBounds { ptr, .. } |
+            EvalErrorKind::ReferenceOutOfBounds { ptr, .. } => ptr.alloc_id,
+            EvalErrorKind::DanglingPointerDeref(id) => id,
+            _ => return Vec::new(),
+        };
+        let mut notes = Vec::new();
+        if let Some((created_at, freed_at)) = self.allocation_history(id) {
+            if created_at != DUMMY_SP {
+                notes.push((created_at, "the allocation was created here"));
+            }
+            match freed_at {
+                Some(freed_at) if freed_at != DUMMY_SP => {
+                    notes.push((freed_at, "the allocation was freed here"));
+                }
+                _ => {}
+            }
+        }
+        notes
+    }
+
+    /// Remembers that the deallocated allocation `id` was a local of `function`, whose frame has
+    /// been popped.
+    pub fn mark_dead_frame_local(&mut self, id: AllocId, function: Instance<'tcx>) {
+        self.dead_frame_locals.insert(id, function);
+    }
+
     pub fn get(&self, id: AllocId) -> EvalResult<'tcx, &Allocation<'tcx, M::MemoryKinds>> {
         match id.into_alloc_id_kind() {
             AllocIdKind::Function(_) => err!(DerefFunctionPointer),
             AllocIdKind::Runtime(id) => {
                 match self.alloc_map.get(&id) {
                     Some(alloc) => Ok(alloc),
-                    None => err!(DanglingPointerDeref),
+                    None => self.dangling(AllocIdKind::Runtime(id).into_alloc_id()),
                 }
             }
         }
@@ -836,17 +1290,32 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
         match id.into_alloc_id_kind() {
             AllocIdKind::Function(_) => err!(DerefFunctionPointer),
             AllocIdKind::Runtime(id) => {
-                match self.alloc_map.get_mut(&id) {
-                    Some(alloc) => Ok(alloc),
-                    None => err!(DanglingPointerDeref),
+                if !self.alloc_map.contains_key(&id) {
+                    return self.dangling(AllocIdKind::Runtime(id).into_alloc_id());
                 }
+                Ok(self.alloc_map.get_mut(&id).expect("checked above"))
             }
         }
     }
 
+    /// Makes the allocation `id` read-only or writable again, e.g. when a program changes the
+    /// protection of a mapping.
+    pub fn set_mutability(&mut self, id: AllocId, mutability: Mutability) -> EvalResult<'tcx> {
+        self.get_mut_unchecked(id)?.mutable = mutability;
+        Ok(())
+    }
+
+    /// Allows writing to the given `UnsafeCell` ranges of `id` once it is made immutable.
+    pub fn set_unsafe_cells(&mut self, id: AllocId, cells: Vec<(u64, u64)>) -> EvalResult<'tcx> {
+        self.get_mut_unchecked(id)?.unsafe_cells = cells;
+        Ok(())
+    }
+
+    /// Fails if the allocation may not be modified at all. The exact range is checked when the
+    /// bytes are written.
     fn get_mut(&mut self, id: AllocId) -> EvalResult<'tcx, &mut Allocation<'tcx, M::MemoryKinds>> {
         let alloc = self.get_mut_unchecked(id)?;
-        if alloc.mutable == Mutability::Mutable {
+        if alloc.mutable == Mutability::Mutable || !alloc.unsafe_cells.is_empty() {
             Ok(alloc)
         } else {
             err!(ModifiedConstantMemory)
@@ -977,9 +1446,10 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
         if size == 0 {
             return Ok(&[]);
         }
+        self.check_range_bounds(ptr, size)?;
         self.check_locks(ptr, size, AccessKind::Read)?;
-        self.check_bounds(ptr.offset(size, self)?, true)?; // if ptr.offset is in bounds, then so is ptr (because offset checks for overflow)
         let alloc = self.get(ptr.alloc_id)?;
+        self.count_access(ptr, size, false);
         assert_eq!(ptr.offset as usize as u64, ptr.offset);
         assert_eq!(size as usize as u64, size);
         let offset = ptr.offset as usize;
@@ -997,8 +1467,19 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
         if size == 0 {
             return Ok(&mut []);
         }
+        self.check_range_bounds(ptr, size)?;
         self.check_locks(ptr, size, AccessKind::Write)?;
-        self.check_bounds(ptr.offset(size, self.layout)?, true)?; // if ptr.offset is in bounds, then so is ptr (because offset checks for overflow)
+        {
+            let alloc = self.get_mut(ptr.alloc_id)?;
+            if alloc.mutable == Mutability::Immutable {
+                let (start, end) = (ptr.offset, ptr.offset + size);
+                if !alloc.unsafe_cells.iter().any(|&(s, e)| s <= start && end <= e) {
+                    return err!(ModifiedConstantMemory);
+                }
+            }
+        }
+        // Only writes that happen are counted.
+        self.count_access(ptr, size, true);
         let alloc = self.get_mut(ptr.alloc_id)?;
         assert_eq!(ptr.offset as usize as u64, ptr.offset);
         assert_eq!(size as usize as u64, size);
@@ -1065,6 +1546,7 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
         };
         let relocations = match self.alloc_map.get_mut(&alloc_id) {
             Some(&mut Allocation {
+                     ref bytes,
                      ref mut relocations,
                      ref mut kind,
                      ref mut mutable,
@@ -1076,7 +1558,11 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
                     MemoryKind::Stack |
                     // The entire point of this function
                     MemoryKind::UninitializedStatic => {},
-                    MemoryKind::Machine(m) => M::mark_static_initialized(m)?,
+                    MemoryKind::Machine(m) => {
+                        M::mark_static_initialized(m)?;
+                        // The allocation is part of the static now, not of the heap.
+                        self.machine_memory_usage -= bytes.len() as u64;
+                    }
                     MemoryKind::Static => {
                         trace!("mark_static_initalized: skipping already initialized static referred to by static currently being initialized");
                         return Ok(());
@@ -1088,7 +1574,10 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
                 // mark recursively
                 mem::replace(relocations, Default::default())
             }
-            None => return err!(DanglingPointerDeref),
+            None => {
+                let id = AllocIdKind::Runtime(alloc_id).into_alloc_id();
+                return err!(DanglingPointerDeref(id));
+            }
         };
         // recurse into inner allocations
         for &alloc in relocations.values() {
@@ -1155,7 +1644,13 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
             }
         }
 
-        self.copy_undef_mask(src, dest, size)?;
+        // Copied undefined bytes keep their origin.
+        if let Some((origin, escape)) = self.copy_undef_mask(src, dest, size)? {
+            self.set_undef_origin(PrimVal::Ptr(dest).into(), size, origin)?;
+            if let Some(escape) = escape {
+                self.set_undef_escape(dest, size, escape)?;
+            }
+        }
         // copy back the relocations
         self.get_mut(dest.alloc_id)?.relocations.extend(relocations);
 
@@ -1243,6 +1738,26 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
         Ok(PrimVal::Bytes(bytes))
     }
 
+    /// Reads an integer like `read_primval`, but takes the bytes as they are where they are
+    /// undefined, i.e. the garbage `fill_undef_with_garbage` put there.
+    pub fn read_garbage(
+        &self,
+        ptr: MemoryPointer,
+        size: u64,
+        signed: bool,
+    ) -> EvalResult<'tcx, u128> {
+        if self.relocations(ptr, size)?.count() != 0 {
+            return err!(ReadPointerAsBytes);
+        }
+        let endianess = self.endianess();
+        let bytes = self.get_bytes_unchecked(ptr, size, self.int_align(size))?;
+        Ok(if signed {
+            read_target_int(endianess, bytes).unwrap() as u128
+        } else {
+            read_target_uint(endianess, bytes).unwrap()
+        })
+    }
+
     pub fn read_ptr_sized_unsigned(&self, ptr: MemoryPointer) -> EvalResult<'tcx, PrimVal> {
         self.read_primval(ptr, self.pointer_size(), false)
     }
@@ -1376,18 +1891,28 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
 /// Undefined bytes
 impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
     // FIXME(solson): This is a very naive, slow version.
+    /// Returns where the first undefined byte of the copied ones became undefined and where it
+    /// left its union, if there is such a byte.
     fn copy_undef_mask(
         &mut self,
         src: MemoryPointer,
         dest: MemoryPointer,
         size: u64,
-    ) -> EvalResult<'tcx> {
+    ) -> EvalResult<'tcx, Option<(Span, Option<Span>)>> {
         // The bits have to be saved locally before writing to dest in case src and dest overlap.
         assert_eq!(size as usize as u64, size);
         let mut v = Vec::with_capacity(size as usize);
-        for i in 0..size {
-            let defined = self.get(src.alloc_id)?.undef_mask.get(src.offset + i);
-            v.push(defined);
+        let mut origin = None;
+        {
+            let src_alloc = self.get(src.alloc_id)?;
+            for i in 0..size {
+                let defined = src_alloc.undef_mask.get(src.offset + i);
+                if !defined && origin.is_none() {
+                    let offset = src.offset + i;
+                    origin = Some((src_alloc.undef_origin(offset), src_alloc.undef_escape(offset)));
+                }
+                v.push(defined);
+            }
         }
         for (i, defined) in v.into_iter().enumerate() {
             self.get_mut(dest.alloc_id)?.undef_mask.set(
@@ -1396,21 +1921,71 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
                 defined,
             );
         }
-        Ok(())
+        Ok(origin)
     }
 
     fn check_defined(&self, ptr: MemoryPointer, size: u64) -> EvalResult<'tcx> {
         let alloc = self.get(ptr.alloc_id)?;
-        if !alloc.undef_mask.is_range_defined(
-            ptr.offset,
-            ptr.offset + size,
-        )
-        {
+        if let Some((origin, escape)) = alloc.first_undef_origin(ptr.offset, size) {
+            self.last_undef_origin.set(Some(origin));
+            self.last_undef_escape.set(escape);
             return err!(ReadUndefBytes);
         }
         Ok(())
     }
 
+    /// Where the undefined bytes that were read last by the current statement became undefined,
+    /// if it read any. See `last_undef_origin`.
+    pub fn last_undef_origin(&self) -> Option<Span> {
+        self.last_undef_origin.get()
+    }
+
+    /// Where the undefined bytes of `last_undef_origin` were read out of a union, if they were.
+    pub fn last_undef_escape(&self) -> Option<Span> {
+        self.last_undef_escape.get()
+    }
+
+    /// Starts the evaluation of the statement or terminator at `span`.
+    pub(super) fn enter_statement(&mut self, span: Span) {
+        self.cur_span = span;
+        self.last_undef_origin.set(None);
+        self.last_undef_escape.set(None);
+    }
+
+    /// Records `origin` as the place where the `size` bytes at `ptr` became undefined.
+    pub fn set_undef_origin(&mut self, ptr: Pointer, size: u64, origin: Span) -> EvalResult<'tcx> {
+        if size == 0 {
+            return Ok(());
+        }
+        let ptr = ptr.to_ptr()?;
+        let (start, end) = (ptr.offset, ptr.offset + size);
+        let alloc = self.get_mut(ptr.alloc_id)?;
+        // Ranges that are covered completely do not matter anymore.
+        alloc.undef_origins.retain(|&(s, e, _)| s < start || e > end);
+        alloc.undef_origins.push((start, end, origin));
+        // Bytes that became undefined anew are not in a union left anymore.
+        alloc.undef_escapes.retain(|&(s, e, _)| s < start || e > end);
+        Ok(())
+    }
+
+    /// Records that the undefined bytes among the `size` bytes at `ptr` were read out of a union
+    /// at `escape`.
+    pub fn set_undef_escape(
+        &mut self,
+        ptr: MemoryPointer,
+        size: u64,
+        escape: Span,
+    ) -> EvalResult<'tcx> {
+        let (start, end) = (ptr.offset, ptr.offset + size);
+        let alloc = self.get_mut(ptr.alloc_id)?;
+        if size == 0 || alloc.undef_mask.is_range_defined(start, end) {
+            return Ok(());
+        }
+        alloc.undef_escapes.retain(|&(s, e, _)| s < start || e > end);
+        alloc.undef_escapes.push((start, end, escape));
+        Ok(())
+    }
+
     pub fn mark_definedness(
         &mut self,
         ptr: Pointer,
@@ -1421,12 +1996,30 @@ impl<'a, 'tcx, M: Machine<'tcx>> Memory<'a, 'tcx, M> {
             return Ok(());
         }
         let ptr = ptr.to_ptr()?;
-        let alloc = self.get_mut(ptr.alloc_id)?;
-        alloc.undef_mask.set_range(
-            ptr.offset,
-            ptr.offset + size,
-            new_state,
-        );
+        let garbage = match self.undef_garbage {
+            Some(ref rng) if !new_state => {
+                let mut garbage = vec![0; size as usize];
+                rng.borrow_mut().fill_bytes(&mut garbage);
+                Some(garbage)
+            }
+            _ => None,
+        };
+        {
+            let alloc = self.get_mut(ptr.alloc_id)?;
+            alloc.undef_mask.set_range(
+                ptr.offset,
+                ptr.offset + size,
+                new_state,
+            );
+            if let Some(garbage) = garbage {
+                let offset = ptr.offset as usize;
+                alloc.bytes[offset..offset + garbage.len()].copy_from_slice(&garbage);
+            }
+        }
+        if !new_state {
+            let origin = self.cur_span;
+            self.set_undef_origin(ptr.into(), size, origin)?;
+        }
         Ok(())
     }
 }
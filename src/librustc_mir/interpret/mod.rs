@@ -5,34 +5,49 @@ macro_rules! err {
     ($($tt:tt)*) => { Err($crate::interpret::EvalErrorKind::$($tt)*.into()) };
 }
 
+mod aliasing;
 mod cast;
 mod const_eval;
 mod error;
 mod eval_context;
+mod events;
+mod json;
 mod lvalue;
 mod validation;
 mod machine;
 mod memory;
 mod operator;
+mod random;
 mod range_map;
+mod reachable;
+mod render;
 mod step;
 mod terminator;
 mod traits;
 mod value;
 
-pub use self::error::{EvalError, EvalResult, EvalErrorKind};
+pub use self::error::{EvalError, EvalResult, EvalErrorKind, FrameInfo, Warning};
 
 pub use self::eval_context::{EvalContext, Frame, ResourceLimits, StackPopCleanup, DynamicLifetime,
                              TyAndPacked, PtrAndAlign, ValTy};
 
+pub use self::events::{Event, EventStream};
+
+pub use self::json::Json;
+
 pub use self::lvalue::{Lvalue, LvalueExtra, GlobalId};
 
-pub use self::memory::{AllocId, Memory, MemoryPointer, MemoryKind, HasMemory, AccessKind, AllocIdKind};
+pub use self::memory::{AllocId, Memory, MemoryPointer, MemoryKind, HasMemory, AccessKind, AllocIdKind,
+                       AccessCounts, AllocationInfo};
 
 use self::memory::{PointerArithmetic, Lock};
 
 use self::range_map::RangeMap;
 
+pub use self::random::Rng;
+
+pub use self::reachable::Reachable;
+
 pub use self::value::{PrimVal, PrimValKind, Value, Pointer};
 
 pub use self::const_eval::{eval_body_as_integer, eval_body_as_primval};
@@ -1,10 +1,10 @@
 use rustc::mir;
-use rustc::ty::Ty;
+use rustc::ty::{self, Ty};
 use rustc_const_math::ConstFloat;
 use syntax::ast::FloatTy;
 use std::cmp::Ordering;
 
-use super::{EvalResult, EvalContext, Lvalue, Machine, ValTy};
+use super::{EvalResult, EvalContext, Lvalue, Machine, ValTy, PointerArithmetic};
 
 use super::value::{PrimVal, PrimValKind, Value, bytes_to_f32, bytes_to_f64, f32_to_bytes,
                    f64_to_bytes};
@@ -130,97 +130,175 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
             }
         }
 
-        // II: From now on, everything must be bytes, no pointers
+        // II: From now on, everything must be bytes, no pointers. Integers made from pointers are
+        // their addresses, if allocations have them.
+        let (left, right) = if left_kind.is_int() && right_kind.is_int() {
+            (self.memory.with_address(left), self.memory.with_address(right))
+        } else {
+            (left, right)
+        };
         let l = left.to_bytes()?;
         let r = right.to_bytes()?;
 
-        // These ops can have an RHS with a different numeric type.
-        if right_kind.is_int() && (bin_op == Shl || bin_op == Shr) {
-            return match bin_op {
-                Shl => int_shift!(left_kind, overflowing_shl, l, r),
-                Shr => int_shift!(left_kind, overflowing_shr, l, r),
-                _ => bug!("it has already been checked that this is a shift op"),
+        // Offsetting an integer pointer (e.g. one obtained from a cast) by a `usize` or `isize`.
+        if bin_op == Offset && left_kind == Ptr && right_kind.is_int() {
+            let pointee_ty = left_ty
+                .builtin_deref(true, ty::LvaluePreference::NoPreference)
+                .expect("Offset called on non-ptr type")
+                .ty;
+            let pointee_size = self.type_size(pointee_ty)?.expect(
+                "cannot offset a pointer to an unsized type",
+            ) as i128;
+            let offset = if right_kind.is_signed_int() {
+                right_kind.sign_extend(r)
+            } else {
+                r as i128
             };
-        }
-
-        if left_kind != right_kind {
-            let msg = format!(
-                "unimplemented binary op {:?}: {:?} ({:?}), {:?} ({:?})",
-                bin_op,
-                left,
-                left_kind,
-                right,
-                right_kind
+            let (res, over) = self.memory.overflowing_signed_offset(
+                l as u64,
+                offset.wrapping_mul(pointee_size),
             );
-            return err!(Unimplemented(msg));
+            return Ok((PrimVal::Bytes(res as u128), over));
         }
 
-        let float_op = |op, l, r, ty| {
-            let l = ConstFloat {
-                bits: l,
-                ty,
-            };
-            let r = ConstFloat {
-                bits: r,
-                ty,
-            };
-            match op {
-                Eq => PrimVal::from_bool(l.try_cmp(r).unwrap() == Ordering::Equal),
-                Ne => PrimVal::from_bool(l.try_cmp(r).unwrap() != Ordering::Equal),
-                Lt => PrimVal::from_bool(l.try_cmp(r).unwrap() == Ordering::Less),
-                Le => PrimVal::from_bool(l.try_cmp(r).unwrap() != Ordering::Greater),
-                Gt => PrimVal::from_bool(l.try_cmp(r).unwrap() == Ordering::Greater),
-                Ge => PrimVal::from_bool(l.try_cmp(r).unwrap() != Ordering::Less),
-                Add => PrimVal::Bytes((l + r).unwrap().bits),
-                Sub => PrimVal::Bytes((l - r).unwrap().bits),
-                Mul => PrimVal::Bytes((l * r).unwrap().bits),
-                Div => PrimVal::Bytes((l / r).unwrap().bits),
-                Rem => PrimVal::Bytes((l % r).unwrap().bits),
-                _ => bug!("invalid float op: `{:?}`", op),
+        match primval_binary_op(bin_op, left, left_kind, right, right_kind)? {
+            Some(result) => Ok(result),
+            None => {
+                let msg = format!(
+                    "unimplemented binary op {:?}: {:?} (of type `{}`), {:?} (of type `{}`)",
+                    bin_op,
+                    left,
+                    left_ty,
+                    right,
+                    right_ty
+                );
+                err!(Unimplemented(msg))
             }
+        }
+    }
+}
+
+/// The part of `EvalContext::binary_op` that only depends on the kinds of the operands, i.e.
+/// everything except pointer arithmetic. Returns `None` if the operation is not defined for
+/// these kinds.
+pub fn primval_binary_op<'tcx>(
+    bin_op: mir::BinOp,
+    left: PrimVal,
+    left_kind: PrimValKind,
+    right: PrimVal,
+    right_kind: PrimValKind,
+) -> EvalResult<'tcx, Option<(PrimVal, bool)>> {
+    use rustc::mir::BinOp::*;
+    use super::PrimValKind::*;
+
+    let l = left.to_bytes()?;
+    let r = right.to_bytes()?;
+
+    // These ops can have an RHS with a different numeric type.
+    if right_kind.is_int() && (bin_op == Shl || bin_op == Shr) {
+        return match bin_op {
+            Shl => int_shift!(left_kind, overflowing_shl, l, r).map(Some),
+            Shr => int_shift!(left_kind, overflowing_shr, l, r).map(Some),
+            _ => bug!("it has already been checked that this is a shift op"),
         };
+    }
 
-        let val = match (bin_op, left_kind) {
-            (_, F32) => float_op(bin_op, l, r, FloatTy::F32),
-            (_, F64) => float_op(bin_op, l, r, FloatTy::F64),
+    if left_kind != right_kind {
+        return Ok(None);
+    }
 
+    let float_op = |op, l, r, ty| {
+        let l = ConstFloat {
+            bits: l,
+            ty,
+        };
+        let r = ConstFloat {
+            bits: r,
+            ty,
+        };
+        // `try_cmp` orders NaN after everything else, but every comparison with NaN except
+        // `!=` is false.
+        let unordered = float_is_nan(l.bits, ty) || float_is_nan(r.bits, ty);
+        let ord = l.try_cmp(r).unwrap();
+        match op {
+            Eq => PrimVal::from_bool(!unordered && ord == Ordering::Equal),
+            Ne => PrimVal::from_bool(unordered || ord != Ordering::Equal),
+            Lt => PrimVal::from_bool(!unordered && ord == Ordering::Less),
+            Le => PrimVal::from_bool(!unordered && ord != Ordering::Greater),
+            Gt => PrimVal::from_bool(!unordered && ord == Ordering::Greater),
+            Ge => PrimVal::from_bool(!unordered && ord != Ordering::Less),
+            Add => PrimVal::Bytes((l + r).unwrap().bits),
+            Sub => PrimVal::Bytes((l - r).unwrap().bits),
+            Mul => PrimVal::Bytes((l * r).unwrap().bits),
+            Div => PrimVal::Bytes((l / r).unwrap().bits),
+            Rem => {
+                // The remainder has the sign of the dividend, even if it is zero (e.g.
+                // `-4.0 % 2.0` is `-0.0`), but apfloat's subtraction loop produces `+0.0`.
+                let bits = (l % r).unwrap().bits;
+                PrimVal::Bytes(float_zero_with_sign_of(bits, l.bits, ty))
+            }
+            _ => bug!("invalid float op: `{:?}`", op),
+        }
+    };
 
-            (Eq, _) => PrimVal::from_bool(l == r),
-            (Ne, _) => PrimVal::from_bool(l != r),
+    let val = match (bin_op, left_kind) {
+        (_, F32) => float_op(bin_op, l, r, FloatTy::F32),
+        (_, F64) => float_op(bin_op, l, r, FloatTy::F64),
 
-            (Lt, k) if k.is_signed_int() => PrimVal::from_bool((l as i128) < (r as i128)),
-            (Lt, _) => PrimVal::from_bool(l < r),
-            (Le, k) if k.is_signed_int() => PrimVal::from_bool((l as i128) <= (r as i128)),
-            (Le, _) => PrimVal::from_bool(l <= r),
-            (Gt, k) if k.is_signed_int() => PrimVal::from_bool((l as i128) > (r as i128)),
-            (Gt, _) => PrimVal::from_bool(l > r),
-            (Ge, k) if k.is_signed_int() => PrimVal::from_bool((l as i128) >= (r as i128)),
-            (Ge, _) => PrimVal::from_bool(l >= r),
 
-            (BitOr, _) => PrimVal::Bytes(l | r),
-            (BitAnd, _) => PrimVal::Bytes(l & r),
-            (BitXor, _) => PrimVal::Bytes(l ^ r),
+        (Eq, _) => PrimVal::from_bool(l == r),
+        (Ne, _) => PrimVal::from_bool(l != r),
 
-            (Add, k) if k.is_int() => return int_arithmetic!(k, overflowing_add, l, r),
-            (Sub, k) if k.is_int() => return int_arithmetic!(k, overflowing_sub, l, r),
-            (Mul, k) if k.is_int() => return int_arithmetic!(k, overflowing_mul, l, r),
-            (Div, k) if k.is_int() => return int_arithmetic!(k, overflowing_div, l, r),
-            (Rem, k) if k.is_int() => return int_arithmetic!(k, overflowing_rem, l, r),
+        (Lt, k) if k.is_signed_int() => {
+            PrimVal::from_bool(k.sign_extend(l) < k.sign_extend(r))
+        }
+        (Lt, _) => PrimVal::from_bool(l < r),
+        (Le, k) if k.is_signed_int() => {
+            PrimVal::from_bool(k.sign_extend(l) <= k.sign_extend(r))
+        }
+        (Le, _) => PrimVal::from_bool(l <= r),
+        (Gt, k) if k.is_signed_int() => {
+            PrimVal::from_bool(k.sign_extend(l) > k.sign_extend(r))
+        }
+        (Gt, _) => PrimVal::from_bool(l > r),
+        (Ge, k) if k.is_signed_int() => {
+            PrimVal::from_bool(k.sign_extend(l) >= k.sign_extend(r))
+        }
+        (Ge, _) => PrimVal::from_bool(l >= r),
 
-            _ => {
-                let msg = format!(
-                    "unimplemented binary op {:?}: {:?} ({:?}), {:?} ({:?})",
-                    bin_op,
-                    left,
-                    left_kind,
-                    right,
-                    right_kind
-                );
-                return err!(Unimplemented(msg));
-            }
-        };
+        (BitOr, _) => PrimVal::Bytes(l | r),
+        (BitAnd, _) => PrimVal::Bytes(l & r),
+        (BitXor, _) => PrimVal::Bytes(l ^ r),
+
+        (Add, k) if k.is_int() => return int_arithmetic!(k, overflowing_add, l, r).map(Some),
+        (Sub, k) if k.is_int() => return int_arithmetic!(k, overflowing_sub, l, r).map(Some),
+        (Mul, k) if k.is_int() => return int_arithmetic!(k, overflowing_mul, l, r).map(Some),
+        (Div, k) if k.is_int() => return int_arithmetic!(k, overflowing_div, l, r).map(Some),
+        (Rem, k) if k.is_int() => return int_arithmetic!(k, overflowing_rem, l, r).map(Some),
+
+        _ => return Ok(None),
+    };
+
+    Ok(Some((val, false)))
+}
+
+fn float_is_nan(bits: u128, ty: FloatTy) -> bool {
+    match ty {
+        FloatTy::F32 => bytes_to_f32(bits).is_nan(),
+        FloatTy::F64 => bytes_to_f64(bits).is_nan(),
+    }
+}
 
-        Ok((val, false))
+/// If `bits` is a zero, gives it the sign of `sign_of`.
+fn float_zero_with_sign_of(bits: u128, sign_of: u128, ty: FloatTy) -> u128 {
+    let sign_bit = match ty {
+        FloatTy::F32 => 1 << 31,
+        FloatTy::F64 => 1 << 63,
+    };
+    if bits & !sign_bit == 0 {
+        (sign_of & sign_bit) | (bits & !sign_bit)
+    } else {
+        bits
     }
 }
 
@@ -237,23 +315,10 @@ pub fn unary_op<'tcx>(
     let result_bytes = match (un_op, val_kind) {
         (Not, Bool) => !val.to_bool()? as u128,
 
-        (Not, U8) => !(bytes as u8) as u128,
-        (Not, U16) => !(bytes as u16) as u128,
-        (Not, U32) => !(bytes as u32) as u128,
-        (Not, U64) => !(bytes as u64) as u128,
-        (Not, U128) => !bytes,
-
-        (Not, I8) => !(bytes as i8) as u128,
-        (Not, I16) => !(bytes as i16) as u128,
-        (Not, I32) => !(bytes as i32) as u128,
-        (Not, I64) => !(bytes as i64) as u128,
-        (Not, I128) => !(bytes as i128) as u128,
-
-        (Neg, I8) => -(bytes as i8) as u128,
-        (Neg, I16) => -(bytes as i16) as u128,
-        (Neg, I32) => -(bytes as i32) as u128,
-        (Neg, I64) => -(bytes as i64) as u128,
-        (Neg, I128) => -(bytes as i128) as u128,
+        (Not, k) if k.is_int() => k.normalize(!bytes),
+        (Neg, k) if k.is_signed_int() => {
+            k.normalize(k.sign_extend(bytes).wrapping_neg() as u128)
+        }
 
         (Neg, F32) => f32_to_bytes(-bytes_to_f32(bytes)),
         (Neg, F64) => f64_to_bytes(-bytes_to_f64(bytes)),
@@ -266,3 +331,244 @@ pub fn unary_op<'tcx>(
 
     Ok(PrimVal::Bytes(result_bytes))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{f32, f64};
+
+    use super::*;
+    use super::super::PrimValKind::*;
+    use rustc::mir::BinOp::*;
+    use rustc::mir::UnOp::*;
+
+    fn eval(op: mir::UnOp, n: i128, kind: PrimValKind) -> i128 {
+        let val = PrimVal::from_i128_of_kind(n, kind);
+        unary_op(op, val, kind).unwrap().to_i128_of_kind(kind).unwrap()
+    }
+
+    #[test]
+    fn unary_ops_match_native_arithmetic() {
+        for &n in &[0, 1, -1, 127, -128, 255, 32767, -32768, i64::min_value() as i128] {
+            assert_eq!(eval(Not, n, I8), !(n as i8) as i128);
+            assert_eq!(eval(Not, n, U8), !(n as u8) as i128);
+            assert_eq!(eval(Not, n, I16), !(n as i16) as i128);
+            assert_eq!(eval(Not, n, U32), !(n as u32) as i128);
+            assert_eq!(eval(Not, n, I64), !(n as i64) as i128);
+            assert_eq!(eval(Not, n, U64), !(n as u64) as i128);
+            assert_eq!(eval(Neg, n, I8), (n as i8).wrapping_neg() as i128);
+            assert_eq!(eval(Neg, n, I16), (n as i16).wrapping_neg() as i128);
+            assert_eq!(eval(Neg, n, I32), (n as i32).wrapping_neg() as i128);
+            assert_eq!(eval(Neg, n, I64), (n as i64).wrapping_neg() as i128);
+            assert_eq!(eval(Neg, n, I128), n.wrapping_neg());
+        }
+    }
+
+    /// Checks an integer operation against the result and overflow flag of the native one.
+    fn check_int(
+        op: mir::BinOp,
+        left: PrimVal,
+        kind: PrimValKind,
+        right: PrimVal,
+        right_kind: PrimValKind,
+        expected: (PrimVal, bool),
+    ) {
+        let (val, overflowed) = primval_binary_op(op, left, kind, right, right_kind)
+            .unwrap()
+            .expect("operation is not implemented");
+        // Signed results are not always normalized, just like loaded ones.
+        assert_eq!(
+            (kind.normalize(val.to_bytes().unwrap()), overflowed),
+            (kind.normalize(expected.0.to_bytes().unwrap()), expected.1),
+            "{:?} {:?} {:?} of kind {:?}",
+            left,
+            op,
+            right,
+            kind
+        );
+    }
+
+    /// Checks a float operation against the native one. All NaNs are considered equal.
+    fn check_float(
+        op: mir::BinOp,
+        left: PrimVal,
+        kind: PrimValKind,
+        right: PrimVal,
+        expected: PrimVal,
+    ) {
+        let (val, overflowed) = primval_binary_op(op, left, kind, right, kind)
+            .unwrap()
+            .expect("operation is not implemented");
+        let is_nan = |val: PrimVal| match kind {
+            F32 => val.to_f32().unwrap().is_nan(),
+            _ => val.to_f64().unwrap().is_nan(),
+        };
+        assert!(!overflowed, "{:?} {:?} {:?} overflowed", left, op, right);
+        if !is_nan(val) || !is_nan(expected) {
+            assert_eq!(
+                val.to_bytes().unwrap(),
+                expected.to_bytes().unwrap(),
+                "{:?} {:?} {:?} of kind {:?}",
+                left,
+                op,
+                right,
+                kind
+            );
+        }
+    }
+
+    /// Runs every operator over all pairs of a set of values close to the boundaries of the type.
+    macro_rules! int_op_tests {
+        ($($name:ident: $ty:ident, $kind:ident;)*) => {$(
+            #[test]
+            fn $name() {
+                let kind = $kind;
+                let samples: &[$ty] = &[
+                    0,
+                    1,
+                    2,
+                    7,
+                    !0,
+                    !1,
+                    $ty::max_value(),
+                    $ty::min_value(),
+                    $ty::max_value() / 3,
+                    $ty::min_value() / 3,
+                ];
+                let val = |n: $ty| PrimVal::from_i128_of_kind(n as i128, kind);
+                let int = |(n, overflowed): ($ty, bool)| (val(n), overflowed);
+                let cmp = |b: bool| (PrimVal::from_bool(b), false);
+                for &l in samples {
+                    for &r in samples {
+                        let check = |op, expected| {
+                            check_int(op, val(l), kind, val(r), kind, expected)
+                        };
+                        check(Add, int(l.overflowing_add(r)));
+                        check(Sub, int(l.overflowing_sub(r)));
+                        check(Mul, int(l.overflowing_mul(r)));
+                        // Division by zero is caught by an assertion in the MIR.
+                        if r != 0 {
+                            check(Div, int(l.overflowing_div(r)));
+                            check(Rem, int(l.overflowing_rem(r)));
+                        }
+                        check(BitXor, (val(l ^ r), false));
+                        check(BitAnd, (val(l & r), false));
+                        check(BitOr, (val(l | r), false));
+                        check(Eq, cmp(l == r));
+                        check(Ne, cmp(l != r));
+                        check(Lt, cmp(l < r));
+                        check(Le, cmp(l <= r));
+                        check(Gt, cmp(l > r));
+                        check(Ge, cmp(l >= r));
+                    }
+                    for &amount in &[0u32, 1, 7, 8, 15, 16, 31, 32, 63, 64, 127, 128, 200] {
+                        let r = PrimVal::from_u128(amount as u128);
+                        check_int(Shl, val(l), kind, r, U32, int(l.overflowing_shl(amount)));
+                        check_int(Shr, val(l), kind, r, U32, int(l.overflowing_shr(amount)));
+                    }
+                    let not = unary_op(Not, val(l), kind).unwrap().to_bytes().unwrap();
+                    assert_eq!(not, kind.normalize(val(!l).to_bytes().unwrap()), "!{}", l);
+                    if kind.is_signed_int() {
+                        let neg = unary_op(Neg, val(l), kind).unwrap().to_bytes().unwrap();
+                        let expected = val(l.wrapping_neg()).to_bytes().unwrap();
+                        assert_eq!(neg, kind.normalize(expected), "-{}", l);
+                    }
+                }
+            }
+        )*}
+    }
+
+    int_op_tests! {
+        i8_ops_match_native: i8, I8;
+        i16_ops_match_native: i16, I16;
+        i32_ops_match_native: i32, I32;
+        i64_ops_match_native: i64, I64;
+        i128_ops_match_native: i128, I128;
+        u8_ops_match_native: u8, U8;
+        u16_ops_match_native: u16, U16;
+        u32_ops_match_native: u32, U32;
+        u64_ops_match_native: u64, U64;
+        u128_ops_match_native: u128, U128;
+    }
+
+    macro_rules! float_op_tests {
+        ($($name:ident: $ty:ident, $kind:ident, $from:ident;)*) => {$(
+            #[test]
+            fn $name() {
+                let kind = $kind;
+                let samples: &[$ty] = &[
+                    0.0,
+                    -0.0,
+                    1.0,
+                    -1.5,
+                    2.5,
+                    1e10,
+                    -7e-3,
+                    $ty::MIN_POSITIVE,
+                    $ty::MAX,
+                    $ty::INFINITY,
+                    $ty::NEG_INFINITY,
+                    $ty::NAN,
+                ];
+                for &l in samples {
+                    for &r in samples {
+                        let check = |op, expected| {
+                            check_float(op, PrimVal::$from(l), kind, PrimVal::$from(r), expected)
+                        };
+                        check(Add, PrimVal::$from(l + r));
+                        check(Sub, PrimVal::$from(l - r));
+                        check(Mul, PrimVal::$from(l * r));
+                        check(Div, PrimVal::$from(l / r));
+                        check(Rem, PrimVal::$from(l % r));
+                        check(Eq, PrimVal::from_bool(l == r));
+                        check(Ne, PrimVal::from_bool(l != r));
+                        check(Lt, PrimVal::from_bool(l < r));
+                        check(Le, PrimVal::from_bool(l <= r));
+                        check(Gt, PrimVal::from_bool(l > r));
+                        check(Ge, PrimVal::from_bool(l >= r));
+                    }
+                    let neg = unary_op(Neg, PrimVal::$from(l), kind).unwrap();
+                    assert_eq!(neg.to_bytes().unwrap(), PrimVal::$from(-l).to_bytes().unwrap());
+                }
+            }
+        )*}
+    }
+
+    float_op_tests! {
+        f32_ops_match_native: f32, F32, from_f32;
+        f64_ops_match_native: f64, F64, from_f64;
+    }
+
+    #[test]
+    fn bool_ops_match_native() {
+        for &l in &[false, true] {
+            for &r in &[false, true] {
+                let check = |op, expected| {
+                    let (l, r) = (PrimVal::from_bool(l), PrimVal::from_bool(r));
+                    let (val, overflowed) = primval_binary_op(op, l, Bool, r, Bool)
+                        .unwrap()
+                        .expect("operation is not implemented");
+                    assert_eq!((val.to_bool().unwrap(), overflowed), (expected, false));
+                };
+                check(BitXor, l ^ r);
+                check(BitAnd, l & r);
+                check(BitOr, l | r);
+                check(Eq, l == r);
+                check(Ne, l != r);
+                check(Lt, l < r);
+                check(Le, l <= r);
+                check(Gt, l > r);
+                check(Ge, l >= r);
+            }
+            assert_eq!(unary_op(Not, PrimVal::from_bool(l), Bool).unwrap().to_bool().unwrap(), !l);
+        }
+    }
+
+    #[test]
+    fn mismatched_kinds_are_not_implemented() {
+        let one = PrimVal::from_u128(1);
+        assert!(primval_binary_op(Add, one, U8, one, U16).unwrap().is_none());
+        assert!(primval_binary_op(Eq, one, I32, one, U32).unwrap().is_none());
+        // Shifts are the exception.
+        assert!(primval_binary_op(Shl, one, U8, one, I64).unwrap().is_some());
+    }
+}
@@ -0,0 +1,153 @@
+//! Discovery of the functions an evaluation can call, before it starts.
+//!
+//! Starting at the entry points, the MIR of every function is searched for the functions it calls,
+//! drops or turns into function pointers, and for the constants and statics it uses. The search
+//! only sees what is known without running the program: functions reached through function
+//! pointers that are created elsewhere, and the methods of trait objects, are still found lazily
+//! by the evaluation.
+
+use std::collections::HashSet;
+
+use rustc::hir::def_id::DefId;
+use rustc::middle::const_val::ConstVal;
+use rustc::mir::{self, Location};
+use rustc::mir::visit::Visitor;
+use rustc::ty::{self, TyCtxt};
+use rustc::ty::subst::Substs;
+
+use super::{EvalContext, Machine};
+use super::eval_context::{apply_param_substs, resolve, resolve_drop_in_place};
+
+/// The functions found by `EvalContext::collect_reachable`.
+pub struct Reachable<'tcx> {
+    /// All instances found, in the order they were found in.
+    pub instances: Vec<ty::Instance<'tcx>>,
+    /// The instances of items that have no MIR. The machine has to provide them, or the
+    /// evaluation fails if it reaches them.
+    pub missing_mir: Vec<ty::Instance<'tcx>>,
+}
+
+impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
+    /// Finds the functions reachable from `entries`. The call resolutions done on the way are
+    /// cached, so the evaluation does not have to repeat them.
+    pub fn collect_reachable(&self, entries: &[ty::Instance<'tcx>]) -> Reachable<'tcx> {
+        let mut reachable = Reachable {
+            instances: Vec::new(),
+            missing_mir: Vec::new(),
+        };
+        let mut seen = HashSet::new();
+        let mut todo = entries.to_vec();
+        while let Some(instance) = todo.pop() {
+            if !seen.insert(instance) {
+                continue;
+            }
+            reachable.instances.push(instance);
+            let mir = match instance.def {
+                // Neither has a body of its own: intrinsics are implemented by the interpreter,
+                // and the callee of a virtual call is only known once the vtable is.
+                ty::InstanceDef::Intrinsic(_) |
+                ty::InstanceDef::Virtual(..) => continue,
+                ty::InstanceDef::Item(def_id) => {
+                    match self.tcx.maybe_optimized_mir(def_id) {
+                        Some(mir) => mir,
+                        None => {
+                            // Statics without MIR are foreign ones, they are not called.
+                            if let ty::TyFnDef(..) = instance.def.def_ty(self.tcx).sty {
+                                reachable.missing_mir.push(instance);
+                            }
+                            continue;
+                        }
+                    }
+                }
+                _ => self.tcx.instance_mir(instance.def),
+            };
+            let mut collector = Collector {
+                ecx: self,
+                mir,
+                instance,
+                found: &mut todo,
+            };
+            collector.visit_mir(mir);
+            for promoted in mir.promoted.iter() {
+                collector.mir = promoted;
+                collector.visit_mir(promoted);
+            }
+        }
+        reachable
+    }
+
+    /// Like `eval_context::resolve`, but remembers the result.
+    pub(crate) fn resolve_cached(
+        &self,
+        def_id: DefId,
+        substs: &'tcx Substs<'tcx>,
+    ) -> ty::Instance<'tcx> {
+        let tcx = self.tcx;
+        *self.resolved.borrow_mut().entry((def_id, substs)).or_insert_with(|| {
+            resolve(tcx, def_id, substs)
+        })
+    }
+}
+
+struct Collector<'a, 'b: 'a, 'tcx: 'b, M: Machine<'tcx> + 'a> {
+    ecx: &'a EvalContext<'b, 'tcx, M>,
+    /// The body being searched, which may be a promoted of `instance`.
+    mir: &'tcx mir::Mir<'tcx>,
+    instance: ty::Instance<'tcx>,
+    found: &'a mut Vec<ty::Instance<'tcx>>,
+}
+
+impl<'a, 'b, 'tcx, M: Machine<'tcx>> Collector<'a, 'b, 'tcx, M> {
+    fn tcx(&self) -> TyCtxt<'b, 'tcx, 'tcx> {
+        self.ecx.tcx
+    }
+}
+
+impl<'a, 'b, 'tcx, M: Machine<'tcx>> Visitor<'tcx> for Collector<'a, 'b, 'tcx, M> {
+    fn visit_constant(&mut self, constant: &mir::Constant<'tcx>, location: Location) {
+        self.super_constant(constant, location);
+        // Functions are zero-sized constants of their `TyFnDef`, whether they are called or not.
+        let ty = apply_param_substs(self.tcx(), self.instance.substs, &constant.ty);
+        if let ty::TyFnDef(def_id, substs) = ty.sty {
+            let instance = self.ecx.resolve_cached(def_id, substs);
+            self.found.push(instance);
+        }
+        if let mir::Literal::Value {
+            value: &ty::Const { val: ConstVal::Unevaluated(def_id, substs), .. },
+        } = constant.literal
+        {
+            let substs = apply_param_substs(self.tcx(), self.instance.substs, &substs);
+            self.found.push(ty::Instance::new(def_id, substs));
+        }
+    }
+
+    fn visit_lvalue(
+        &mut self,
+        lvalue: &mir::Lvalue<'tcx>,
+        context: mir::visit::LvalueContext<'tcx>,
+        location: Location,
+    ) {
+        self.super_lvalue(lvalue, context, location);
+        if let mir::Lvalue::Static(ref static_) = *lvalue {
+            self.found.push(ty::Instance::mono(self.tcx(), static_.def_id));
+        }
+    }
+
+    fn visit_terminator_kind(
+        &mut self,
+        block: mir::BasicBlock,
+        kind: &mir::TerminatorKind<'tcx>,
+        location: Location,
+    ) {
+        self.super_terminator_kind(block, kind, location);
+        match *kind {
+            mir::TerminatorKind::Drop { location: ref lvalue, .. } |
+            mir::TerminatorKind::DropAndReplace { location: ref lvalue, .. } => {
+                let ty = lvalue.ty(self.mir, self.tcx()).to_ty(self.tcx());
+                let ty = apply_param_substs(self.tcx(), self.instance.substs, &ty);
+                self.found.push(resolve_drop_in_place(self.tcx(), ty));
+            }
+            _ => {}
+        }
+    }
+}
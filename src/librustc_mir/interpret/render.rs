@@ -0,0 +1,373 @@
+//! Rendering of values for diagnostics, according to their type: `true`, `'a'`, `-3`,
+//! `&alloc7+4`, `Some(&alloc7+4)` or `Point { x: 1, y: <uninit> }`. Bits that are not a valid
+//! value of their type are shown in hex, along with what was expected, e.g. `0x05 (expected a
+//! bool)`.
+//!
+//! Rendering never fails, what cannot be read is shown as `<...>` instead.
+
+use rustc::hir;
+use rustc::hir::def::CtorKind;
+use rustc::ty::{self, Ty};
+use rustc::ty::layout::{Layout, Size};
+use rustc::ty::subst::Substs;
+use syntax::ast::FloatTy;
+
+use super::{AllocIdKind, EvalContext, Machine, MemoryPointer, PrimVal, PtrAndAlign, Value};
+use super::value::{bytes_to_f32, bytes_to_f64};
+
+/// Compound values nested deeper than this are shown as `..`.
+const MAX_DEPTH: usize = 4;
+
+/// Arrays show at most this many elements.
+const MAX_ELEMS: u64 = 8;
+
+/// Renders the `size` bytes `bits` that are not a valid value, e.g. `0x05 (expected a bool)`.
+pub fn invalid_bits(bits: u128, size: u64, expected: &str) -> String {
+    format!("{:#0width$x} (expected {})", bits, expected, width = 2 + 2 * size as usize)
+}
+
+/// Renders a pointer, e.g. `alloc7+4`.
+pub fn render_ptr(ptr: MemoryPointer) -> String {
+    let alloc = match ptr.alloc_id.into_alloc_id_kind() {
+        AllocIdKind::Function(index) => format!("fn{}", index),
+        AllocIdKind::Runtime(index) => format!("alloc{}", index),
+    };
+    if ptr.offset == 0 {
+        alloc
+    } else {
+        format!("{}+{}", alloc, ptr.offset)
+    }
+}
+
+/// Renders a struct, tuple or enum variant with the already rendered `fields`. Tuples have no
+/// `variant`.
+fn render_variant(variant: Option<&ty::VariantDef>, fields: Vec<String>) -> String {
+    let variant = match variant {
+        Some(variant) => variant,
+        None if fields.len() == 1 => return format!("({},)", fields[0]),
+        None => return format!("({})", fields.join(", ")),
+    };
+    match variant.ctor_kind {
+        CtorKind::Const => variant.name.to_string(),
+        CtorKind::Fn => format!("{}({})", variant.name, fields.join(", ")),
+        CtorKind::Fictive if fields.is_empty() => format!("{} {{}}", variant.name),
+        CtorKind::Fictive => {
+            let fields: Vec<_> = variant
+                .fields
+                .iter()
+                .zip(fields)
+                .map(|(field, value)| format!("{}: {}", field.name, value))
+                .collect();
+            format!("{} {{ {} }}", variant.name, fields.join(", "))
+        }
+    }
+}
+
+impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
+    /// Renders `value` of type `ty`.
+    pub fn render_value(&self, value: Value, ty: Ty<'tcx>) -> String {
+        self.render_value_at(value, ty, 0)
+    }
+
+    /// Renders the scalar `val` of type `ty`.
+    pub fn render_primval(&self, val: PrimVal, ty: Ty<'tcx>) -> String {
+        let bits = match val {
+            PrimVal::Undef => return "<uninit>".to_owned(),
+            PrimVal::Ptr(ptr) => {
+                return match ty.sty {
+                    ty::TyRef(_, ty::TypeAndMut { mutbl: hir::MutMutable, .. }) => {
+                        format!("&mut {}", render_ptr(ptr))
+                    }
+                    ty::TyRef(..) => format!("&{}", render_ptr(ptr)),
+                    ty::TyAdt(def, _) if def.is_box() => format!("Box({})", render_ptr(ptr)),
+                    _ => render_ptr(ptr),
+                }
+            }
+            PrimVal::Bytes(bits) => bits,
+        };
+        let size = self.type_size(ty).ok().and_then(|size| size).unwrap_or(16);
+        match ty.sty {
+            ty::TyBool if bits <= 1 => (bits == 1).to_string(),
+            ty::TyBool => invalid_bits(bits, size, "a bool"),
+            ty::TyChar => {
+                match ::std::char::from_u32(bits as u32) {
+                    Some(c) => format!("{:?}", c),
+                    None => invalid_bits(bits, size, "a char"),
+                }
+            }
+            ty::TyInt(_) => {
+                // Sign extend, `bits` may come from an unsigned read.
+                let shift = 128 - size * 8;
+                (((bits << shift) as i128) >> shift).to_string()
+            }
+            ty::TyUint(_) => bits.to_string(),
+            ty::TyFloat(FloatTy::F32) => format!("{:?}", bytes_to_f32(bits)),
+            ty::TyFloat(FloatTy::F64) => format!("{:?}", bytes_to_f64(bits)),
+            ty::TyRawPtr(_) if bits == 0 => "null".to_owned(),
+            ty::TyRef(..) => invalid_bits(bits, size, "a reference"),
+            ty::TyAdt(def, _) if def.is_box() => invalid_bits(bits, size, "a box"),
+            ty::TyFnPtr(_) => invalid_bits(bits, size, "a function pointer"),
+            _ => format!("{:#x}", bits),
+        }
+    }
+
+    fn render_value_at(&self, value: Value, ty: Ty<'tcx>, depth: usize) -> String {
+        if depth > MAX_DEPTH {
+            return "..".to_owned();
+        }
+        match value {
+            Value::ByRef(PtrAndAlign { ptr, .. }) => {
+                match ptr.to_ptr() {
+                    Ok(ptr) => self.render_memory(ptr, ty, depth),
+                    Err(_) => {
+                        let addr = self.render_primval(ptr.into_inner_primval(), ty);
+                        format!("<value at {}>", addr)
+                    }
+                }
+            }
+            Value::ByVal(val) => {
+                match ty.sty {
+                    ty::TyAdt(def, _) if def.is_box() => self.render_primval(val, ty),
+                    ty::TyAdt(..) | ty::TyTuple(..) => self.render_scalar_aggregate(val, ty, depth),
+                    _ => self.render_primval(val, ty),
+                }
+            }
+            Value::ByValPair(a, b) => self.render_pair(a, b, ty, depth),
+        }
+    }
+
+    /// Renders a struct, tuple or enum that is a single scalar, like `Option<&T>`.
+    fn render_scalar_aggregate(&self, val: PrimVal, ty: Ty<'tcx>, depth: usize) -> String {
+        let (variant, substs) = match ty.sty {
+            ty::TyAdt(def, substs) if def.is_enum() => {
+                let layout = match self.type_layout(ty) {
+                    Ok(layout) => layout,
+                    Err(e) => return format!("<{}>", e),
+                };
+                let bits = match val {
+                    PrimVal::Undef => return "<uninit>".to_owned(),
+                    PrimVal::Ptr(_) => None,
+                    PrimVal::Bytes(bits) => Some(bits),
+                };
+                let index = match *layout {
+                    Layout::CEnum { .. } => {
+                        let bits = bits.unwrap_or(0);
+                        match def.discriminants(self.tcx).position(|discr| {
+                            discr.to_u128_unchecked() == bits
+                        }) {
+                            Some(index) => index,
+                            None => return self.invalid_discriminant(bits, ty),
+                        }
+                    }
+                    Layout::RawNullablePointer { nndiscr, .. } if bits == Some(0) => {
+                        1 - nndiscr as usize
+                    }
+                    Layout::RawNullablePointer { nndiscr, .. } => nndiscr as usize,
+                    _ => return format!("<{:?} of type `{}`>", val, ty),
+                };
+                (Some(&def.variants[index]), substs)
+            }
+            ty::TyAdt(def, substs) => (Some(def.struct_variant()), substs),
+            _ => (None, self.tcx.intern_substs(&[])),
+        };
+        let field_tys = self.field_tys(ty, variant, substs);
+        // The one field that is not zero-sized holds the scalar.
+        let fields = field_tys
+            .into_iter()
+            .map(|field_ty| {
+                let value = if self.type_size(field_ty).ok() == Some(Some(0)) {
+                    Value::ByVal(PrimVal::Undef)
+                } else {
+                    Value::ByVal(val)
+                };
+                self.render_value_at(value, field_ty, depth + 1)
+            })
+            .collect();
+        render_variant(variant, fields)
+    }
+
+    /// Renders a fat pointer, or a struct or tuple of two scalars.
+    fn render_pair(&self, a: PrimVal, b: PrimVal, ty: Ty<'tcx>, depth: usize) -> String {
+        let pointee = match ty.sty {
+            ty::TyRef(_, ref tam) |
+            ty::TyRawPtr(ref tam) => Some(tam.ty),
+            ty::TyAdt(def, _) if def.is_box() => Some(ty.boxed_ty()),
+            _ => None,
+        };
+        if let Some(pointee) = pointee {
+            let ptr = self.render_primval(a, ty);
+            return match pointee.sty {
+                ty::TyStr => {
+                    let string = match (a, b) {
+                        (PrimVal::Ptr(ptr), PrimVal::Bytes(len)) => {
+                            self.memory.read_bytes(ptr.into(), len as u64).ok().and_then(
+                                |bytes| ::std::str::from_utf8(bytes).ok().map(str::to_owned),
+                            )
+                        }
+                        _ => None,
+                    };
+                    match string {
+                        Some(string) => format!("{:?}", string),
+                        None => format!("{} (len {})", ptr, self.render_len(b)),
+                    }
+                }
+                ty::TySlice(_) => format!("{} (len {})", ptr, self.render_len(b)),
+                _ => format!("{} (vtable {})", ptr, self.render_primval(b, self.tcx.types.usize)),
+            };
+        }
+        let field_tys = match ty.sty {
+            ty::TyAdt(def, substs) if !def.is_enum() => {
+                self.field_tys(ty, Some(def.struct_variant()), substs)
+            }
+            ty::TyTuple(..) => self.field_tys(ty, None, self.tcx.intern_substs(&[])),
+            _ => return format!("<({:?}, {:?}) of type `{}`>", a, b, ty),
+        };
+        // The first and the second field that are not zero-sized hold the scalars.
+        let mut scalars = vec![a, b].into_iter();
+        let fields = field_tys
+            .into_iter()
+            .map(|field_ty| {
+                let val = if self.type_size(field_ty).ok() == Some(Some(0)) {
+                    PrimVal::Undef
+                } else {
+                    scalars.next().unwrap_or(PrimVal::Undef)
+                };
+                self.render_value_at(Value::ByVal(val), field_ty, depth + 1)
+            })
+            .collect();
+        let variant = match ty.sty {
+            ty::TyAdt(def, _) => Some(def.struct_variant()),
+            _ => None,
+        };
+        render_variant(variant, fields)
+    }
+
+    fn render_len(&self, len: PrimVal) -> String {
+        self.render_primval(len, self.tcx.types.usize)
+    }
+
+    /// Renders the value of type `ty` in memory at `ptr`.
+    fn render_memory(&self, ptr: MemoryPointer, ty: Ty<'tcx>, depth: usize) -> String {
+        if depth > MAX_DEPTH {
+            return "..".to_owned();
+        }
+        let layout = match self.type_layout(ty) {
+            Ok(layout) => layout,
+            Err(e) => return format!("<{}>", e),
+        };
+        let (variant, substs, offsets) = match ty.sty {
+            ty::TyAdt(def, substs) if def.is_enum() => {
+                let discr = match self.read_discriminant_value(ptr, ty) {
+                    Ok(discr) => discr,
+                    Err(e) => return format!("<{}>", e),
+                };
+                let index = match def.discriminants(self.tcx).position(|variant_discr| {
+                    variant_discr.to_u128_unchecked() == discr
+                }) {
+                    Some(index) => index,
+                    None => return self.invalid_discriminant(discr, ty),
+                };
+                let variant = &def.variants[index];
+                let offsets = match *layout {
+                    Layout::General { ref variants, .. } => variants[index].offsets.clone(),
+                    Layout::StructWrappedNullablePointer { nndiscr, ref nonnull, .. }
+                        if nndiscr as usize == index => nonnull.offsets.clone(),
+                    Layout::Univariant { ref variant, .. } => variant.offsets.clone(),
+                    // The fields of the variant are zero-sized, or it is the pointer.
+                    _ => vec![Size::from_bytes(0); variant.fields.len()],
+                };
+                (Some(variant), substs, offsets)
+            }
+            ty::TyAdt(def, _) if def.is_union() => return format!("<union `{}`>", ty),
+            ty::TyAdt(def, substs) if !def.is_box() => {
+                match *layout {
+                    Layout::Univariant { ref variant, .. } => {
+                        (Some(def.struct_variant()), substs, variant.offsets.clone())
+                    }
+                    _ => return format!("<value of type `{}`>", ty),
+                }
+            }
+            ty::TyTuple(..) => {
+                match *layout {
+                    Layout::Univariant { ref variant, .. } => {
+                        (None, self.tcx.intern_substs(&[]), variant.offsets.clone())
+                    }
+                    _ => return format!("<value of type `{}`>", ty),
+                }
+            }
+            ty::TyArray(elem_ty, len) => {
+                let len = len.val.to_const_int().unwrap().to_u64().unwrap();
+                let elem_size = match self.type_size(elem_ty) {
+                    Ok(Some(size)) => size,
+                    _ => return format!("<value of type `{}`>", ty),
+                };
+                let mut elems: Vec<_> = (0..len.min(MAX_ELEMS))
+                    .map(|i| {
+                        let elem_ptr = MemoryPointer::new(ptr.alloc_id, ptr.offset + i * elem_size);
+                        self.render_memory(elem_ptr, elem_ty, depth + 1)
+                    })
+                    .collect();
+                if len > MAX_ELEMS {
+                    elems.push("..".to_owned());
+                }
+                return format!("[{}]", elems.join(", "));
+            }
+            ty::TyClosure(..) |
+            ty::TyGenerator(..) => return format!("<value of type `{}`>", ty),
+            _ => return self.render_scalar_memory(ptr, ty, depth),
+        };
+        let fields = self.field_tys(ty, variant, substs)
+            .into_iter()
+            .zip(offsets)
+            .map(|(field_ty, offset)| {
+                let field_ptr = MemoryPointer::new(ptr.alloc_id, ptr.offset + offset.bytes());
+                self.render_memory(field_ptr, field_ty, depth + 1)
+            })
+            .collect();
+        render_variant(variant, fields)
+    }
+
+    /// Renders a scalar or fat pointer in memory. Scalars are read as raw bits, so invalid ones
+    /// are rendered instead of failing the read.
+    fn render_scalar_memory(&self, ptr: MemoryPointer, ty: Ty<'tcx>, depth: usize) -> String {
+        let thin = match ty.sty {
+            ty::TyAdt(def, _) if def.is_box() => self.type_is_sized(ty.boxed_ty()),
+            _ => self.ty_to_primval_kind(ty).is_ok(),
+        };
+        if !thin {
+            return match self.read_value(ptr.into(), ty) {
+                Ok(Value::ByRef { .. }) => format!("<value of type `{}`>", ty),
+                Ok(value) => self.render_value_at(value, ty, depth),
+                Err(e) => format!("<{}>", e),
+            };
+        }
+        let size = match self.type_size(ty) {
+            Ok(Some(size)) => size,
+            _ => return format!("<value of type `{}`>", ty),
+        };
+        match self.memory.read_primval(ptr, size, false) {
+            Ok(val) => self.render_primval(val, ty),
+            Err(e) => format!("<{}>", e),
+        }
+    }
+
+    /// The types of the fields of `variant` of `ty`, or of the tuple `ty` if there is no variant.
+    fn field_tys(
+        &self,
+        ty: Ty<'tcx>,
+        variant: Option<&ty::VariantDef>,
+        substs: &'tcx Substs<'tcx>,
+    ) -> Vec<Ty<'tcx>> {
+        match (variant, &ty.sty) {
+            (Some(variant), _) => {
+                variant.fields.iter().map(|field| field.ty(self.tcx, substs)).collect()
+            }
+            (None, &ty::TyTuple(tys, _)) => tys.to_vec(),
+            (None, _) => Vec::new(),
+        }
+    }
+
+    fn invalid_discriminant(&self, discr: u128, ty: Ty<'tcx>) -> String {
+        format!("{} (expected a discriminant of `{}`)", discr, ty)
+    }
+}
@@ -6,7 +6,6 @@ use rustc::hir::def_id::DefId;
 use rustc::hir;
 use rustc::mir::visit::{Visitor, LvalueContext};
 use rustc::mir;
-use rustc::traits::Reveal;
 use rustc::ty;
 use rustc::ty::layout::Layout;
 use rustc::ty::subst::Substs;
@@ -14,14 +13,24 @@ use rustc::middle::const_val::ConstVal;
 
 use super::{EvalResult, EvalContext, StackPopCleanup, PtrAndAlign, GlobalId, Lvalue,
             MemoryKind, Machine, PrimVal};
+use super::eval_context::apply_param_substs;
 
 use syntax::codemap::Span;
 use syntax::ast::Mutability;
 
 impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
     pub fn inc_step_counter_and_check_limit(&mut self, n: u64) -> EvalResult<'tcx> {
-        self.steps_remaining = self.steps_remaining.saturating_sub(n);
-        if self.steps_remaining > 0 {
+        // The innermost function with a step limit of its own pays for the steps.
+        let remaining = match self.stack
+            .iter_mut()
+            .rev()
+            .filter_map(|frame| frame.steps_remaining.as_mut())
+            .next() {
+            Some(remaining) => remaining,
+            None => &mut self.steps_remaining,
+        };
+        *remaining = remaining.saturating_sub(n);
+        if *remaining > 0 {
             Ok(())
         } else {
             err!(ExecutionTimeLimitReached)
@@ -30,6 +39,16 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
 
     /// Returns true as long as there are more things to do.
     pub fn step(&mut self) -> EvalResult<'tcx, bool> {
+        self.step_inner().map_err(|mut e| {
+            // Errors from nested evaluations already have the more precise stack trace.
+            if e.stacktrace.is_empty() {
+                e.stacktrace = self.generate_stacktrace();
+            }
+            e
+        })
+    }
+
+    fn step_inner(&mut self) -> EvalResult<'tcx, bool> {
         self.inc_step_counter_and_check_limit(1)?;
         if self.stack.is_empty() {
             return Ok(false);
@@ -41,6 +60,7 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         let basic_block = &mir.basic_blocks()[block];
 
         if let Some(stmt) = basic_block.statements.get(stmt_id) {
+            self.memory.enter_statement(stmt.source_info.span);
             let mut new = Ok(0);
             ConstantExtractor {
                 span: stmt.source_info.span,
@@ -65,6 +85,7 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         }
 
         let terminator = basic_block.terminator();
+        self.memory.enter_statement(terminator.source_info.span);
         let mut new = Ok(0);
         ConstantExtractor {
             span: terminator.source_info.span,
@@ -174,10 +195,24 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
             }
 
             // Defined to do nothing. These are added by optimization passes, to avoid changing the
-            // size of MIR constantly.
-            Nop => {}
+            // size of MIR constantly. This is the only bookkeeping-only statement the MIR of this
+            // compiler has; the match is exhaustive, so any new one has to be handled here.
+            Nop => debug!("skipping nop statement"),
 
-            InlineAsm { .. } => return err!(InlineAsm),
+            // Empty templates (e.g. the `asm!("" ::: "memory")` compiler fences) and single `nop`s
+            // have no effect on the abstract machine.
+            InlineAsm { ref asm, ref outputs, .. } => {
+                let trivial = outputs.is_empty() && match asm.asm.as_str().trim() {
+                    "" | "nop" => true,
+                    _ => false,
+                };
+                let instance = self.frame().instance;
+                if trivial || M::skip_inline_asm(self, instance) {
+                    debug!("skipping inline assembly in {}", instance);
+                } else {
+                    return err!(InlineAsm(self.tcx.item_path_str(instance.def_id())));
+                }
+            }
         }
 
         self.stack[frame_idx].stmt += 1;
@@ -193,6 +228,10 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         Ok(())
     }
 
+    /// Starts evaluating a static or constant unless its value is known already. This happens
+    /// right before the first statement using it is executed, so globals the program never uses
+    /// are never evaluated at all.
+    ///
     /// returns `true` if a stackframe was pushed
     fn global_item(
         &mut self,
@@ -231,18 +270,16 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                 aligned,
             },
         );
-        let internally_mutable = !mir.return_ty.is_freeze(
-            self.tcx,
-            ty::ParamEnv::empty(Reveal::All),
-            span,
-        );
-        let mutability = if mutability == Mutability::Mutable || internally_mutable {
-            Mutability::Mutable
-        } else {
-            Mutability::Immutable
-        };
+        if mutability == Mutability::Immutable {
+            // Only the `UnsafeCell`s of an immutable static may be written to.
+            let ty = self.monomorphize(mir.return_ty, substs);
+            let mut cells = Vec::new();
+            self.unsafe_cell_ranges(ty, 0, &mut cells)?;
+            self.memory.set_unsafe_cells(ptr.alloc_id, cells)?;
+        }
         let cleanup = StackPopCleanup::MarkStatic(mutability);
         let name = ty::tls::with(|tcx| tcx.item_path_str(def_id));
+        self.memory.set_label(ptr.alloc_id, name.clone())?;
         trace!("pushing stack frame for global: {}", name);
         self.push_stack_frame(
             instance,
@@ -291,6 +328,8 @@ impl<'a, 'b, 'tcx, M: Machine<'tcx>> Visitor<'tcx> for ConstantExtractor<'a, 'b,
         match constant.literal {
             // already computed by rustc
             mir::Literal::Value { value: &ty::Const { val: ConstVal::Unevaluated(def_id, substs), .. } } => {
+                // `T::CONST` in a generic function only resolves to an impl once `T` is known.
+                let substs = apply_param_substs(self.ecx.tcx, self.instance.substs, &substs);
                 self.try(|this| {
                     this.ecx.global_item(
                         def_id,
@@ -323,6 +362,8 @@ impl<'a, 'b, 'tcx, M: Machine<'tcx>> Visitor<'tcx> for ConstantExtractor<'a, 'b,
                         align,
                         MemoryKind::UninitializedStatic,
                     )?;
+                    let label = format!("{:?} of {}", index, this.instance);
+                    this.ecx.memory.set_label(ptr.alloc_id, label)?;
                     let aligned = !this.ecx.is_packed(mir.return_ty)?;
                     this.ecx.globals.insert(
                         cid,
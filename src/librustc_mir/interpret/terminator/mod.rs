@@ -1,10 +1,11 @@
 use rustc::mir;
 use rustc::ty::{self, TypeVariants};
+use rustc::ty::subst::Subst;
 use rustc::ty::layout::Layout;
 use syntax::codemap::Span;
 use syntax::abi::Abi;
 
-use super::{EvalResult, EvalContext, eval_context,
+use super::{EvalResult, EvalContext, eval_context, AllocIdKind,
             PtrAndAlign, Lvalue, PrimVal, Value, Machine, ValTy};
 
 use rustc_data_structures::indexed_vec::Idx;
@@ -69,24 +70,23 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                 let (fn_def, sig) = match func_ty.sty {
                     ty::TyFnPtr(sig) => {
                         let fn_ptr = self.eval_operand_to_primval(func)?.to_ptr()?;
-                        let instance = self.memory.get_fn(fn_ptr)?;
-                        let instance_ty = instance.def.def_ty(self.tcx);
-                        let instance_ty = self.monomorphize(instance_ty, instance.substs);
-                        match instance_ty.sty {
-                            ty::TyFnDef(..) => {
-                                let real_sig = instance_ty.fn_sig(self.tcx);
-                                let sig = self.tcx.erase_late_bound_regions_and_normalize(&sig);
-                                let real_sig = self.tcx.erase_late_bound_regions_and_normalize(&real_sig);
-                                if !self.check_sig_compat(sig, real_sig)? {
-                                    return err!(FunctionPointerTyMismatch(real_sig, sig));
-                                }
+                        if let AllocIdKind::Runtime(_) = fn_ptr.alloc_id.into_alloc_id_kind() {
+                            let args = self.operands_to_args(args)?;
+                            let sig = self.tcx.erase_late_bound_regions_and_normalize(&sig);
+                            if M::call_memory_fn_ptr(self, fn_ptr, destination, &args, sig)? {
+                                return Ok(());
                             }
-                            ref other => bug!("instance def ty: {:?}", other),
+                        }
+                        let instance = self.memory.get_fn(fn_ptr)?;
+                        let real_sig = self.callee_sig(instance);
+                        let sig = self.tcx.erase_late_bound_regions_and_normalize(&sig);
+                        if !self.check_sig_compat(sig, real_sig)? {
+                            return err!(FunctionPointerTyMismatch(real_sig, sig));
                         }
                         (instance, sig)
                     }
                     ty::TyFnDef(def_id, substs) => (
-                        eval_context::resolve(self.tcx, def_id, substs),
+                        self.resolve_cached(def_id, substs),
                         func_ty.fn_sig(self.tcx),
                     ),
                     _ => {
@@ -94,15 +94,28 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                         return err!(Unimplemented(msg));
                     }
                 };
+                if self.check_aliasing {
+                    let arg_tags = args.iter().map(|arg| match *arg {
+                        mir::Operand::Consume(mir::Lvalue::Local(local)) => {
+                            self.frame().ref_tags.get(&local).cloned()
+                        }
+                        _ => None,
+                    }).collect();
+                    self.memory.mut_borrows.arg_tags = arg_tags;
+                }
                 let args = self.operands_to_args(args)?;
                 let sig = self.tcx.erase_late_bound_regions_and_normalize(&sig);
-                self.eval_fn_call(
+                let result = self.eval_fn_call(
                     fn_def,
                     destination,
                     &args,
                     terminator.source_info.span,
                     sig,
-                )?;
+                );
+                // Only the callee's own frame may take the borrows, not the frames of drops or
+                // hooks that run later.
+                self.memory.mut_borrows.arg_tags.clear();
+                result?;
             }
 
             Drop {
@@ -152,22 +165,69 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                         Math(ref err) => {
                             err!(Math(terminator.source_info.span, err.clone()))
                         }
-                        GeneratorResumedAfterReturn |
-                        GeneratorResumedAfterPanic => unimplemented!(),
+                        GeneratorResumedAfterReturn => err!(GeneratorResumedAfterReturn),
+                        GeneratorResumedAfterPanic => err!(GeneratorResumedAfterPanic),
                     };
                 }
             }
 
-            Yield { .. } => unimplemented!("{:#?}", terminator.kind),
-            GeneratorDrop => unimplemented!(),
-            DropAndReplace { .. } => unimplemented!(),
-            Resume => unimplemented!(),
+            // The generator transformation replaces these with assignments to the generator's state
+            // and returns before the MIR gets here.
+            Yield { .. } => bug!("generator MIR was not transformed: {:#?}", terminator.kind),
+            // The end of the drop glue of a generator, which is run like any other function.
+            GeneratorDrop => {
+                self.dump_local(self.frame().return_lvalue);
+                self.pop_stack_frame()?
+            }
+            // Drop elaboration turns these into a `Drop` followed by an assignment.
+            DropAndReplace { .. } => {
+                bug!("DropAndReplace should have been removed by drop elaboration")
+            }
+            // The end of a cleanup block, after which the caller continues unwinding.
+            Resume => {
+                if !self.pop_unwound_frame()? {
+                    self.unwind()?;
+                }
+            }
             Unreachable => return err!(Unreachable),
         }
 
         Ok(())
     }
 
+    /// The signature `instance` was declared with, which can differ from the one it is called with
+    /// if it is called through a function pointer or a vtable.
+    fn callee_sig(&self, instance: ty::Instance<'tcx>) -> ty::FnSig<'tcx> {
+        let instance_ty = instance.def.def_ty(self.tcx);
+        let instance_ty = self.monomorphize(instance_ty, instance.substs);
+        match instance_ty.sty {
+            ty::TyFnDef(..) => {
+                let real_sig = instance_ty.fn_sig(self.tcx);
+                self.tcx.erase_late_bound_regions_and_normalize(&real_sig)
+            }
+            // The `Fn` and `FnMut` methods in the vtables of closures are their bodies, which take
+            // the environment and the arguments as a tuple.
+            ty::TyClosure(def_id, substs) => {
+                let sig = self.tcx.fn_sig(def_id).subst(self.tcx, substs.substs);
+                let sig = self.tcx.erase_late_bound_regions_and_normalize(&sig);
+                let re_erased = self.tcx.types.re_erased;
+                let env_ty = match self.tcx.closure_kind(def_id) {
+                    ty::ClosureKind::Fn => self.tcx.mk_imm_ref(re_erased, instance_ty),
+                    ty::ClosureKind::FnMut => self.tcx.mk_mut_ref(re_erased, instance_ty),
+                    ty::ClosureKind::FnOnce => instance_ty,
+                };
+                let mut inputs_and_output = vec![env_ty];
+                inputs_and_output.extend(sig.inputs_and_output.iter().cloned());
+                ty::FnSig {
+                    inputs_and_output: self.tcx.intern_type_list(&inputs_and_output),
+                    abi: Abi::RustCall,
+                    ..sig
+                }
+            }
+            ref other => bug!("instance def ty: {:?}", other),
+        }
+    }
+
     /// Decides whether it is okay to call the method with signature `real_sig` using signature `sig`.
     /// FIXME: This should take into account the platform-dependent ABI description.
     fn check_sig_compat(
@@ -175,29 +235,52 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         sig: ty::FnSig<'tcx>,
         real_sig: ty::FnSig<'tcx>,
     ) -> EvalResult<'tcx, bool> {
-        fn check_ty_compat<'tcx>(ty: ty::Ty<'tcx>, real_ty: ty::Ty<'tcx>) -> bool {
+        fn check_ty_compat<'a, 'tcx>(
+            tcx: ty::TyCtxt<'a, 'tcx, 'tcx>,
+            ty: ty::Ty<'tcx>,
+            real_ty: ty::Ty<'tcx>,
+        ) -> bool {
             if ty == real_ty {
                 return true;
             } // This is actually a fast pointer comparison
             return match (&ty.sty, &real_ty.sty) {
                 // Permit changing the pointer type of raw pointers and references as well as
-                // mutability of raw pointers.
-                // TODO: Should not be allowed when fat pointers are involved.
-                (&TypeVariants::TyRawPtr(_), &TypeVariants::TyRawPtr(_)) => true,
-                (&TypeVariants::TyRef(_, _), &TypeVariants::TyRef(_, _)) => {
-                    ty.is_mutable_pointer() == real_ty.is_mutable_pointer()
+                // mutability of raw pointers, as long as the pointers carry the same metadata.
+                (&TypeVariants::TyRawPtr(tam), &TypeVariants::TyRawPtr(real_tam)) => {
+                    same_metadata(tcx, tam.ty, real_tam.ty)
+                }
+                (&TypeVariants::TyRef(_, tam), &TypeVariants::TyRef(_, real_tam)) => {
+                    ty.is_mutable_pointer() == real_ty.is_mutable_pointer() &&
+                        same_metadata(tcx, tam.ty, real_tam.ty)
                 }
                 // rule out everything else
                 _ => false,
             };
         }
 
+        /// Whether pointers to `pointee` and `real_pointee` are both thin, both carry a length or
+        /// both carry a vtable.
+        fn same_metadata<'a, 'tcx>(
+            tcx: ty::TyCtxt<'a, 'tcx, 'tcx>,
+            pointee: ty::Ty<'tcx>,
+            real_pointee: ty::Ty<'tcx>,
+        ) -> bool {
+            fn metadata<'tcx>(tail: ty::Ty<'tcx>) -> u8 {
+                match tail.sty {
+                    TypeVariants::TyStr | TypeVariants::TySlice(_) => 1,
+                    TypeVariants::TyDynamic(..) => 2,
+                    _ => 0,
+                }
+            }
+            metadata(tcx.struct_tail(pointee)) == metadata(tcx.struct_tail(real_pointee))
+        }
+
         if sig.abi == real_sig.abi && sig.variadic == real_sig.variadic &&
             sig.inputs_and_output.len() == real_sig.inputs_and_output.len() &&
             sig.inputs_and_output
                 .iter()
                 .zip(real_sig.inputs_and_output)
-                .all(|(ty, real_ty)| check_ty_compat(ty, real_ty))
+                .all(|(ty, real_ty)| check_ty_compat(self.tcx, ty, real_ty))
         {
             // Definitely good.
             return Ok(true);
@@ -211,7 +294,7 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         // We need to allow what comes up when a non-capturing closure is cast to a fn().
         match (sig.abi, real_sig.abi) {
             (Abi::Rust, Abi::RustCall) // check the ABIs.  This makes the test here non-symmetric.
-                if check_ty_compat(sig.output(), real_sig.output()) && real_sig.inputs_and_output.len() == 3 => {
+                if check_ty_compat(self.tcx, sig.output(), real_sig.output()) && real_sig.inputs_and_output.len() == 3 => {
                 // First argument of real_sig must be a ZST
                 let fst_ty = real_sig.inputs_and_output[0];
                 let layout = self.type_layout(fst_ty)?;
@@ -221,7 +304,7 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                     let snd_ty = real_sig.inputs_and_output[1];
                     match snd_ty.sty {
                         TypeVariants::TyTuple(tys, _) if sig.inputs().len() == tys.len() =>
-                            if sig.inputs().iter().zip(tys).all(|(ty, real_ty)| check_ty_compat(ty, real_ty)) {
+                            if sig.inputs().iter().zip(tys).all(|(ty, real_ty)| check_ty_compat(self.tcx, ty, real_ty)) {
                                 return Ok(true)
                             },
                         _ => {}
@@ -248,9 +331,25 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
             ty::InstanceDef::Intrinsic(..) => {
                 let (ret, target) = match destination {
                     Some(dest) => dest,
-                    _ => return err!(Unreachable),
+                    None => {
+                        let name = self.tcx.item_name(instance.def_id());
+                        return match &name[..] {
+                            // Reaching this is exactly what the caller promised would never happen.
+                            "unreachable" => err!(Unreachable),
+                            "abort" => err!(Abort),
+                            // Calls of e.g. `transmute::<_, !>` have no destination.
+                            _ if self.type_is_uninhabited(sig.output()) => {
+                                err!(UninhabitedValue(sig.output()))
+                            }
+                            _ => err!(Unimplemented(format!("diverging intrinsic `{}`", name))),
+                        };
+                    }
                 };
                 let ty = sig.output();
+                // Intrinsics like `transmute` write their result without `write_value`.
+                if self.type_is_uninhabited(ty) {
+                    return err!(UninhabitedValue(ty));
+                }
                 let layout = self.type_layout(ty)?;
                 M::call_intrinsic(self, instance, args, ret, ty, layout, target)?;
                 self.dump_local(ret);
@@ -296,6 +395,8 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
             ty::InstanceDef::DropGlue(..) |
             ty::InstanceDef::CloneShim(..) |
             ty::InstanceDef::Item(_) => {
+                let arg_tags = self.take_arg_tags();
+
                 // Push the stack frame, and potentially be entirely done if the call got hooked
                 if M::eval_fn_call(self, instance, destination, args, span, sig)? {
                     return Ok(());
@@ -354,6 +455,17 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                                         }
                                     }
                                     Value::ByVal(PrimVal::Undef) => {}
+                                    // e.g. the arguments of `Fn::call(&f, (a, b))` with a tuple
+                                    // of two primitives
+                                    Value::ByValPair(a, b) if fields.len() == 2 => {
+                                        for (&(val, ty), arg_local) in
+                                            [(a, fields[0]), (b, fields[1])].iter().zip(arg_locals)
+                                        {
+                                            let dest =
+                                                self.eval_lvalue(&mir::Lvalue::Local(arg_local))?;
+                                            self.write_primval(dest, val, ty)?;
+                                        }
+                                    }
                                     other => {
                                         assert_eq!(fields.len(), 1);
                                         let dest = self.eval_lvalue(&mir::Lvalue::Local(
@@ -383,9 +495,12 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                         }
                     }
                     _ => {
-                        for (arg_local, &valty) in arg_locals.zip(args) {
+                        for (i, (arg_local, &valty)) in arg_locals.zip(args).enumerate() {
                             let dest = self.eval_lvalue(&mir::Lvalue::Local(arg_local))?;
                             self.write_value(valty, dest)?;
+                            if let Some(&Some(tag)) = arg_tags.get(i) {
+                                self.frame_mut().ref_tags.insert(arg_local, tag);
+                            }
                         }
                     }
                 }
@@ -399,6 +514,23 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                     vtable.offset(ptr_size * (idx as u64 + 3), &self)?
                 )?.to_ptr()?;
                 let instance = self.memory.get_fn(fn_ptr)?;
+                // A vtable of another trait, e.g. from a transmuted trait object, can hold a
+                // method of a different signature at this index. The receiver is the trait object
+                // at the call site but the concrete type in the method, so it is not compared.
+                let real_sig = self.callee_sig(instance);
+                let mut expected = sig.inputs_and_output.to_vec();
+                if let (Some(receiver), Some(&real_receiver)) =
+                    (expected.first_mut(), real_sig.inputs().first())
+                {
+                    *receiver = real_receiver;
+                }
+                let expected_sig = ty::FnSig {
+                    inputs_and_output: self.tcx.intern_type_list(&expected),
+                    ..sig
+                };
+                if !self.check_sig_compat(expected_sig, real_sig)? {
+                    return err!(FunctionPointerTyMismatch(real_sig, sig));
+                }
                 let mut args = args.to_vec();
                 let ty = self.get_field_ty(args[0].ty, 0)?.ty; // TODO: packed flag is ignored
                 args[0].ty = ty;
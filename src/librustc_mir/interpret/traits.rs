@@ -47,6 +47,9 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         trait_ref: ty::PolyTraitRef<'tcx>,
     ) -> EvalResult<'tcx, MemoryPointer> {
         debug!("get_vtable(trait_ref={:?})", trait_ref);
+        if let Some(&vtable) = self.vtables.get(&(ty, trait_ref)) {
+            return Ok(vtable);
+        }
 
         let size = self.type_size(trait_ref.self_ty())?.expect(
             "can't create a vtable for an unsized type",
@@ -72,17 +75,19 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
 
         for (i, method) in ::rustc::traits::get_vtable_methods(self.tcx, trait_ref).enumerate() {
             if let Some((def_id, substs)) = method {
-                let instance = eval_context::resolve(self.tcx, def_id, substs);
+                let instance = self.resolve_cached(def_id, substs);
                 let fn_ptr = self.memory.create_fn_alloc(instance);
                 let method_ptr = vtable.offset(ptr_size * (3 + i as u64), &self)?;
                 self.memory.write_ptr_sized_unsigned(method_ptr, PrimVal::Ptr(fn_ptr))?;
             }
         }
 
+        // Vtables are shared by all trait objects of the type, nobody may write to them.
         self.memory.mark_static_initalized(
             vtable.alloc_id,
-            Mutability::Mutable,
+            Mutability::Immutable,
         )?;
+        self.vtables.insert((ty, trait_ref), vtable);
 
         Ok(vtable)
     }
@@ -9,8 +9,10 @@ use rustc::traits::Reveal;
 use rustc::middle::region;
 use rustc_data_structures::indexed_vec::Idx;
 
+use std::cmp;
+
 use super::{EvalError, EvalResult, EvalErrorKind, EvalContext, DynamicLifetime, AccessKind, Value,
-            Lvalue, LvalueExtra, Machine, ValTy};
+            Lvalue, LvalueExtra, HasMemory, Machine, PrimVal, PtrAndAlign, ValTy};
 
 pub type ValidationQuery<'tcx> = ValidationOperand<'tcx, (AbsLvalue<'tcx>, Lvalue)>;
 
@@ -112,7 +114,7 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         // because other crates may have been compiled with mir-emit-validate > 0.  Ignore those
         // commands.  This makes mir-emit-validate also a flag to control whether miri will do
         // validation or not.
-        if self.tcx.sess.opts.debugging_opts.mir_emit_validate == 0 {
+        if self.tcx.sess.opts.debugging_opts.mir_emit_validate == 0 || !self.validate {
             return Ok(());
         }
         debug_assert!(self.memory.cur_frame == self.cur_frame());
@@ -350,6 +352,87 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         Ok(())
     }
 
+    /// Checks that the reference `val` to a `pointee_ty` is non-null, aligned, and points to live
+    /// memory large enough for its pointee. This is done whenever a reference is created or passed
+    /// to a function if `check_references` is set, independently of the validation statements.
+    /// References to zero-sized pointees only need an aligned, non-null address.
+    pub(crate) fn check_reference(&mut self, val: Value, pointee_ty: Ty<'tcx>) -> EvalResult<'tcx> {
+        let (size, align) = self.size_and_align_of_dst(pointee_ty, val)?;
+        let (offset, alloc_align) = match val.into_ptr(&self.memory)?.into_inner_primval() {
+            PrimVal::Bytes(0) => return err!(NullReference),
+            // Integer addresses are fine for zero-sized pointees, e.g. `NonNull::dangling`.
+            PrimVal::Bytes(_) if size > 0 => return err!(DanglingReference),
+            PrimVal::Bytes(addr) => (addr as u64, align),
+            PrimVal::Ptr(ptr) => {
+                let alloc = match self.memory.get(ptr.alloc_id) {
+                    Ok(alloc) => alloc,
+                    Err(EvalError { kind: EvalErrorKind::DanglingPointerDeref(_), .. }) |
+                    Err(EvalError { kind: EvalErrorKind::DanglingLocalDeref(_), .. }) => {
+                        if size == 0 {
+                            return Ok(());
+                        }
+                        return err!(DanglingReference);
+                    }
+                    Err(e) => return Err(e),
+                };
+                let allocation_size = alloc.bytes.len() as u64;
+                if ptr.offset.checked_add(size).map_or(true, |end| end > allocation_size) {
+                    return err!(ReferenceOutOfBounds { ptr, size, allocation_size });
+                }
+                (ptr.offset, alloc.align)
+            }
+            PrimVal::Undef => return err!(ReadUndefBytes),
+        };
+        // The largest power of two dividing the address.
+        let has = if offset == 0 {
+            alloc_align
+        } else {
+            cmp::min(alloc_align, 1 << offset.trailing_zeros())
+        };
+        if has < align {
+            return err!(UnalignedReference { required: align, has });
+        }
+        Ok(())
+    }
+
+    /// Checks the validity invariants that are cheap enough to check on every write of a `ty`:
+    /// `bool`s must be 0 or 1, `char`s must be valid codepoints, and references and the contents of
+    /// `NonZero`s must not be 0. This catches invalid values made by transmutes, union reads and
+    /// writes through raw pointers where they are stored, instead of where they are used. Like in
+    /// validation, undefined values are not ruled out.
+    pub fn check_written_value(&self, value: Value, ty: Ty<'tcx>) -> EvalResult<'tcx> {
+        // The type the first scalar of a `ty` is read at from memory.
+        let scalar_ty = match ty.sty {
+            ty::TyBool => self.tcx.types.u8,
+            ty::TyChar => self.tcx.types.u32,
+            ty::TyRef(..) => ty,
+            ty::TyAdt(adt, subst) if Some(adt.did) == self.tcx.lang_items().non_zero() => {
+                adt.struct_variant().fields[0].ty(self.tcx, subst)
+            }
+            _ => return Ok(()),
+        };
+        let value = match value {
+            Value::ByRef(PtrAndAlign { ptr, aligned }) => {
+                self.read_maybe_aligned(aligned, |ectx| ectx.read_value(ptr, scalar_ty))?
+            }
+            other => other,
+        };
+        let bits = match value {
+            Value::ByVal(PrimVal::Bytes(bits)) |
+            Value::ByValPair(PrimVal::Bytes(bits), _) => bits,
+            _ => return Ok(()),
+        };
+        match ty.sty {
+            ty::TyBool if bits > 1 => err!(InvalidBool(bits)),
+            ty::TyChar if ::std::char::from_u32(bits as u32).is_none() => err!(InvalidChar(bits)),
+            ty::TyRef(..) if bits == 0 => err!(NullReference),
+            ty::TyAdt(..) if bits == 0 => {
+                err!(ValidationFailure(format!("encountered 0 in a `{}`", ty)))
+            }
+            _ => Ok(()),
+        }
+    }
+
     fn validate_ptr(
         &mut self,
         val: Value,
@@ -377,6 +460,29 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
         )
     }
 
+    fn validate_non_zero(
+        &mut self,
+        lval: Lvalue,
+        ty: Ty<'tcx>,
+        adt: &'tcx ty::AdtDef,
+        subst: &'tcx Substs<'tcx>,
+    ) -> EvalResult<'tcx> {
+        let inner_ty = adt.struct_variant().fields[0].ty(self.tcx, subst);
+        let inner_lvalue = self.lvalue_field(lval, mir::Field::new(0), ty, inner_ty)?;
+        let val = self.read_lvalue(inner_lvalue)?;
+        let first = match self.follow_by_ref_value(val, inner_ty)? {
+            Value::ByVal(primval) | Value::ByValPair(primval, _) => primval,
+            Value::ByRef(..) => return Ok(()),
+        };
+        // Like for raw pointers, undefined data is not ruled out here.
+        match first {
+            PrimVal::Bytes(0) => {
+                err!(ValidationFailure(format!("encountered 0 in a `{}`", ty)))
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Validate the lvalue at the given type. If `acquire` is false, just do a release of all write locks
     fn validate(
         &mut self,
@@ -620,6 +726,10 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                         // No locks for shared unsafe cells.  Also no other validation, the only field is private anyway.
                         return Ok(());
                     }
+                    if Some(adt.did) == self.tcx.lang_items().non_zero() && mode.acquiring() {
+                        // This covers `Unique` and `Shared`, which wrap a `NonZero` pointer.
+                        self.validate_non_zero(query.lval.1, query.ty, adt, subst)?;
+                    }
 
                     match adt.adt_kind() {
                         AdtKind::Enum => {
@@ -633,7 +743,7 @@ impl<'a, 'tcx, M: Machine<'tcx>> EvalContext<'a, 'tcx, M> {
                             });
                             let variant_idx = match variant_idx {
                                 Some(val) => val,
-                                None => return err!(InvalidDiscriminant),
+                                None => return err!(InvalidDiscriminant(discr, query.ty)),
                             };
                             let variant = &adt.variants[variant_idx];
 
@@ -211,7 +211,7 @@ impl<'a, 'tcx: 'a> Value {
         }
     }
 
-    pub(super) fn into_slice<M: Machine<'tcx>>(
+    pub fn into_slice<M: Machine<'tcx>>(
         &self,
         mem: &Memory<'a, 'tcx, M>,
     ) -> EvalResult<'tcx, (Pointer, u64)> {
@@ -302,6 +302,24 @@ impl<'tcx> PrimVal {
         }
     }
 
+    /// Creates an integer of kind `kind` from `n`, wrapping it around if it does not fit.
+    pub fn from_i128_of_kind(n: i128, kind: PrimValKind) -> Self {
+        PrimVal::Bytes(kind.normalize(n as u128))
+    }
+
+    /// Reads an integer of kind `kind`, ignoring whatever is stored in the bits that do not
+    /// belong to it. Unlike `to_i128`, this gives the right result for signed kinds that were
+    /// not sign-extended and for unsigned kinds that were.
+    pub fn to_i128_of_kind(self, kind: PrimValKind) -> EvalResult<'tcx, i128> {
+        let bytes = self.to_bytes()?;
+        Ok(if kind.is_signed_int() {
+            kind.sign_extend(bytes)
+        } else {
+            // `U128` values above `i128::MAX` wrap around, like an `as` cast would.
+            kind.normalize(bytes) as i128
+        })
+    }
+
     pub fn to_u128(self) -> EvalResult<'tcx, u128> {
         self.to_bytes()
     }
@@ -343,7 +361,7 @@ impl<'tcx> PrimVal {
         match self.to_bytes()? {
             0 => Ok(false),
             1 => Ok(true),
-            _ => err!(InvalidBool),
+            bits => err!(InvalidBool(bits)),
         }
     }
 }
@@ -402,4 +420,115 @@ impl PrimValKind {
             _ => false,
         }
     }
+
+    /// The width of an integer kind in bits.
+    pub fn int_bits(self) -> u32 {
+        use self::PrimValKind::*;
+        match self {
+            I8 | U8 => 8,
+            I16 | U16 => 16,
+            I32 | U32 => 32,
+            I64 | U64 => 64,
+            I128 | U128 => 128,
+            _ => bug!("int_bits called on non-integer kind {:?}", self),
+        }
+    }
+
+    /// Interprets the raw bits of a signed integer of this kind as an `i128`. The upper bits of
+    /// `bytes` are not guaranteed to be sign-extended already, so we cannot just cast.
+    pub fn sign_extend(self, bytes: u128) -> i128 {
+        if !self.is_signed_int() {
+            bug!("sign_extend called on non-signed kind {:?}", self);
+        }
+        let shift = 128 - self.int_bits();
+        ((bytes << shift) as i128) >> shift
+    }
+
+    /// Drops the bits of `bytes` that do not fit into an integer of this kind and returns the
+    /// canonical representation of the rest: sign-extended for signed kinds and zero-extended for
+    /// unsigned ones.
+    pub fn normalize(self, bytes: u128) -> u128 {
+        if self.is_signed_int() {
+            self.sign_extend(bytes) as u128
+        } else {
+            let shift = 128 - self.int_bits();
+            (bytes << shift) >> shift
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::PrimValKind::*;
+
+    /// Values close to the boundaries of every integer width.
+    fn samples() -> Vec<i128> {
+        let mut samples = vec![0, 1, -1, i128::min_value(), i128::max_value()];
+        for &bits in &[8, 16, 32, 64] {
+            let max = (1i128 << (bits - 1)) - 1;
+            let umax = (1i128 << bits) - 1;
+            for &n in &[max, max + 1, -max - 1, -max - 2, umax, umax + 1] {
+                samples.push(n);
+                samples.push(n.wrapping_neg());
+            }
+        }
+        samples
+    }
+
+    /// What a native `as` cast to `kind` and back to `i128` gives.
+    fn native(n: i128, kind: PrimValKind) -> i128 {
+        match kind {
+            I8 => n as i8 as i128,
+            I16 => n as i16 as i128,
+            I32 => n as i32 as i128,
+            I64 => n as i64 as i128,
+            I128 => n,
+            U8 => n as u8 as i128,
+            U16 => n as u16 as i128,
+            U32 => n as u32 as i128,
+            U64 => n as u64 as i128,
+            U128 => n as u128 as i128,
+            _ => unreachable!(),
+        }
+    }
+
+    const INT_KINDS: &[PrimValKind] = &[I8, I16, I32, I64, I128, U8, U16, U32, U64, U128];
+
+    #[test]
+    fn round_trip_matches_native_casts() {
+        for &kind in INT_KINDS {
+            for n in samples() {
+                let val = PrimVal::from_i128_of_kind(n, kind);
+                let expected = native(n, kind);
+                assert_eq!(val.to_i128_of_kind(kind).unwrap(), expected, "{} as {:?}", n, kind);
+            }
+        }
+    }
+
+    #[test]
+    fn upper_bits_are_ignored() {
+        for &kind in INT_KINDS {
+            for n in samples() {
+                let bits = kind.int_bits();
+                let garbage = if bits == 128 { 0 } else { !0u128 << bits };
+                let canonical = PrimVal::from_i128_of_kind(n, kind).to_bytes().unwrap();
+                for &upper in &[0, garbage] {
+                    let val = PrimVal::Bytes((canonical & !garbage) | upper);
+                    assert_eq!(val.to_i128_of_kind(kind).unwrap(), native(n, kind));
+                    assert_eq!(kind.normalize(val.to_bytes().unwrap()), canonical);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn signed_values_are_sign_extended() {
+        for &kind in &[I8, I16, I32, I64, I128] {
+            for n in samples() {
+                let bytes = PrimVal::from_i128_of_kind(n, kind).to_bytes().unwrap();
+                assert_eq!(bytes as i128, native(n, kind));
+            }
+        }
+    }
 }
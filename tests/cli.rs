@@ -0,0 +1,426 @@
+//! Tests of what compiletest cannot check: the files miri writes, the environment variables it
+//! reads and its exit codes. Each test runs miri on a program in `tests/cli`.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+fn miri_path() -> PathBuf {
+    match option_env!("RUSTC_TEST_SUITE") {
+        Some(_) => PathBuf::from(option_env!("MIRI_PATH").unwrap()),
+        None => PathBuf::from(concat!("target/", env!("PROFILE"), "/miri")),
+    }
+}
+
+/// The command running miri on `tests/cli/<program>.rs`.
+fn miri_command(program: &str, args: &[&str], vars: &[(&str, &str)]) -> Command {
+    let mut cmd = Command::new(miri_path());
+    cmd.arg(format!("tests/cli/{}.rs", program)).args(args);
+    for &(key, value) in vars {
+        cmd.env(key, value);
+    }
+    cmd
+}
+
+/// Runs miri on `tests/cli/<program>.rs`.
+fn miri(program: &str, args: &[&str], vars: &[(&str, &str)]) -> Output {
+    miri_command(program, args, vars).output().expect("could not run miri")
+}
+
+/// A path for miri to write to, which no other test uses.
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("miri-cli-{}", name))
+}
+
+fn read(path: &Path) -> String {
+    let mut contents = String::new();
+    File::open(path).and_then(|mut f| f.read_to_string(&mut contents)).unwrap();
+    contents
+}
+
+/// The value of `"key":` in a flat JSON object, as written.
+fn field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    line.find(&format!("\"{}\":", key)).map(|pos| {
+        let value = &line[pos + key.len() + 3..];
+        &value[..value.find(|c| c == ',' || c == '}').unwrap_or(value.len())]
+    })
+}
+
+/// `text` with the ids of allocations replaced by `N`, they depend on everything miri allocated
+/// before.
+fn without_alloc_ids(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(pos) = rest.find("alloc") {
+        result.push_str(&rest[..pos + "alloc".len()]);
+        rest = &rest[pos + "alloc".len()..];
+        let digits = rest.find(|c: char| !c.is_digit(10)).unwrap_or(rest.len());
+        if digits > 0 {
+            result.push('N');
+        }
+        rest = &rest[digits..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// What miri prints running `tests/cli/<program>.rs`, which has to succeed.
+fn stdout(program: &str, args: &[&str]) -> Vec<u8> {
+    let output = miri(program, args, &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    output.stdout
+}
+
+#[test]
+fn event_stream() {
+    let path = temp_path("events");
+    let output = miri("events", &[], &[("MIRI_EVENTS", path.to_str().unwrap())]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let events = read(&path);
+    let lines: Vec<&str> = events.lines().collect();
+
+    let count = |event: &str| lines.iter().filter(|l| field(l, "event") == Some(event)).count();
+    assert!(count("\"push_frame\"") > 0);
+    assert_eq!(count("\"push_frame\""), count("\"pop_frame\""));
+    assert!(lines.iter().any(|l| field(l, "function").map_or(false, |f| f.contains("add"))));
+
+    // The box is allocated and freed again.
+    let boxed = lines
+        .iter()
+        .find(|l| field(l, "kind") == Some("\"Machine(Rust)\"") && field(l, "size") == Some("4"))
+        .expect("the box was not allocated");
+    let id = field(boxed, "id").unwrap();
+    assert!(lines.iter().any(|l| {
+        field(l, "event") == Some("\"deallocate\"") && field(l, "id") == Some(id)
+    }));
+    assert_eq!(count("\"diagnostic\""), 0);
+}
+
+#[test]
+fn core_dump() {
+    let path = temp_path("core_dump");
+    let output = miri("core_dump", &[], &[("MIRI_CORE_DUMP", path.to_str().unwrap())]);
+    assert!(!output.status.success());
+    let dump = read(&path);
+    assert!(dump.contains(r#""kind":"PointerOutOfBounds"#), "{}", dump);
+    assert!(dump.contains(r#""name":"bytes","type":"[u8; 4]""#), "{}", dump);
+    // The allocation the error is about is in the dump.
+    assert!(dump.contains(r#""bytes":"2a2a2a2a""#), "{}", dump);
+
+    let output = Command::new(miri_path())
+        .arg("--print-core-dump")
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let printed = String::from_utf8(output.stdout).unwrap();
+    assert!(printed.starts_with("error: "), "{}", printed);
+    assert!(printed.contains("\n      bytes: [u8; 4] = "), "{}", printed);
+    assert!(printed.contains("\n    0000: 2a 2a 2a 2a\n"), "{}", printed);
+}
+
+/// Sends a debug adapter protocol request.
+fn dap_request(stream: &mut TcpStream, seq: u32, command: &str, arguments: &str) {
+    let content = format!(
+        r#"{{"seq":{},"type":"request","command":"{}","arguments":{}}}"#,
+        seq,
+        command,
+        arguments
+    );
+    write!(stream, "Content-Length: {}\r\n\r\n{}", content.len(), content).unwrap();
+}
+
+/// Skips debug adapter protocol messages until one that contains `needle`.
+fn dap_message(reader: &mut BufReader<TcpStream>, needle: &str) -> String {
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).unwrap();
+        assert!(header.starts_with("Content-Length:"), "no message containing {}", needle);
+        let length = header["Content-Length:".len()..].trim().parse().unwrap();
+        reader.read_line(&mut String::new()).unwrap();
+        let mut content = vec![0; length];
+        reader.read_exact(&mut content).unwrap();
+        let content = String::from_utf8(content).unwrap();
+        if content.contains(needle) {
+            return content;
+        }
+    }
+}
+
+#[test]
+fn debug_adapter() {
+    let mut child = miri_command("dap", &[], &[("MIRI_DAP_PORT", "0")])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Miri announces the free port it got once it listens on it. The pipe has to stay open for
+    // the rest of the test, miri fails writing to a closed stderr.
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let announcement = "miri: waiting for a debug adapter client on port ";
+    let port = loop {
+        let mut line = String::new();
+        assert!(stderr.read_line(&mut line).unwrap() != 0, "miri does not listen");
+        if line.starts_with(announcement) {
+            break line[announcement.len()..].trim().parse::<u16>().unwrap();
+        }
+    };
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+    dap_request(&mut stream, 1, "initialize", "{}");
+    dap_message(&mut reader, r#""event":"initialized""#);
+    let breakpoints = r#"{"source":{"path":"tests/cli/dap.rs"},"breakpoints":[{"line":8}]}"#;
+    dap_request(&mut stream, 2, "setBreakpoints", breakpoints);
+    dap_request(&mut stream, 3, "configurationDone", "{}");
+    let stopped = dap_message(&mut reader, r#""event":"stopped""#);
+    assert!(stopped.contains(r#""reason":"breakpoint""#), "{}", stopped);
+
+    dap_request(&mut stream, 4, "variables", r#"{"variablesReference":1}"#);
+    let variables = dap_message(&mut reader, r#""command":"variables""#);
+    assert!(variables.contains(r#""name":"answer","value":"42""#), "{}", variables);
+    let variables = without_alloc_ids(&variables);
+    assert!(variables.contains(r#""name":"second","value":"Some(&allocN+4)""#), "{}", variables);
+
+    dap_request(&mut stream, 5, "miriAllocations", "{}");
+    let allocations = dap_message(&mut reader, r#""command":"miriAllocations""#);
+    assert!(allocations.contains(r#""kind":"Static","label":"ANSWER""#), "{}", allocations);
+
+    dap_request(&mut stream, 6, "continue", "{}");
+    let exited = dap_message(&mut reader, r#""event":"exited""#);
+    assert!(exited.contains(r#""exitCode":0"#), "{}", exited);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn heatmap_only_counts_writes_that_happen() {
+    // Whether the program succeeded, and the writes to the static of 13 bytes.
+    let static_writes = |program: &str| {
+        let path = temp_path(program);
+        let output = miri(program, &[], &[("MIRI_HEATMAP", path.to_str().unwrap())]);
+        let heatmap = read(&path);
+        let line = heatmap.lines().find(|l| l.contains(", 13 bytes)")).expect("no static");
+        (output.status.success(), line[line.rfind(", ").unwrap() + 2..].to_owned())
+    };
+    let (succeeded, writes) = static_writes("heatmap");
+    assert!(succeeded);
+    // The write to the immutable static fails.
+    assert_eq!(static_writes("heatmap_const_write"), (false, writes));
+}
+
+#[test]
+fn exit_codes() {
+    let output = miri("exit", &[], &[]);
+    assert_eq!(output.status.code(), Some(42));
+    assert!(output.stdout.is_empty());
+    // Like a process killed by `SIGABRT` in a shell.
+    assert_eq!(miri("abort", &[], &[]).status.code(), Some(134));
+    assert_eq!(miri("empty", &[], &[]).status.code(), Some(0));
+    // A `#[start]` function returns the exit code.
+    assert_eq!(miri("start_code", &[], &[]).status.code(), Some(3));
+}
+
+#[test]
+fn main_returning_err() {
+    let output = miri("main_result_err", &[], &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Error: \"odd\""));
+}
+
+#[test]
+fn suppressions() {
+    let generated = temp_path("suppressions");
+    let _ = fs::remove_file(&generated);
+    let vars = [("MIRI_GENERATE_SUPPRESSIONS", generated.to_str().unwrap())];
+    assert!(!miri("dangling", &[], &vars).status.success());
+    assert_eq!(read(&generated), "DanglingPointerDeref\tmain\ttests/cli/dangling.rs:5\n");
+
+    let suppressed = |suppressions: &Path| {
+        let vars = [("MIRI_SUPPRESSIONS", suppressions.to_str().unwrap())];
+        let output = miri("dangling", &[], &vars);
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        output.status.success() && stderr.contains("warning: suppressed: ")
+    };
+    assert!(suppressed(&generated));
+    let written = temp_path("suppressions_written");
+    let write = |contents: &str| {
+        File::create(&written).and_then(|mut f| f.write_all(contents.as_bytes())).unwrap();
+        suppressed(&written)
+    };
+    assert!(write("# Known\n\nDanglingPointerDeref\t*\n"));
+    assert!(!write("DanglingPointerDeref\tmain\ttests/cli/dangling.rs:4\n"));
+    assert!(!write("ReadUndefBytes\t*\t*\n"));
+}
+
+#[test]
+fn severities() {
+    // Whether the program succeeded, and what miri printed to stderr.
+    let run = |program: &str, severities: &str| {
+        let output = miri(program, &[], &[("MIRI_SEVERITY", severities)]);
+        (output.status.success(), String::from_utf8_lossy(&output.stderr).into_owned())
+    };
+    let (succeeded, stderr) = run("leak", "MemoryLeak=warn");
+    assert!(succeeded && stderr.contains("warning: the evaluated program leaked memory"));
+    let (succeeded, stderr) = run("leak", " MemoryLeak = allow ,");
+    assert!(succeeded && !stderr.contains("leaked"), "{}", stderr);
+    let (succeeded, stderr) = run("dangling", "DanglingPointerDeref=warn");
+    assert!(succeeded && stderr.contains("warning: "), "{}", stderr);
+    assert!(stderr.contains("(at tests/cli/dangling.rs:5)"), "{}", stderr);
+    assert!(!run("dangling", "MemoryLeak=warn").0);
+
+    let (succeeded, stderr) = run("empty", "MemoryLeak=loud");
+    assert!(!succeeded && stderr.contains("invalid severity override `MemoryLeak=loud`"));
+}
+
+#[test]
+fn ignore_leaks() {
+    assert!(!miri("leak", &[], &[]).status.success());
+    assert!(miri("leak", &["-Zmiri-ignore-leaks"], &[]).status.success());
+}
+
+#[test]
+fn disable_validation() {
+    let emit = "-Zmir-emit-validate=1";
+    assert!(!miri("aliasing_args", &[emit], &[]).status.success());
+    assert!(miri("aliasing_args", &[emit, "-Zmiri-disable-validation"], &[]).status.success());
+}
+
+#[test]
+fn miriflags() {
+    let vars = [("MIRIFLAGS", "-Zmiri-seed=1 -Zmiri-ignore-leaks")];
+    assert!(miri("leak", &[], &vars).status.success());
+
+    let output = miri("empty", &[], &[("MIRIFLAGS", "-Zmiri-seed=x")]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is not a valid seed"));
+}
+
+#[test]
+fn invalid_env_var() {
+    let output = miri("empty", &[], &[("MIRI_DAP_PORT", "x")]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("MIRI_DAP_PORT=x"));
+}
+
+#[test]
+fn capabilities() {
+    let output = Command::new(miri_path()).arg("--print-capabilities").output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let capabilities = String::from_utf8(output.stdout).unwrap();
+    assert!(capabilities.contains("\"transmute\""));
+    assert!(capabilities.contains("\"MIRIFLAGS\""));
+
+    // Every flag without a value that is listed is accepted.
+    let flags = &capabilities[capabilities.find("\"flags\":[").unwrap() + 9..];
+    let flags = &flags[..flags.find(']').unwrap()];
+    assert!(flags.contains("\"-Zmiri-seed=\""));
+    for flag in flags.split(',').map(|flag| flag.trim_matches('"')) {
+        if !flag.ends_with('=') {
+            let output = miri("empty", &[flag], &[]);
+            assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        }
+    }
+}
+
+#[test]
+fn json_error_report() {
+    let path = temp_path("json-events");
+    let vars = [("MIRI_EVENTS", path.to_str().unwrap())];
+    let output = miri("dangling", &["--error-format=json"], &vars);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let report = stderr
+        .lines()
+        .find(|l| field(l, "miri") == Some("\"error\""))
+        .expect("the error was not reported as JSON");
+    assert_eq!(field(report, "level"), Some("\"error\""));
+    assert_eq!(field(report, "kind"), Some("\"DanglingPointerDeref\""));
+    assert_eq!(field(report, "unsupported"), Some("false"));
+    // The same notes as the human-readable error.
+    assert!(report.contains("\"label\":\"the allocation was freed here\""));
+
+    let events = read(&path);
+    let diagnostics = events.lines().filter(|l| field(l, "event") == Some("\"diagnostic\""));
+    assert_eq!(diagnostics.count(), 1);
+}
+
+#[test]
+fn rendered_pointer_comparison() {
+    let output = miri("unorderable", &[], &[]);
+    assert!(!output.status.success());
+    let stderr = without_alloc_ids(&String::from_utf8_lossy(&output.stderr));
+    let msg = "pointers into different allocations: `allocN+1 < allocN`";
+    assert!(stderr.contains(msg), "{}", stderr);
+}
+
+#[test]
+fn printf() {
+    let expected = "plain\n-42 42 ff z\n[    7|ab  |-05]\n3.14 done%\n-1 123\n";
+    assert_eq!(String::from_utf8(stdout("printf", &[])).unwrap(), expected);
+}
+
+#[test]
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn random_bytes_only_depend_on_the_seed() {
+    let bytes = stdout("random", &["-Zmiri-seed=42"]);
+    assert_eq!(bytes.len(), 16);
+    assert_eq!(stdout("random", &["-Zmiri-seed=42"]), bytes);
+    assert!(stdout("random", &["-Zmiri-seed=43"]) != bytes);
+}
+
+#[test]
+fn padding_garbage_only_depends_on_the_seed() {
+    let padding = |seed: &str| stdout("padding", &["-Zmiri-undef-int-garbage", seed]);
+    let bytes = padding("-Zmiri-seed=42");
+    assert_eq!(padding("-Zmiri-seed=42"), bytes);
+    assert!(padding("-Zmiri-seed=43") != bytes);
+
+    // Without garbage, the padding cannot be read at all.
+    assert!(!miri("padding", &[], &[]).status.success());
+}
+
+#[test]
+fn addresses_only_depend_on_the_seed() {
+    let addresses = |seed: &str| stdout("addresses", &["-Zmiri-randomize-addresses", seed]);
+    let printed = addresses("-Zmiri-seed=42");
+    assert_eq!(addresses("-Zmiri-seed=42"), printed);
+    assert!(addresses("-Zmiri-seed=43") != printed);
+
+    // Without addresses, there is nothing to print.
+    assert!(!miri("addresses", &[], &[]).status.success());
+}
+
+/// The arguments giving miri a libstd with full MIR, like compiletest passes for the `-fullmir`
+/// tests.
+fn fullmir_args() -> Vec<String> {
+    // if we are building as part of the rustc test suite, we already have fullmir for everything
+    if option_env!("RUSTC_TEST_SUITE").is_some() {
+        return Vec::new();
+    }
+    let sysroot = env::home_dir().unwrap().join(".xargo").join("HOST");
+    vec!["--sysroot".to_owned(), sysroot.to_str().unwrap().to_owned()]
+}
+
+#[test]
+fn host_files() {
+    let fullmir = fullmir_args();
+    let mut args: Vec<&str> = fullmir.iter().map(|arg| &arg[..]).collect();
+    args.push("-Zmiri-disable-isolation");
+    let output = miri("files", &args, &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn skip_asm() {
+    // Without the flag, the assembly in `spin_hint` is not skipped.
+    assert!(!miri("skip_asm", &[], &[]).status.success());
+    stdout("skip_asm", &["-Zmiri-skip-asm=spin_hint"]);
+}
+
+#[test]
+fn aliasing_reborrow_loop() {
+    // Reborrowing a `&mut` over and over and borrowing disjoint fields is no aliasing violation.
+    stdout("aliasing_reborrow_loop", &["-Zmiri-check-aliasing"]);
+}
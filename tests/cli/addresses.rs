@@ -0,0 +1,10 @@
+fn main() {
+    let byte = 0u8;
+    let word = 0u64;
+    let boxed = Box::new([0u32; 4]);
+    println!("{:p} {:p} {:p}", &byte, &word, boxed.as_ptr());
+    // Whatever the addresses are, they are aligned.
+    assert_eq!(&word as *const u64 as usize % 8, 0);
+    assert_eq!(boxed.as_ptr() as usize % 4, 0);
+    assert!(&word as *const u64 as usize != 0);
+}
@@ -0,0 +1,8 @@
+#![allow(unused_variables)]
+
+fn safe(x: &mut i32, y: &mut i32) {}
+
+fn main() {
+    let x = &mut 0 as *mut _;
+    unsafe { safe(&mut *x, &mut *x) };
+}
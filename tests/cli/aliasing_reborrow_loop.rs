@@ -0,0 +1,30 @@
+fn bump(x: &mut u32) {
+    *x += 1;
+}
+
+fn main() {
+    let mut n = 0;
+    for _ in 0..100 {
+        let r = &mut n;
+        bump(r);
+        *r += 1;
+    }
+    assert_eq!(n, 200);
+
+    // Borrows of disjoint fields do not invalidate each other.
+    let mut pair = (0, 0);
+    let (a, b) = (&mut pair.0, &mut pair.1);
+    *a = 1;
+    *b = 2;
+    *a += 1;
+    assert_eq!(pair, (2, 2));
+
+    let mut boxes = Vec::new();
+    for i in 0..10 {
+        let mut b = Box::new(i);
+        let r = &mut *b;
+        *r += 1;
+        boxes.push(b);
+    }
+    assert_eq!(boxes.iter().map(|b| **b).sum::<i32>(), 55);
+}
@@ -0,0 +1,5 @@
+fn main() {
+    let bytes = [0x2au8; 4];
+    let ptr = bytes.as_ptr();
+    let _byte = unsafe { *ptr.offset(4) };
+}
@@ -0,0 +1,7 @@
+fn main() {
+    let ptr = Box::into_raw(Box::new(42u32));
+    unsafe {
+        drop(Box::from_raw(ptr));
+        let _x = *ptr;
+    }
+}
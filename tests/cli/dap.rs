@@ -0,0 +1,9 @@
+static ANSWER: i32 = 42;
+
+fn main() {
+    let answer = 6 * 7;
+    assert_eq!(answer, ANSWER);
+    let pair = [answer; 2];
+    let second = Some(&pair[1]);
+    let _copy = second;
+}
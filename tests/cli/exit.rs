@@ -0,0 +1,12 @@
+struct NotDropped;
+
+impl Drop for NotDropped {
+    fn drop(&mut self) {
+        println!("dropped");
+    }
+}
+
+fn main() {
+    let _not_dropped = NotDropped;
+    std::process::exit(42);
+}
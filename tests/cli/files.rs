@@ -0,0 +1,21 @@
+use std::fs::{self, File};
+use std::io::{Read, Write};
+
+fn main() {
+    let path = std::env::temp_dir().join("miri_test_files.txt");
+    let bytes = b"Hello, World!\n";
+    {
+        let mut file = File::create(&path).unwrap();
+        file.write_all(bytes).unwrap();
+    }
+
+    let mut file = File::open(&path).unwrap();
+    let metadata = file.metadata().unwrap();
+    assert!(metadata.is_file());
+    assert_eq!(metadata.len(), bytes.len() as u64);
+    assert_eq!(fs::metadata(&path).unwrap().len(), bytes.len() as u64);
+
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents).unwrap();
+    assert_eq!(contents, bytes);
+}
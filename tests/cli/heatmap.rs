@@ -0,0 +1,5 @@
+static DATA: [u8; 13] = [1; 13];
+
+fn main() {
+    assert_eq!(DATA.iter().map(|&byte| byte as u32).sum::<u32>(), 13);
+}
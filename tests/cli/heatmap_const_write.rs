@@ -0,0 +1,6 @@
+static DATA: [u8; 13] = [1; 13];
+
+fn main() {
+    assert_eq!(DATA.iter().map(|&byte| byte as u32).sum::<u32>(), 13);
+    unsafe { *(&DATA as *const [u8; 13] as *mut u8) = 0 };
+}
@@ -0,0 +1,3 @@
+fn main() {
+    std::mem::forget(Box::new(42));
+}
@@ -0,0 +1,15 @@
+#[repr(C)]
+struct Padded {
+    a: u8,
+    b: u32,
+}
+
+fn main() {
+    let bytes: [u8; 8] = unsafe { std::mem::transmute(Padded { a: 1, b: 2 }) };
+    // The fields keep their values, only the padding is garbage.
+    assert_eq!(bytes[0], 1);
+    if cfg!(target_endian = "little") {
+        assert_eq!(&bytes[4..], &[2, 0, 0, 0]);
+    }
+    println!("{:?}", &bytes[1..4]);
+}
@@ -0,0 +1,13 @@
+extern "C" {
+    fn printf(format: *const u8, ...) -> i32;
+}
+
+fn main() {
+    unsafe {
+        assert_eq!(printf(b"plain\n\0".as_ptr()), 6);
+        assert_eq!(printf(b"%d %u %x %c\n\0".as_ptr(), -42i32, 42u32, 255u32, b'z' as i32), 12);
+        assert_eq!(printf(b"[%5d|%-4s|%03d]\n\0".as_ptr(), 7i32, b"ab\0".as_ptr(), -5i32), 17);
+        assert_eq!(printf(b"%.2f %s%%\n\0".as_ptr(), 3.14159f64, b"done\0".as_ptr()), 11);
+        assert_eq!(printf(b"%ld %zu\n\0".as_ptr(), -1i64, 123usize), 7);
+    }
+}
@@ -0,0 +1,14 @@
+extern "C" {
+    fn syscall(number: i64, ...) -> i64;
+    fn write(fd: i32, buf: *const u8, count: usize) -> isize;
+}
+
+const SYS_GETRANDOM: i64 = 318;
+
+fn main() {
+    let mut buf = [0u8; 16];
+    unsafe {
+        assert_eq!(syscall(SYS_GETRANDOM, buf.as_mut_ptr(), buf.len(), 0), 16);
+        assert_eq!(write(1, buf.as_ptr(), buf.len()), 16);
+    }
+}
@@ -0,0 +1,18 @@
+// Only the assembly in `spin_hint` is skipped because of the flag, empty `asm!`s and `nop`s
+// always are.
+
+#![feature(asm)]
+
+fn spin_hint() {
+    unsafe { asm!("pause" :::: "volatile") };
+}
+
+fn fences() {
+    unsafe { asm!("" ::: "memory" : "volatile") };
+    unsafe { asm!("nop") };
+}
+
+fn main() {
+    spin_hint();
+    fences();
+}
@@ -0,0 +1,9 @@
+fn main() {
+    let pair = [1u8, 2];
+    let single = 3u8;
+    let x: *const u8 = &pair[1];
+    let y: *const u8 = &single;
+    if x < y {
+        unreachable!()
+    }
+}
@@ -0,0 +1,15 @@
+// error-pattern: the evaluated program panicked
+
+struct Noisy;
+
+impl Drop for Noisy {
+    fn drop(&mut self) {
+        println!("never printed, the panic is reported before unwinding");
+    }
+}
+
+fn main() {
+    let _noisy = Noisy;
+    let v: Vec<u32> = Vec::new();
+    v[3];
+}
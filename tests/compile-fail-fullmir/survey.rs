@@ -0,0 +1,15 @@
+// compile-flags: -Zmiri-survey
+use std::panic;
+
+extern "C" {
+    fn miri_no_such_function();
+}
+
+fn main() {
+    panic::set_hook(Box::new(|_| {}));
+    // Caught, so the survey goes on after the unsupported call.
+    let result = panic::catch_unwind(|| unsafe { miri_no_such_function() });
+    assert!(result.unwrap_err().is::<()>());
+    // Nothing catches this one.
+    unsafe { miri_no_such_function() } //~ ERROR can't call C ABI function: miri_no_such_function
+}
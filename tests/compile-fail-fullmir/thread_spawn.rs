@@ -0,0 +1,7 @@
+// error-pattern: miri does not support spawning threads
+
+use std::thread;
+
+fn main() {
+    thread::spawn(|| {}).join().unwrap();
+}
@@ -0,0 +1,5 @@
+//error-pattern: the evaluated program aborted
+
+fn main() {
+    std::process::abort();
+}
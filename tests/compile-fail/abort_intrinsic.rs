@@ -0,0 +1,5 @@
+#![feature(core_intrinsics)]
+
+fn main() {
+    unsafe { std::intrinsics::abort() } //~ ERROR the evaluated program aborted
+}
@@ -0,0 +1,12 @@
+// Validation locks would reject the aliased accesses before the aliasing check sees them
+// compile-flags: -Zmiri-check-aliasing -Zmir-emit-validate=0
+
+fn main() {
+    let mut x = 0i32;
+    let ptr = &mut x as *mut i32;
+    unsafe {
+        let r = &mut *ptr;
+        *ptr = 1;
+        *r = 2; //~ ERROR a `&mut` was used after the memory it points to was accessed through another path
+    }
+}
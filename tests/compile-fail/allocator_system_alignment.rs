@@ -0,0 +1,19 @@
+// compile-flags: -Zmiri-allocator=system
+#![feature(alloc, allocator_api)]
+
+extern crate alloc;
+
+use alloc::heap::Heap;
+use alloc::allocator::*;
+
+fn main() {
+    unsafe {
+        let x = Heap.alloc(Layout::from_size_align_unchecked(8, 1)).unwrap();
+        // Like `malloc`, the system allocator aligns everything to at least twice the pointer size.
+        *(x as *mut u64) = 42;
+        assert_eq!(*(x as *mut u64), 42);
+        // The program still has to pass the alignment it asked for.
+        Heap.dealloc(x, Layout::from_size_align_unchecked(8, 8));
+        //~^ ERROR tried to deallocate or reallocate using incorrect alignment or size
+    }
+}
@@ -0,0 +1,10 @@
+// compile-flags: -Zmiri-allocator=zeroing
+
+fn main() {
+    let v: Vec<u32> = Vec::with_capacity(2);
+    // The zeroing allocator makes the spare capacity readable...
+    let spare = unsafe { *v.as_ptr().offset(1) };
+    assert_eq!(spare, 0);
+    // ...but not the memory behind it.
+    let _x = unsafe { *v.as_ptr().wrapping_offset(2) }; //~ ERROR: memory access at offset 12, outside bounds of allocation
+}
@@ -0,0 +1,11 @@
+// error-pattern: tried to deallocate dangling pointer
+
+use std::ptr;
+
+fn main() {
+    let b: Box<[u8]> = Box::new([1, 2, 3]);
+    unsafe {
+        drop(ptr::read(&b));
+    }
+    drop(b);
+}
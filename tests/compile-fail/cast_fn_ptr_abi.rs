@@ -0,0 +1,9 @@
+extern "C" fn f() {}
+
+fn main() {
+    let g = unsafe {
+        std::mem::transmute::<extern "C" fn(), fn()>(f)
+    };
+
+    g() //~ ERROR tried to call a function with sig extern "C" fn() through a function pointer of type fn()
+}
@@ -0,0 +1,10 @@
+fn main() {
+    fn f(_: *const u8) {}
+
+    let g = unsafe {
+        std::mem::transmute::<fn(*const u8), fn(*const [u8])>(f)
+    };
+
+    let bytes: &[u8] = &[1, 2, 3];
+    g(bytes) //~ ERROR tried to call a function with sig fn(*const u8) through a function pointer of type fn(*const [u8])
+}
@@ -0,0 +1,17 @@
+// compile-flags: -Zmiri-check-reachable
+// error-pattern: can't call C ABI function: miri_no_such_function
+
+extern "C" {
+    fn miri_no_such_function();
+}
+
+// Never called with `true`, but the call is reachable without running the program.
+fn maybe_call(call: bool) {
+    if call {
+        unsafe { miri_no_such_function() };
+    }
+}
+
+fn main() {
+    maybe_call(false);
+}
@@ -0,0 +1,11 @@
+// compile-flags: -Zmiri-check-reachable
+// error-pattern: miri does not support program arguments
+
+// Without the MIR of libstd, miri knows it cannot stand in for `std::env::args`.
+fn maybe_count_args(count: bool) -> usize {
+    if count { std::env::args().count() } else { 0 }
+}
+
+fn main() {
+    maybe_count_args(false);
+}
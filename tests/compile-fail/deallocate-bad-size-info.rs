@@ -0,0 +1,15 @@
+#![feature(alloc, allocator_api)]
+
+extern crate alloc;
+
+use alloc::heap::Heap;
+use alloc::allocator::*;
+
+// error-pattern: size 4 and alignment 2 were given, but the allocation has size 2 and alignment 1
+
+fn main() {
+    unsafe {
+        let x = Heap.alloc(Layout::from_size_align_unchecked(2, 1)).unwrap();
+        Heap.dealloc(x, Layout::from_size_align_unchecked(4, 2));
+    }
+}
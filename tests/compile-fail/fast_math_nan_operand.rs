@@ -0,0 +1,11 @@
+#![feature(core_intrinsics)]
+
+use std::intrinsics::*;
+
+//error-pattern: fadd_fast called with a non-finite operand
+
+fn main() {
+    unsafe {
+        let _x: f32 = fadd_fast(::std::f32::NAN, 1.0);
+    }
+}
@@ -0,0 +1,11 @@
+#![feature(core_intrinsics)]
+
+use std::intrinsics::*;
+
+//error-pattern: fmul_fast produced a non-finite result
+
+fn main() {
+    unsafe {
+        let _x: f64 = fmul_fast(1.0e300, 1.0e300);
+    }
+}
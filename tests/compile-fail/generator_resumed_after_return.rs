@@ -0,0 +1,14 @@
+#![feature(generators, generator_trait)]
+
+// error-pattern: generator resumed after completion
+
+use std::ops::Generator;
+
+fn main() {
+    let mut gen = || {
+        yield 1;
+    };
+    gen.resume();
+    gen.resume();
+    gen.resume();
+}
@@ -0,0 +1,21 @@
+// The heap usage hooks only exist in miri, so their results are checked before the test ends
+// with a failing miri assertion.
+
+extern "C" {
+    fn miri_live_heap_bytes() -> usize;
+    fn miri_peak_heap_bytes() -> usize;
+    fn miri_assert_freed(ptr: *const u8);
+}
+
+fn main() {
+    unsafe {
+        let base = miri_live_heap_bytes();
+        let a = Box::new([0u8; 16]);
+        let b = Box::new(0u64);
+        assert_eq!(miri_live_heap_bytes(), base + 24);
+        drop(a);
+        assert_eq!(miri_live_heap_bytes(), base + 8);
+        assert_eq!(miri_peak_heap_bytes(), base + 24);
+        miri_assert_freed(&*b as *const u64 as *const u8); //~ ERROR miri assertion failed
+    }
+}
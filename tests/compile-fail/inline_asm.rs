@@ -0,0 +1,11 @@
+#![feature(asm)]
+
+fn stack_pointer() -> usize {
+    let sp: usize;
+    unsafe { asm!("mov %rsp, $0" : "=r"(sp)) }; //~ ERROR miri does not support inline assembly
+    sp
+}
+
+fn main() {
+    stack_pointer();
+}
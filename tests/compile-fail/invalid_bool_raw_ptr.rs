@@ -0,0 +1,6 @@
+fn main() {
+    let x = 2u8;
+    let ptr = &x as *const u8 as *const bool;
+    let b = unsafe { *ptr }; //~ ERROR: invalid boolean value read
+    if b { unreachable!() } else { unreachable!() }
+}
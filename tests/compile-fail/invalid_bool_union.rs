@@ -0,0 +1,11 @@
+#![feature(untagged_unions)]
+
+union U {
+    n: u8,
+    b: bool,
+}
+
+fn main() {
+    let u = U { n: 2 };
+    let _b = unsafe { u.b }; //~ ERROR invalid boolean value read
+}
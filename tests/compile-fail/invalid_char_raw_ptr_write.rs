@@ -0,0 +1,8 @@
+fn main() {
+    let mut c = 'a';
+    let n = 0xD800u32;
+    let ptr = &mut c as *mut char;
+    unsafe {
+        *ptr = *(&n as *const u32 as *const char); //~ ERROR tried to interpret an invalid 32-bit value as a char: 55296
+    }
+}
@@ -9,7 +9,7 @@ pub enum Foo {
 fn main() {
     let f = unsafe { std::mem::transmute::<i32, Foo>(42) };
     match f {
-        Foo::A => {}, //~ ERROR invalid enum discriminant value read
+        Foo::A => {}, //~ ERROR invalid enum discriminant value read: 42 (expected a discriminant of `Foo`)
         Foo::B => {},
         Foo::C => {},
         Foo::D => {},
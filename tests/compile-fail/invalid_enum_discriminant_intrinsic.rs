@@ -0,0 +1,17 @@
+// Validation makes this fail in the wrong place
+// compile-flags: -Zmir-emit-validate=0
+
+use std::mem;
+
+#[repr(u8)]
+enum Sign {
+    Minus = 1,
+    Plus = 2,
+}
+
+fn main() {
+    let signs = [Sign::Minus, Sign::Plus];
+    assert!(mem::discriminant(&signs[0]) != mem::discriminant(&signs[1]));
+    let sign: Sign = unsafe { mem::transmute(7u8) };
+    mem::discriminant(&sign); //~ ERROR invalid enum discriminant value read: 7
+}
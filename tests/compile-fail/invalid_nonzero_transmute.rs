@@ -0,0 +1,9 @@
+#![feature(nonzero)]
+
+extern crate core;
+
+use core::nonzero::NonZero;
+
+fn main() {
+    let _x = unsafe { std::mem::transmute::<usize, NonZero<usize>>(0) }; //~ ERROR encountered 0 in a
+}
@@ -0,0 +1,26 @@
+use std::mem;
+
+union MaybeUninit<T: Copy> {
+    uninit: (),
+    value: T,
+}
+
+impl<T: Copy> MaybeUninit<T> {
+    fn as_mut_ptr(&mut self) -> *mut T {
+        unsafe { &mut self.value as *mut T }
+    }
+
+    unsafe fn assume_init(self) -> T {
+        self.value
+    }
+}
+
+fn main() { //~ NOTE inside call to
+    let mut buf: MaybeUninit<[u8; 4]> = unsafe { mem::uninitialized() }; //~ NOTE uninitialized memory was created here
+    unsafe { *(buf.as_mut_ptr() as *mut u8) = 1 };
+    // Only the first byte is initialized.
+    let buf = unsafe { buf.assume_init() }; //~ NOTE the uninitialized value left its union here
+    if buf[3] == 0 { //~ ERROR attempted to read undefined bytes
+        println!("zero");
+    }
+}
@@ -0,0 +1,17 @@
+//ignore-windows
+
+#![feature(libc)]
+extern crate libc;
+
+//error-pattern: copy_nonoverlapping called on overlapping ranges
+
+fn main() {
+    let mut buf = [1u8, 2, 3, 4, 5, 6];
+    unsafe {
+        libc::memcpy(
+            buf.as_mut_ptr().offset(2) as *mut libc::c_void,
+            buf.as_ptr() as *const libc::c_void,
+            4,
+        );
+    }
+}
@@ -0,0 +1,9 @@
+extern "C" {
+    fn malloc(size: usize) -> *mut u8;
+}
+
+fn main() { //~ ERROR the evaluated program leaked memory
+    unsafe {
+        malloc(4); //~ NOTE the allocation of 4 bytes was created here
+    }
+}
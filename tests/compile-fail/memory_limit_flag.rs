@@ -0,0 +1,6 @@
+// compile-flags: -Zmiri-memory-limit=4095
+
+fn main() {
+    let _x = [42; 1024];
+    //~^ERROR tried to allocate 4096 more bytes, but only
+}
@@ -0,0 +1,13 @@
+use std::mem;
+
+extern "C" {
+    fn miri_assert_defined(ptr: *const u8, len: usize);
+}
+
+fn main() {
+    unsafe {
+        let mut pair: (u8, u8) = mem::uninitialized();
+        pair.0 = 1;
+        miri_assert_defined(&pair as *const _ as *const u8, 2); //~ ERROR miri assertion failed
+    }
+}
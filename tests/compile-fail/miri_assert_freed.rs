@@ -0,0 +1,31 @@
+// The hooks only exist in miri, so the assertions that hold are tested here, too, before the
+// last one fails.
+
+use std::mem;
+
+extern "C" {
+    fn miri_assert_defined(ptr: *const u8, len: usize);
+    fn miri_assert_undefined(ptr: *const u8, len: usize);
+    fn miri_assert_live(ptr: *const u8);
+    fn miri_assert_freed(ptr: *const u8);
+}
+
+fn main() {
+    unsafe {
+        let mut buf: [u8; 4] = mem::uninitialized();
+        miri_assert_undefined(buf.as_ptr(), 4);
+        buf[1] = 7;
+        miri_assert_defined(buf.as_ptr().offset(1), 1);
+        miri_assert_undefined(buf.as_ptr().offset(2), 2);
+
+        let b = Box::new(5u32);
+        let ptr = &*b as *const u32 as *const u8;
+        miri_assert_live(ptr);
+        miri_assert_defined(ptr, 4);
+        drop(b);
+        miri_assert_freed(ptr);
+
+        let b = Box::new(6u32);
+        miri_assert_freed(&*b as *const u32 as *const u8); //~ ERROR miri assertion failed
+    }
+}
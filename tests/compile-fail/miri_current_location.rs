@@ -0,0 +1,29 @@
+// The hooks only exist in miri, so the strings they return are checked before the test ends
+// with a failing miri assertion.
+
+use std::slice;
+
+extern "C" {
+    fn miri_current_function(len: *mut usize) -> *const u8;
+    fn miri_current_location(len: *mut usize) -> *const u8;
+    fn miri_assert_freed(ptr: *const u8);
+}
+
+fn checkpoint() -> (&'static [u8], &'static [u8]) {
+    unsafe {
+        let mut len = 0;
+        let function = miri_current_function(&mut len);
+        let function = slice::from_raw_parts(function, len);
+        let location = miri_current_location(&mut len);
+        let location = slice::from_raw_parts(location, len);
+        (function, location)
+    }
+}
+
+fn main() {
+    let (function, location) = checkpoint();
+    assert!(function.ends_with(b"checkpoint"));
+    assert!(location.ends_with(b"miri_current_location.rs:17:24"));
+    let b = Box::new(0u8);
+    unsafe { miri_assert_freed(&*b) }; //~ ERROR miri assertion failed
+}
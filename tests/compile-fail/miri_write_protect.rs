@@ -0,0 +1,26 @@
+extern "C" {
+    fn miri_write_protect(ptr: *const u8);
+    fn miri_write_unprotect(ptr: *const u8);
+}
+
+fn sum(buf: &[u8]) -> u32 {
+    buf.iter().map(|&b| b as u32).sum()
+}
+
+unsafe fn sneaky_first(ptr: *const u8) -> u8 {
+    let first = *ptr;
+    *(ptr as *mut u8) = 0; //~ ERROR: tried to modify constant memory
+    first
+}
+
+fn main() {
+    let mut buf = vec![1u8, 2, 3];
+    unsafe {
+        miri_write_protect(buf.as_ptr());
+        assert_eq!(sum(&buf), 6);
+        miri_write_unprotect(buf.as_ptr());
+    }
+    buf[0] = 4;
+    unsafe { miri_write_protect(buf.as_ptr()) };
+    unsafe { sneaky_first(buf.as_ptr()) };
+}
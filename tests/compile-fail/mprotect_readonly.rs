@@ -0,0 +1,22 @@
+//ignore-windows
+
+#![feature(libc)]
+extern crate libc;
+
+use std::ptr;
+
+fn main() {
+    unsafe {
+        let p = libc::mmap(
+            ptr::null_mut(),
+            4096,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANON,
+            -1,
+            0,
+        ) as *mut u8;
+        *p = 1;
+        libc::mprotect(p as *mut libc::c_void, 4096, libc::PROT_READ);
+        *p = 2; //~ ERROR: tried to modify constant memory
+    }
+}
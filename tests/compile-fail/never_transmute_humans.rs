@@ -9,7 +9,7 @@ struct Human;
 
 fn main() {
     let x: ! = unsafe {
-        std::mem::transmute::<Human, !>(Human) //~ ERROR entered unreachable code
+        std::mem::transmute::<Human, !>(Human) //~ ERROR encountered a value of the uninhabited type !
     };
     f(x)
 }
@@ -8,12 +8,12 @@
 enum Void {}
 
 fn f(v: Void) -> ! {
-    match v {} //~ ERROR entered unreachable code
+    match v {}
 }
 
 fn main() {
     let v: Void = unsafe {
-        std::mem::transmute::<(), Void>(())
+        std::mem::transmute::<(), Void>(()) //~ ERROR encountered a value of the uninhabited type Void
     };
     f(v);
 }
@@ -0,0 +1,4 @@
+fn main() {
+    let _dir = std::env::current_dir();
+    //~^ ERROR no mir for `std::env::current_dir`, crate `std` has to be built with
+}
@@ -0,0 +1,3 @@
+fn main() {
+    let _x = unsafe { std::mem::transmute::<usize, &i32>(0) }; //~ ERROR encountered a null reference
+}
@@ -0,0 +1,12 @@
+//ignore-windows
+
+#![feature(libc)]
+extern crate libc;
+
+//error-pattern: requires -Zmiri-disable-isolation
+
+fn main() {
+    unsafe {
+        libc::open(b"Cargo.toml\0".as_ptr() as *const libc::c_char, libc::O_RDONLY);
+    }
+}
@@ -0,0 +1,10 @@
+extern "C" {
+    fn malloc(size: usize) -> *mut u8;
+}
+
+fn main() { //~ NOTE inside call to
+    unsafe {
+        let ptr = malloc(2); //~ NOTE the allocation was created here
+        let _x = *ptr.wrapping_offset(5); //~ ERROR which has size 2
+    }
+}
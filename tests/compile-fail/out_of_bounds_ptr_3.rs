@@ -0,0 +1,10 @@
+// error-pattern: pointer computed at offset 10, outside bounds of allocation
+fn main() {
+    let v = [0i8; 4];
+    let x = &v as *const i8;
+    // Going out of bounds with `wrapping_offset` is fine, but `offset` requires the pointer it
+    // starts from to be in bounds as well.
+    let x = x.wrapping_offset(10);
+    let x = unsafe { x.offset(-8) };
+    panic!("this should never print: {:?}", x);
+}
@@ -0,0 +1,11 @@
+#![feature(custom_attribute, attr_literals)]
+#![miri(overflow_checks = true)]
+// compile-flags: -C overflow-checks=off
+
+fn add(a: u8, b: u8) -> u8 {
+    a + b //~ ERROR: attempted to do overflowing math
+}
+
+fn main() {
+    add(255, 1);
+}
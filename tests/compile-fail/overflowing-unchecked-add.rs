@@ -0,0 +1,11 @@
+#![feature(core_intrinsics)]
+
+use std::intrinsics::*;
+
+//error-pattern: Overflowing arithmetic in unchecked_add
+
+fn main() {
+    unsafe {
+        let _n = unchecked_add(u8::max_value(), 1);
+    }
+}
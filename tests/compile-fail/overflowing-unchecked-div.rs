@@ -0,0 +1,11 @@
+#![feature(core_intrinsics)]
+
+use std::intrinsics::*;
+
+//error-pattern: Overflowing division in unchecked_div
+
+fn main() {
+    unsafe {
+        let _n = unchecked_div(i32::min_value(), -1);
+    }
+}
@@ -0,0 +1,11 @@
+#![feature(core_intrinsics)]
+
+use std::intrinsics::*;
+
+//error-pattern: Overflowing arithmetic in unchecked_mul
+
+fn main() {
+    unsafe {
+        let _n = unchecked_mul(u64::max_value(), 2);
+    }
+}
@@ -0,0 +1,11 @@
+#![feature(core_intrinsics)]
+
+use std::intrinsics::*;
+
+//error-pattern: Overflowing arithmetic in unchecked_sub
+
+fn main() {
+    unsafe {
+        let _n = unchecked_sub(i32::min_value(), 1);
+    }
+}
@@ -0,0 +1,7 @@
+//error-pattern: the evaluated program panicked
+
+fn main() {
+    if 1 + 1 == 2 {
+        panic!("literal panic message");
+    }
+}
@@ -1,7 +1,8 @@
 fn main() {
     let x: *const u8 = &1;
     let y: *const u8 = &2;
-    if x < y { //~ ERROR: attempted to do invalid arithmetic on pointers
+    if x < y {
+    //~^ ERROR: attempted to do invalid arithmetic on pointers into different allocations: `alloc
         unreachable!()
     }
 }
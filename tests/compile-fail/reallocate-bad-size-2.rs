@@ -0,0 +1,16 @@
+#![feature(alloc, allocator_api)]
+
+extern crate alloc;
+
+use alloc::heap::Heap;
+use alloc::allocator::*;
+
+// error-pattern: tried to deallocate or reallocate using incorrect alignment or size
+
+fn main() {
+    unsafe {
+        let x = Heap.alloc(Layout::from_size_align_unchecked(1, 1)).unwrap();
+        // Claim the allocation is bigger than it is.
+        let _y = Heap.realloc(x, Layout::from_size_align_unchecked(4, 1), Layout::from_size_align_unchecked(8, 1)).unwrap();
+    }
+}
@@ -0,0 +1,12 @@
+// Validation would reject the transmuted reference before it is passed
+// compile-flags: -Zmiri-check-references -Zmir-emit-validate=0
+
+use std::mem;
+
+fn inspect(_x: &i32) {}
+
+fn main() {
+    // No reference is created, but the transmuted one is still checked when it is passed.
+    let dangling: &i32 = unsafe { mem::transmute(8usize) };
+    inspect(dangling); //~ ERROR encountered a dangling reference
+}
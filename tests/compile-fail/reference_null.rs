@@ -0,0 +1,6 @@
+// compile-flags: -Zmiri-check-references
+
+fn main() {
+    let ptr = 0 as *const i32;
+    let _x: &i32 = unsafe { &*ptr }; //~ ERROR encountered a null reference
+}
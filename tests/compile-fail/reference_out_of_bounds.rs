@@ -0,0 +1,7 @@
+// compile-flags: -Zmiri-check-references
+
+fn main() {
+    let x = 0u8;
+    let ptr = &x as *const u8 as *const u32;
+    let _y = unsafe { &*ptr }; //~ ERROR encountered a reference to 4 bytes at offset 0, outside bounds
+}
@@ -0,0 +1,7 @@
+// compile-flags: -Zmiri-check-references
+
+fn main() {
+    let x = [0u32; 2];
+    let ptr = (&x[0] as *const u32 as *const u8).wrapping_offset(1) as *const u32;
+    let _y = unsafe { &*ptr }; //~ ERROR encountered a reference with alignment 1, but alignment 4 is required
+}
@@ -0,0 +1,10 @@
+fn local_ptr() -> *const i32 {
+    let x = 42;
+    &x
+}
+
+fn main() {
+    let p = local_ptr();
+    let x = unsafe { *p }; //~ ERROR: dangling pointer to a local of `local_ptr` was dereferenced after its frame was popped
+    panic!("this should never print: {}", x);
+}
@@ -0,0 +1,15 @@
+// compile-flags: -Zmiri-stack-limit=8
+
+// The flag overrides the limit given by the attribute.
+#![feature(custom_attribute, attr_literals)]
+#![miri(stack_limit=1000)]
+
+//error-pattern: reached the configured maximum number of stack frames
+
+fn recurse(n: u64) -> u64 {
+    if n == 0 { 0 } else { recurse(n - 1) + 1 }
+}
+
+fn main() {
+    assert_eq!(recurse(20), 20);
+}
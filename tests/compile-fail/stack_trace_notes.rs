@@ -0,0 +1,20 @@
+extern "C" {
+    fn malloc(size: usize) -> *mut u8;
+    fn free(ptr: *mut u8);
+}
+
+fn read(ptr: *const u8) -> u8 {
+    unsafe { *ptr } //~ ERROR dangling pointer was dereferenced
+}
+
+fn read_through(ptr: *const u8) -> u8 {
+    read(ptr) //~ NOTE inside call to read
+}
+
+fn main() { //~ NOTE inside call to main
+    let ptr = unsafe { malloc(1) }; //~ NOTE the allocation was created here
+    unsafe { free(ptr) }; //~ NOTE the allocation was freed here
+    // Every frame gets a note at the call that created it.
+    let apply = |f: fn(*const u8) -> u8| f(ptr); //~ NOTE inside call to read_through
+    apply(read_through); //~ NOTE inside call to closure
+}
@@ -0,0 +1,16 @@
+static X: usize = 5;
+
+fn read() -> usize {
+    X
+}
+
+fn write(val: usize) {
+    unsafe {
+        *(&X as *const usize as *mut usize) = val; //~ ERROR: tried to modify constant memory
+    }
+}
+
+fn main() {
+    assert_eq!(read(), 5);
+    write(6);
+}
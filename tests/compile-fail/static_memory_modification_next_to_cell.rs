@@ -0,0 +1,15 @@
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+struct Counter {
+    hits: AtomicUsize,
+    limit: usize,
+}
+
+static COUNTER: Counter = Counter { hits: ATOMIC_USIZE_INIT, limit: 10 };
+
+fn main() {
+    COUNTER.hits.fetch_add(1, Ordering::SeqCst);
+    unsafe {
+        *(&COUNTER.limit as *const usize as *mut usize) = 20; //~ ERROR: tried to modify constant memory
+    }
+}
@@ -0,0 +1,11 @@
+//error-pattern: reached the configured maximum execution time
+#![feature(custom_attribute, attr_literals)]
+
+#[miri(step_limit=1000)]
+fn endless() {
+    loop {}
+}
+
+fn main() {
+    endless();
+}
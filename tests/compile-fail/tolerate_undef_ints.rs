@@ -0,0 +1,14 @@
+// compile-flags: -Zmir-emit-validate=0 -Zmiri-tolerate-undef-ints
+
+use std::mem;
+
+fn main() {
+    let undef: u32 = unsafe { mem::uninitialized() };
+    let x = undef + 1; //~ WARN read an undefined value of type `u32`, using 0 instead
+    assert_eq!(x, 1);
+    // Only integers are tolerated.
+    let b: bool = unsafe { mem::uninitialized() };
+    if b { //~ ERROR: attempted to read undefined bytes
+        panic!("this should never print");
+    }
+}
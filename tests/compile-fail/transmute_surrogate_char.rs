@@ -0,0 +1,4 @@
+fn main() {
+    let c = unsafe { std::mem::transmute::<u32, char>(0xD800) }; //~ ERROR tried to interpret an invalid 32-bit value as a char: 55296
+    let _x = c;
+}
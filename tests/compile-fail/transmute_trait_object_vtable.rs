@@ -0,0 +1,19 @@
+trait Foo {
+    fn foo(&self, x: i32);
+}
+
+trait Bar {
+    fn bar(&self, x: f64);
+}
+
+struct S;
+
+impl Foo for S {
+    fn foo(&self, _x: i32) {}
+}
+
+fn main() {
+    let foo: &Foo = &S;
+    let bar = unsafe { std::mem::transmute::<&Foo, &Bar>(foo) };
+    bar.bar(1.0); //~ ERROR tried to call a function with sig
+}
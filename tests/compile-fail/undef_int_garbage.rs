@@ -0,0 +1,15 @@
+// compile-flags: -Zmir-emit-validate=0 -Zmiri-undef-int-garbage -Zmiri-seed=3
+
+use std::mem;
+
+fn main() {
+    let a: u64 = unsafe { mem::uninitialized() };
+    let b: u64 = unsafe { mem::uninitialized() };
+    // Unlike with -Zmiri-tolerate-undef-ints, the values are not all 0.
+    let different = a != b; //~ WARN read an undefined value of type `u64`, using garbage instead
+    assert!(different);
+    let c: bool = unsafe { mem::uninitialized() };
+    if c { //~ ERROR: attempted to read undefined bytes
+        panic!("this should never print");
+    }
+}
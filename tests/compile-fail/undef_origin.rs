@@ -0,0 +1,8 @@
+use std::mem;
+
+fn main() { //~ NOTE inside call to
+    let buf: [u8; 8] = unsafe { mem::uninitialized() }; //~ NOTE uninitialized memory was created here
+    if buf[3] == 0 { //~ ERROR attempted to read undefined bytes
+        println!("zero");
+    }
+}
@@ -0,0 +1,8 @@
+#[derive(Clone, Copy)]
+enum Void {}
+
+fn main() {
+    let x = 0u8;
+    let ptr = &x as *const u8 as *const (u8, Void);
+    let _y = unsafe { *ptr }; //~ ERROR encountered a value of the uninhabited type (u8, Void)
+}
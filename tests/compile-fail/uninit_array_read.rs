@@ -0,0 +1,10 @@
+use std::mem;
+
+fn main() {
+    let mut buf: [u8; 8] = unsafe { mem::uninitialized() };
+    buf[0] = 1;
+    // only the first byte has been written
+    if buf[3] == 0 { //~ ERROR attempted to read undefined bytes
+        buf[1] = 2;
+    }
+}
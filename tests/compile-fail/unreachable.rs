@@ -0,0 +1,8 @@
+#![feature(core_intrinsics)]
+
+fn main() {
+    let x = 5;
+    if x == 5 {
+        unsafe { std::intrinsics::unreachable() } //~ ERROR: entered unreachable code
+    }
+}
@@ -0,0 +1,13 @@
+extern "C" {
+    fn malloc(size: usize) -> *mut u8;
+    fn free(ptr: *mut u8);
+}
+
+fn main() { //~ NOTE inside call to
+    unsafe {
+        let ptr = malloc(4); //~ NOTE the allocation was created here
+        *ptr = 1;
+        free(ptr); //~ NOTE the allocation was freed here
+        let _x = *ptr; //~ ERROR dangling pointer was dereferenced
+    }
+}
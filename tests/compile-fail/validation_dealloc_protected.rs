@@ -0,0 +1,13 @@
+extern "Rust" {
+    fn __rust_dealloc(ptr: *mut u8, size: usize, align: usize);
+}
+
+unsafe fn free_while_borrowed(x: &mut u32, stash: *mut u32) {
+    __rust_dealloc(stash as *mut u8, 4, 4); //~ ERROR: in conflict with lock WriteLock
+    *x = 1;
+}
+
+fn main() {
+    let stash = Box::into_raw(Box::new(0u32));
+    unsafe { free_while_borrowed(&mut *stash, stash) };
+}
@@ -0,0 +1,12 @@
+// error-pattern: encountered 0 in a `std::ptr::Unique<u8>`
+#![feature(unique)]
+
+use std::mem;
+use std::ptr::Unique;
+
+fn take(_ptr: Unique<u8>) {}
+
+fn main() {
+    let ptr: Unique<u8> = unsafe { mem::transmute(0usize) };
+    take(ptr);
+}
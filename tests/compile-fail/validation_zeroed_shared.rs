@@ -0,0 +1,12 @@
+// error-pattern: encountered 0 in a `std::ptr::Shared<u32>`
+#![feature(shared)]
+
+use std::mem;
+use std::ptr::Shared;
+
+fn take(_ptr: Shared<u32>) {}
+
+fn main() {
+    let ptr: Shared<u32> = unsafe { mem::zeroed() };
+    take(ptr);
+}
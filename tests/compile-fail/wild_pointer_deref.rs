@@ -1,5 +1,6 @@
 fn main() {
     let p = 44 as *const i32;
     let x = unsafe { *p }; //~ ERROR: a memory access tried to interpret some bytes as a pointer
+    //~| WARN dereferenced the pointer 0x2c, which was made from an integer
     panic!("this should never print: {}", x);
 }
@@ -0,0 +1,6 @@
+fn main() {
+    let v = [1u16, 2, 3, 4];
+    // One element before the array, so the end of the read wraps around to offset 0.
+    let before = v.as_ptr().wrapping_offset(-1);
+    let _x = unsafe { *before }; //~ ERROR outside bounds of allocation
+}
@@ -0,0 +1,5 @@
+// error-pattern: outside bounds of allocation
+fn main() {
+    let mut v = [0u32; 2];
+    unsafe { std::ptr::write_bytes(v.as_mut_ptr(), 0, 3) };
+}
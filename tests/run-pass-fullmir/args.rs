@@ -0,0 +1,9 @@
+use std::env;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    // only the program name
+    assert_eq!(args.len(), 1);
+    assert!(!args[0].is_empty());
+    assert_eq!(env::args().skip(1).next(), None);
+}
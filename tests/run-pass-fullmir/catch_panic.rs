@@ -0,0 +1,42 @@
+//ignore-msvc
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+
+struct Guard<'a>(&'a Cell<u32>);
+
+impl<'a> Drop for Guard<'a> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+fn inner(drops: &Cell<u32>) -> u32 {
+    let _guard = Guard(drops);
+    panic!("boom {}", 42);
+}
+
+fn main() {
+    panic::set_hook(Box::new(|_| {}));
+    let drops = Cell::new(0);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let _outer = Guard(&drops);
+        inner(&drops)
+    }));
+    // Both guards were dropped while unwinding.
+    assert_eq!(drops.get(), 2);
+    let payload = result.unwrap_err();
+    assert_eq!(payload.downcast_ref::<String>().unwrap(), "boom 42");
+
+    let result = panic::catch_unwind(|| 7);
+    assert_eq!(result.unwrap(), 7);
+
+    let result = panic::catch_unwind(|| panic!("literal"));
+    assert_eq!(*result.unwrap_err().downcast_ref::<&str>().unwrap(), "literal");
+
+    // A nested `catch_unwind` whose closure returned normally does not catch the outer panic.
+    let result = panic::catch_unwind(|| {
+        assert_eq!(panic::catch_unwind(|| 1).unwrap(), 1);
+        panic!("outer");
+    });
+    assert_eq!(*result.unwrap_err().downcast_ref::<&str>().unwrap(), "outer");
+}
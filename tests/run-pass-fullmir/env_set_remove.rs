@@ -0,0 +1,11 @@
+use std::env;
+
+fn main() {
+    assert_eq!(env::var("MIRI_TEST_VAR").ok(), None);
+    env::set_var("MIRI_TEST_VAR", "first");
+    assert_eq!(env::var("MIRI_TEST_VAR").unwrap(), "first");
+    env::set_var("MIRI_TEST_VAR", "second value");
+    assert_eq!(env::var("MIRI_TEST_VAR").unwrap(), "second value");
+    env::remove_var("MIRI_TEST_VAR");
+    assert!(env::var("MIRI_TEST_VAR").is_err());
+}
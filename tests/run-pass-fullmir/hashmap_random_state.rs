@@ -0,0 +1,16 @@
+//ignore-msvc
+use std::collections::{HashMap, HashSet};
+
+fn main() {
+    // `HashMap::new` uses `RandomState`, which reads random bytes.
+    let mut map = HashMap::new();
+    for i in 0..20 {
+        map.insert(i, i * i);
+    }
+    assert_eq!(map.len(), 20);
+    assert_eq!(map[&7], 49);
+    assert_eq!(map.values().sum::<i32>(), (0..20).map(|i| i * i).sum());
+
+    let set: HashSet<_> = map.keys().cloned().collect();
+    assert!(set.contains(&19));
+}
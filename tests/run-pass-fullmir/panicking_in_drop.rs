@@ -0,0 +1,28 @@
+//ignore-msvc
+use std::panic::catch_unwind;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::thread;
+
+static DROPS_WHILE_PANICKING: AtomicUsize = ATOMIC_USIZE_INIT;
+
+struct CheckPanicking;
+
+impl Drop for CheckPanicking {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            DROPS_WHILE_PANICKING.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+fn main() {
+    assert!(catch_unwind(|| {
+        let _check = CheckPanicking;
+        panic!("unwinding");
+    }).is_err());
+    assert_eq!(DROPS_WHILE_PANICKING.load(Ordering::SeqCst), 1);
+    // After the panic was caught, the thread is no longer panicking.
+    assert!(!thread::panicking());
+    drop(CheckPanicking);
+    assert_eq!(DROPS_WHILE_PANICKING.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,46 @@
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+
+static NOISY_DROPPED: AtomicBool = ATOMIC_BOOL_INIT;
+
+struct Noisy(u32);
+
+impl Drop for Noisy {
+    fn drop(&mut self) {
+        assert_eq!(self.0, 3);
+        NOISY_DROPPED.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Thread locals are destroyed after `main` returns, so the last one to go checks the others.
+struct Check;
+
+impl Drop for Check {
+    fn drop(&mut self) {
+        assert!(NOISY_DROPPED.load(Ordering::SeqCst));
+        println!("NOISY was dropped");
+    }
+}
+
+thread_local! {
+    static CHECK: Check = Check;
+    static COUNTER: Cell<u32> = Cell::new(0);
+    static NOISY: RefCell<Noisy> = RefCell::new(Noisy(0));
+}
+
+fn bump() -> u32 {
+    COUNTER.with(|c| {
+        c.set(c.get() + 1);
+        c.get()
+    })
+}
+
+fn main() {
+    // Destructors run in the reverse order of the first uses, so this one runs last.
+    CHECK.with(|_| ());
+    assert_eq!(bump(), 1);
+    assert_eq!(bump(), 2);
+    assert_eq!(bump(), 3);
+    NOISY.with(|n| n.borrow_mut().0 = COUNTER.with(Cell::get));
+    println!("counted to {}", COUNTER.with(Cell::get));
+}
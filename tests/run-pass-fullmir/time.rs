@@ -0,0 +1,17 @@
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let start = Instant::now();
+    let mut sum = 0u64;
+    for i in 0..10 {
+        sum += i;
+    }
+    assert_eq!(sum, 45);
+    let later = Instant::now();
+    assert!(later > start);
+    assert!(later.duration_since(start) == later - start);
+
+    let now = SystemTime::now();
+    assert!(now.duration_since(UNIX_EPOCH).is_ok());
+    assert!(SystemTime::now() >= now);
+}
@@ -0,0 +1,69 @@
+enum Tree {
+    Leaf,
+    Node(Box<Tree>, i32, Box<Tree>),
+}
+
+impl Tree {
+    fn insert(self, value: i32) -> Tree {
+        match self {
+            Tree::Leaf => Tree::Node(Box::new(Tree::Leaf), value, Box::new(Tree::Leaf)),
+            Tree::Node(left, v, right) => {
+                if value < v {
+                    Tree::Node(Box::new(left.insert(value)), v, right)
+                } else {
+                    Tree::Node(left, v, Box::new(right.insert(value)))
+                }
+            }
+        }
+    }
+
+    fn in_order(&self, out: &mut Vec<i32>) {
+        if let Tree::Node(ref left, v, ref right) = *self {
+            left.in_order(out);
+            out.push(v);
+            right.in_order(out);
+        }
+    }
+
+    fn depth(&self) -> usize {
+        match *self {
+            Tree::Leaf => 0,
+            Tree::Node(ref left, _, ref right) => 1 + ::std::cmp::max(left.depth(), right.depth()),
+        }
+    }
+}
+
+struct List {
+    value: u32,
+    next: Option<Box<List>>,
+}
+
+fn main() {
+    let mut tree = Tree::Leaf;
+    for &v in &[5, 3, 8, 1, 4, 7, 9, 2, 6] {
+        tree = tree.insert(v);
+    }
+    let mut values = Vec::new();
+    tree.in_order(&mut values);
+    assert_eq!(values, [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    assert_eq!(tree.depth(), 4);
+
+    let mut list = None;
+    for i in 0..20 {
+        list = Some(Box::new(List { value: i, next: list }));
+    }
+    let mut sum = 0;
+    let mut cur = &list;
+    while let Some(ref node) = *cur {
+        sum += node.value;
+        cur = &node.next;
+    }
+    assert_eq!(sum, 190);
+
+    // moving out of and back into a box through a deref
+    let mut b = Box::new((1u8, String::from("a")));
+    let (n, s) = *b;
+    b = Box::new((n + 1, s + "b"));
+    assert_eq!(b.0, 2);
+    assert_eq!(&b.1[..], "ab");
+}
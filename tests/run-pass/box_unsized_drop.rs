@@ -0,0 +1,44 @@
+use std::cell::Cell;
+
+struct Counted<'a>(&'a Cell<u32>);
+
+impl<'a> Drop for Counted<'a> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+trait Shape {
+    fn sides(&self) -> u32;
+}
+
+struct Square<'a>(Counted<'a>);
+
+impl<'a> Shape for Square<'a> {
+    fn sides(&self) -> u32 {
+        4
+    }
+}
+
+fn sides_of<'a>(shape: Box<Shape + 'a>) -> u32 {
+    shape.sides()
+}
+
+fn main() {
+    let drops = Cell::new(0);
+
+    let slice: Box<[Counted]> = Box::new([Counted(&drops), Counted(&drops), Counted(&drops)]);
+    assert_eq!(slice.len(), 3);
+    drop(slice);
+    assert_eq!(drops.get(), 3);
+
+    let from_vec = vec![Counted(&drops), Counted(&drops)].into_boxed_slice();
+    drop(from_vec);
+    assert_eq!(drops.get(), 5);
+
+    assert_eq!(sides_of(Box::new(Square(Counted(&drops)))), 4);
+    assert_eq!(drops.get(), 6);
+
+    let s: Box<str> = String::from("boxed").into_boxed_str();
+    assert_eq!(&*s, "boxed");
+}
@@ -0,0 +1,25 @@
+fn call_boxed<'a>(mut f: Box<FnMut(u32) -> u32 + 'a>) -> u32 {
+    f(1) + f(2)
+}
+
+fn call_ref(f: &Fn(u32, u32) -> u32) -> u32 {
+    f(3, 4)
+}
+
+fn main() {
+    let mut calls = 0;
+    {
+        let counter = Box::new(|x| {
+            calls += 1;
+            x * 10
+        });
+        assert_eq!(call_boxed(counter), 30);
+    }
+    assert_eq!(calls, 2);
+
+    let offset = 5;
+    assert_eq!(call_ref(&|a, b| a * b + offset), 17);
+
+    let closures: Vec<Box<Fn() -> u32>> = vec![Box::new(|| 1), Box::new(move || offset)];
+    assert_eq!(closures.iter().map(|f| f()).sum::<u32>(), 6);
+}
@@ -0,0 +1,55 @@
+#![feature(fn_traits)]
+
+fn call_once<F: FnOnce() -> String>(f: F) -> String {
+    f()
+}
+
+fn call_mut<F: FnMut(u32)>(mut f: F) {
+    f(1);
+    f(2);
+}
+
+fn call<F: Fn(u32, u32) -> u32>(f: F) -> u32 {
+    f(3, 4)
+}
+
+fn pair() -> (u32, u32) {
+    (5, 6)
+}
+
+fn make_adder(n: u32) -> Box<Fn(u32) -> u32> {
+    Box::new(move |x| x + n)
+}
+
+fn main() {
+    // Capturing by value and consuming the capture
+    let s = String::from("moved");
+    assert_eq!(call_once(move || s), "moved");
+
+    // Capturing by mutable reference
+    let mut sum = 0;
+    call_mut(|x| sum += x);
+    assert_eq!(sum, 3);
+
+    // Capturing by reference, called through `Fn`, through `&F` and as `FnOnce`
+    let factor = 10;
+    let mul = |a: u32, b: u32| (a + b) * factor;
+    assert_eq!(call(&mul), 70);
+    // The `Fn*` methods called directly, with a tuple argument that is a pair of primitives
+    assert_eq!(mul.call(pair()), 110);
+    assert_eq!(call(mul), 70);
+    let describe = || format!("{}", factor);
+    assert_eq!(call_once(describe), "10");
+
+    let mut count = 0;
+    {
+        let mut inc = |n: u32| count += n;
+        inc.call_mut((2,));
+        inc.call_once((3,));
+    }
+    assert_eq!(count, 5);
+
+    // A closure returned as a trait object
+    let add = make_adder(7);
+    assert_eq!(add(1), 8);
+}
@@ -0,0 +1,22 @@
+//ignore-windows
+
+#![feature(libc)]
+extern crate libc;
+
+use std::mem;
+
+fn main() {
+    unsafe {
+        let name = b"miri_no_such_symbol\0";
+        let missing = libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr() as *const _);
+        assert!(missing.is_null());
+
+        let f = libc::dlsym(libc::RTLD_DEFAULT, b"clock_gettime\0".as_ptr() as *const _);
+        assert!(!f.is_null());
+        let clock_gettime: extern "C" fn(libc::clockid_t, *mut libc::timespec) -> libc::c_int =
+            mem::transmute(f);
+        let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        assert_eq!(clock_gettime(libc::CLOCK_MONOTONIC, &mut ts), 0);
+        assert!(ts.tv_nsec < 1_000_000_000);
+    }
+}
@@ -0,0 +1,42 @@
+use std::cell::Cell;
+use std::mem::ManuallyDrop;
+use std::ptr;
+
+struct Noisy<'a>(&'a Cell<usize>);
+
+impl<'a> Drop for Noisy<'a> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+struct Pair<'a> {
+    _a: Noisy<'a>,
+    _b: (u8, Noisy<'a>),
+}
+
+trait Foo {}
+impl<'a> Foo for Noisy<'a> {}
+
+fn main() {
+    let count = Cell::new(0);
+
+    let mut pair = ManuallyDrop::new(Pair { _a: Noisy(&count), _b: (0, Noisy(&count)) });
+    unsafe { ptr::drop_in_place(&mut *pair as *mut Pair) };
+    assert_eq!(count.get(), 2);
+
+    let mut array = ManuallyDrop::new([Noisy(&count), Noisy(&count), Noisy(&count)]);
+    unsafe { ptr::drop_in_place(&mut array[..] as *mut [Noisy]) };
+    assert_eq!(count.get(), 5);
+
+    let mut object = ManuallyDrop::new(Noisy(&count));
+    unsafe { ptr::drop_in_place(&mut *object as &mut Foo as *mut Foo) };
+    assert_eq!(count.get(), 6);
+
+    // replacing a value drops the old one
+    let mut x = Noisy(&count);
+    x = Noisy(&count);
+    assert_eq!(count.get(), 7);
+    drop(x);
+    assert_eq!(count.get(), 8);
+}
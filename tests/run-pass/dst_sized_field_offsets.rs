@@ -0,0 +1,42 @@
+struct Foo<T: ?Sized> {
+    a: u8,
+    b: u16,
+    c: T,
+}
+
+trait Get {
+    fn get(&self) -> u64;
+}
+
+impl Get for u8 {
+    fn get(&self) -> u64 {
+        *self as u64
+    }
+}
+
+impl Get for u64 {
+    fn get(&self) -> u64 {
+        *self
+    }
+}
+
+fn check(foo: &Foo<Get>, a: u8, b: u16, c: u64) {
+    assert_eq!(foo.a, a);
+    assert_eq!(foo.b, b);
+    assert_eq!(foo.c.get(), c);
+}
+
+fn main() {
+    let small = Foo { a: 1, b: 2, c: 3u8 };
+    let big = Foo { a: 4, b: 5, c: 6u64 };
+    check(&small, 1, 2, 3);
+    check(&big, 4, 5, 6);
+
+    let nested: &Foo<Foo<Get>> = &Foo { a: 7, b: 8, c: Foo { a: 9, b: 10, c: 11u64 } };
+    assert_eq!(nested.b, 8);
+    check(&nested.c, 9, 10, 11);
+
+    let slice: &Foo<[u32]> = &Foo { a: 12, b: 13, c: [14, 15] };
+    assert_eq!(slice.b, 13);
+    assert_eq!(&slice.c, &[14, 15]);
+}
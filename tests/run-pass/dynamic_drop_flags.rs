@@ -0,0 +1,72 @@
+use std::cell::Cell;
+
+struct Noisy<'a>(u8, &'a Cell<u32>);
+
+impl<'a> Drop for Noisy<'a> {
+    fn drop(&mut self) {
+        self.1.set(self.1.get() * 10 + self.0 as u32);
+    }
+}
+
+fn consume(_n: Noisy) {}
+
+fn conditional_move(log: &Cell<u32>, take: bool) {
+    let n = Noisy(1, log);
+    if take {
+        consume(n);
+    }
+    // `n` is only dropped here if it was not moved
+}
+
+fn early_return(log: &Cell<u32>, early: bool) -> u8 {
+    let a = Noisy(1, log);
+    if early {
+        return 0;
+    }
+    let _b = Noisy(2, log);
+    a.0
+}
+
+fn partial_move(log: &Cell<u32>, which: bool) {
+    let pair = (Noisy(1, log), Noisy(2, log));
+    if which {
+        consume(pair.0);
+    } else {
+        consume(pair.1);
+    }
+}
+
+fn conditional_init(log: &Cell<u32>, init: bool) {
+    let n;
+    if init {
+        n = Box::new(Noisy(3, log));
+        assert_eq!(n.0, 3);
+    }
+}
+
+fn main() {
+    let log = Cell::new(0);
+    conditional_move(&log, true);
+    conditional_move(&log, false);
+    assert_eq!(log.get(), 11);
+
+    log.set(0);
+    early_return(&log, true);
+    assert_eq!(log.get(), 1);
+    log.set(0);
+    early_return(&log, false);
+    assert_eq!(log.get(), 21);
+
+    log.set(0);
+    partial_move(&log, true);
+    assert_eq!(log.get(), 12);
+    log.set(0);
+    partial_move(&log, false);
+    assert_eq!(log.get(), 21);
+
+    log.set(0);
+    conditional_init(&log, false);
+    assert_eq!(log.get(), 0);
+    conditional_init(&log, true);
+    assert_eq!(log.get(), 3);
+}
@@ -0,0 +1,13 @@
+#![feature(core_intrinsics)]
+
+use std::intrinsics::{fadd_fast, fsub_fast, fmul_fast, fdiv_fast, frem_fast};
+
+fn main() {
+    unsafe {
+        assert_eq!(fadd_fast(1.5f64, 2.0), 3.5);
+        assert_eq!(fsub_fast(1.5f32, 2.0), -0.5);
+        assert_eq!(fmul_fast(1.5f64, 2.0), 3.0);
+        assert_eq!(fdiv_fast(3.0f32, 2.0), 1.5);
+        assert_eq!(frem_fast(7.0f64, 4.0), 3.0);
+    }
+}
@@ -0,0 +1,22 @@
+extern "C" {
+    fn fcntl(fd: i32, cmd: i32, ...) -> i32;
+    fn ioctl(fd: i32, request: u64, ...) -> i32;
+}
+
+const F_GETFD: i32 = 1;
+const F_SETFD: i32 = 2;
+const FD_CLOEXEC: i32 = 1;
+#[cfg(target_os = "macos")]
+const FIOCLEX: u64 = 0x2000_6601;
+#[cfg(not(target_os = "macos"))]
+const FIOCLEX: u64 = 0x5451;
+
+fn main() {
+    unsafe {
+        assert_eq!(fcntl(1, F_SETFD, FD_CLOEXEC), 0);
+        assert_eq!(fcntl(1, F_GETFD), FD_CLOEXEC);
+        assert_eq!(fcntl(42, F_GETFD), -1);
+        assert_eq!(ioctl(2, FIOCLEX), 0);
+        assert_eq!(ioctl(42, FIOCLEX), -1);
+    }
+}
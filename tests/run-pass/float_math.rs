@@ -0,0 +1,24 @@
+#![feature(core_intrinsics)]
+
+use std::intrinsics;
+
+fn main() {
+    assert_eq!(4f64.sqrt(), 2.0);
+    assert_eq!(8f64.cbrt(), 2.0);
+    assert_eq!(2f32.powf(3.0), 8.0);
+    assert_eq!(2f64.powi(10), 1024.0);
+    assert_eq!(3.5f64.round(), 4.0);
+    assert_eq!((-3.5f32).round(), -4.0);
+    assert_eq!(1f64.ln(), 0.0);
+    assert_eq!(0f64.sin(), 0.0);
+    assert_eq!(0f32.cos(), 1.0);
+    assert_eq!(0f64.tan(), 0.0);
+    assert_eq!(3f64.hypot(4.0), 5.0);
+    assert_eq!(2f32.mul_add(3.0, 4.0), 10.0);
+    unsafe {
+        assert_eq!(intrinsics::copysignf32(1.0, -0.0), -1.0);
+        assert_eq!(intrinsics::copysignf64(-1.0, 2.0), 1.0);
+        assert_eq!(intrinsics::minnumf64(1.0, 2.0), 1.0);
+        assert_eq!(intrinsics::maxnumf32(std::f32::NAN, 2.0), 2.0);
+    }
+}
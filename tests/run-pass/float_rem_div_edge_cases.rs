@@ -0,0 +1,62 @@
+// Compares bit patterns, so `-0.0` and `0.0` are told apart. All NaNs are treated as equal.
+
+fn same64(a: f64, b: f64) -> bool {
+    (a.is_nan() && b.is_nan()) || a.to_bits() == b.to_bits()
+}
+
+fn same32(a: f32, b: f32) -> bool {
+    (a.is_nan() && b.is_nan()) || a.to_bits() == b.to_bits()
+}
+
+fn main() {
+    use std::f64::{INFINITY as INF, NAN};
+
+    let values = [0.0, -0.0, 1.0, -1.0, 2.5, -4.0, INF, -INF, NAN];
+    // (a, b, a / b, a % b)
+    let cases: &[(f64, f64, f64, f64)] = &[
+        (1.0, 0.0, INF, NAN),
+        (-1.0, 0.0, -INF, NAN),
+        (1.0, -0.0, -INF, NAN),
+        (0.0, 0.0, NAN, NAN),
+        (-0.0, 1.0, -0.0, -0.0),
+        (0.0, -1.0, -0.0, 0.0),
+        (-4.0, 2.0, -2.0, -0.0),
+        (4.0, -2.0, -2.0, 0.0),
+        (-5.0, 2.0, -2.5, -1.0),
+        (5.0, -2.0, -2.5, 1.0),
+        (2.5, INF, 0.0, 2.5),
+        (-2.5, INF, -0.0, -2.5),
+        (2.5, -INF, -0.0, 2.5),
+        (INF, 2.0, INF, NAN),
+        (INF, INF, NAN, NAN),
+        (-INF, -2.0, INF, NAN),
+        (NAN, 1.0, NAN, NAN),
+        (1.0, NAN, NAN, NAN),
+    ];
+    for &(a, b, div, rem) in cases {
+        assert!(same64(a / b, div), "{} / {}", a, b);
+        assert!(same64(a % b, rem), "{} % {}", a, b);
+        assert!(same32(a as f32 / b as f32, div as f32), "{} / {} as f32", a, b);
+        assert!(same32(a as f32 % b as f32, rem as f32), "{} % {} as f32", a, b);
+    }
+
+    // the sign of a zero remainder follows the dividend for all operands
+    for &a in &values {
+        for &b in &values {
+            let r = a % b;
+            if r == 0.0 {
+                assert_eq!(r.is_sign_negative(), a.is_sign_negative(), "{} % {}", a, b);
+            }
+        }
+    }
+
+    // comparisons involving NaN are all false, except `!=`
+    for &x in &values {
+        assert!(!(NAN == x) && !(x == NAN));
+        assert!(NAN != x && x != NAN);
+        assert!(!(NAN < x) && !(NAN <= x) && !(NAN > x) && !(NAN >= x));
+        assert!(!(x < NAN) && !(x <= NAN) && !(x > NAN) && !(x >= NAN));
+    }
+    assert_eq!(0.0f64, -0.0);
+    assert!(!(0.0f64 < -0.0));
+}
@@ -0,0 +1,17 @@
+#![feature(custom_attribute, attr_literals)]
+#![allow(unused_attributes)]
+#![miri(step_limit=1000)]
+
+// This would exceed the global limit, but runs with an unlimited budget of its own.
+#[miri(step_limit=0)]
+fn heavy_initializer() -> u32 {
+    let mut sum = 0;
+    for i in 0..10000 {
+        sum += i % 7;
+    }
+    sum
+}
+
+fn main() {
+    assert_eq!(heavy_initializer(), 29994);
+}
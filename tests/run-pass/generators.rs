@@ -0,0 +1,59 @@
+#![feature(generators, generator_trait)]
+
+use std::ops::{Generator, GeneratorState};
+
+struct Noisy<'a>(&'a mut u32);
+
+impl<'a> Drop for Noisy<'a> {
+    fn drop(&mut self) {
+        *self.0 += 1;
+    }
+}
+
+fn sum<G: Generator<Yield = u32, Return = &'static str>>(mut gen: G) -> u32 {
+    let mut total = 0;
+    loop {
+        match gen.resume() {
+            GeneratorState::Yielded(i) => total += i,
+            GeneratorState::Complete(s) => {
+                assert_eq!(s, "done");
+                return total;
+            }
+        }
+    }
+}
+
+fn main() {
+    assert_eq!(sum(|| {
+        for i in 1..4 {
+            yield i;
+        }
+        "done"
+    }), 6);
+
+    // Captured values and locals that live across a yield
+    let step = 10;
+    assert_eq!(sum(move || {
+        let mut x = 0;
+        while x < 30 {
+            x += step;
+            yield x;
+        }
+        "done"
+    }), 60);
+
+    // A generator dropped while suspended drops the locals it saved.
+    let mut drops = 0;
+    {
+        let mut gen = || {
+            let _noisy = Noisy(&mut drops);
+            yield 1u32;
+            "done"
+        };
+        match gen.resume() {
+            GeneratorState::Yielded(1) => {}
+            _ => panic!("the generator did not yield"),
+        }
+    }
+    assert_eq!(drops, 1);
+}
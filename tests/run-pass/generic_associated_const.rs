@@ -0,0 +1,38 @@
+trait Bounded {
+    const MIN: Self;
+    const MAX: Self;
+    const NAME: &'static str = "number";
+}
+
+impl Bounded for u8 {
+    const MIN: u8 = 0;
+    const MAX: u8 = 255;
+}
+
+impl Bounded for i16 {
+    const MIN: i16 = -32768;
+    const MAX: i16 = 32767;
+    const NAME: &'static str = "i16";
+}
+
+fn range<T: Bounded>() -> (T, T) {
+    (T::MIN, T::MAX)
+}
+
+fn name<T: Bounded>() -> &'static str {
+    T::NAME
+}
+
+struct Wrapper<T>(T);
+
+impl<T: Bounded> Wrapper<T> {
+    const SMALLEST: Wrapper<T> = Wrapper(T::MIN);
+}
+
+fn main() {
+    assert_eq!(range::<u8>(), (0, 255));
+    assert_eq!(range::<i16>(), (-32768, 32767));
+    assert_eq!(name::<u8>(), "number");
+    assert_eq!(name::<i16>(), "i16");
+    assert_eq!(Wrapper::<i16>::SMALLEST.0, -32768);
+}
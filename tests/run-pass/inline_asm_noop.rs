@@ -0,0 +1,14 @@
+#![feature(asm)]
+
+// Empty assembly only constrains the compiler, and a `nop` does nothing at all.
+fn fence() {
+    unsafe { asm!("" : : : "memory" : "volatile") };
+}
+
+fn main() {
+    let mut x = 1;
+    fence();
+    x += 1;
+    unsafe { asm!("nop") };
+    assert_eq!(x, 2);
+}
@@ -0,0 +1,11 @@
+fn main() {
+    let base = 0x1000 as *const u32;
+    unsafe {
+        assert_eq!(base.offset(4) as usize, 0x1010);
+        assert_eq!(base.offset(-4) as usize, 0xff0);
+        assert_eq!(base.offset(-4).offset(4), base);
+        assert!(base.offset(1) > base);
+    }
+    let odd = 0x1003 as *const u16;
+    assert_eq!(odd.wrapping_offset(-2) as usize, 0xfff);
+}
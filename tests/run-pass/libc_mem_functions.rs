@@ -0,0 +1,51 @@
+//ignore-windows
+
+#![feature(libc)]
+extern crate libc;
+
+use std::mem;
+
+fn main() {
+    unsafe {
+        let src = [1u8, 2, 3, 4, 5];
+        let mut dst = [0u8; 5];
+        let ret = libc::memcpy(
+            dst.as_mut_ptr() as *mut libc::c_void,
+            src.as_ptr() as *const libc::c_void,
+            5,
+        );
+        assert_eq!(ret as *mut u8, dst.as_mut_ptr());
+        assert_eq!(dst, src);
+
+        // pointers survive being copied bytewise
+        let x = 42i32;
+        let refs = [&x];
+        let mut copy: [&i32; 1] = mem::uninitialized();
+        libc::memcpy(
+            copy.as_mut_ptr() as *mut libc::c_void,
+            refs.as_ptr() as *const libc::c_void,
+            mem::size_of::<&i32>(),
+        );
+        assert_eq!(*copy[0], 42);
+
+        // overlapping moves
+        let mut buf = [1u8, 2, 3, 4, 5, 6];
+        libc::memmove(
+            buf.as_mut_ptr().offset(2) as *mut libc::c_void,
+            buf.as_ptr() as *const libc::c_void,
+            4,
+        );
+        assert_eq!(buf, [1, 2, 1, 2, 3, 4]);
+
+        libc::memset(buf.as_mut_ptr() as *mut libc::c_void, 0xab, 3);
+        assert_eq!(buf, [0xab, 0xab, 0xab, 2, 3, 4]);
+
+        let a = b"abcd";
+        let b = b"abce";
+        assert!(libc::memcmp(a.as_ptr() as *const _, b.as_ptr() as *const _, 4) < 0);
+        assert!(libc::memcmp(b.as_ptr() as *const _, a.as_ptr() as *const _, 4) > 0);
+        assert_eq!(libc::memcmp(a.as_ptr() as *const _, b.as_ptr() as *const _, 3), 0);
+
+        assert_eq!(libc::strlen(b"hello\0".as_ptr() as *const libc::c_char), 5);
+    }
+}
@@ -0,0 +1,10 @@
+#![feature(termination_trait)]
+
+fn half(n: u32) -> Result<u32, &'static str> {
+    if n % 2 == 0 { Ok(n / 2) } else { Err("odd") }
+}
+
+fn main() -> Result<(), &'static str> {
+    assert_eq!(half(42)?, 21);
+    Ok(())
+}
@@ -0,0 +1,40 @@
+//ignore-windows
+
+#![feature(libc)]
+extern crate libc;
+
+use std::ptr;
+
+fn main() {
+    unsafe {
+        // `malloc(0)` and `calloc` with a zero size may return null
+        let p = libc::malloc(0);
+        libc::free(p);
+        libc::free(ptr::null_mut());
+
+        let p = libc::calloc(4, 2) as *mut u16;
+        for i in 0..4 {
+            assert_eq!(*p.offset(i), 0);
+            *p.offset(i) = i as u16;
+        }
+
+        // growing keeps the old contents
+        let p = libc::realloc(p as *mut libc::c_void, 16) as *mut u16;
+        for i in 0..4 {
+            assert_eq!(*p.offset(i), i as u16);
+        }
+
+        // shrinking keeps the prefix
+        let p = libc::realloc(p as *mut libc::c_void, 4) as *mut u16;
+        assert_eq!(*p.offset(1), 1);
+
+        // `realloc(ptr, 0)` frees the memory
+        assert!(libc::realloc(p as *mut libc::c_void, 0).is_null());
+
+        // `realloc(NULL, size)` allocates
+        let p = libc::realloc(ptr::null_mut(), 8) as *mut u64;
+        *p = 42;
+        assert_eq!(*p, 42);
+        libc::free(p as *mut libc::c_void);
+    }
+}
@@ -0,0 +1,41 @@
+// Memory that is uninitialized on purpose lives in a union until it is initialized, like in
+// `std::mem::MaybeUninit`, so nothing complains about it in the meantime.
+
+union MaybeUninit<T: Copy> {
+    uninit: (),
+    value: T,
+}
+
+impl<T: Copy> MaybeUninit<T> {
+    fn uninit() -> Self {
+        MaybeUninit { uninit: () }
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut T {
+        unsafe { &mut self.value as *mut T }
+    }
+
+    unsafe fn assume_init(self) -> T {
+        self.value
+    }
+}
+
+fn init_floats(out: *mut [f32; 4]) {
+    for i in 0..4 {
+        unsafe { *(out as *mut f32).offset(i) = i as f32 };
+    }
+}
+
+fn init_flag(out: *mut bool) {
+    unsafe { *out = true };
+}
+
+fn main() {
+    let mut floats = MaybeUninit::<[f32; 4]>::uninit();
+    init_floats(floats.as_mut_ptr());
+    assert_eq!(unsafe { floats.assume_init() }, [0.0, 1.0, 2.0, 3.0]);
+
+    let mut flag = MaybeUninit::<bool>::uninit();
+    init_flag(flag.as_mut_ptr());
+    assert!(unsafe { flag.assume_init() });
+}
@@ -0,0 +1,32 @@
+//ignore-windows
+
+#![feature(libc)]
+extern crate libc;
+
+use std::ptr;
+
+fn main() {
+    unsafe {
+        let len = 10000;
+        let p = libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANON,
+            -1,
+            0,
+        );
+        assert!(p != libc::MAP_FAILED);
+        let p = p as *mut u8;
+        // anonymous mappings are zeroed
+        for i in 0..len {
+            assert_eq!(*p.offset(i as isize), 0);
+        }
+        *p.offset(9999) = 42;
+        assert_eq!(*p.offset(9999), 42);
+
+        assert_eq!(libc::mprotect(p as *mut libc::c_void, len, libc::PROT_READ), 0);
+        assert_eq!(*p.offset(9999), 42);
+        assert_eq!(libc::munmap(p as *mut libc::c_void, len), 0);
+    }
+}
@@ -0,0 +1,31 @@
+#![feature(core_intrinsics)]
+
+use std::intrinsics::{init, move_val_init};
+
+#[derive(Debug, PartialEq)]
+struct Point {
+    x: i32,
+    y: u8,
+    name: &'static str,
+}
+
+fn main() {
+    unsafe {
+        let mut p = std::mem::uninitialized::<Point>();
+        move_val_init(&mut p, Point { x: -1, y: 2, name: "p" });
+        assert_eq!(p, Point { x: -1, y: 2, name: "p" });
+
+        let mut b: Box<[u16; 3]> = Box::new([0; 3]);
+        move_val_init(&mut *b, [4, 5, 6]);
+        assert_eq!(*b, [4, 5, 6]);
+
+        let x: u64 = init();
+        assert_eq!(x, 0);
+        let pair: (u8, i64) = init();
+        assert_eq!(pair, (0, 0));
+        let arr: [i16; 4] = init();
+        assert_eq!(arr, [0; 4]);
+        let opt: Option<usize> = init();
+        assert_eq!(opt, None);
+    }
+}
@@ -0,0 +1,9 @@
+// compile-flags: -C overflow-checks=off
+
+fn add(a: u8, b: u8) -> u8 { a + b }
+fn sub(a: u32, b: u32) -> u32 { a - b }
+
+fn main() {
+    assert_eq!(add(255, 1), 0);
+    assert_eq!(sub(0, 1), u32::max_value());
+}
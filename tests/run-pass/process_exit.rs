@@ -0,0 +1,5 @@
+fn main() {
+    // `exit` does not run destructors, but the memory still held by `v` is not a leak
+    let _v = vec![1, 2, 3];
+    std::process::exit(0);
+}
@@ -0,0 +1,35 @@
+struct Entry {
+    name: &'static str,
+    values: &'static [u32],
+}
+
+const SQUARES: [u32; 4] = [0 * 0, 1 * 1, 2 * 2, 3 * 3];
+const SUM: u32 = SQUARES[0] + SQUARES[1] + SQUARES[2] + SQUARES[3];
+
+static GREETING: &'static str = "hello";
+static ENTRIES: [Entry; 2] = [
+    Entry { name: "squares", values: &SQUARES },
+    Entry { name: "empty", values: &[] },
+];
+
+fn five() -> &'static i32 {
+    &5
+}
+
+fn pair() -> &'static (u8, &'static str) {
+    &(1 << 3, "eight")
+}
+
+fn main() {
+    assert_eq!(*five(), 5);
+    assert_eq!(five() as *const i32, five() as *const i32);
+    assert_eq!(pair().0, 8);
+    assert_eq!(pair().1, "eight");
+
+    assert_eq!(SUM, 14);
+    assert_eq!(GREETING.len(), 5);
+    assert_eq!(&GREETING[1..3], "el");
+    assert_eq!(ENTRIES[0].name, "squares");
+    assert_eq!(ENTRIES[0].values.iter().sum::<u32>(), SUM);
+    assert!(ENTRIES[1].values.is_empty());
+}
@@ -0,0 +1,29 @@
+// The borrow checker of this compiler has no two-phase borrows, so `v.push(v.len())` is rejected
+// before miri sees it. These are the forms that are accepted instead, and the write locks taken
+// for their `&mut` borrows must not get in each other's way.
+
+fn push_len(v: &mut Vec<usize>) {
+    let len = v.len();
+    v.push(len);
+}
+
+fn push_twice(v: &mut Vec<usize>) {
+    push_len(v);
+    push_len(&mut *v);
+    let len = v.len();
+    v.push(len);
+}
+
+fn main() {
+    let mut v = Vec::new();
+    push_len(&mut v);
+    {
+        let r = &mut v;
+        push_twice(r);
+        let len = r.len();
+        r.push(len);
+    }
+    let last = v[v.len() - 1];
+    v.push(last + 1);
+    assert_eq!(v, [0, 1, 2, 3, 4, 5]);
+}
@@ -0,0 +1,26 @@
+fn lt<T: PartialOrd>(a: T, b: T) -> bool { a < b }
+fn ge<T: PartialOrd>(a: T, b: T) -> bool { a >= b }
+
+fn main() {
+    assert!(lt(-1i8, 1));
+    assert!(lt(-128i8, 127));
+    assert!(ge(0i8, -1));
+    assert!(lt(-1i16, 1));
+    assert!(lt(-32768i16, 32767));
+    assert!(ge(0i16, -1));
+    assert!(lt(-1i32, 1));
+    assert!(lt(i32::min_value(), i32::max_value()));
+    assert!(ge(0i32, -1));
+    assert!(lt(-1i64, 1));
+    assert!(lt(i64::min_value(), i64::max_value()));
+    assert!(ge(0i64, -1));
+    assert!(lt(-1isize, 1));
+    assert!(lt(-1i128, 1));
+
+    // values that went through a bit-level reinterpretation are not sign-extended
+    let x: i8 = unsafe { std::mem::transmute(0xffu8) };
+    assert!(x < 0);
+    let y: i16 = 0xfffeu16 as i16;
+    assert!(y < -1);
+    assert!(!(y > 0));
+}
@@ -0,0 +1,12 @@
+#![feature(start)]
+
+// A `#[start]` function is called with the program's arguments, and returns its exit code.
+#[start]
+fn start(argc: isize, argv: *const *const u8) -> isize {
+    assert_eq!(argc, 1);
+    unsafe {
+        assert!(!(*argv).is_null());
+        assert!((*argv.offset(1)).is_null());
+    }
+    0
+}
@@ -0,0 +1,43 @@
+use std::sync::atomic::{AtomicUsize, AtomicBool, Ordering, ATOMIC_USIZE_INIT, ATOMIC_BOOL_INIT};
+
+static mut COUNTER: u32 = 0;
+static CALLS: AtomicUsize = ATOMIC_USIZE_INIT;
+static INITIALIZED: AtomicBool = ATOMIC_BOOL_INIT;
+
+fn bump() -> u32 {
+    unsafe {
+        COUNTER += 1;
+        COUNTER
+    }
+}
+
+fn count_call() -> usize {
+    CALLS.fetch_add(1, Ordering::SeqCst) + 1
+}
+
+fn init_once() -> bool {
+    !INITIALIZED.swap(true, Ordering::SeqCst)
+}
+
+fn local_static() -> usize {
+    static LOCAL: AtomicUsize = ATOMIC_USIZE_INIT;
+    LOCAL.fetch_add(2, Ordering::SeqCst)
+}
+
+fn main() {
+    assert_eq!(bump(), 1);
+    assert_eq!(bump(), 2);
+    assert_eq!(unsafe { COUNTER }, 2);
+
+    for i in 1..4 {
+        assert_eq!(count_call(), i);
+    }
+    assert_eq!(CALLS.load(Ordering::SeqCst), 3);
+
+    assert!(init_once());
+    assert!(!init_once());
+
+    assert_eq!(local_static(), 0);
+    assert_eq!(local_static(), 2);
+    assert_eq!(local_static(), 4);
+}
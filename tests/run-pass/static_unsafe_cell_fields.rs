@@ -0,0 +1,35 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering, ATOMIC_BOOL_INIT, ATOMIC_USIZE_INIT};
+
+struct Stats {
+    name: &'static str,
+    hits: AtomicUsize,
+    limit: usize,
+    flags: [AtomicBool; 2],
+}
+
+static STATS: Stats = Stats {
+    name: "stats",
+    hits: ATOMIC_USIZE_INIT,
+    limit: 3,
+    flags: [ATOMIC_BOOL_INIT, ATOMIC_BOOL_INIT],
+};
+
+static PAIR: (u8, AtomicUsize) = (7, ATOMIC_USIZE_INIT);
+
+fn hit() -> bool {
+    STATS.hits.fetch_add(1, Ordering::SeqCst) < STATS.limit
+}
+
+fn main() {
+    assert!(hit());
+    assert!(hit());
+    assert!(hit());
+    assert!(!hit());
+    STATS.flags[1].store(true, Ordering::SeqCst);
+    assert!(!STATS.flags[0].load(Ordering::SeqCst));
+    assert!(STATS.flags[1].load(Ordering::SeqCst));
+    assert_eq!(STATS.name, "stats");
+
+    PAIR.1.store(PAIR.0 as usize, Ordering::SeqCst);
+    assert_eq!(PAIR.1.load(Ordering::SeqCst), 7);
+}
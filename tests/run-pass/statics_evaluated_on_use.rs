@@ -0,0 +1,19 @@
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+static BASE: u32 = 40;
+static DERIVED: &'static u32 = &BASE;
+static COUNTER: AtomicUsize = ATOMIC_USIZE_INIT;
+// Never used, so never evaluated.
+static UNUSED: [u8; 1024 * 1024 * 1024] = [0; 1024 * 1024 * 1024];
+
+fn unused() -> u8 {
+    UNUSED[0]
+}
+
+fn main() {
+    if COUNTER.fetch_add(1, Ordering::SeqCst) > 0 {
+        unused();
+    }
+    assert_eq!(*DERIVED + 2, 42);
+    assert_eq!(COUNTER.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,66 @@
+trait Shape {
+    fn area(&self) -> u32;
+    fn scale(&mut self, factor: u32);
+    fn describe(&self) -> u32 {
+        self.area() + 1
+    }
+    fn into_area(self: Box<Self>) -> u32;
+}
+
+trait Named: Shape {
+    fn name(&self) -> &'static str;
+}
+
+struct Square(u32);
+struct Rect(u32, u32);
+
+impl Shape for Square {
+    fn area(&self) -> u32 { self.0 * self.0 }
+    fn scale(&mut self, factor: u32) { self.0 *= factor; }
+    fn into_area(self: Box<Self>) -> u32 { self.area() }
+}
+
+impl Shape for Rect {
+    fn area(&self) -> u32 { self.0 * self.1 }
+    fn scale(&mut self, factor: u32) { self.0 *= factor; self.1 *= factor; }
+    fn describe(&self) -> u32 { 0 }
+    fn into_area(self: Box<Self>) -> u32 { self.0 * self.1 }
+}
+
+impl Named for Square {
+    fn name(&self) -> &'static str { "square" }
+}
+
+fn total(shapes: &[Box<Shape>]) -> u32 {
+    shapes.iter().map(|shape| shape.area()).sum()
+}
+
+fn main() {
+    let mut shapes: Vec<Box<Shape>> = Vec::new();
+    shapes.push(Box::new(Square(2)));
+    shapes.push(Box::new(Rect(2, 3)));
+    assert_eq!(total(&shapes), 10);
+    for shape in shapes.iter_mut() {
+        shape.scale(2);
+    }
+    assert_eq!(total(&shapes), 40);
+    // Default methods call the object's own methods, overridden ones don't.
+    assert_eq!(shapes[0].describe(), 17);
+    assert_eq!(shapes[1].describe(), 0);
+    let areas: Vec<u32> = shapes.into_iter().map(|shape| shape.into_area()).collect();
+    assert_eq!(areas, [16, 24]);
+
+    // Methods of supertraits are in the vtable of the subtrait.
+    let square = Square(3);
+    let named: &Named = &square;
+    assert_eq!(named.name(), "square");
+    assert_eq!(named.area(), 9);
+
+    // Going through the same vtable many times.
+    let mut square = Square(1);
+    for _ in 0..10 {
+        let shape: &mut Shape = &mut square;
+        shape.scale(1);
+    }
+    assert_eq!(square.area(), 1);
+}
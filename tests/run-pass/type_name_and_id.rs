@@ -0,0 +1,26 @@
+#![feature(core_intrinsics)]
+
+use std::any::{Any, TypeId};
+use std::intrinsics::type_name;
+
+struct Foo;
+
+fn name_of<T>() -> &'static str {
+    unsafe { type_name::<T>() }
+}
+
+fn main() {
+    assert_eq!(name_of::<u32>(), "u32");
+    assert_eq!(name_of::<Option<&str>>(), "std::option::Option<&str>");
+    assert_eq!(name_of::<Foo>(), "Foo");
+
+    assert_eq!(TypeId::of::<u8>(), TypeId::of::<u8>());
+    assert!(TypeId::of::<u8>() != TypeId::of::<i8>());
+
+    let b: Box<Any> = Box::new(42i32);
+    assert!(b.downcast_ref::<u32>().is_none());
+    assert_eq!(b.downcast_ref::<i32>(), Some(&42));
+
+    let foo: &Any = &Foo;
+    assert!(foo.is::<Foo>());
+}
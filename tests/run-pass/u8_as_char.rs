@@ -0,0 +1,8 @@
+fn main() {
+    for i in 0..256u32 {
+        let c = i as u8 as char;
+        assert_eq!(c as u32, i);
+    }
+    assert_eq!(unsafe { std::char::from_u32_unchecked(0x1F600) }, '\u{1F600}');
+    assert!(std::char::from_u32(0xDFFF).is_none());
+}
@@ -0,0 +1,15 @@
+#![feature(core_intrinsics)]
+
+use std::intrinsics::{unaligned_volatile_load, unaligned_volatile_store};
+
+fn main() {
+    let mut bytes = [0u8; 8];
+    unsafe {
+        let ptr = bytes.as_mut_ptr().offset(1) as *mut u32;
+        unaligned_volatile_store(ptr, u32::to_le(0x0403_0201));
+        assert_eq!(unaligned_volatile_load(ptr), u32::to_le(0x0403_0201));
+        let ptr = bytes.as_ptr().offset(3) as *const u16;
+        assert_eq!(unaligned_volatile_load(ptr), u16::from_le(0x0403));
+    }
+    assert_eq!(bytes, [0, 1, 2, 3, 4, 0, 0, 0]);
+}
@@ -0,0 +1,13 @@
+#![feature(core_intrinsics)]
+
+use std::intrinsics::*;
+
+fn main() {
+    unsafe {
+        assert_eq!(unchecked_add(u8::max_value() - 1, 1), u8::max_value());
+        assert_eq!(unchecked_sub(i32::min_value() + 1, 1), i32::min_value());
+        assert_eq!(unchecked_mul(-3i64, 7), -21);
+        assert_eq!(unchecked_div(i32::min_value(), 1), i32::min_value());
+        assert_eq!(unchecked_rem(-7i16, 2), -1);
+    }
+}
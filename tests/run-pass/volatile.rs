@@ -0,0 +1,18 @@
+use std::ptr;
+
+fn main() {
+    let mut x = 42u64;
+    unsafe {
+        assert_eq!(ptr::read_volatile(&x), 42);
+        ptr::write_volatile(&mut x, 13);
+        assert_eq!(ptr::read_volatile(&x), 13);
+    }
+    assert_eq!(x, 13);
+
+    let mut b = Box::new([1u8, 2, 3]);
+    unsafe {
+        ptr::write_volatile(&mut b[1], 7);
+        assert_eq!(ptr::read_volatile(&b[1]), 7);
+    }
+    assert_eq!(*b, [1, 7, 3]);
+}
@@ -0,0 +1,17 @@
+fn main() {
+    let v = [1u16, 2, 3, 4];
+    let p = v.as_ptr();
+    // Going out of bounds and back is fine as long as nothing is accessed in between.
+    let far = p.wrapping_offset(1000);
+    assert_eq!(unsafe { *far.wrapping_offset(-998) }, 3);
+    let before = p.wrapping_offset(-1);
+    assert_eq!(unsafe { *before.wrapping_offset(2) }, 2);
+    // Even wrapping around the end of the address space
+    let wrapped = p.wrapping_offset(isize::min_value());
+    assert_eq!(unsafe { *wrapped.wrapping_offset(isize::min_value()) }, 1);
+    // Out-of-bounds pointers can still be compared.
+    assert!(far != p);
+    assert_eq!(far.wrapping_offset(-1000), p);
+    let end = p.wrapping_offset(4);
+    assert_eq!(end, unsafe { p.offset(4) });
+}
@@ -0,0 +1,28 @@
+use std::ptr;
+
+fn main() {
+    let mut bytes = [0u8; 8];
+    unsafe { ptr::write_bytes(bytes.as_mut_ptr().offset(2), 0xab, 4) };
+    assert_eq!(bytes, [0, 0, 0xab, 0xab, 0xab, 0xab, 0, 0]);
+
+    let mut words = [1u32; 3];
+    unsafe { ptr::write_bytes(words.as_mut_ptr(), 0xff, 2) };
+    assert_eq!(words, [u32::max_value(), u32::max_value(), 1]);
+
+    // overwriting pointers turns them into plain integers
+    let x = 42;
+    let mut ptrs: [*const i32; 2] = [&x, &x];
+    unsafe {
+        ptr::write_bytes(&mut ptrs[0] as *mut *const i32, 0, 1);
+        assert!(ptrs[0].is_null());
+        assert_eq!(*ptrs[1], 42);
+    }
+
+    // uninitialized memory becomes defined
+    let mut uninit: [u16; 4] = unsafe { std::mem::uninitialized() };
+    unsafe { ptr::write_bytes(uninit.as_mut_ptr(), 1, 4) };
+    assert_eq!(uninit, [0x0101; 4]);
+
+    // empty writes to dangling pointers are fine
+    unsafe { ptr::write_bytes(ptr::null_mut::<u64>(), 0, 0) };
+}